@@ -0,0 +1,2 @@
+-- Remove the idempotency table
+DROP TABLE IF EXISTS idempotency;
@@ -0,0 +1,12 @@
+-- Create idempotency table if it doesn't exist
+-- Stores the request hash and the original response for each Idempotency-Key so that client
+-- retries can be replayed rather than re-executed. A NULL status marks a request in flight.
+CREATE TABLE IF NOT EXISTS idempotency (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  key VARCHAR(255) NOT NULL UNIQUE,
+  request_hash VARCHAR(255) NOT NULL,
+  status INTEGER,
+  content_type VARCHAR(255),
+  body BLOB,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
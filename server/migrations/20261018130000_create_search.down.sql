@@ -0,0 +1,15 @@
+-- Remove the full text search triggers
+DROP TRIGGER IF EXISTS action_fts_insert;
+DROP TRIGGER IF EXISTS action_fts_delete;
+DROP TRIGGER IF EXISTS action_fts_update;
+DROP TRIGGER IF EXISTS category_fts_insert;
+DROP TRIGGER IF EXISTS category_fts_delete;
+DROP TRIGGER IF EXISTS category_fts_update;
+DROP TRIGGER IF EXISTS user_fts_insert;
+DROP TRIGGER IF EXISTS user_fts_delete;
+DROP TRIGGER IF EXISTS user_fts_update;
+
+-- Remove the full text search indexes
+DROP TABLE IF EXISTS action_fts;
+DROP TABLE IF EXISTS category_fts;
+DROP TABLE IF EXISTS user_fts;
@@ -0,0 +1,56 @@
+-- Create full text search index over action descriptions
+-- External content table so the text is stored only once in the action table
+CREATE VIRTUAL TABLE IF NOT EXISTS action_fts USING fts5(
+  desc, content='action', content_rowid='id', prefix='2 3'
+);
+
+-- Keep the action index in sync with the action table
+CREATE TRIGGER action_fts_insert AFTER INSERT ON action BEGIN
+  INSERT INTO action_fts (rowid, desc) VALUES (NEW.id, NEW.desc);
+END;
+CREATE TRIGGER action_fts_delete AFTER DELETE ON action BEGIN
+  INSERT INTO action_fts (action_fts, rowid, desc) VALUES ('delete', OLD.id, OLD.desc);
+END;
+CREATE TRIGGER action_fts_update AFTER UPDATE OF desc ON action BEGIN
+  INSERT INTO action_fts (action_fts, rowid, desc) VALUES ('delete', OLD.id, OLD.desc);
+  INSERT INTO action_fts (rowid, desc) VALUES (NEW.id, NEW.desc);
+END;
+
+-- Create full text search index over category names
+CREATE VIRTUAL TABLE IF NOT EXISTS category_fts USING fts5(
+  name, content='category', content_rowid='id', prefix='2 3'
+);
+
+-- Keep the category index in sync with the category table
+CREATE TRIGGER category_fts_insert AFTER INSERT ON category BEGIN
+  INSERT INTO category_fts (rowid, name) VALUES (NEW.id, NEW.name);
+END;
+CREATE TRIGGER category_fts_delete AFTER DELETE ON category BEGIN
+  INSERT INTO category_fts (category_fts, rowid, name) VALUES ('delete', OLD.id, OLD.name);
+END;
+CREATE TRIGGER category_fts_update AFTER UPDATE OF name ON category BEGIN
+  INSERT INTO category_fts (category_fts, rowid, name) VALUES ('delete', OLD.id, OLD.name);
+  INSERT INTO category_fts (rowid, name) VALUES (NEW.id, NEW.name);
+END;
+
+-- Create full text search index over usernames
+CREATE VIRTUAL TABLE IF NOT EXISTS user_fts USING fts5(
+  username, content='user', content_rowid='id', prefix='2 3'
+);
+
+-- Keep the user index in sync with the user table
+CREATE TRIGGER user_fts_insert AFTER INSERT ON user BEGIN
+  INSERT INTO user_fts (rowid, username) VALUES (NEW.id, NEW.username);
+END;
+CREATE TRIGGER user_fts_delete AFTER DELETE ON user BEGIN
+  INSERT INTO user_fts (user_fts, rowid, username) VALUES ('delete', OLD.id, OLD.username);
+END;
+CREATE TRIGGER user_fts_update AFTER UPDATE OF username ON user BEGIN
+  INSERT INTO user_fts (user_fts, rowid, username) VALUES ('delete', OLD.id, OLD.username);
+  INSERT INTO user_fts (rowid, username) VALUES (NEW.id, NEW.username);
+END;
+
+-- Index any existing rows
+INSERT INTO action_fts (action_fts) VALUES ('rebuild');
+INSERT INTO category_fts (category_fts) VALUES ('rebuild');
+INSERT INTO user_fts (user_fts) VALUES ('rebuild');
@@ -0,0 +1,15 @@
+-- Remove the soft deletion indexes
+DROP INDEX IF EXISTS reward_deleted_at;
+DROP INDEX IF EXISTS point_deleted_at;
+DROP INDEX IF EXISTS action_deleted_at;
+DROP INDEX IF EXISTS user_deleted_at;
+
+-- Remove the deleted_at columns, dropping anything still in the trash first
+DELETE FROM reward WHERE deleted_at IS NOT NULL;
+DELETE FROM point WHERE deleted_at IS NOT NULL;
+DELETE FROM action WHERE deleted_at IS NOT NULL;
+DELETE FROM user WHERE deleted_at IS NOT NULL;
+ALTER TABLE reward DROP COLUMN deleted_at;
+ALTER TABLE point DROP COLUMN deleted_at;
+ALTER TABLE action DROP COLUMN deleted_at;
+ALTER TABLE user DROP COLUMN deleted_at;
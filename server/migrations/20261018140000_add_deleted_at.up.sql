@@ -0,0 +1,12 @@
+-- Add deleted_at columns for soft deletion
+-- Rows with a deleted_at are in the trash, hidden from normal queries until restored or purged
+ALTER TABLE user ADD COLUMN deleted_at TIMESTAMP DATETIME;
+ALTER TABLE action ADD COLUMN deleted_at TIMESTAMP DATETIME;
+ALTER TABLE point ADD COLUMN deleted_at TIMESTAMP DATETIME;
+ALTER TABLE reward ADD COLUMN deleted_at TIMESTAMP DATETIME;
+
+-- Create indexes to keep trash listing and purging cheap
+CREATE INDEX IF NOT EXISTS user_deleted_at ON user(deleted_at);
+CREATE INDEX IF NOT EXISTS action_deleted_at ON action(deleted_at);
+CREATE INDEX IF NOT EXISTS point_deleted_at ON point(deleted_at);
+CREATE INDEX IF NOT EXISTS reward_deleted_at ON reward(deleted_at);
@@ -0,0 +1,4 @@
+-- Drop the audit table along with its indexes and triggers
+DROP TRIGGER IF EXISTS audit_no_delete;
+DROP TRIGGER IF EXISTS audit_no_update;
+DROP TABLE IF EXISTS audit;
@@ -0,0 +1,29 @@
+-- Create audit table if it doesn't exist
+-- Append-only record of every change made through the API, written in the same transaction as the
+-- change itself. The actor is kept as a plain id so that entries outlive purged users.
+CREATE TABLE IF NOT EXISTS audit (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  actor_id INTEGER,
+  entity VARCHAR(255) NOT NULL,
+  entity_id INTEGER NOT NULL,
+  action VARCHAR(255) NOT NULL,
+  before TEXT,
+  after TEXT,
+  request_id VARCHAR(255),
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+
+-- Create indexes for the audit filters
+CREATE INDEX IF NOT EXISTS audit_actor_id ON audit(actor_id);
+CREATE INDEX IF NOT EXISTS audit_entity ON audit(entity, entity_id);
+CREATE INDEX IF NOT EXISTS audit_created_at ON audit(created_at);
+
+-- Create triggers to keep the audit table append-only
+CREATE TRIGGER IF NOT EXISTS audit_no_update BEFORE UPDATE ON audit
+BEGIN
+  SELECT RAISE(ABORT, 'audit entries can not be changed');
+END;
+CREATE TRIGGER IF NOT EXISTS audit_no_delete BEFORE DELETE ON audit
+BEGIN
+  SELECT RAISE(ABORT, 'audit entries can not be deleted');
+END;
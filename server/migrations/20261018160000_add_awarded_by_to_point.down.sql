@@ -0,0 +1,6 @@
+-- Remove the occurred_at index
+DROP INDEX IF EXISTS point_occurred_at;
+
+-- Remove the awarded_by and occurred_at columns
+ALTER TABLE point DROP COLUMN occurred_at;
+ALTER TABLE point DROP COLUMN awarded_by;
@@ -0,0 +1,9 @@
+-- Add who awarded the points and when the deed actually happened
+-- Points created before this change have no known awarder and are taken to have happened when
+-- they were recorded.
+ALTER TABLE point ADD COLUMN awarded_by INTEGER REFERENCES user(id) ON DELETE SET NULL;
+ALTER TABLE point ADD COLUMN occurred_at TIMESTAMP DATETIME;
+UPDATE point SET occurred_at = created_at;
+
+-- Create index as date range filters and sums run on occurred_at
+CREATE INDEX IF NOT EXISTS point_occurred_at ON point(occurred_at);
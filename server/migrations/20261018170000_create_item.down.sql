@@ -0,0 +1,7 @@
+-- Unlink rewards from items
+DROP INDEX IF EXISTS reward_item_id;
+ALTER TABLE reward DROP COLUMN item_id;
+
+-- Drop the item table along with its trigger
+DROP TRIGGER IF EXISTS update_item;
+DROP TABLE IF EXISTS item;
@@ -0,0 +1,25 @@
+-- Create item table if it doesn't exist
+-- Catalog of rewards that can be redeemed for points. A NULL stock is unlimited and a limit is the
+-- most times each user may redeem the item per period.
+CREATE TABLE IF NOT EXISTS item (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(255) NOT NULL UNIQUE,
+  desc VARCHAR(1024) NOT NULL DEFAULT '',
+  cost INTEGER NOT NULL,
+  stock INTEGER,
+  period_limit INTEGER,
+  period VARCHAR(255),
+  active INTEGER NOT NULL DEFAULT 1,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  updated_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+
+-- Create trigger to update the updated_at field on item changes
+CREATE TRIGGER update_item AFTER UPDATE OF name, desc, cost, stock, period_limit, period, active
+ON item BEGIN
+  UPDATE item SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
+
+-- Link rewards to the item they were redeemed for
+ALTER TABLE reward ADD COLUMN item_id INTEGER REFERENCES item(id);
+CREATE INDEX IF NOT EXISTS reward_item_id ON reward(item_id);
@@ -0,0 +1,4 @@
+-- Drop the redemption table along with its trigger and index
+DROP TRIGGER IF EXISTS update_redemption;
+DROP INDEX IF EXISTS redemption_user_id_status;
+DROP TABLE IF EXISTS redemption;
@@ -0,0 +1,24 @@
+-- Create redemption table if it doesn't exist
+-- Requests to cash points out for a reward, the value is held from the user's available balance
+-- while requested and becomes a reward once approved.
+CREATE TABLE IF NOT EXISTS redemption (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES user(id) on DELETE CASCADE,
+  item_id INTEGER REFERENCES item(id),
+  value INTEGER NOT NULL,
+  reason VARCHAR(1024) NOT NULL DEFAULT '',
+  status VARCHAR(255) NOT NULL DEFAULT 'requested',
+  note VARCHAR(1024),
+  reward_id INTEGER REFERENCES reward(id) on DELETE SET NULL,
+  decided_by INTEGER REFERENCES user(id) on DELETE SET NULL,
+  decided_at TIMESTAMP DATETIME,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  updated_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+CREATE INDEX IF NOT EXISTS redemption_user_id_status ON redemption(user_id, status);
+
+-- Create trigger to update the updated_at field on redemption changes
+CREATE TRIGGER update_redemption AFTER UPDATE OF status, note, reward_id, decided_by, decided_at
+ON redemption BEGIN
+  UPDATE redemption SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
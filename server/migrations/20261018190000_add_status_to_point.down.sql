@@ -0,0 +1,14 @@
+-- Restore the original trigger
+DROP TRIGGER IF EXISTS update_point;
+CREATE TRIGGER update_point AFTER UPDATE OF value, user_id, action_id ON point BEGIN
+  UPDATE point SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
+
+-- Remove the status index
+DROP INDEX IF EXISTS point_user_id_status;
+
+-- Remove the review columns
+ALTER TABLE point DROP COLUMN reviewed_at;
+ALTER TABLE point DROP COLUMN reviewed_by;
+ALTER TABLE point DROP COLUMN note;
+ALTER TABLE point DROP COLUMN status;
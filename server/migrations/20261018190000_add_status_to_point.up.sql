@@ -0,0 +1,15 @@
+-- Add a review status to points so that points submitted by non-admins wait on approval
+-- Points created before this change were trusted instantly and are taken as approved.
+ALTER TABLE point ADD COLUMN status VARCHAR(255) NOT NULL DEFAULT 'approved';
+ALTER TABLE point ADD COLUMN note VARCHAR(1024);
+ALTER TABLE point ADD COLUMN reviewed_by INTEGER REFERENCES user(id) ON DELETE SET NULL;
+ALTER TABLE point ADD COLUMN reviewed_at TIMESTAMP DATETIME;
+
+-- Create index as sums and balances only count approved points
+CREATE INDEX IF NOT EXISTS point_user_id_status ON point(user_id, status);
+
+-- Recreate the trigger to also update the updated_at field on reviews
+DROP TRIGGER IF EXISTS update_point;
+CREATE TRIGGER update_point AFTER UPDATE OF value, user_id, action_id, status, note ON point BEGIN
+  UPDATE point SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
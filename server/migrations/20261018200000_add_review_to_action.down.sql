@@ -0,0 +1,5 @@
+-- Remove the review columns
+ALTER TABLE action DROP COLUMN reviewed_at;
+ALTER TABLE action DROP COLUMN reviewed_by;
+ALTER TABLE action DROP COLUMN note;
+ALTER TABLE action DROP COLUMN proposed_by;
@@ -0,0 +1,6 @@
+-- Add who proposed an action and who reviewed it so that non-admin proposals wait in a queue
+-- Actions created before this change have no known proposer or reviewer.
+ALTER TABLE action ADD COLUMN proposed_by INTEGER REFERENCES user(id) ON DELETE SET NULL;
+ALTER TABLE action ADD COLUMN note VARCHAR(1024);
+ALTER TABLE action ADD COLUMN reviewed_by INTEGER REFERENCES user(id) ON DELETE SET NULL;
+ALTER TABLE action ADD COLUMN reviewed_at TIMESTAMP DATETIME;
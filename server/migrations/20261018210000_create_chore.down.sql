@@ -0,0 +1,10 @@
+-- Drop the missed occurrences
+DROP TABLE IF EXISTS chore_miss;
+
+-- Drop the assignments along with their index
+DROP INDEX IF EXISTS chore_user_user_id;
+DROP TABLE IF EXISTS chore_user;
+
+-- Drop the chore table along with its trigger
+DROP TRIGGER IF EXISTS update_chore;
+DROP TABLE IF EXISTS chore;
@@ -0,0 +1,48 @@
+-- Create chore table if it doesn't exist
+-- Recurring schedule for an action. Weekly chores list the ISO weekdays they fall on as a JSON
+-- array, interval chores fall every given number of days from the start and monthly chores fall
+-- on the given day or the last day of shorter months.
+CREATE TABLE IF NOT EXISTS chore (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  action_id INTEGER NOT NULL REFERENCES action(id),
+  schedule VARCHAR(255) NOT NULL,
+  weekdays VARCHAR(255),
+  every INTEGER,
+  day_of_month INTEGER,
+  starts_on DATE NOT NULL,
+  active INTEGER NOT NULL DEFAULT 1,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  updated_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+
+-- Create trigger to update the updated_at field on chore changes
+CREATE TRIGGER update_chore AFTER UPDATE OF action_id, schedule, weekdays, every, day_of_month,
+  starts_on, active ON chore BEGIN
+  UPDATE chore SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
+
+-- Create chore_user table if it doesn't exist
+-- Users a chore is assigned to, occurrences before the assignment was made are never missed.
+CREATE TABLE IF NOT EXISTS chore_user (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  chore_id INTEGER NOT NULL REFERENCES chore(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  UNIQUE(chore_id, user_id)
+);
+
+-- Create index as due chores are looked up by user
+CREATE INDEX IF NOT EXISTS chore_user_user_id ON chore_user(user_id);
+
+-- Create chore_miss table if it doesn't exist
+-- Occurrences a user missed along with the penalty points taken for it if any. Each occurrence is
+-- only ever missed once.
+CREATE TABLE IF NOT EXISTS chore_miss (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  chore_id INTEGER NOT NULL REFERENCES chore(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  due_on DATE NOT NULL,
+  point_id INTEGER REFERENCES point(id) ON DELETE SET NULL,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  UNIQUE(chore_id, user_id, due_on)
+);
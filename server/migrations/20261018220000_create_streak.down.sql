@@ -0,0 +1,5 @@
+-- Drop the streaks
+DROP TABLE IF EXISTS streak;
+
+-- Drop the daily counts
+DROP TABLE IF EXISTS point_day;
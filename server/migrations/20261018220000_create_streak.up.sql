@@ -0,0 +1,31 @@
+-- Create point_day table if it doesn't exist
+-- Number of points each user logged for each action per day in the household timezone, kept up to
+-- date as points change so that streaks never need to scan every point. Penalties, rejected points
+-- and points in the trash aren't counted.
+CREATE TABLE IF NOT EXISTS point_day (
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  action_id INTEGER NOT NULL REFERENCES action(id) ON DELETE CASCADE,
+  day DATE NOT NULL,
+  count INTEGER NOT NULL,
+  PRIMARY KEY (user_id, action_id, day)
+);
+
+-- Count the points logged before this change
+INSERT INTO point_day (user_id, action_id, day, count)
+  SELECT user_id, action_id, date(occurred_at, 'localtime'), COUNT(*) FROM point
+  WHERE value > 0 AND status != 'rejected' AND deleted_at IS NULL
+  GROUP BY user_id, action_id, date(occurred_at, 'localtime');
+
+-- Create streak table if it doesn't exist
+-- Current and longest run of consecutive days or scheduled occurrences per user for an action or
+-- a category. Rows are dropped when they can't be extended in place and rebuilt from point_day the
+-- next time they are needed.
+CREATE TABLE IF NOT EXISTS streak (
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  kind VARCHAR(255) NOT NULL,
+  ref_id INTEGER NOT NULL,
+  current INTEGER NOT NULL,
+  longest INTEGER NOT NULL,
+  last_on DATE NOT NULL,
+  PRIMARY KEY (user_id, kind, ref_id)
+);
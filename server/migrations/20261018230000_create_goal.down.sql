@@ -0,0 +1,4 @@
+-- Drop the goal table along with its index and trigger
+DROP TRIGGER IF EXISTS update_goal;
+DROP INDEX IF EXISTS goal_user_id;
+DROP TABLE IF EXISTS goal;
@@ -0,0 +1,21 @@
+-- Create goal table if it doesn't exist
+-- Points a user is saving up toward, optionally by a deadline or for a catalog item. Progress is
+-- taken from the user's available balance and completed_at is stamped the first time it's reached.
+CREATE TABLE IF NOT EXISTS goal (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  name VARCHAR(255) NOT NULL,
+  target INTEGER NOT NULL,
+  deadline DATE,
+  item_id INTEGER REFERENCES item(id) ON DELETE SET NULL,
+  completed_at TIMESTAMP DATETIME,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  updated_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+CREATE INDEX IF NOT EXISTS goal_user_id ON goal(user_id);
+
+-- Create trigger to update the updated_at field on goal changes
+CREATE TRIGGER update_goal AFTER UPDATE OF name, target, deadline, item_id, completed_at
+ON goal BEGIN
+  UPDATE goal SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
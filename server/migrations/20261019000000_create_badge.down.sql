@@ -0,0 +1,7 @@
+-- Drop the earned badges along with their index
+DROP INDEX IF EXISTS user_badge_user_id;
+DROP TABLE IF EXISTS user_badge;
+
+-- Drop the badge table along with its trigger
+DROP TRIGGER IF EXISTS update_badge;
+DROP TABLE IF EXISTS badge;
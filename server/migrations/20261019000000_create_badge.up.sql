@@ -0,0 +1,27 @@
+-- Create badge table if it doesn't exist
+-- Achievements defined as data, the rule is JSON holding the metric to measure, the threshold to
+-- reach and optionally the action or category and the number of trailing days it applies to.
+CREATE TABLE IF NOT EXISTS badge (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(255) NOT NULL UNIQUE,
+  icon VARCHAR(255) NOT NULL,
+  rule TEXT NOT NULL,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  updated_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+
+-- Create trigger to update the updated_at field on badge changes
+CREATE TRIGGER update_badge AFTER UPDATE OF name, icon, rule ON badge BEGIN
+  UPDATE badge SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
+
+-- Create user_badge table if it doesn't exist
+-- Badges each user has earned, kept once earned even if the points behind them later change
+CREATE TABLE IF NOT EXISTS user_badge (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  badge_id INTEGER NOT NULL REFERENCES badge(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  earned_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  UNIQUE (badge_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS user_badge_user_id ON user_badge(user_id);
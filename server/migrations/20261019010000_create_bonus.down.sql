@@ -0,0 +1,7 @@
+-- Remove the base value and multiplier from points
+ALTER TABLE point DROP COLUMN multiplier;
+ALTER TABLE point DROP COLUMN base_value;
+
+-- Drop the bonus table along with its trigger
+DROP TRIGGER IF EXISTS update_bonus;
+DROP TABLE IF EXISTS bonus;
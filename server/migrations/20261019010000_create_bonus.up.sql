@@ -0,0 +1,27 @@
+-- Create bonus table if it doesn't exist
+-- Time windowed point multipliers e.g. double points weekend, applied to points as they are
+-- created. Without a category, action or user a bonus applies to everyone's points.
+CREATE TABLE IF NOT EXISTS bonus (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  multiplier REAL NOT NULL,
+  starts_at TIMESTAMP DATETIME NOT NULL,
+  ends_at TIMESTAMP DATETIME NOT NULL,
+  category_id INTEGER REFERENCES category(id) ON DELETE CASCADE,
+  action_id INTEGER REFERENCES action(id) ON DELETE CASCADE,
+  user_id INTEGER REFERENCES user(id) ON DELETE CASCADE,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec')),
+  updated_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+
+-- Create trigger to update the updated_at field on bonus changes
+CREATE TRIGGER update_bonus AFTER UPDATE OF name, multiplier, starts_at, ends_at, category_id,
+  action_id, user_id ON bonus BEGIN
+  UPDATE bonus SET updated_at = CURRENT_TIMESTAMP WHERE id=NEW.id;
+END;
+
+-- Record the value before any bonus and the multiplier used so that point history stays
+-- explainable. Points created before this change were never multiplied.
+ALTER TABLE point ADD COLUMN base_value INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE point ADD COLUMN multiplier REAL NOT NULL DEFAULT 1;
+UPDATE point SET base_value = value;
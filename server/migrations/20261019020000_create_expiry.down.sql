@@ -0,0 +1,3 @@
+-- Drop the expiry table along with its index
+DROP INDEX IF EXISTS expiry_user_id;
+DROP TABLE IF EXISTS expiry;
@@ -0,0 +1,13 @@
+-- Create expiry table if it doesn't exist
+-- Points that went unspent past their lifetime, written by the expiry job as explicit entries
+-- that the balance and ledger take away so totals stay auditable. Each points entry expires at
+-- most once.
+CREATE TABLE IF NOT EXISTS expiry (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+  point_id INTEGER NOT NULL UNIQUE REFERENCES point(id) ON DELETE CASCADE,
+  value INTEGER NOT NULL,
+  expired_at TIMESTAMP DATETIME NOT NULL,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+CREATE INDEX IF NOT EXISTS expiry_user_id ON expiry(user_id);
@@ -0,0 +1,2 @@
+-- Remove the kind from points
+ALTER TABLE point DROP COLUMN kind;
@@ -0,0 +1,5 @@
+-- Add an explicit kind to points so that awards, penalties, adjustments and the like can be told
+-- apart without relying on the sign of the value. Points created before this change are
+-- classified by their sign.
+ALTER TABLE point ADD COLUMN kind VARCHAR(255) NOT NULL DEFAULT 'award';
+UPDATE point SET kind = 'penalty' WHERE value < 0;
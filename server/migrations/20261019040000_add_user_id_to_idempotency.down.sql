@@ -0,0 +1,12 @@
+-- Recreate the idempotency table with keys shared by all callers
+DROP INDEX IF EXISTS idempotency_key_user_id;
+DROP TABLE IF EXISTS idempotency;
+CREATE TABLE IF NOT EXISTS idempotency (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  key VARCHAR(255) NOT NULL UNIQUE,
+  request_hash VARCHAR(255) NOT NULL,
+  status INTEGER,
+  content_type VARCHAR(255),
+  body BLOB,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
@@ -0,0 +1,18 @@
+-- Scope idempotency keys to the caller so that one user can't replay another user's response
+-- by sending the same key. Entries only live for minutes so the table is recreated rather than
+-- migrated, anonymous callers share the NULL user.
+DROP TABLE IF EXISTS idempotency;
+CREATE TABLE IF NOT EXISTS idempotency (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  key VARCHAR(255) NOT NULL,
+  user_id INTEGER REFERENCES user(id) ON DELETE CASCADE,
+  request_hash VARCHAR(255) NOT NULL,
+  status INTEGER,
+  content_type VARCHAR(255),
+  body BLOB,
+  created_at TIMESTAMP DATETIME DEFAULT(datetime('subsec'))
+);
+
+-- Create unique index so that each caller can only use a key once
+CREATE UNIQUE INDEX IF NOT EXISTS idempotency_key_user_id
+  ON idempotency(key, COALESCE(user_id, 0));
@@ -0,0 +1,7 @@
+-- Recount the points per day including pending points
+DELETE FROM point_day;
+INSERT INTO point_day (user_id, action_id, day, count)
+  SELECT user_id, action_id, date(occurred_at, 'localtime'), COUNT(*) FROM point
+  WHERE value > 0 AND status != 'rejected' AND deleted_at IS NULL
+  GROUP BY user_id, action_id, date(occurred_at, 'localtime');
+DELETE FROM streak;
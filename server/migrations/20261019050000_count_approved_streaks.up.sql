@@ -0,0 +1,9 @@
+-- Recount the points per day from approved points only
+-- Streaks count the same points as badges, points waiting on review no longer count until they
+-- are approved. The streaks are dropped and rebuilt the next time they are needed.
+DELETE FROM point_day;
+INSERT INTO point_day (user_id, action_id, day, count)
+  SELECT user_id, action_id, date(occurred_at, 'localtime'), COUNT(*) FROM point
+  WHERE value > 0 AND status = 'approved' AND deleted_at IS NULL
+  GROUP BY user_id, action_id, date(occurred_at, 'localtime');
+DELETE FROM streak;
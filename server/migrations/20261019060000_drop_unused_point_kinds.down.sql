@@ -0,0 +1,2 @@
+-- Reclassified points can't be told apart from other adjustments so there is nothing to undo
+SELECT 1;
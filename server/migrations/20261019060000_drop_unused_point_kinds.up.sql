@@ -0,0 +1,4 @@
+-- Reclassify points given a kind that is no longer supported
+-- Bonuses, expiries and transfers were never recorded as points by the server so any such points
+-- were labeled by clients and are kept as adjustments.
+UPDATE point SET kind = 'adjustment' WHERE kind IN ('bonus', 'expiry', 'transfer');
@@ -1,9 +1,10 @@
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, SqliteConnection, SqlitePool};
 use axum::http::StatusCode;
 use crate::{ errors, model };
 
 /// Insert a new Action into the database
 /// 
+/// - shorthand for an action without a known proposer
 /// - error on empty desc
 /// - error on duplicate desc
 /// - error on other SQL errors
@@ -13,7 +14,28 @@ use crate::{ errors, model };
 /// 
 /// #### Returns
 /// - ***id*** - id of the action
+#[cfg(test)]
 pub async fn insert(db: &SqlitePool, action: &model::CreateAction) -> errors::Result<i64>
+{
+  insert_with(db, action, None).await
+}
+
+/// Insert a new Action into the database recording who proposed it
+/// 
+/// - unapproved actions wait in the review queue see `fetch_pending` and `review`
+/// - error on empty desc
+/// - error on duplicate desc
+/// - error on invalid category
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***action*** - CreateAction struct containing the action data
+/// - ***proposed_by*** - user proposing the action if known
+/// 
+/// #### Returns
+/// - ***id*** - id of the action
+pub async fn insert_with(db: &SqlitePool, action: &model::CreateAction, proposed_by: Option<i64>)
+  -> errors::Result<i64>
 {
   validate_desc(&action.desc)?;
 
@@ -23,10 +45,19 @@ pub async fn insert(db: &SqlitePool, action: &model::CreateAction) -> errors::Re
   let approved = if action.approved.unwrap_or(false) { 1 } else { 0 };
 
   // Create new Action in database
-  let result = sqlx::query(r#"INSERT INTO action (desc, value, category_id, approved) VALUES (?, ?, ?, ?)"#)
-    .bind(&action.desc).bind(value).bind(category_id).bind(approved).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO action (desc, value, category_id, approved, proposed_by)
+      VALUES (?, ?, ?, ?, ?)"#)
+      .bind(&action.desc).bind(value).bind(category_id).bind(approved).bind(proposed_by)
+      .execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Action>(&mut tx, "action", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
   match result {
-    Ok(query) => Ok(query.last_insert_rowid()),
+    Ok(id) => Ok(id),
     Err(e) => {
 
       // Error on duplicates
@@ -53,7 +84,7 @@ pub async fn insert(db: &SqlitePool, action: &model::CreateAction) -> errors::Re
 
 /// Get an action by id from the database
 /// 
-/// - error on not found
+/// - error on not found or in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -63,7 +94,7 @@ pub async fn insert(db: &SqlitePool, action: &model::CreateAction) -> errors::Re
 /// - ***action*** - action entry
 pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Action>
 {
-  let result = sqlx::query_as::<_, model::Action>(r#"SELECT * FROM action WHERE id = ?"#)
+  let result = sqlx::query_as::<_, model::Action>(r#"SELECT * FROM action WHERE id = ? AND deleted_at IS NULL"#)
     .bind(id).fetch_one(db).await;
   match result {
     Ok(action) => Ok(action),
@@ -80,15 +111,45 @@ pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Acti
   }
 }
 
+/// Get a Action by description from the database
+/// 
+/// - error on not found or in the trash
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***desc*** - description of the action to fetch
+/// 
+/// #### Returns
+/// - ***action*** - action entry
+pub async fn fetch_by_desc(db: &SqlitePool, desc: &str) -> errors::Result<model::Action>
+{
+  let result = sqlx::query_as::<_, model::Action>(r#"SELECT * FROM action WHERE desc = ? AND deleted_at IS NULL"#)
+    .bind(desc).fetch_one(db).await;
+  match result {
+    Ok(action) => Ok(action),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Action '{desc}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching action '{desc}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
 /// Get all actions from the database
 /// 
 /// - orders the actions by desc ignoring case
+/// - excludes actions in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
 /// - ***db*** - database connection pool
 /// - ***filter*** - supports:
-///   - ***approved=***
+///   - ***approved=***, ***category_id=***
 /// 
 /// #### Returns
 /// - ***actions*** - actions entries
@@ -98,18 +159,16 @@ pub async fn fetch_all(db: &SqlitePool, filter: model::Filter) ->
   let result = if !filter.any_action_filters() {
 
     // Get all actions when no filter options are specified
-    sqlx::query_as::<_, model::Action>(r#"SELECT * FROM action ORDER BY LOWER(desc)"#)
+    sqlx::query_as::<_, model::Action>(r#"SELECT * FROM action WHERE deleted_at IS NULL
+      ORDER BY LOWER(desc)"#)
       .fetch_all(db).await
   } else {
 
     // Get actions with the given filter
-    let where_clause = filter.to_actions_where_clause(db).await?;
-    let query_str = format!(r#"SELECT * FROM action {where_clause} ORDER BY LOWER(desc)"#);
-    let mut query = sqlx::query_as::<_, model::Action>(&query_str);
-    if let Some(approved) = filter.approved {
-      query = query.bind(approved);
-    }
-    query.fetch_all(db).await
+    let mut query = QueryBuilder::new("SELECT * FROM action");
+    filter.push_actions_where_clause(db, &mut query).await?;
+    query.push(" ORDER BY LOWER(desc)");
+    query.build_query_as::<model::Action>().fetch_all(db).await
   };
 
   match result {
@@ -123,6 +182,93 @@ pub async fn fetch_all(db: &SqlitePool, filter: model::Filter) ->
 
 }
 
+/// Get the actions waiting on a review from the database
+/// 
+/// - oldest first so that proposals are reviewed in the order they were made
+/// - excludes the Unspecified action, actions already reviewed and actions in the trash
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// 
+/// #### Returns
+/// - ***actions*** - actions entries
+pub async fn fetch_pending(db: &SqlitePool) -> errors::Result<Vec<model::Action>>
+{
+  let result = sqlx::query_as::<_, model::Action>(r#"SELECT * FROM action
+    WHERE id != 1 AND approved = 0 AND reviewed_at IS NULL AND deleted_at IS NULL
+    ORDER BY created_at, id"#)
+    .fetch_all(db).await;
+  match result {
+    Ok(actions) => Ok(actions),
+    Err(e) => {
+      let msg = "Error fetching pending actions";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Approve or reject an action waiting on a review
+/// 
+/// - the value and category may be changed while approving
+/// - error on not found, in the trash or not pending
+/// - error on invalid category
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the action
+/// - ***approved*** - whether the action is approved or rejected
+/// - ***review*** - ReviewAction struct containing the changes and note for the proposer
+/// - ***reviewed_by*** - user reviewing the action
+pub async fn review(db: &SqlitePool, id: i64, approved: bool, review: &model::ReviewAction,
+  reviewed_by: Option<i64>) -> errors::Result<()>
+{
+  let existing_action = fetch_by_id(db, id).await?;
+  let (value, category_id) = match approved {
+    true => (review.value.unwrap_or(existing_action.value),
+      review.category_id.unwrap_or(existing_action.category_id)),
+    false => (existing_action.value, existing_action.category_id),
+  };
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Action>(&mut tx, "action", id).await?;
+    let query = sqlx::query(r#"UPDATE action SET value = ?, category_id = ?, approved = ?,
+      note = ?, reviewed_by = ?, reviewed_at = datetime('subsec')
+      WHERE id = ? AND id != 1 AND approved = 0 AND reviewed_at IS NULL AND deleted_at IS NULL"#)
+      .bind(value).bind(category_id).bind(approved).bind(&review.note).bind(reviewed_by).bind(id)
+      .execute(&mut *tx).await?;
+    if query.rows_affected() == 0 {
+      return Ok(false);
+    }
+    super::audit::record::<model::Action>(&mut tx, "action", id, model::AuditAction::Update,
+      before).await?;
+    recategorize(&mut tx, existing_action.category_id, category_id).await?;
+    tx.commit().await?;
+    Ok(true)
+  }.await;
+  match result {
+    Ok(true) => Ok(()),
+    Ok(false) => {
+      let msg = format!("Action with id '{id}' is not pending a review");
+      log::warn!("{msg}");
+      Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg))
+    },
+    Err(e) => {
+      if errors::Error::is_sqlx_foreign_key_constraint_failed(&e) {
+        let msg = format!("Invalid category_id '{category_id}'");
+        log::warn!("{msg}");
+        return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+      }
+      let msg = format!("Error reviewing action with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
 /// Update a Action in the database
 /// 
 /// - error on not found
@@ -143,8 +289,16 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, action: &model::UpdateAction
   validate_desc(&desc)?;
 
   // Update action in database
-  let result = sqlx::query(r#"UPDATE action SET desc = ?, value = ?, category_id = ?, approved = ? WHERE id = ?"#)
-    .bind(&desc).bind(value).bind(category_id).bind(approved).bind(&id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Action>(&mut tx, "action", id).await?;
+    sqlx::query(r#"UPDATE action SET desc = ?, value = ?, category_id = ?, approved = ? WHERE id = ?"#)
+      .bind(&desc).bind(value).bind(category_id).bind(approved).bind(&id).execute(&mut *tx).await?;
+    super::audit::record::<model::Action>(&mut tx, "action", id, model::AuditAction::Update, before)
+      .await?;
+    recategorize(&mut tx, existing_action.category_id, category_id).await?;
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error updating action with id '{id}'");
     log::error!("{msg}");
@@ -153,8 +307,11 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, action: &model::UpdateAction
   Ok(())
 }
 
-/// Delete a Action in the database
+/// Move an action to the trash
 /// 
+/// - points for the action keep pointing at it so history is unchanged
+/// - does nothing if the action is not found or already in the trash
+/// - error on the Unspecified action
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -168,7 +325,17 @@ pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
     return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
   }
 
-  let result = sqlx::query(r#"DELETE from action WHERE id = ?"#).bind(id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Action>(&mut tx, "action", id).await?;
+    let query = sqlx::query(r#"UPDATE action SET deleted_at = datetime('subsec')
+      WHERE id = ? AND deleted_at IS NULL"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::record::<model::Action>(&mut tx, "action", id, model::AuditAction::Delete,
+        before).await?;
+    }
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error deleting action with id '{id}'");
     log::error!("{msg}");
@@ -177,6 +344,50 @@ pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
   Ok(())
 }
 
+/// Restore an action from the trash
+/// 
+/// - error on not found in the trash
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***id*** id of the action to restore
+pub async fn restore_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Action>(&mut tx, "action", id).await?;
+    sqlx::query_scalar::<_, i64>(r#"UPDATE action SET deleted_at = NULL
+      WHERE id = ? AND deleted_at IS NOT NULL RETURNING id"#).bind(id).fetch_one(&mut *tx).await?;
+    super::audit::record::<model::Action>(&mut tx, "action", id, model::AuditAction::Restore,
+      before).await?;
+    tx.commit().await
+  }.await;
+  match result {
+    Ok(()) => Ok(()),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Action with id '{id}' was not found in the trash");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error restoring action with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+// Drop the category streaks when the action moves to another category
+async fn recategorize(conn: &mut SqliteConnection, from: i64, to: i64) -> Result<(), sqlx::Error>
+{
+  if from != to {
+    for category_id in [from, to] {
+      super::streak::invalidate(conn, model::StreakKind::Category, category_id).await?;
+    }
+  }
+  Ok(())
+}
+
 // Helper for desc not given error
 fn validate_desc(desc: &str) -> errors::Result<()>
 {
@@ -227,6 +438,45 @@ mod tests
     assert_eq!(action.desc, "Unspecified");
   }
 
+  #[tokio::test]
+  async fn test_review_pending_actions()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let id1 = insert_with(state.db(), &model::CreateAction::new().with_desc("action1")
+      .with_value(10), Some(user_id)).await.unwrap();
+    let id2 = insert_with(state.db(), &model::CreateAction::new().with_desc("action2"),
+      Some(user_id)).await.unwrap();
+    insert(state.db(), &model::CreateAction::new().with_desc("action3").with_approved(true))
+      .await.unwrap();
+
+    // The Unspecified and approved actions aren't waiting on a review
+    let pending = fetch_pending(state.db()).await.unwrap();
+    assert_eq!(pending.iter().map(|x| x.id).collect::<Vec<_>>(), vec![id1, id2]);
+    assert_eq!(pending[0].proposed_by, Some(user_id));
+
+    // Approving may change the value
+    let review1 = model::ReviewAction { value: Some(5), ..Default::default() };
+    review(state.db(), id1, true, &review1, Some(1)).await.unwrap();
+    let action = fetch_by_id(state.db(), id1).await.unwrap();
+    assert_eq!((action.approved, action.value, action.reviewed_by), (true, 5, Some(1)));
+    assert!(action.reviewed_at.is_some());
+
+    // Rejecting keeps the value and only the note is taken
+    let review2 = model::ReviewAction { value: Some(5), note: Some("too easy".into()),
+      ..Default::default() };
+    review(state.db(), id2, false, &review2, Some(1)).await.unwrap();
+    let action = fetch_by_id(state.db(), id2).await.unwrap();
+    assert_eq!((action.approved, action.value, action.note.as_deref()), (false, 0, Some("too easy")));
+    assert!(fetch_pending(state.db()).await.unwrap().is_empty());
+
+    // Reviewed actions and the Unspecified action can't be reviewed again
+    for id in [id1, id2, 1] {
+      let err = review(state.db(), id, true, &review1, Some(1)).await.unwrap_err().to_http();
+      assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+  }
+
   #[tokio::test]
   async fn test_update_success()
   {
@@ -0,0 +1,275 @@
+use std::future::Future;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+tokio::task_local! {
+  // Actor and request the changes being made belong to
+  static CONTEXT: model::AuditContext;
+}
+
+// Fields that change on every update and would only add noise to the diff
+const IGNORED_FIELDS: [&str; 1] = ["updated_at"];
+
+/// Run the given future with its changes attributed to the given actor and request
+///
+/// - changes made outside of a scope are recorded as anonymous without a request id
+///
+/// #### Parameters
+/// - ***context*** - actor and request id to record
+/// - ***f*** - future making the changes
+pub async fn scope<F: Future>(context: model::AuditContext, f: F) -> F::Output
+{
+  CONTEXT.scope(context, f).await
+}
+
+/// Get a row as JSON to compare before and after a change
+///
+/// #### Parameters
+/// - ***conn*** - connection or transaction the change is being made in
+/// - ***table*** - table the row is in
+/// - ***id*** - id of the row
+///
+/// #### Returns
+/// - ***row*** - the row as JSON, None if it doesn't exist
+pub(crate) async fn snapshot<T>(conn: &mut SqliteConnection, table: &str, id: i64)
+  -> Result<Option<Value>, sqlx::Error>
+where
+  T: for<'r> sqlx::FromRow<'r, SqliteRow> + Serialize + Send + Unpin,
+{
+  let row = sqlx::query_as::<_, T>(&format!("SELECT * FROM {table} WHERE id = ?"))
+    .bind(id).fetch_optional(&mut *conn).await?;
+  row.map(serde_json::to_value).transpose().map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+/// Record a change to a row comparing it against the snapshot taken before the change
+///
+/// - must be called with the transaction making the change so both commit or neither does
+///
+/// #### Parameters
+/// - ***conn*** - transaction the change was made in
+/// - ***table*** - table the row is in, recorded as the entity
+/// - ***id*** - id of the row
+/// - ***action*** - kind of change made
+/// - ***before*** - snapshot taken before the change, None for creates
+pub(crate) async fn record<T>(conn: &mut SqliteConnection, table: &str, id: i64,
+  action: model::AuditAction, before: Option<Value>) -> Result<(), sqlx::Error>
+where
+  T: for<'r> sqlx::FromRow<'r, SqliteRow> + Serialize + Send + Unpin,
+{
+  let after = snapshot::<T>(conn, table, id).await?;
+  insert(conn, table, id, action, before, after).await
+}
+
+/// Insert an audit entry for the given change
+///
+/// - only the fields that differ between ***before*** and ***after*** are kept
+/// - updates that didn't change anything are not recorded
+/// - must be called with the transaction making the change so both commit or neither does
+///
+/// #### Parameters
+/// - ***conn*** - transaction the change was made in
+/// - ***entity*** - kind of entry changed
+/// - ***entity_id*** - id of the entry changed
+/// - ***action*** - kind of change made
+/// - ***before*** - entry before the change, None for creates
+/// - ***after*** - entry after the change, None for hard deletes
+pub(crate) async fn insert(conn: &mut SqliteConnection, entity: &str, entity_id: i64,
+  action: model::AuditAction, before: Option<Value>, after: Option<Value>)
+  -> Result<(), sqlx::Error>
+{
+  let (before, after) = diff(before, after);
+  if action == model::AuditAction::Update && before.is_none() && after.is_none() {
+    return Ok(());
+  }
+
+  let context = CONTEXT.try_with(|x| x.clone()).unwrap_or_default();
+  sqlx::query(r#"INSERT INTO audit (actor_id, entity, entity_id, action, before, after, request_id)
+    VALUES (?, ?, ?, ?, ?, ?, ?)"#)
+    .bind(context.actor_id).bind(entity).bind(entity_id).bind(action)
+    .bind(before.map(|x| x.to_string())).bind(after.map(|x| x.to_string()))
+    .bind(context.request_id).execute(&mut *conn).await?;
+  Ok(())
+}
+
+/// Get a page of audit entries matching the given query
+///
+/// - orders the entries newest first
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***query*** - filters and paging
+///
+/// #### Returns
+/// - ***page*** - the matching entries and the total across all pages
+pub async fn fetch_by_query(db: &SqlitePool, query: &model::AuditQuery)
+  -> errors::Result<model::AuditPage>
+{
+  let offset = query.offset.unwrap_or(0).max(0);
+  let limit = query.limit.unwrap_or(model::AUDIT_PAGE_LIMIT).clamp(1, model::AUDIT_PAGE_MAX);
+
+  let result = async {
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM audit");
+    push_where_clause(&mut count, query);
+    let total = count.build_query_scalar::<i64>().fetch_one(db).await?;
+
+    let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM audit");
+    push_where_clause(&mut select, query);
+    select.push(" ORDER BY id DESC LIMIT ").push_bind(limit).push(" OFFSET ").push_bind(offset);
+    let entries = select.build_query_as::<model::Audit>().fetch_all(db).await?;
+    Ok(model::AuditPage { total, offset, limit, entries })
+  }.await;
+
+  match result {
+    Ok(page) => Ok(page),
+    Err(e) => {
+      let msg = "Error fetching audit entries";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+// Push the query filters onto the audit query
+fn push_where_clause(query: &mut QueryBuilder<'_, Sqlite>, audit: &model::AuditQuery)
+{
+  let mut separator = " WHERE ";
+  let mut next = |query: &mut QueryBuilder<'_, Sqlite>| {
+    query.push(separator);
+    separator = " AND ";
+  };
+  if let Some(actor_id) = audit.actor_id {
+    next(query);
+    query.push("actor_id = ").push_bind(actor_id);
+  }
+  if let Some(entity) = &audit.entity {
+    next(query);
+    query.push("entity = ").push_bind(entity.clone());
+  }
+  if let Some(entity_id) = audit.entity_id {
+    next(query);
+    query.push("entity_id = ").push_bind(entity_id);
+  }
+  if let Some(start) = audit.start_date {
+    next(query);
+    query.push("datetime(created_at) >= datetime(").push_bind(start).push(")");
+  }
+  if let Some(end) = audit.end_date {
+    next(query);
+    query.push("datetime(created_at) <= datetime(").push_bind(end).push(")");
+  }
+}
+
+// Reduce the before and after of a change down to only the fields that differ
+fn diff(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>)
+{
+  let (Some(Value::Object(before)), Some(Value::Object(after))) = (&before, &after) else {
+    return (before, after);
+  };
+  let (mut old, mut new) = (Map::new(), Map::new());
+  for (key, value) in after {
+    if IGNORED_FIELDS.contains(&key.as_str()) || before.get(key) == Some(value) {
+      continue;
+    }
+    old.insert(key.clone(), before.get(key).cloned().unwrap_or(Value::Null));
+    new.insert(key.clone(), value.clone());
+  }
+  if new.is_empty() {
+    return (None, None);
+  }
+  (Some(Value::Object(old)), Some(Value::Object(new)))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+  use serde_json::json;
+
+  #[test]
+  fn test_diff()
+  {
+    let before = json!({"id": 1, "value": 10, "updated_at": "a"});
+    let after = json!({"id": 1, "value": 20, "updated_at": "b"});
+    assert_eq!(diff(Some(before.clone()), Some(after.clone())),
+      (Some(json!({"value": 10})), Some(json!({"value": 20}))));
+    assert_eq!(diff(Some(before.clone()), Some(before.clone())), (None, None));
+    assert_eq!(diff(None, Some(after.clone())), (None, Some(after)));
+    assert_eq!(diff(Some(before.clone()), None), (Some(before), None));
+  }
+
+  #[tokio::test]
+  async fn test_record_changes_with_context()
+  {
+    let state = state::test().await;
+    let context = model::AuditContext { actor_id: Some(1), request_id: Some("abc".to_string()) };
+    let id = scope(context, async {
+      let id = db::point::insert(state.db(), 10, 1, 1).await.unwrap();
+      db::point::update_by_id(state.db(), id, 20, None).await.unwrap();
+      db::point::update_by_id(state.db(), id, 20, None).await.unwrap();
+      id
+    }).await;
+
+    // Changes outside of a scope are anonymous
+    db::point::delete_by_id(state.db(), id).await.unwrap();
+
+    let query = model::AuditQuery { entity: Some("point".to_string()), ..Default::default() };
+    let page = fetch_by_query(state.db(), &query).await.unwrap();
+    assert_eq!(page.total, 3);
+    let actions = page.entries.iter().map(|x| x.action).collect::<Vec<_>>();
+    assert_eq!(actions, vec![model::AuditAction::Delete, model::AuditAction::Update,
+      model::AuditAction::Create]);
+
+    let delete = &page.entries[0];
+    assert_eq!((delete.actor_id, delete.request_id.as_deref()), (None, None));
+    assert_eq!(delete.before.as_ref().unwrap()["deleted_at"], Value::Null);
+    assert!(delete.after.as_ref().unwrap()["deleted_at"].is_string());
+
+    let update = &page.entries[1];
+    assert_eq!((update.actor_id, update.request_id.as_deref()), (Some(1), Some("abc")));
+    assert_eq!(update.before, Some(json!({"value": 10, "base_value": 10})));
+    assert_eq!(update.after, Some(json!({"value": 20, "base_value": 20})));
+    assert!(page.entries[2].before.is_none());
+    assert_eq!(page.entries[2].after.as_ref().unwrap()["value"], 10);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_by_query_filters_and_pages()
+  {
+    let state = state::test().await;
+    let context = model::AuditContext { actor_id: Some(1), request_id: None };
+    scope(context, async {
+      for value in 1..6 {
+        db::point::insert(state.db(), value, 1, 1).await.unwrap();
+      }
+    }).await;
+    db::reward::insert(state.db(), 5, 1).await.unwrap();
+
+    let query = model::AuditQuery { actor_id: Some(1), offset: Some(1), limit: Some(2),
+      ..Default::default() };
+    let page = fetch_by_query(state.db(), &query).await.unwrap();
+    assert_eq!((page.total, page.offset, page.limit), (5, 1, 2));
+    let values = page.entries.iter().map(|x| x.after.as_ref().unwrap()["value"].clone())
+      .collect::<Vec<_>>();
+    assert_eq!(values, vec![json!(4), json!(3)]);
+
+    let query = model::AuditQuery { entity: Some("reward".to_string()), ..Default::default() };
+    assert_eq!(fetch_by_query(state.db(), &query).await.unwrap().total, 1);
+
+    let query = model::AuditQuery { end_date: Some(chrono::Utc::now() - chrono::Duration::hours(1)),
+      ..Default::default() };
+    assert_eq!(fetch_by_query(state.db(), &query).await.unwrap().total, 0);
+  }
+
+  #[tokio::test]
+  async fn test_append_only()
+  {
+    let state = state::test().await;
+    db::point::insert(state.db(), 10, 1, 1).await.unwrap();
+    assert!(sqlx::query("UPDATE audit SET actor_id = 2").execute(state.db()).await.is_err());
+    assert!(sqlx::query("DELETE FROM audit").execute(state.db()).await.is_err());
+  }
+}
@@ -0,0 +1,472 @@
+use axum::http::StatusCode;
+use std::path::Path;
+use sqlx::{
+  sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePoolOptions, SqliteRow}, SqlitePool,
+};
+use crate::{ errors, model };
+
+/// Take a full snapshot of the household data
+///
+/// - the snapshot is read in a single transaction so that it is consistent
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***version*** - latest database migration version
+/// - ***passwords*** - include the password hashes
+///
+/// #### Returns
+/// - ***backup*** - the snapshot
+pub async fn fetch(db: &SqlitePool, version: i64, passwords: bool) -> errors::Result<model::Backup>
+{
+  let mut tx = db.begin().await.map_err(|e| error(e, "Error starting backup"))?;
+  let backup = model::Backup {
+    format: model::BACKUP_FORMAT,
+    version,
+    created_at: chrono::Local::now(),
+    users: fetch_table(&mut tx, "user").await?,
+    roles: fetch_table(&mut tx, "role").await?,
+    user_roles: fetch_table(&mut tx, "user_role").await?,
+    categories: fetch_table(&mut tx, "category").await?,
+    category_parents: fetch_table(&mut tx, "category_parent").await?,
+    actions: fetch_table(&mut tx, "action").await?,
+    points: fetch_table(&mut tx, "point").await?,
+    items: fetch_table(&mut tx, "item").await?,
+    rewards: fetch_table(&mut tx, "reward").await?,
+    redemptions: fetch_table(&mut tx, "redemption").await?,
+    chores: fetch_table(&mut tx, "chore").await?,
+    chore_users: fetch_table(&mut tx, "chore_user").await?,
+    chore_misses: fetch_table(&mut tx, "chore_miss").await?,
+    goals: fetch_table(&mut tx, "goal").await?,
+    badges: fetch_table(&mut tx, "badge").await?,
+    user_badges: fetch_table(&mut tx, "user_badge").await?,
+    bonuses: fetch_table(&mut tx, "bonus").await?,
+    expiries: fetch_table(&mut tx, "expiry").await?,
+    passwords: match passwords {
+      true => Some(fetch_table(&mut tx, "password").await?),
+      false => None,
+    },
+  };
+  tx.commit().await.map_err(|e| error(e, "Error finishing backup"))?;
+  Ok(backup)
+}
+
+/// Replace all household data with the given snapshot
+///
+/// - everything is swapped in a single transaction so a failed restore changes nothing
+/// - when the snapshot has no passwords existing passwords are kept for users with the same
+///   username so that they can still login
+/// - error on backup format or version not matching this server
+/// - error on broken references between the restored rows
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***version*** - latest database migration version
+/// - ***backup*** - the snapshot to restore
+pub async fn restore(db: &SqlitePool, version: i64, backup: &model::Backup) -> errors::Result<()>
+{
+  if backup.format != model::BACKUP_FORMAT {
+    let msg = format!("Backup format '{}' is not supported, expected '{}'",
+      backup.format, model::BACKUP_FORMAT);
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+  if backup.version != version {
+    let msg = format!("Backup version '{}' doesn't match database version '{version}', \
+      upgrade both servers to the same release first", backup.version);
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+
+  let mut tx = db.begin().await.map_err(|e| error(e, "Error starting restore"))?;
+
+  // Hold off on reference checks until everything has been swapped in
+  sqlx::query("PRAGMA defer_foreign_keys = ON").execute(&mut *tx).await
+    .map_err(|e| error(e, "Error deferring foreign keys"))?;
+
+  // Keep existing passwords by username if the backup doesn't carry any
+  let kept_passwords = match backup.passwords {
+    Some(_) => vec![],
+    None => sqlx::query_as::<_, (String, String, String, String)>(r#"SELECT user.username,
+        password.salt, password.hash, password.created_at FROM password
+        INNER JOIN user ON user.id = password.user_id ORDER BY password.id"#)
+      .fetch_all(&mut *tx).await.map_err(|e| error(e, "Error fetching passwords"))?,
+  };
+
+  // Clear out the existing data children first
+  for table in ["expiry", "bonus", "user_badge", "badge", "goal", "streak", "point_day",
+    "chore_miss", "chore_user", "chore", "password", "point", "redemption", "reward", "item",
+    "user_role", "category_parent", "action", "category", "role", "user"]
+  {
+    sqlx::query(&format!("DELETE FROM {table}")).execute(&mut *tx).await
+      .map_err(|e| error(e, &format!("Error clearing {table}")))?;
+  }
+
+  for x in backup.users.iter() {
+    sqlx::query(r#"INSERT INTO user (id, username, email, created_at, updated_at, deleted_at)
+      VALUES (?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.username).bind(&x.email)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .bind(x.deleted_at.map(|x| x.naive_utc()))
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring users"))?;
+  }
+  for x in backup.roles.iter() {
+    sqlx::query(r#"INSERT INTO role (id, name, created_at, updated_at) VALUES (?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.name).bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring roles"))?;
+  }
+  for x in backup.user_roles.iter() {
+    sqlx::query(r#"INSERT INTO user_role (id, user_id, role_id, created_at, updated_at)
+      VALUES (?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.user_id).bind(x.role_id)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring user roles"))?;
+  }
+  for x in backup.categories.iter() {
+    sqlx::query(r#"INSERT INTO category (id, name, created_at, updated_at) VALUES (?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.name).bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring categories"))?;
+  }
+  for x in backup.category_parents.iter() {
+    sqlx::query(r#"INSERT INTO category_parent (id, category_id, parent_id, created_at, updated_at)
+      VALUES (?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.category_id).bind(x.parent_id)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring category parents"))?;
+  }
+  for x in backup.actions.iter() {
+    sqlx::query(r#"INSERT INTO action (id, desc, value, category_id, approved, proposed_by, note,
+      reviewed_by, reviewed_at, created_at, updated_at, deleted_at)
+      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.desc).bind(x.value).bind(x.category_id).bind(x.approved)
+      .bind(x.proposed_by).bind(&x.note).bind(x.reviewed_by)
+      .bind(x.reviewed_at.map(|x| x.naive_utc()))
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .bind(x.deleted_at.map(|x| x.naive_utc()))
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring actions"))?;
+  }
+  for x in backup.points.iter() {
+    sqlx::query(r#"INSERT INTO point (id, value, base_value, multiplier, kind, user_id,
+      action_id, awarded_by, occurred_at, status, note, reviewed_by, reviewed_at, created_at,
+      updated_at, deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.value).bind(x.base_value).bind(x.multiplier).bind(x.kind).bind(x.user_id)
+      .bind(x.action_id).bind(x.awarded_by)
+      .bind(x.occurred_at.naive_utc()).bind(x.status).bind(&x.note).bind(x.reviewed_by)
+      .bind(x.reviewed_at.map(|x| x.naive_utc()))
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .bind(x.deleted_at.map(|x| x.naive_utc()))
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring points"))?;
+  }
+  super::streak::reset(&mut tx, None).await.map_err(|e| error(e, "Error counting streaks"))?;
+  for x in backup.items.iter() {
+    sqlx::query(r#"INSERT INTO item (id, name, desc, cost, stock, period_limit, period, active,
+      created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.name).bind(&x.desc).bind(x.cost).bind(x.stock).bind(x.period_limit)
+      .bind(x.period).bind(x.active).bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring items"))?;
+  }
+  for x in backup.rewards.iter() {
+    sqlx::query(r#"INSERT INTO reward (id, value, user_id, item_id, created_at, updated_at,
+      deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.value).bind(x.user_id).bind(x.item_id)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .bind(x.deleted_at.map(|x| x.naive_utc()))
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring rewards"))?;
+  }
+  for x in backup.redemptions.iter() {
+    sqlx::query(r#"INSERT INTO redemption (id, user_id, item_id, value, reason, status, note,
+      reward_id, decided_by, decided_at, created_at, updated_at)
+      VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.user_id).bind(x.item_id).bind(x.value).bind(&x.reason).bind(x.status)
+      .bind(&x.note).bind(x.reward_id).bind(x.decided_by).bind(x.decided_at.map(|x| x.naive_utc()))
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring redemptions"))?;
+  }
+  for x in backup.chores.iter() {
+    sqlx::query(r#"INSERT INTO chore (id, action_id, schedule, weekdays, every, day_of_month,
+      starts_on, active, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.action_id).bind(x.schedule).bind(&x.weekdays).bind(x.every)
+      .bind(x.day_of_month).bind(x.starts_on).bind(x.active)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring chores"))?;
+  }
+  for x in backup.chore_users.iter() {
+    sqlx::query(r#"INSERT INTO chore_user (id, chore_id, user_id, created_at) VALUES (?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.chore_id).bind(x.user_id).bind(x.created_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring chore users"))?;
+  }
+  for x in backup.chore_misses.iter() {
+    sqlx::query(r#"INSERT INTO chore_miss (id, chore_id, user_id, due_on, point_id, created_at)
+      VALUES (?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.chore_id).bind(x.user_id).bind(x.due_on).bind(x.point_id)
+      .bind(x.created_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring chore misses"))?;
+  }
+  for x in backup.goals.iter() {
+    sqlx::query(r#"INSERT INTO goal (id, user_id, name, target, deadline, item_id, completed_at,
+      created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.user_id).bind(&x.name).bind(x.target).bind(x.deadline).bind(x.item_id)
+      .bind(x.completed_at.map(|x| x.naive_utc()))
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring goals"))?;
+  }
+  for x in backup.badges.iter() {
+    sqlx::query(r#"INSERT INTO badge (id, name, icon, rule, created_at, updated_at)
+      VALUES (?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.name).bind(&x.icon).bind(&x.rule)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring badges"))?;
+  }
+  for x in backup.user_badges.iter() {
+    sqlx::query(r#"INSERT INTO user_badge (id, badge_id, user_id, earned_at) VALUES (?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.badge_id).bind(x.user_id).bind(x.earned_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring user badges"))?;
+  }
+  for x in backup.bonuses.iter() {
+    sqlx::query(r#"INSERT INTO bonus (id, name, multiplier, starts_at, ends_at, category_id,
+      action_id, user_id, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.name).bind(x.multiplier).bind(x.starts_at.naive_utc())
+      .bind(x.ends_at.naive_utc()).bind(x.category_id).bind(x.action_id).bind(x.user_id)
+      .bind(x.created_at.naive_utc()).bind(x.updated_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring bonuses"))?;
+  }
+  for x in backup.expiries.iter() {
+    sqlx::query(r#"INSERT INTO expiry (id, user_id, point_id, value, expired_at, created_at)
+      VALUES (?, ?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(x.user_id).bind(x.point_id).bind(x.value).bind(x.expired_at.naive_utc())
+      .bind(x.created_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring expiries"))?;
+  }
+  for x in backup.passwords.iter().flatten() {
+    sqlx::query(r#"INSERT INTO password (id, salt, hash, user_id, created_at)
+      VALUES (?, ?, ?, ?, ?)"#)
+      .bind(x.id).bind(&x.salt).bind(&x.hash).bind(x.user_id).bind(x.created_at.naive_utc())
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error restoring passwords"))?;
+  }
+  for (username, salt, hash, created_at) in kept_passwords.iter() {
+    sqlx::query(r#"INSERT INTO password (salt, hash, user_id, created_at)
+      SELECT ?, ?, id, ? FROM user WHERE username = ?"#)
+      .bind(salt).bind(hash).bind(created_at).bind(username)
+      .execute(&mut *tx).await.map_err(|e| error(e, "Error keeping passwords"))?;
+  }
+
+  // Verify referential integrity before committing
+  let violations = sqlx::query_as::<_, (String, Option<i64>, String)>(
+    r#"SELECT "table", rowid, parent FROM pragma_foreign_key_check"#)
+    .fetch_all(&mut *tx).await.map_err(|e| error(e, "Error checking foreign keys"))?;
+  if let Some((table, rowid, parent)) = violations.first() {
+    let msg = format!("Backup has {} broken references e.g. {table} with id '{}' references \
+      a missing {parent}", violations.len(), rowid.unwrap_or_default());
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+
+  // Record the restore as a whole rather than every row it replaced
+  let after = serde_json::json!({ "version": backup.version, "created_at": backup.created_at });
+  super::audit::insert(&mut tx, "backup", backup.version, model::AuditAction::Restore, None,
+    Some(after)).await.map_err(|e| error(e, "Error auditing restore"))?;
+
+  tx.commit().await.map_err(|e| error(e, "Error committing restore"))?;
+  log::info!("Restored backup version '{}' taken at {}", backup.version, backup.created_at);
+  Ok(())
+}
+
+/// Write a consistent copy of the live database to the given file
+///
+/// - uses `VACUUM INTO` which is safe to run while the database is in use
+/// - error if the file already exists
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***path*** - file to write the snapshot to
+pub async fn snapshot(db: &SqlitePool, path: &Path) -> errors::Result<()>
+{
+  // Connections are opened with URI filenames so spell out a file URI else the target inherits
+  // the source's URI parameters e.g. in-memory databases never write the file
+  let uri = path.to_string_lossy().replace('%', "%25").replace('?', "%3f").replace('#', "%23");
+  let result = sqlx::query("VACUUM INTO ?")
+    .bind(format!("file:{uri}?mode=rwc"))
+    .execute(db).await;
+
+  match result {
+    Ok(_) => Ok(()),
+    Err(e) => {
+      let msg = format!("Error writing snapshot to '{}'", path.display());
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Verify the integrity of the database snapshot at the given file
+///
+/// - opens the snapshot read only and runs `PRAGMA integrity_check`
+/// - error on the snapshot being corrupt
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***path*** - snapshot file to verify
+pub async fn verify(path: &Path) -> errors::Result<()>
+{
+  let options = SqliteConnectOptions::new().filename(path).read_only(true);
+  let snapshot = SqlitePoolOptions::new().max_connections(1).connect_with(options).await
+    .map_err(|e| error(e, &format!("Error opening snapshot '{}'", path.display())))?;
+  let result = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+    .fetch_all(&snapshot).await;
+  snapshot.close().await;
+
+  let problems = result
+    .map_err(|e| error(e, &format!("Error checking snapshot '{}'", path.display())))?;
+  if problems.len() == 1 && problems[0] == "ok" {
+    return Ok(());
+  }
+  let msg = format!("Snapshot '{}' failed integrity check: {}", path.display(), problems.join("; "));
+  log::error!("{msg}");
+  Err(errors::Error::http(StatusCode::INTERNAL_SERVER_ERROR, &msg))
+}
+
+// Get all rows of the given table in id order
+async fn fetch_table<T>(conn: &mut SqliteConnection, table: &str) -> errors::Result<Vec<T>>
+where
+  T: for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+{
+  sqlx::query_as::<_, T>(&format!("SELECT * FROM {table} ORDER BY id"))
+    .fetch_all(conn).await
+    .map_err(|e| error(e, &format!("Error backing up {table}")))
+}
+
+// Log and convert the SQL error
+fn error(e: sqlx::Error, msg: &str) -> errors::Error
+{
+  log::error!("{msg}");
+  errors::Error::from_sqlx(e, msg)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state, security::auth};
+
+  // Helper to populate a database with a little of everything
+  async fn populate(db: &SqlitePool) -> i64
+  {
+    let user_id = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let creds = auth::hash_password("pass1").unwrap();
+    db::password::insert(db, user_id, &creds.salt, &creds.hash).await.unwrap();
+    let category_id = db::category::insert(db, "category1").await.unwrap();
+    let action_id = db::action::insert(db, &model::CreateAction::new()
+      .with_desc("action1").with_category_id(category_id)).await.unwrap();
+    db::point::insert(db, 10, user_id, action_id).await.unwrap();
+    db::reward::insert(db, 5, user_id).await.unwrap();
+    let item_id = db::item::insert(db, &model::ItemPartial::new("item1", 1).with_stock(3)).await
+      .unwrap();
+    db::item::redeem(db, item_id, user_id).await.unwrap();
+    db::redemption::insert(db, user_id, &model::CreateRedemption { value: Some(1),
+      ..Default::default() }).await.unwrap();
+    user_id
+  }
+
+  #[tokio::test]
+  async fn test_fetch_and_restore_into_fresh_database()
+  {
+    let source = state::test().await;
+    let user_id = populate(source.db()).await;
+    let backup = fetch(source.db(), 1, true).await.unwrap();
+    assert_eq!(backup.users.len(), 2);
+    assert_eq!(backup.passwords.as_ref().unwrap().len(), 2);
+
+    // Round trip through JSON as it would be moved between machines
+    let backup: model::Backup = serde_json::from_str(&serde_json::to_string(&backup).unwrap())
+      .unwrap();
+    let target = state::test().await;
+    db::user::insert(target.db(), "other", "other@foo.com").await.unwrap();
+    restore(target.db(), 1, &backup).await.unwrap();
+
+    let users = db::user::fetch_all(target.db(), model::Filter::default()).await.unwrap();
+    assert_eq!(users.iter().map(|x| x.username.as_str()).collect::<Vec<_>>(), vec!["admin", "user1"]);
+    let points = db::point::fetch_all(target.db()).await.unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].user_id, user_id);
+    let source_points = db::point::fetch_all(source.db()).await.unwrap();
+    assert_eq!(points[0].created_at, source_points[0].created_at);
+    let rewards = db::reward::fetch_all(target.db()).await.unwrap();
+    assert_eq!(rewards.iter().map(|x| x.item_id).collect::<Vec<_>>(), vec![None, Some(1)]);
+    assert_eq!(db::item::fetch_by_id(target.db(), 1).await.unwrap().stock, Some(2));
+    assert_eq!(db::ledger::balance(target.db(), user_id).await.unwrap().held, 1);
+    assert_eq!(db::action::fetch_by_desc(target.db(), "action1").await.unwrap().category_id,
+      backup.categories[1].id);
+    let password = db::password::fetch_active(target.db(), user_id).await.unwrap();
+    auth::verify_password(&model::Credential { salt: password.salt, hash: password.hash },
+      "pass1").unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_restore_without_passwords_keeps_existing()
+  {
+    let source = state::test().await;
+    populate(source.db()).await;
+    let backup = fetch(source.db(), 1, false).await.unwrap();
+    assert!(backup.passwords.is_none());
+
+    // The target admin password is kept for the restored admin user
+    let target = state::test().await;
+    let creds = auth::hash_password("changed").unwrap();
+    db::password::insert(target.db(), 1, &creds.salt, &creds.hash).await.unwrap();
+    restore(target.db(), 1, &backup).await.unwrap();
+
+    let password = db::password::fetch_active(target.db(), 1).await.unwrap();
+    auth::verify_password(&model::Credential { salt: password.salt, hash: password.hash },
+      "changed").unwrap();
+    assert!(db::password::fetch_active(target.db(), 2).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_snapshot_and_verify()
+  {
+    let state = state::test().await;
+    populate(state.db()).await;
+    let dir = std::env::temp_dir().join(format!("oneup-snapshot-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("snapshot.db");
+
+    snapshot(state.db(), &path).await.unwrap();
+    verify(&path).await.unwrap();
+    assert!(snapshot(state.db(), &path).await.is_err());
+
+    // Corrupt the snapshot past the header
+    let mut bytes = std::fs::read(&path).unwrap();
+    let len = bytes.len();
+    bytes[len / 2..].fill(0xff);
+    std::fs::write(&path, bytes).unwrap();
+    assert!(verify(&path).await.is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_restore_failure_incompatible_version()
+  {
+    let state = state::test().await;
+    let backup = fetch(state.db(), 1, false).await.unwrap();
+
+    let err = restore(state.db(), 2, &backup).await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+  }
+
+  #[tokio::test]
+  async fn test_restore_failure_broken_references_changes_nothing()
+  {
+    let state = state::test().await;
+    populate(state.db()).await;
+    let mut backup = fetch(state.db(), 1, false).await.unwrap();
+    backup.users.retain(|x| x.username != "user1");
+
+    let err = restore(state.db(), 1, &backup).await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(db::point::fetch_all(state.db()).await.unwrap().len(), 1);
+    assert!(db::user::fetch_by_handle(state.db(), "user1").await.is_ok());
+  }
+}
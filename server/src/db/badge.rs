@@ -0,0 +1,478 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+// Points that count toward badges, only approved points that aren't penalties or in the trash
+const COUNTED: &str = r#"point.user_id = ?1 AND point.value > 0 AND point.status = 'approved'
+  AND point.deleted_at IS NULL"#;
+
+/// Insert a new badge into the database
+///
+/// - users already meeting the rule are awarded the badge right away
+/// - error on invalid badge see `validate`
+/// - error on duplicate name
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***badge*** - badge to insert
+///
+/// #### Returns
+/// - ***id*** - id of the badge
+pub async fn insert(db: &SqlitePool, badge: &model::BadgePartial) -> errors::Result<i64>
+{
+  validate(db, badge).await?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO badge (name, icon, rule) VALUES (?, ?, ?)"#)
+      .bind(&badge.name).bind(&badge.icon).bind(sqlx::types::Json(&badge.rule))
+      .execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Badge>(&mut tx, "badge", id, model::AuditAction::Create, None)
+      .await?;
+    evaluate_all(&mut tx).await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
+  match result {
+    Ok(id) => Ok(id),
+    Err(e) => Err(write_error(e, badge, &format!("Error inserting badge '{}'", badge.name))),
+  }
+}
+
+/// Get a badge by id from the database
+///
+/// - error on not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the badge
+///
+/// #### Returns
+/// - ***badge*** - the badge entry
+pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Badge>
+{
+  let result = sqlx::query_as::<_, model::Badge>(r#"SELECT * FROM badge WHERE id = ?"#)
+    .bind(id).fetch_one(db).await;
+  match result {
+    Ok(badge) => Ok(badge),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Badge with id '{id}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching badge with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get all badges from the database
+///
+/// - orders the badges by id
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+///
+/// #### Returns
+/// - ***badges*** - the badge entries
+pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Badge>>
+{
+  let result = sqlx::query_as::<_, model::Badge>(r#"SELECT * FROM badge ORDER BY id"#)
+    .fetch_all(db).await;
+  match result {
+    Ok(badges) => Ok(badges),
+    Err(e) => {
+      let msg = "Error fetching badges";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Get the badges the given user has earned
+///
+/// - orders the badges by when they were earned
+/// - error on user not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+///
+/// #### Returns
+/// - ***badges*** - the earned badges
+pub async fn fetch_by_user_id(db: &SqlitePool, user_id: i64)
+  -> errors::Result<Vec<model::EarnedBadge>>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let result = sqlx::query_as::<_, model::EarnedBadge>(r#"SELECT badge.id AS badge_id,
+    badge.name, badge.icon, user_badge.earned_at FROM user_badge
+    JOIN badge ON badge.id = user_badge.badge_id WHERE user_badge.user_id = ?
+    ORDER BY user_badge.earned_at, user_badge.id"#).bind(user_id).fetch_all(db).await;
+  match result {
+    Ok(badges) => Ok(badges),
+    Err(e) => {
+      let msg = format!("Error fetching badges for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Update a badge in the database
+///
+/// - all fields are replaced with the given values
+/// - badges already earned are kept, users meeting the new rule are awarded the badge right away
+/// - error on not found
+/// - error on invalid badge see `validate`
+/// - error on duplicate name
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the badge
+/// - ***badge*** - new values for the badge
+pub async fn update_by_id(db: &SqlitePool, id: i64, badge: &model::BadgePartial)
+  -> errors::Result<()>
+{
+  fetch_by_id(db, id).await?;
+  validate(db, badge).await?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Badge>(&mut tx, "badge", id).await?;
+    sqlx::query(r#"UPDATE badge SET name = ?, icon = ?, rule = ? WHERE id = ?"#)
+      .bind(&badge.name).bind(&badge.icon).bind(sqlx::types::Json(&badge.rule)).bind(id)
+      .execute(&mut *tx).await?;
+    super::audit::record::<model::Badge>(&mut tx, "badge", id, model::AuditAction::Update, before)
+      .await?;
+    evaluate_all(&mut tx).await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    return Err(write_error(e, badge, &format!("Error updating badge with id '{id}'")));
+  }
+  Ok(())
+}
+
+/// Delete a badge from the database
+///
+/// - the awards of the badge go with it
+/// - does nothing if the badge is not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the badge
+pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Badge>(&mut tx, "badge", id).await?;
+    let query = sqlx::query(r#"DELETE FROM badge WHERE id = ?"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::insert(&mut tx, "badge", id, model::AuditAction::Delete, before, None).await?;
+    }
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error deleting badge with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Award the badges the owner of the given points now meets the rules of
+///
+/// - used inside the transaction changing the points after the streaks were kept up to date
+///
+/// #### Parameters
+/// - ***conn*** - transaction that changed the points
+/// - ***point_id*** - id of the points
+pub(crate) async fn evaluate_by_point(conn: &mut SqliteConnection, point_id: i64)
+  -> Result<(), sqlx::Error>
+{
+  let user_id = sqlx::query_scalar::<_, i64>(r#"SELECT user_id FROM point WHERE id = ?"#)
+    .bind(point_id).fetch_optional(&mut *conn).await?;
+  match user_id {
+    Some(user_id) => evaluate(conn, user_id).await,
+    None => Ok(()),
+  }
+}
+
+// Award the badges the given user now meets the rules of
+//
+// - badges already earned aren't checked again, trashed users aren't awarded anything
+async fn evaluate(conn: &mut SqliteConnection, user_id: i64) -> Result<(), sqlx::Error>
+{
+  let badges = sqlx::query_as::<_, model::Badge>(r#"SELECT * FROM badge
+    WHERE EXISTS (SELECT 1 FROM user WHERE id = ?1 AND deleted_at IS NULL)
+    AND id NOT IN (SELECT badge_id FROM user_badge WHERE user_id = ?1) ORDER BY id"#)
+    .bind(user_id).fetch_all(&mut *conn).await?;
+  for badge in badges {
+    if !meets(conn, user_id, &badge.rule).await? {
+      continue;
+    }
+    let id = sqlx::query(r#"INSERT INTO user_badge (badge_id, user_id) VALUES (?, ?)"#)
+      .bind(badge.id).bind(user_id).execute(&mut *conn).await?.last_insert_rowid();
+    super::audit::record::<model::UserBadge>(&mut *conn, "user_badge", id,
+      model::AuditAction::Create, None).await?;
+    log::info!("User with id '{user_id}' earned badge '{}'", badge.name);
+  }
+  Ok(())
+}
+
+// Award the badges every user now meets the rules of
+async fn evaluate_all(conn: &mut SqliteConnection) -> Result<(), sqlx::Error>
+{
+  let user_ids = sqlx::query_scalar::<_, i64>(r#"SELECT id FROM user WHERE deleted_at IS NULL"#)
+    .fetch_all(&mut *conn).await?;
+  for user_id in user_ids {
+    evaluate(conn, user_id).await?;
+  }
+  Ok(())
+}
+
+// Check if the user meets the rule
+async fn meets(conn: &mut SqliteConnection, user_id: i64, rule: &model::BadgeRule)
+  -> Result<bool, sqlx::Error>
+{
+  let value = match rule.metric {
+    model::BadgeMetric::Streak => {
+      let keys = match (rule.action_id, rule.category_id) {
+        (Some(action_id), _) => vec![(model::StreakKind::Action, action_id)],
+        (None, Some(category_id)) => vec![(model::StreakKind::Category, category_id)],
+        (None, None) => sqlx::query_scalar::<_, i64>(r#"SELECT DISTINCT action_id FROM point_day
+          WHERE user_id = ?"#).bind(user_id).fetch_all(&mut *conn).await?
+          .into_iter().map(|x| (model::StreakKind::Action, x)).collect(),
+      };
+      let mut longest = 0;
+      for (kind, ref_id) in keys {
+        longest = longest.max(super::streak::longest(conn, user_id, kind, ref_id).await?);
+      }
+      longest
+    },
+    model::BadgeMetric::Points | model::BadgeMetric::Count => {
+      let measure = match rule.metric {
+        model::BadgeMetric::Points => "COALESCE(SUM(point.value), 0)",
+        _ => "COUNT(*)",
+      };
+      let since = rule.days.map(|x| Utc::now() - chrono::Duration::days(x));
+      sqlx::query_scalar::<_, i64>(&format!(r#"SELECT {measure} FROM point
+        JOIN action ON action.id = point.action_id WHERE {COUNTED}
+        AND (?2 IS NULL OR point.action_id = ?2) AND (?3 IS NULL OR action.category_id = ?3)
+        AND (?4 IS NULL OR datetime(point.occurred_at) >= datetime(?4))"#))
+        .bind(user_id).bind(rule.action_id).bind(rule.category_id).bind(since)
+        .fetch_one(&mut *conn).await?
+    },
+  };
+  Ok(value >= rule.threshold)
+}
+
+// Map write errors to the responses callers can act on
+//
+// - error on duplicate name
+// - error on other SQL errors
+fn write_error(e: sqlx::Error, badge: &model::BadgePartial, msg: &str) -> errors::Error
+{
+  if errors::Error::is_sqlx_unique_violation(&e) {
+    let msg = format!("Badge '{}' already exists", badge.name);
+    log::warn!("{msg}");
+    return errors::Error::from_sqlx(e, &msg);
+  }
+  log::error!("{msg}");
+  errors::Error::from_sqlx(e, msg)
+}
+
+// Ensure the badge values make sense before writing them
+//
+// - error on empty name or icon
+// - error on threshold less than one or days less than one
+// - error on both action_id and category_id given or days given for a streak
+// - error on action or category not found
+async fn validate(db: &SqlitePool, badge: &model::BadgePartial) -> errors::Result<()>
+{
+  let rule = &badge.rule;
+  let msg = match rule.metric {
+    _ if badge.name.trim().is_empty() => "Badge name is required",
+    _ if badge.icon.trim().is_empty() => "Badge icon is required",
+    _ if rule.threshold < 1 => "Badge rule threshold must be at least 1",
+    _ if rule.days.is_some_and(|x| x < 1) => "Badge rule days must be at least 1",
+    _ if rule.action_id.is_some() && rule.category_id.is_some() =>
+      "Badge rules take an action_id or a category_id but not both",
+    model::BadgeMetric::Streak if rule.days.is_some() => "Streak badge rules don't take days",
+    _ => {
+      if let Some(action_id) = rule.action_id {
+        super::action::fetch_by_id(db, action_id).await?;
+      }
+      if let Some(category_id) = rule.category_id {
+        super::category::fetch_by_id(db, category_id).await?;
+      }
+      return Ok(());
+    },
+  };
+  log::warn!("{msg}");
+  Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use chrono::Local;
+  use crate::{db, state};
+
+  // Helper to create approved points for the given action that occurred the given days ago
+  async fn points_days_ago(db: &SqlitePool, value: i64, user_id: i64, action_id: i64, days: i64)
+    -> i64
+  {
+    let occurred_at = Local::now() - chrono::Duration::days(days);
+    db::point::insert_with(db, &model::CreatePoints { value, user_id, action_id,
+      occurred_at: Some(occurred_at), kind: None }, None, model::PointsStatus::Approved).await
+      .unwrap()
+  }
+
+  // Helper to create a badge with the given rule
+  async fn badge(db: &SqlitePool, name: &str, rule: model::BadgeRule) -> i64
+  {
+    insert(db, &model::BadgePartial { name: name.to_string(), icon: name.to_string(), rule }).await
+      .unwrap()
+  }
+
+  // Helper to get the ids of the badges the user earned
+  async fn earned(db: &SqlitePool, user_id: i64) -> Vec<i64>
+  {
+    fetch_by_user_id(db, user_id).await.unwrap().iter().map(|x| x.badge_id).collect()
+  }
+
+  #[tokio::test]
+  async fn test_points_total()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let id = badge(state.db(), "first 100", model::BadgeRule::new(model::BadgeMetric::Points, 100))
+      .await;
+
+    // Penalties, pending points and rejected points don't count
+    points_days_ago(state.db(), 60, user_id, 1, 0).await;
+    points_days_ago(state.db(), -20, user_id, 1, 0).await;
+    for status in [model::PointsStatus::Pending, model::PointsStatus::Rejected] {
+      db::point::insert_with(state.db(), &model::CreatePoints { value: 50, user_id, action_id: 1,
+        occurred_at: None, kind: None }, None, status).await.unwrap();
+    }
+    assert!(earned(state.db(), user_id).await.is_empty());
+
+    let earned_id = points_days_ago(state.db(), 40, user_id, 1, 3).await;
+    assert_eq!(earned(state.db(), user_id).await, vec![id]);
+
+    // Earned badges are kept
+    db::point::delete_by_id(state.db(), earned_id).await.unwrap();
+    assert_eq!(earned(state.db(), user_id).await, vec![id]);
+  }
+
+  #[tokio::test]
+  async fn test_count_in_category_within_days()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let category_id = db::category::insert(state.db(), "Kitchen").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new().with_desc("dishes")
+      .with_category_id(category_id)).await.unwrap();
+    let rule = model::BadgeRule::new(model::BadgeMetric::Count, 3).with_category_id(category_id)
+      .with_days(7);
+    let id = badge(state.db(), "kitchen hand", rule).await;
+
+    // Points outside the window or the category don't count
+    points_days_ago(state.db(), 1, user_id, action_id, 10).await;
+    points_days_ago(state.db(), 1, user_id, 1, 1).await;
+    points_days_ago(state.db(), 1, user_id, action_id, 2).await;
+    points_days_ago(state.db(), 1, user_id, action_id, 1).await;
+    assert!(earned(state.db(), user_id).await.is_empty());
+    points_days_ago(state.db(), 1, user_id, action_id, 0).await;
+    assert_eq!(earned(state.db(), user_id).await, vec![id]);
+  }
+
+  #[tokio::test]
+  async fn test_streak_and_new_badges_awarded_to_existing_history()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let other_id = db::user::insert(state.db(), "user2", "user2@foo.com").await.unwrap();
+    for days in [6, 5, 4, 3, 2, 1] {
+      points_days_ago(state.db(), 1, user_id, 1, days).await;
+    }
+    points_days_ago(state.db(), 1, other_id, 1, 0).await;
+
+    let id = badge(state.db(), "7-day streak", model::BadgeRule::new(model::BadgeMetric::Streak, 7))
+      .await;
+    let early_id = badge(state.db(), "3-day streak", model::BadgeRule::new(
+      model::BadgeMetric::Streak, 3).with_action_id(1)).await;
+    assert_eq!(earned(state.db(), user_id).await, vec![early_id]);
+    assert!(earned(state.db(), other_id).await.is_empty());
+
+    points_days_ago(state.db(), 1, user_id, 1, 0).await;
+    assert_eq!(earned(state.db(), user_id).await, vec![early_id, id]);
+  }
+
+  #[tokio::test]
+  async fn test_streak_from_pending_not_awarded()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let id = badge(state.db(), "7-day streak", model::BadgeRule::new(model::BadgeMetric::Streak, 7))
+      .await;
+
+    // Back filled points waiting on review don't build a streak
+    let mut ids = vec![];
+    for days in 0..7 {
+      ids.push(db::point::insert_with(state.db(), &model::CreatePoints { value: 1, user_id,
+        action_id: 1, occurred_at: Some(Local::now() - chrono::Duration::days(days)), kind: None },
+        None, model::PointsStatus::Pending).await.unwrap());
+    }
+    assert!(earned(state.db(), user_id).await.is_empty());
+    db::point::review(state.db(), &ids[1..], model::PointsStatus::Rejected, None, None).await
+      .unwrap();
+    assert!(earned(state.db(), user_id).await.is_empty());
+
+    // Approving the points earns the badge
+    for days in 1..7 {
+      points_days_ago(state.db(), 1, user_id, 1, days).await;
+    }
+    assert!(earned(state.db(), user_id).await.is_empty());
+    db::point::review(state.db(), &ids[..1], model::PointsStatus::Approved, None, None).await
+      .unwrap();
+    assert_eq!(earned(state.db(), user_id).await, vec![id]);
+  }
+
+  #[tokio::test]
+  async fn test_insert_failure_invalid()
+  {
+    let state = state::test().await;
+    let partial = |rule| model::BadgePartial { name: "badge".to_string(),
+      icon: "star".to_string(), rule };
+    for (rule, msg) in [
+      (model::BadgeRule::new(model::BadgeMetric::Points, 0),
+        "Badge rule threshold must be at least 1"),
+      (model::BadgeRule::new(model::BadgeMetric::Count, 1).with_days(0),
+        "Badge rule days must be at least 1"),
+      (model::BadgeRule::new(model::BadgeMetric::Count, 1).with_action_id(1).with_category_id(1),
+        "Badge rules take an action_id or a category_id but not both"),
+      (model::BadgeRule::new(model::BadgeMetric::Streak, 7).with_days(7),
+        "Streak badge rules don't take days"),
+    ] {
+      let err = insert(state.db(), &partial(rule)).await.unwrap_err().to_http();
+      assert_eq!((err.status, err.msg.as_str()), (StatusCode::UNPROCESSABLE_ENTITY, msg));
+    }
+    let rule = model::BadgeRule::new(model::BadgeMetric::Count, 1).with_category_id(99);
+    let err = insert(state.db(), &partial(rule)).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+    assert!(fetch_all(state.db()).await.unwrap().is_empty());
+  }
+}
@@ -0,0 +1,350 @@
+use axum::http::StatusCode;
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+/// Insert a new bonus into the database
+///
+/// - applies to points created from then on, existing points are never changed
+/// - error on invalid bonus see `validate`
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***bonus*** - bonus to insert
+///
+/// #### Returns
+/// - ***id*** - id of the bonus
+pub async fn insert(db: &SqlitePool, bonus: &model::BonusPartial) -> errors::Result<i64>
+{
+  validate(db, bonus).await?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO bonus (name, multiplier, starts_at, ends_at, category_id,
+      action_id, user_id) VALUES (?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(&bonus.name).bind(bonus.multiplier).bind(bonus.starts_at.naive_utc())
+      .bind(bonus.ends_at.naive_utc()).bind(bonus.category_id).bind(bonus.action_id)
+      .bind(bonus.user_id).execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Bonus>(&mut tx, "bonus", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
+  match result {
+    Ok(id) => Ok(id),
+    Err(e) => {
+      let msg = format!("Error inserting bonus '{}'", bonus.name);
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get a bonus by id from the database
+///
+/// - error on not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the bonus
+///
+/// #### Returns
+/// - ***bonus*** - the bonus entry
+pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Bonus>
+{
+  let result = sqlx::query_as::<_, model::Bonus>(r#"SELECT * FROM bonus WHERE id = ?"#)
+    .bind(id).fetch_one(db).await;
+  match result {
+    Ok(bonus) => Ok(bonus),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Bonus with id '{id}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching bonus with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get all bonuses from the database
+///
+/// - orders the bonuses by when they start
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+///
+/// #### Returns
+/// - ***bonuses*** - the bonus entries
+pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Bonus>>
+{
+  let result = sqlx::query_as::<_, model::Bonus>(r#"SELECT * FROM bonus
+    ORDER BY datetime(starts_at), id"#).fetch_all(db).await;
+  match result {
+    Ok(bonuses) => Ok(bonuses),
+    Err(e) => {
+      let msg = "Error fetching bonuses";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Update a bonus in the database
+///
+/// - all fields are replaced with the given values
+/// - points already created keep the multiplier they were given
+/// - error on not found
+/// - error on invalid bonus see `validate`
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the bonus
+/// - ***bonus*** - new values for the bonus
+pub async fn update_by_id(db: &SqlitePool, id: i64, bonus: &model::BonusPartial)
+  -> errors::Result<()>
+{
+  fetch_by_id(db, id).await?;
+  validate(db, bonus).await?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Bonus>(&mut tx, "bonus", id).await?;
+    sqlx::query(r#"UPDATE bonus SET name = ?, multiplier = ?, starts_at = ?, ends_at = ?,
+      category_id = ?, action_id = ?, user_id = ? WHERE id = ?"#)
+      .bind(&bonus.name).bind(bonus.multiplier).bind(bonus.starts_at.naive_utc())
+      .bind(bonus.ends_at.naive_utc()).bind(bonus.category_id).bind(bonus.action_id)
+      .bind(bonus.user_id).bind(id).execute(&mut *tx).await?;
+    super::audit::record::<model::Bonus>(&mut tx, "bonus", id, model::AuditAction::Update, before)
+      .await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error updating bonus with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Delete a bonus from the database
+///
+/// - points already created keep the multiplier they were given
+/// - does nothing if the bonus is not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the bonus
+pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Bonus>(&mut tx, "bonus", id).await?;
+    let query = sqlx::query(r#"DELETE FROM bonus WHERE id = ?"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::insert(&mut tx, "bonus", id, model::AuditAction::Delete, before, None).await?;
+    }
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error deleting bonus with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Preview the value the given points would be created with
+///
+/// - the same bonus is picked as when the points are created see `find`
+/// - error on user not found
+/// - error on action not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***points*** - points that would be created
+///
+/// #### Returns
+/// - ***preview*** - base value, multiplier and resulting value
+pub async fn preview(db: &SqlitePool, points: &model::CreatePoints)
+  -> errors::Result<model::BonusPreview>
+{
+  super::user::fetch_by_id(db, points.user_id).await?;
+  super::action::fetch_by_id(db, points.action_id).await?;
+
+  let result = async {
+    let mut conn = db.acquire().await?;
+    find(&mut conn, points).await
+  }.await;
+  match result {
+    Ok(bonus) => {
+      let multiplier = bonus.as_ref().map_or(1.0, |x| x.multiplier);
+      Ok(model::BonusPreview {
+        base_value: points.value,
+        multiplier,
+        value: apply(points.value, multiplier),
+        bonus_id: bonus.map(|x| x.id),
+      })
+    },
+    Err(e) => {
+      let msg = "Error previewing bonus";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Find the bonus that applies to the given points
+///
+/// - bonuses apply to points occurring from their start up to but not including their end
+/// - when several bonuses apply the one with the largest multiplier is used
+/// - penalties are never multiplied so no bonus applies to values below one
+/// - only awards are multiplied, other kinds such as adjustments keep their value
+///
+/// #### Parameters
+/// - ***conn*** - connection or transaction to look in
+/// - ***points*** - points being created, occurring now when no time is given
+///
+/// #### Returns
+/// - ***bonus*** - the bonus to apply if any
+pub(crate) async fn find(conn: &mut SqliteConnection, points: &model::CreatePoints)
+  -> Result<Option<model::Bonus>, sqlx::Error>
+{
+  if points.value < 1 || points.kind.is_some_and(|x| x != model::PointsKind::Award) {
+    return Ok(None);
+  }
+  sqlx::query_as::<_, model::Bonus>(r#"SELECT * FROM bonus
+    WHERE datetime(starts_at) <= datetime(COALESCE(?1, datetime('subsec')))
+    AND datetime(COALESCE(?1, datetime('subsec'))) < datetime(ends_at)
+    AND (user_id IS NULL OR user_id = ?2) AND (action_id IS NULL OR action_id = ?3)
+    AND (category_id IS NULL OR category_id = (SELECT category_id FROM action WHERE id = ?3))
+    ORDER BY multiplier DESC, id LIMIT 1"#)
+    .bind(points.occurred_at.map(|x| x.naive_utc())).bind(points.user_id).bind(points.action_id)
+    .fetch_optional(&mut *conn).await
+}
+
+/// Apply the given multiplier to a base value
+///
+/// - rounds to the nearest whole point, penalties are left as they are
+pub(crate) fn apply(base_value: i64, multiplier: f64) -> i64
+{
+  if base_value < 1 {
+    return base_value;
+  }
+  (base_value as f64 * multiplier).round() as i64
+}
+
+// Ensure the bonus values make sense before writing them
+//
+// - error on empty name
+// - error on multiplier not above zero
+// - error on the window ending before it starts
+// - error on both action_id and category_id given
+// - error on category, action or user not found
+async fn validate(db: &SqlitePool, bonus: &model::BonusPartial) -> errors::Result<()>
+{
+  let msg = match bonus {
+    _ if bonus.name.trim().is_empty() => "Bonus name is required",
+    _ if !bonus.multiplier.is_finite() || bonus.multiplier <= 0.0 =>
+      "Bonus multiplier must be above zero",
+    _ if bonus.ends_at <= bonus.starts_at => "Bonus must end after it starts",
+    _ if bonus.action_id.is_some() && bonus.category_id.is_some() =>
+      "Bonuses take an action_id or a category_id but not both",
+    _ => {
+      if let Some(category_id) = bonus.category_id {
+        super::category::fetch_by_id(db, category_id).await?;
+      }
+      if let Some(action_id) = bonus.action_id {
+        super::action::fetch_by_id(db, action_id).await?;
+      }
+      if let Some(user_id) = bonus.user_id {
+        super::user::fetch_by_id(db, user_id).await?;
+      }
+      return Ok(());
+    },
+  };
+  log::warn!("{msg}");
+  Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use chrono::{Duration, Local};
+  use super::*;
+  use crate::{db, model, state};
+
+  #[tokio::test]
+  async fn test_insert_applies_largest_multiplier()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user_id = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let category_id = db::category::insert(db, "Kitchen").await.unwrap();
+    let action_id = db::action::insert(db, &model::CreateAction::new().with_desc("Dishes")
+      .with_category_id(category_id)).await.unwrap();
+    let now = Local::now();
+    insert(db, &model::BonusPartial::new("Double points", 2.0, now - Duration::days(1),
+      now + Duration::days(1))).await.unwrap();
+    let kitchen = insert(db, &model::BonusPartial::new("Kitchen week", 2.5,
+      now - Duration::days(1), now + Duration::days(6)).with_category_id(category_id))
+      .await.unwrap();
+
+    // Kitchen points get the larger kitchen bonus
+    let id = db::point::insert(db, 3, user_id, action_id).await.unwrap();
+    let points = db::point::fetch_by_id(db, id).await.unwrap();
+    assert_eq!((points.value, points.base_value, points.multiplier), (8, 3, 2.5));
+
+    // Other points only get the global bonus
+    let id = db::point::insert(db, 3, user_id, 1).await.unwrap();
+    let points = db::point::fetch_by_id(db, id).await.unwrap();
+    assert_eq!((points.value, points.base_value, points.multiplier), (6, 3, 2.0));
+
+    // Changing the value keeps the multiplier the points were given
+    db::point::update_by_id(db, id, 5, None).await.unwrap();
+    let points = db::point::fetch_by_id(db, id).await.unwrap();
+    assert_eq!((points.value, points.base_value, points.multiplier), (10, 5, 2.0));
+
+    // Penalties and points outside the window aren't multiplied
+    let id = db::point::insert(db, -3, user_id, action_id).await.unwrap();
+    let points = db::point::fetch_by_id(db, id).await.unwrap();
+    assert_eq!((points.value, points.multiplier), (-3, 1.0));
+    let later = preview(db, &model::CreatePoints { value: 3, user_id, action_id,
+      occurred_at: Some(now + Duration::days(7)), kind: None }).await.unwrap();
+    assert_eq!((later.value, later.bonus_id), (3, None));
+    let sooner = preview(db, &model::CreatePoints { value: 3, user_id, action_id,
+      occurred_at: Some(now + Duration::days(2)), kind: None }).await.unwrap();
+    assert_eq!((sooner.value, sooner.bonus_id), (8, Some(kitchen)));
+  }
+
+  #[tokio::test]
+  async fn test_insert_user_bonus_and_validate()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user_id = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let now = Local::now();
+    insert(db, &model::BonusPartial::new("Birthday", 3.0, now - Duration::hours(1),
+      now + Duration::hours(1)).with_user_id(user_id)).await.unwrap();
+
+    let id = db::point::insert(db, 2, 1, 1).await.unwrap();
+    assert_eq!(db::point::fetch_by_id(db, id).await.unwrap().value, 2);
+    let id = db::point::insert(db, 2, user_id, 1).await.unwrap();
+    assert_eq!(db::point::fetch_by_id(db, id).await.unwrap().value, 6);
+
+    let err = insert(db, &model::BonusPartial::new("Zero", 0.0, now, now + Duration::hours(1)))
+      .await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+    let err = insert(db, &model::BonusPartial::new("Backwards", 2.0, now, now))
+      .await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(fetch_all(db).await.unwrap().len(), 1);
+  }
+}
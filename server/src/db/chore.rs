@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+use axum::http::StatusCode;
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+// Points for the chore's action done on the day, penalties and rejected points don't count
+const DONE: &str = r#"EXISTS (SELECT 1 FROM point WHERE user_id = ?1 AND action_id = ?2
+  AND value > 0 AND status != 'rejected' AND deleted_at IS NULL
+  AND datetime(occurred_at) >= datetime(?3) AND datetime(occurred_at) < datetime(?4))"#;
+
+/// Insert a new chore into the database assigning it to the given users
+///
+/// - ***starts_on*** defaults to today
+/// - error on invalid chore see `validate`
+/// - error on action not found
+/// - error on any of the users not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***chore*** - chore to insert
+///
+/// #### Returns
+/// - ***id*** - id of the chore
+pub async fn insert(db: &SqlitePool, chore: &model::ChorePartial) -> errors::Result<i64>
+{
+  validate(chore)?;
+  super::action::fetch_by_id(db, chore.action_id).await?;
+  let starts_on = chore.starts_on.unwrap_or_else(|| Local::now().date_naive());
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO chore (action_id, schedule, weekdays, every, day_of_month,
+      starts_on, active) VALUES (?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(chore.action_id).bind(chore.schedule)
+      .bind(chore.weekdays.as_ref().map(sqlx::types::Json)).bind(chore.every)
+      .bind(chore.day_of_month).bind(starts_on).bind(chore.active)
+      .execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Chore>(&mut tx, "chore", id, model::AuditAction::Create, None)
+      .await?;
+    assign(&mut tx, id, &chore.user_ids).await?;
+    super::streak::invalidate(&mut tx, model::StreakKind::Action, chore.action_id).await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
+  match result {
+    Ok(id) => Ok(id),
+    Err(e) => Err(write_error(e, "Error inserting chore")),
+  }
+}
+
+/// Get a chore by id from the database
+///
+/// - error on not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the chore
+///
+/// #### Returns
+/// - ***chore*** - the chore entry with its assigned users
+pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Chore>
+{
+  let result = async {
+    let mut chore = sqlx::query_as::<_, model::Chore>(r#"SELECT * FROM chore WHERE id = ?"#)
+      .bind(id).fetch_one(db).await?;
+    chore.user_ids = sqlx::query_scalar::<_, i64>(r#"SELECT user_id FROM chore_user
+      WHERE chore_id = ? ORDER BY user_id"#).bind(id).fetch_all(db).await?;
+    Ok::<_, sqlx::Error>(chore)
+  }.await;
+  match result {
+    Ok(chore) => Ok(chore),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Chore with id '{id}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching chore with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get all chores from the database
+///
+/// - orders the chores by id
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+///
+/// #### Returns
+/// - ***chores*** - the chore entries with their assigned users
+pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Chore>>
+{
+  let result = async {
+    let mut chores = sqlx::query_as::<_, model::Chore>(r#"SELECT * FROM chore ORDER BY id"#)
+      .fetch_all(db).await?;
+    let assignments = sqlx::query_as::<_, model::ChoreUser>(r#"SELECT * FROM chore_user
+      ORDER BY user_id"#).fetch_all(db).await?;
+    for chore in chores.iter_mut() {
+      chore.user_ids = assignments.iter().filter(|x| x.chore_id == chore.id)
+        .map(|x| x.user_id).collect();
+    }
+    Ok::<_, sqlx::Error>(chores)
+  }.await;
+  match result {
+    Ok(chores) => Ok(chores),
+    Err(e) => {
+      let msg = "Error fetching chores";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Update a chore in the database
+///
+/// - all fields are replaced with the given values including the assigned users
+/// - users that stay assigned keep their assignment so earlier occurrences can still be missed
+/// - error on not found
+/// - error on invalid chore see `validate`
+/// - error on action not found
+/// - error on any of the users not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the chore
+/// - ***chore*** - new values for the chore
+pub async fn update_by_id(db: &SqlitePool, id: i64, chore: &model::ChorePartial)
+  -> errors::Result<()>
+{
+  let existing = fetch_by_id(db, id).await?;
+  validate(chore)?;
+  super::action::fetch_by_id(db, chore.action_id).await?;
+  let starts_on = chore.starts_on.unwrap_or(existing.starts_on);
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Chore>(&mut tx, "chore", id).await?;
+    sqlx::query(r#"UPDATE chore SET action_id = ?, schedule = ?, weekdays = ?, every = ?,
+      day_of_month = ?, starts_on = ?, active = ? WHERE id = ?"#)
+      .bind(chore.action_id).bind(chore.schedule)
+      .bind(chore.weekdays.as_ref().map(sqlx::types::Json)).bind(chore.every)
+      .bind(chore.day_of_month).bind(starts_on).bind(chore.active).bind(id)
+      .execute(&mut *tx).await?;
+    super::audit::record::<model::Chore>(&mut tx, "chore", id, model::AuditAction::Update, before)
+      .await?;
+    assign(&mut tx, id, &chore.user_ids).await?;
+    for action_id in [existing.action_id, chore.action_id] {
+      super::streak::invalidate(&mut tx, model::StreakKind::Action, action_id).await?;
+    }
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    return Err(write_error(e, &format!("Error updating chore with id '{id}'")));
+  }
+  Ok(())
+}
+
+/// Delete a chore from the database
+///
+/// - its assignments and missed occurrences go with it, penalty points are kept
+/// - does nothing if the chore is not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the chore
+pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    assign(&mut tx, id, &[]).await?;
+    let before = super::audit::snapshot::<model::Chore>(&mut tx, "chore", id).await?;
+    let action_id = sqlx::query_scalar::<_, i64>(r#"DELETE FROM chore WHERE id = ?
+      RETURNING action_id"#).bind(id).fetch_optional(&mut *tx).await?;
+    if let Some(action_id) = action_id {
+      super::audit::insert(&mut tx, "chore", id, model::AuditAction::Delete, before, None).await?;
+      super::streak::invalidate(&mut tx, model::StreakKind::Action, action_id).await?;
+    }
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error deleting chore with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Get the chores the given user still has to do on the given day
+///
+/// - a chore is done once the user has points for its action that occurred that day, pending
+///   points count so that a chore waiting on approval isn't listed again
+/// - excludes inactive chores and chores whose action is in the trash
+/// - error on user not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+/// - ***date*** - day to check, usually today
+///
+/// #### Returns
+/// - ***due*** - the outstanding chores
+pub async fn fetch_due(db: &SqlitePool, user_id: i64, date: NaiveDate)
+  -> errors::Result<Vec<model::ChoreDue>>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let (start, end) = (day_start(date), day_start(date + Days::new(1)));
+
+  let result = async {
+    let chores = sqlx::query_as::<_, model::Chore>(r#"SELECT chore.* FROM chore
+      JOIN chore_user ON chore_user.chore_id = chore.id WHERE chore_user.user_id = ?
+      ORDER BY chore.id"#).bind(user_id).fetch_all(db).await?;
+    let mut due = vec![];
+    for chore in chores.iter().filter(|x| is_due(x, date)) {
+      let entry = sqlx::query_as::<_, model::ChoreDue>(&format!(r#"SELECT ?5 AS chore_id,
+        id AS action_id, desc, value, ?6 AS due_on FROM action
+        WHERE id = ?2 AND deleted_at IS NULL AND NOT {DONE}"#))
+        .bind(user_id).bind(chore.action_id).bind(start).bind(end).bind(chore.id).bind(date)
+        .fetch_optional(db).await?;
+      due.extend(entry);
+    }
+    Ok::<_, sqlx::Error>(due)
+  }.await;
+  match result {
+    Ok(due) => Ok(due),
+    Err(e) => {
+      let msg = format!("Error fetching due chores for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Record the chore occurrences that were missed on the given days
+///
+/// - occurrences before the user was assigned the chore are skipped as are trashed users
+/// - each occurrence is only missed once so days can be checked again safely
+/// - the penalty is taken as approved points for the chore's action dated the missed day, the miss,
+///   the penalty and the link between them are recorded together so neither is ever lost
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***from*** - first day to check
+/// - ***to*** - day to stop at, not checked itself
+/// - ***penalty*** - points to take for each missed occurrence if any
+///
+/// #### Returns
+/// - ***count*** - number of occurrences newly missed
+pub async fn mark_missed(db: &SqlitePool, from: NaiveDate, to: NaiveDate, penalty: Option<i64>)
+  -> errors::Result<u64>
+{
+  let chores = fetch_all(db).await?.into_iter().map(|x| (x.id, x))
+    .collect::<HashMap<_, _>>();
+  let result = sqlx::query_as::<_, model::ChoreUser>(r#"SELECT chore_user.* FROM chore_user
+    JOIN user ON user.id = chore_user.user_id WHERE user.deleted_at IS NULL"#)
+    .fetch_all(db).await;
+  let assignments = result.map_err(|e| {
+    let msg = "Error fetching chore assignments";
+    log::error!("{msg}");
+    errors::Error::from_sqlx(e, msg)
+  })?;
+
+  let mut count = 0;
+  let penalty = penalty.filter(|x| *x > 0);
+  for date in from.iter_days().take_while(|x| *x < to) {
+    for assignment in assignments.iter() {
+      let Some(chore) = chores.get(&assignment.chore_id) else { continue };
+      if !is_due(chore, date) || date < assignment.created_at.date_naive() {
+        continue;
+      }
+      if miss(db, chore, assignment.user_id, date, penalty).await? {
+        count += 1;
+      }
+    }
+  }
+  Ok(count)
+}
+
+// Record the occurrence as missed unless it was done or already missed taking the penalty if any
+//
+// - returns whether the miss was newly recorded
+async fn miss(db: &SqlitePool, chore: &model::Chore, user_id: i64, date: NaiveDate,
+  penalty: Option<i64>) -> errors::Result<bool>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query_scalar::<_, i64>(&format!(r#"INSERT INTO chore_miss (chore_id, user_id,
+      due_on) SELECT ?5, ?1, ?6 WHERE NOT {DONE} ON CONFLICT DO NOTHING RETURNING id"#))
+      .bind(user_id).bind(chore.action_id).bind(day_start(date))
+      .bind(day_start(date + Days::new(1))).bind(chore.id).bind(date)
+      .fetch_optional(&mut *tx).await?;
+    let Some(id) = id else { return Ok(false) };
+    if let Some(penalty) = penalty {
+      let points = model::CreatePoints { value: -penalty, user_id, action_id: chore.action_id,
+        occurred_at: Some(day_start(date).with_timezone(&Local)),
+        kind: Some(model::PointsKind::Penalty) };
+      let point_id = super::point::insert_in(&mut tx, &points, None,
+        model::PointsStatus::Approved, None, None).await?;
+      sqlx::query(r#"UPDATE chore_miss SET point_id = ? WHERE id = ?"#)
+        .bind(point_id).bind(id).execute(&mut *tx).await?;
+    }
+    super::audit::record::<model::ChoreMiss>(&mut tx, "chore_miss", id,
+      model::AuditAction::Create, None).await?;
+    tx.commit().await?;
+    Ok(true)
+  }.await;
+  result.map_err(|e| {
+    let msg = format!("Error recording missed chore with id '{}'", chore.id);
+    log::error!("{msg}");
+    errors::Error::from_sqlx(e, &msg)
+  })
+}
+
+/// Get the active chores for the given action assigned to the given user
+///
+/// - used to count streaks in scheduled occurrences rather than days
+///
+/// #### Parameters
+/// - ***conn*** - connection or transaction to read with
+/// - ***user_id*** - id of the user
+/// - ***action_id*** - id of the action
+///
+/// #### Returns
+/// - ***chores*** - the chore entries without their assigned users
+pub(crate) async fn fetch_assigned(conn: &mut SqliteConnection, user_id: i64, action_id: i64)
+  -> Result<Vec<model::Chore>, sqlx::Error>
+{
+  sqlx::query_as::<_, model::Chore>(r#"SELECT chore.* FROM chore
+    JOIN chore_user ON chore_user.chore_id = chore.id
+    WHERE chore_user.user_id = ? AND chore.action_id = ? AND chore.active ORDER BY chore.id"#)
+    .bind(user_id).bind(action_id).fetch_all(&mut *conn).await
+}
+
+// Replace the users assigned to the chore, keeping the assignments of users that stay
+async fn assign(conn: &mut SqliteConnection, chore_id: i64, user_ids: &[i64])
+  -> Result<(), sqlx::Error>
+{
+  let mut user_ids = user_ids.to_vec();
+  user_ids.sort();
+  user_ids.dedup();
+  let existing = sqlx::query_as::<_, model::ChoreUser>(r#"SELECT * FROM chore_user
+    WHERE chore_id = ?"#).bind(chore_id).fetch_all(&mut *conn).await?;
+  for x in existing.iter().filter(|x| !user_ids.contains(&x.user_id)) {
+    let before = super::audit::snapshot::<model::ChoreUser>(&mut *conn, "chore_user", x.id).await?;
+    sqlx::query(r#"DELETE FROM chore_user WHERE id = ?"#).bind(x.id).execute(&mut *conn).await?;
+    super::audit::insert(&mut *conn, "chore_user", x.id, model::AuditAction::Delete, before, None)
+      .await?;
+  }
+  for user_id in user_ids.iter().filter(|x| !existing.iter().any(|y| y.user_id == **x)) {
+    let id = sqlx::query(r#"INSERT INTO chore_user (chore_id, user_id) VALUES (?, ?)"#)
+      .bind(chore_id).bind(user_id).execute(&mut *conn).await?.last_insert_rowid();
+    super::audit::record::<model::ChoreUser>(&mut *conn, "chore_user", id,
+      model::AuditAction::Create, None).await?;
+  }
+  Ok(())
+}
+
+// Check if the chore falls due on the given day
+pub(crate) fn is_due(chore: &model::Chore, date: NaiveDate) -> bool
+{
+  if !chore.active || date < chore.starts_on {
+    return false;
+  }
+  match chore.schedule {
+    model::ChoreSchedule::Daily => true,
+    model::ChoreSchedule::Weekly => chore.weekdays.as_ref()
+      .is_some_and(|x| x.contains(&date.weekday().number_from_monday())),
+    model::ChoreSchedule::Interval => chore.every
+      .is_some_and(|x| (date - chore.starts_on).num_days() % x == 0),
+    model::ChoreSchedule::Monthly => chore.day_of_month
+      .is_some_and(|x| date.day() == x.min(last_day_of_month(date))),
+  }
+}
+
+// Get the number of the last day in the month of the given date
+fn last_day_of_month(date: NaiveDate) -> u32
+{
+  let first = date.with_day(1).unwrap_or(date);
+  (first + chrono::Months::new(1) - Days::new(1)).day()
+}
+
+// Get the start of the given day in UTC to compare against the database
+//
+// - midnight is used as is unless daylight saving skips it in which case the hour after is used
+fn day_start(date: NaiveDate) -> DateTime<Utc>
+{
+  let midnight = date.and_time(NaiveTime::MIN);
+  Local.from_local_datetime(&midnight).earliest()
+    .or_else(|| Local.from_local_datetime(&(midnight + chrono::Duration::hours(1))).earliest())
+    .unwrap_or_else(|| Utc.from_utc_datetime(&midnight).with_timezone(&Local)).to_utc()
+}
+
+// Map write errors to the responses callers can act on
+//
+// - error on any of the users not found
+// - error on other SQL errors
+fn write_error(e: sqlx::Error, msg: &str) -> errors::Error
+{
+  if errors::Error::is_sqlx_foreign_key_constraint_failed(&e) {
+    let msg = "Chore user_ids must all be existing users";
+    log::warn!("{msg}");
+    return errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg);
+  }
+  log::error!("{msg}");
+  errors::Error::from_sqlx(e, msg)
+}
+
+// Ensure the chore values make sense before writing them
+//
+// - error on the schedule missing the field it uses or given a field it doesn't use
+// - error on weekdays outside of 1 to 7, every less than one or day_of_month outside of 1 to 31
+fn validate(chore: &model::ChorePartial) -> errors::Result<()>
+{
+  let given = (chore.weekdays.is_some(), chore.every.is_some(), chore.day_of_month.is_some());
+  let msg = match chore.schedule {
+    model::ChoreSchedule::Daily if given != (false, false, false) =>
+      "Daily chores don't take weekdays, every or day_of_month",
+    model::ChoreSchedule::Weekly if given != (true, false, false) =>
+      "Weekly chores take weekdays only",
+    model::ChoreSchedule::Interval if given != (false, true, false) =>
+      "Interval chores take every only",
+    model::ChoreSchedule::Monthly if given != (false, false, true) =>
+      "Monthly chores take day_of_month only",
+    _ if chore.weekdays.as_ref()
+      .is_some_and(|x| x.is_empty() || x.iter().any(|x| !(1..=7).contains(x))) =>
+      "Chore weekdays must be between 1 for Monday and 7 for Sunday",
+    _ if chore.every.is_some_and(|x| x < 1) => "Chore every must be at least 1",
+    _ if chore.day_of_month.is_some_and(|x| !(1..=31).contains(&x)) =>
+      "Chore day_of_month must be between 1 and 31",
+    _ => return Ok(()),
+  };
+  log::warn!("{msg}");
+  Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  // Helper to build a chore as stored from the given partial
+  fn chore(partial: model::ChorePartial) -> model::Chore
+  {
+    let now = Local::now();
+    model::Chore { id: 1, action_id: partial.action_id, schedule: partial.schedule,
+      weekdays: partial.weekdays.map(sqlx::types::Json), every: partial.every,
+      day_of_month: partial.day_of_month, starts_on: partial.starts_on.unwrap(),
+      active: partial.active, user_ids: vec![], created_at: now, updated_at: now }
+  }
+
+  // Helper to create points for the given action that occurred on the given day
+  async fn points_on(db: &SqlitePool, value: i64, user_id: i64, action_id: i64, date: NaiveDate,
+    status: model::PointsStatus) -> i64
+  {
+    let occurred_at = day_start(date).with_timezone(&Local) + chrono::Duration::hours(12);
+    db::point::insert_with(db, &model::CreatePoints { value, user_id, action_id,
+      occurred_at: Some(occurred_at), kind: None }, None, status).await.unwrap()
+  }
+
+  #[test]
+  fn test_is_due()
+  {
+    // Thursday the 1st of October 2026
+    let start = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+    let partial = |schedule| model::ChorePartial::new(1, schedule).with_starts_on(start);
+
+    let daily = chore(partial(model::ChoreSchedule::Daily));
+    assert!(is_due(&daily, date(1)) && is_due(&daily, date(2)));
+    assert!(!is_due(&daily, date(1) - Days::new(1)));
+
+    let weekdays = chore(partial(model::ChoreSchedule::Weekly).with_weekdays(vec![1, 2, 3, 4, 5]));
+    assert!(is_due(&weekdays, date(2)));
+    assert!(!is_due(&weekdays, date(3)) && !is_due(&weekdays, date(4)));
+
+    let interval = chore(partial(model::ChoreSchedule::Interval).with_every(3));
+    assert_eq!([1, 2, 3, 4, 7].map(|x| is_due(&interval, date(x))), [true, false, false, true, true]);
+
+    // Months without the day fall on their last day instead
+    let monthly = chore(partial(model::ChoreSchedule::Monthly).with_day_of_month(31));
+    assert!(is_due(&monthly, date(31)) && !is_due(&monthly, date(30)));
+    assert!(is_due(&monthly, NaiveDate::from_ymd_opt(2026, 11, 30).unwrap()));
+    assert!(is_due(&monthly, NaiveDate::from_ymd_opt(2027, 2, 28).unwrap()));
+
+    let mut inactive = chore(partial(model::ChoreSchedule::Daily));
+    inactive.active = false;
+    assert!(!is_due(&inactive, date(2)));
+  }
+
+  #[tokio::test]
+  async fn test_insert_failure_invalid()
+  {
+    let state = state::test().await;
+    for (partial, msg) in [
+      (model::ChorePartial::new(1, model::ChoreSchedule::Daily).with_every(2),
+        "Daily chores don't take weekdays, every or day_of_month"),
+      (model::ChorePartial::new(1, model::ChoreSchedule::Weekly),
+        "Weekly chores take weekdays only"),
+      (model::ChorePartial::new(1, model::ChoreSchedule::Weekly).with_weekdays(vec![0]),
+        "Chore weekdays must be between 1 for Monday and 7 for Sunday"),
+      (model::ChorePartial::new(1, model::ChoreSchedule::Interval).with_every(0),
+        "Chore every must be at least 1"),
+      (model::ChorePartial::new(1, model::ChoreSchedule::Monthly).with_day_of_month(32),
+        "Chore day_of_month must be between 1 and 31"),
+      (model::ChorePartial::new(1, model::ChoreSchedule::Daily).with_user_ids(vec![1, 99]),
+        "Chore user_ids must all be existing users"),
+    ] {
+      let err = insert(state.db(), &partial).await.unwrap_err().to_http();
+      assert_eq!((err.status, err.msg.as_str()), (StatusCode::UNPROCESSABLE_ENTITY, msg));
+    }
+    assert!(fetch_all(state.db()).await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_update_keeps_assignments()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let partial = model::ChorePartial::new(1, model::ChoreSchedule::Daily)
+      .with_user_ids(vec![user_id, 1, user_id]);
+    let id = insert(state.db(), &partial).await.unwrap();
+    assert_eq!(fetch_by_id(state.db(), id).await.unwrap().user_ids, vec![1, user_id]);
+    let assigned = sqlx::query_scalar::<_, i64>("SELECT id FROM chore_user WHERE user_id = ?")
+      .bind(user_id).fetch_one(state.db()).await.unwrap();
+
+    let partial = model::ChorePartial::new(1, model::ChoreSchedule::Interval).with_every(2)
+      .with_user_ids(vec![user_id]);
+    update_by_id(state.db(), id, &partial).await.unwrap();
+    let chore = fetch_by_id(state.db(), id).await.unwrap();
+    assert_eq!((chore.schedule, chore.every, chore.user_ids),
+      (model::ChoreSchedule::Interval, Some(2), vec![user_id]));
+    let kept = sqlx::query_scalar::<_, i64>("SELECT id FROM chore_user WHERE user_id = ?")
+      .bind(user_id).fetch_one(state.db()).await.unwrap();
+    assert_eq!(kept, assigned);
+
+    delete_by_id(state.db(), id).await.unwrap();
+    let err = fetch_by_id(state.db(), id).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_due()
+  {
+    let state = state::test().await;
+    let today = Local::now().date_naive();
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new().with_desc("bed")
+      .with_value(2)).await.unwrap();
+    let partial = |action_id| model::ChorePartial::new(action_id, model::ChoreSchedule::Daily)
+      .with_user_ids(vec![user_id]);
+    let id = insert(state.db(), &partial(action_id)).await.unwrap();
+    let other_id = insert(state.db(), &partial(1)).await.unwrap();
+    insert(state.db(), &model::ChorePartial::new(1, model::ChoreSchedule::Daily)).await.unwrap();
+
+    let due = fetch_due(state.db(), user_id, today).await.unwrap();
+    assert_eq!(due.iter().map(|x| x.chore_id).collect::<Vec<_>>(), vec![id, other_id]);
+    assert_eq!((due[0].action_id, due[0].desc.as_str(), due[0].value, due[0].due_on),
+      (action_id, "bed", 2, today));
+
+    // Rejected points and points from yesterday don't count but pending points do
+    points_on(state.db(), 2, user_id, action_id, today, model::PointsStatus::Rejected).await;
+    points_on(state.db(), 2, user_id, action_id, today - Days::new(1),
+      model::PointsStatus::Approved).await;
+    assert_eq!(fetch_due(state.db(), user_id, today).await.unwrap().len(), 2);
+    points_on(state.db(), 2, user_id, action_id, today, model::PointsStatus::Pending).await;
+    let due = fetch_due(state.db(), user_id, today).await.unwrap();
+    assert_eq!(due.iter().map(|x| x.chore_id).collect::<Vec<_>>(), vec![other_id]);
+
+    let err = fetch_due(state.db(), -1, today).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_mark_missed_with_penalty()
+  {
+    let state = state::test().await;
+    let today = Local::now().date_naive();
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let id = insert(state.db(), &model::ChorePartial::new(1, model::ChoreSchedule::Daily)
+      .with_starts_on(today - Days::new(10)).with_user_ids(vec![user_id])).await.unwrap();
+
+    // Nothing before the assignment was made is missed
+    let from = today - Days::new(3);
+    assert_eq!(mark_missed(state.db(), from, today, Some(5)).await.unwrap(), 0);
+
+    let assigned_at = day_start(today - Days::new(5));
+    sqlx::query("UPDATE chore_user SET created_at = ?").bind(assigned_at.naive_utc())
+      .execute(state.db()).await.unwrap();
+    points_on(state.db(), 1, user_id, 1, today - Days::new(2), model::PointsStatus::Approved)
+      .await;
+    assert_eq!(mark_missed(state.db(), from, today, Some(5)).await.unwrap(), 2);
+    assert_eq!(mark_missed(state.db(), from, today, Some(5)).await.unwrap(), 0);
+
+    let misses = sqlx::query_as::<_, model::ChoreMiss>("SELECT * FROM chore_miss ORDER BY due_on")
+      .fetch_all(state.db()).await.unwrap();
+    assert_eq!(misses.iter().map(|x| (x.chore_id, x.due_on)).collect::<Vec<_>>(),
+      vec![(id, today - Days::new(3)), (id, today - Days::new(1))]);
+    assert!(misses.iter().all(|x| x.point_id.is_some()));
+    assert_eq!(db::ledger::balance(state.db(), user_id).await.unwrap().earned, -9);
+
+    // Penalties land on the day that was missed rather than when the check ran
+    for miss in misses.iter() {
+      let points = db::point::fetch_by_id(state.db(), miss.point_id.unwrap()).await.unwrap();
+      assert_eq!(points.occurred_at.date_naive(), miss.due_on);
+    }
+
+    // The penalty doesn't count as doing today's chore
+    assert_eq!(fetch_due(state.db(), user_id, today).await.unwrap().len(), 1);
+  }
+}
@@ -0,0 +1,290 @@
+use std::collections::{HashMap, VecDeque};
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Local, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+// A user's approved points, rewards and held redemptions in the order they happened. Points in
+// the trash and points that aren't approved are left out, as they are from the balance.
+const HISTORY: &str = r#"SELECT id, value, at FROM (
+    SELECT 'points' AS kind, id, value, occurred_at AS at FROM point
+      WHERE user_id = ?1 AND status = 'approved' AND deleted_at IS NULL
+    UNION ALL
+    SELECT 'reward' AS kind, id, -value AS value, created_at AS at FROM reward
+      WHERE user_id = ?1 AND deleted_at IS NULL
+    UNION ALL
+    SELECT 'redemption' AS kind, id, -value AS value, created_at AS at FROM redemption
+      WHERE user_id = ?1 AND status = 'requested'
+  ) ORDER BY datetime(at), kind, id"#;
+
+// Entry of a user's history, only the ids of points are used
+#[derive(sqlx::FromRow)]
+struct Entry {
+  id: i64,
+  value: i64,
+  at: DateTime<Utc>,
+}
+
+// Points still able to expire, ***remaining*** being what spending hasn't used up
+struct Lot {
+  point_id: i64,
+  value: i64,
+  remaining: i64,
+  expires_at: DateTime<Utc>,
+}
+
+// Points that expired or will expire, ***written*** once there is an expiry entry for them
+struct Lapse {
+  point_id: i64,
+  value: i64,
+  expires_at: DateTime<Utc>,
+  written: bool,
+}
+
+/// Get the points of the given user that will expire soon unless spent first
+///
+/// - points past their lifetime that haven't been expired yet are included as well
+/// - nothing expires when no lifetime is configured
+/// - error on user not found
+/// - error on days less than one
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+/// - ***lifetime*** - days points can go unspent, see `Config::points_expiry_days`
+/// - ***policy*** - how the points expire
+/// - ***query*** - how many days ahead to look
+///
+/// #### Returns
+/// - ***expiring*** - the points soonest to expire first
+pub async fn fetch_expiring(db: &SqlitePool, user_id: i64, lifetime: Option<i64>,
+  policy: model::ExpiryPolicy, query: &model::ExpiringQuery)
+  -> errors::Result<Vec<model::ExpiringPoints>>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let days = query.days.unwrap_or(model::EXPIRING_DAYS);
+  if days < 1 {
+    let msg = "Expiring days must be at least 1";
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg));
+  }
+  let Some(lifetime) = lifetime else {
+    return Ok(vec![]);
+  };
+
+  let result = async {
+    let mut conn = db.acquire().await?;
+    lapses(&mut conn, user_id, lifetime, policy, Utc::now() + Duration::days(days)).await
+  }.await;
+  match result {
+    Ok(lapses) => Ok(lapses.into_iter().filter(|x| !x.written && x.value > 0)
+      .map(|x| model::ExpiringPoints { point_id: x.point_id, value: x.value,
+        expires_at: x.expires_at.with_timezone(&Local) }).collect()),
+    Err(e) => {
+      let msg = format!("Error fetching expiring points for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Expire the points that reached their lifetime unspent
+///
+/// - writes an expiry entry for each points entry that expired which the balance and ledger take
+///   away from the user's points
+/// - points are only expired once so this can be called as often as needed
+/// - users in the trash are skipped
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***lifetime*** - days points can go unspent, see `Config::points_expiry_days`
+/// - ***policy*** - how the points expire
+///
+/// #### Returns
+/// - ***expiries*** - the expiry entries written
+pub async fn expire(db: &SqlitePool, lifetime: i64, policy: model::ExpiryPolicy)
+  -> errors::Result<Vec<model::Expiry>>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let user_ids = sqlx::query_scalar::<_, i64>(r#"SELECT id FROM user WHERE deleted_at IS NULL
+      AND id IN (SELECT user_id FROM point WHERE status = 'approved' AND deleted_at IS NULL)"#)
+      .fetch_all(&mut *tx).await?;
+    let mut expiries = vec![];
+    for user_id in user_ids {
+      for lapse in lapses(&mut tx, user_id, lifetime, policy, Utc::now()).await? {
+        if lapse.written || lapse.value < 1 {
+          continue;
+        }
+        let expiry = sqlx::query_as::<_, model::Expiry>(r#"INSERT INTO expiry (user_id, point_id,
+          value, expired_at) VALUES (?, ?, ?, ?) RETURNING *"#)
+          .bind(user_id).bind(lapse.point_id).bind(lapse.value).bind(lapse.expires_at.naive_utc())
+          .fetch_one(&mut *tx).await?;
+        super::audit::record::<model::Expiry>(&mut tx, "expiry", expiry.id,
+          model::AuditAction::Create, None).await?;
+        expiries.push(expiry);
+      }
+    }
+    tx.commit().await?;
+    Ok(expiries)
+  }.await;
+  match result {
+    Ok(expiries) => Ok(expiries),
+    Err(e) => {
+      let msg = "Error expiring points";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+// Step through the user's history to find the points that expire up until the given time
+//
+// - spending and penalties use up the oldest points first for the fifo policy
+// - points already expired keep the value that was written for them
+async fn lapses(conn: &mut SqliteConnection, user_id: i64, lifetime: i64,
+  policy: model::ExpiryPolicy, until: DateTime<Utc>) -> Result<Vec<Lapse>, sqlx::Error>
+{
+  let history = sqlx::query_as::<_, Entry>(HISTORY).bind(user_id).fetch_all(&mut *conn).await?;
+  let written: HashMap<i64, i64> = sqlx::query_as::<_, (i64, i64)>(r#"SELECT point_id, value
+    FROM expiry WHERE user_id = ?"#).bind(user_id).fetch_all(&mut *conn).await?
+    .into_iter().collect();
+
+  // Points all share the same lifetime so they expire in the order they occurred
+  let mut lots: VecDeque<Lot> = VecDeque::new();
+  let mut lapses = vec![];
+  let mut balance = 0;
+  for entry in history {
+    lapse(&mut lots, &mut lapses, &mut balance, &written, policy, entry.at);
+    balance += entry.value;
+    if entry.value > 0 {
+      lots.push_back(Lot { point_id: entry.id, value: entry.value, remaining: entry.value,
+        expires_at: entry.at + Duration::days(lifetime) });
+      continue;
+    }
+    let mut used = -entry.value;
+    for lot in lots.iter_mut() {
+      if used == 0 {
+        break;
+      }
+      let take = lot.remaining.min(used);
+      lot.remaining -= take;
+      used -= take;
+    }
+  }
+  lapse(&mut lots, &mut lapses, &mut balance, &written, policy, until);
+  Ok(lapses)
+}
+
+// Expire the lots that reached their lifetime by the given time
+fn lapse(lots: &mut VecDeque<Lot>, lapses: &mut Vec<Lapse>, balance: &mut i64,
+  written: &HashMap<i64, i64>, policy: model::ExpiryPolicy, at: DateTime<Utc>)
+{
+  while lots.front().is_some_and(|x| x.expires_at <= at) {
+    let Some(lot) = lots.pop_front() else {
+      break;
+    };
+    let value = match written.get(&lot.point_id) {
+      Some(value) => *value,
+      None => match policy {
+        model::ExpiryPolicy::Fifo => lot.remaining,
+        model::ExpiryPolicy::Fixed => lot.value.min(*balance).max(0),
+      },
+    };
+    *balance -= value;
+
+    // Later spending can't use up expired points, the fixed policy doesn't track what is left
+    if policy == model::ExpiryPolicy::Fifo {
+      let mut over = value - lot.remaining;
+      for lot in lots.iter_mut() {
+        if over <= 0 {
+          break;
+        }
+        let take = lot.remaining.min(over);
+        lot.remaining -= take;
+        over -= take;
+      }
+    }
+    lapses.push(Lapse { point_id: lot.point_id, value, expires_at: lot.expires_at,
+      written: written.contains_key(&lot.point_id) });
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  // Helper to create points that occurred the given number of days ago
+  async fn points_days_ago(db: &SqlitePool, value: i64, user_id: i64, days: i64) -> i64
+  {
+    let occurred_at = Local::now() - Duration::days(days);
+    db::point::insert_with(db, &model::CreatePoints { value, user_id, action_id: 1,
+      occurred_at: Some(occurred_at), kind: None }, None, model::PointsStatus::Approved).await
+      .unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_expire_fifo()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user_id = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    points_days_ago(db, 10, user_id, 100).await;
+    let next = points_days_ago(db, 5, user_id, 98).await;
+    points_days_ago(db, -12, user_id, 97).await;
+    let soon = points_days_ago(db, 7, user_id, 85).await;
+
+    // The penalty used up the oldest points first
+    let query = model::ExpiringQuery::default();
+    let policy = model::ExpiryPolicy::Fifo;
+    let expiring = fetch_expiring(db, user_id, Some(90), policy, &query).await.unwrap();
+    let values: Vec<_> = expiring.iter().map(|x| (x.point_id, x.value)).collect();
+    assert_eq!(values, vec![(next, 3), (soon, 7)]);
+
+    // Only what was left expires and only once
+    let expiries = expire(db, 90, policy).await.unwrap();
+    assert_eq!(expiries.iter().map(|x| (x.point_id, x.value)).collect::<Vec<_>>(),
+      vec![(next, 3)]);
+    assert!(expire(db, 90, policy).await.unwrap().is_empty());
+    let balance = db::ledger::balance(db, user_id).await.unwrap();
+    assert_eq!((balance.earned, balance.expired, balance.available), (10, 3, 7));
+
+    // The expiry shows in the ledger as the newest entry
+    let page = db::ledger::fetch_by_user_id(db, user_id, &model::LedgerQuery::default())
+      .await.unwrap();
+    let newest = &page.entries[0];
+    assert_eq!((newest.kind, newest.id, newest.value, newest.balance),
+      (model::LedgerKind::Expiry, expiries[0].id, -3, 7));
+  }
+
+  #[tokio::test]
+  async fn test_expire_fixed()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user_id = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let old = points_days_ago(db, 10, user_id, 100).await;
+    points_days_ago(db, -8, user_id, 95).await;
+    points_days_ago(db, 5, user_id, 50).await;
+
+    // The whole entry expires but never more than is available
+    let policy = model::ExpiryPolicy::Fixed;
+    let expiries = expire(db, 90, policy).await.unwrap();
+    assert_eq!(expiries.iter().map(|x| (x.point_id, x.value)).collect::<Vec<_>>(),
+      vec![(old, 7)]);
+    assert_eq!(db::ledger::balance(db, user_id).await.unwrap().available, 0);
+    let query = model::ExpiringQuery { days: Some(60) };
+    assert!(fetch_expiring(db, user_id, Some(90), policy, &query).await.unwrap().is_empty());
+
+    // Nothing expires without a lifetime
+    let expiring = fetch_expiring(db, user_id, None, policy, &query).await.unwrap();
+    assert!(expiring.is_empty());
+    let query = model::ExpiringQuery { days: Some(0) };
+    let err = fetch_expiring(db, user_id, Some(90), policy, &query).await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+  }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use axum::http::StatusCode;
+use chrono::{Days, Local, Utc};
+use sqlx::SqlitePool;
+use crate::{ errors, model };
+
+/// Insert a new savings goal into the database for the given user
+///
+/// - ***target*** defaults to the cost of the linked item
+/// - error on user not found
+/// - error on item not found
+/// - error on invalid goal see `validate`
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user saving up
+/// - ***goal*** - goal to insert
+///
+/// #### Returns
+/// - ***id*** - id of the goal
+pub async fn insert(db: &SqlitePool, user_id: i64, goal: &model::GoalPartial)
+  -> errors::Result<i64>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let target = target(db, goal).await?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO goal (user_id, name, target, deadline, item_id)
+      VALUES (?, ?, ?, ?, ?)"#)
+      .bind(user_id).bind(&goal.name).bind(target).bind(goal.deadline).bind(goal.item_id)
+      .execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Goal>(&mut tx, "goal", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
+  match result {
+    Ok(id) => Ok(id),
+    Err(e) => {
+      let msg = format!("Error inserting goal '{}'", goal.name);
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get a savings goal of the given user by id from the database
+///
+/// - error on not found including goals of other users
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+/// - ***id*** - id of the goal
+///
+/// #### Returns
+/// - ***goal*** - the goal entry with its progress and projection
+pub async fn fetch_by_id(db: &SqlitePool, user_id: i64, id: i64) -> errors::Result<model::Goal>
+{
+  let result = sqlx::query_as::<_, model::Goal>(r#"SELECT * FROM goal
+    WHERE id = ? AND user_id = ?"#).bind(id).bind(user_id).fetch_one(db).await;
+  match result {
+    Ok(goal) => {
+      let mut goals = vec![goal];
+      progress(db, &mut goals).await?;
+      Ok(goals.remove(0))
+    },
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Goal with id '{id}' was not found for user with id '{user_id}'");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching goal with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get the savings goals of the given user from the database
+///
+/// - orders the goals by id
+/// - error on user not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+///
+/// #### Returns
+/// - ***goals*** - the goal entries with their progress and projections
+pub async fn fetch_by_user_id(db: &SqlitePool, user_id: i64) -> errors::Result<Vec<model::Goal>>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let result = sqlx::query_as::<_, model::Goal>(r#"SELECT * FROM goal WHERE user_id = ?
+    ORDER BY id"#).bind(user_id).fetch_all(db).await;
+  match result {
+    Ok(mut goals) => {
+      progress(db, &mut goals).await?;
+      Ok(goals)
+    },
+    Err(e) => {
+      let msg = format!("Error fetching goals for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Update a savings goal of the given user in the database
+///
+/// - all fields are replaced with the given values
+/// - changing the target clears ***completed_at*** so that the new target can be completed
+/// - error on not found including goals of other users
+/// - error on item not found
+/// - error on invalid goal see `validate`
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+/// - ***id*** - id of the goal
+/// - ***goal*** - new values for the goal
+pub async fn update_by_id(db: &SqlitePool, user_id: i64, id: i64, goal: &model::GoalPartial)
+  -> errors::Result<()>
+{
+  fetch_by_id(db, user_id, id).await?;
+  let target = target(db, goal).await?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Goal>(&mut tx, "goal", id).await?;
+    sqlx::query(r#"UPDATE goal SET completed_at = CASE WHEN target = ?1 THEN completed_at END,
+      target = ?1, name = ?2, deadline = ?3, item_id = ?4 WHERE id = ?5"#)
+      .bind(target).bind(&goal.name).bind(goal.deadline).bind(goal.item_id).bind(id)
+      .execute(&mut *tx).await?;
+    super::audit::record::<model::Goal>(&mut tx, "goal", id, model::AuditAction::Update, before)
+      .await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error updating goal with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Delete a savings goal of the given user from the database
+///
+/// - does nothing if the goal is not found for the user
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+/// - ***id*** - id of the goal
+pub async fn delete_by_id(db: &SqlitePool, user_id: i64, id: i64) -> errors::Result<()>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Goal>(&mut tx, "goal", id).await?;
+    let query = sqlx::query(r#"DELETE FROM goal WHERE id = ? AND user_id = ?"#)
+      .bind(id).bind(user_id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::insert(&mut tx, "goal", id, model::AuditAction::Delete, before, None).await?;
+    }
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error deleting goal with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Stamp the goals whose target was reached as completed
+///
+/// - goals are checked against the user's available balance, goals of trashed users are skipped
+/// - each goal is only completed once so this can be called as often as needed
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - only check the goals of this user, all users when None
+///
+/// #### Returns
+/// - ***goals*** - the newly completed goals
+pub async fn complete(db: &SqlitePool, user_id: Option<i64>) -> errors::Result<Vec<model::Goal>>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let open = sqlx::query_as::<_, model::Goal>(r#"SELECT goal.* FROM goal
+      JOIN user ON user.id = goal.user_id
+      WHERE goal.completed_at IS NULL AND user.deleted_at IS NULL
+      AND (?1 IS NULL OR goal.user_id = ?1) ORDER BY goal.id"#)
+      .bind(user_id).fetch_all(&mut *tx).await?;
+    let mut available = HashMap::new();
+    let mut completed = vec![];
+    for mut goal in open {
+      let balance = match available.get(&goal.user_id) {
+        Some(balance) => *balance,
+        None => {
+          let balance = super::ledger::available(&mut tx, goal.user_id).await?;
+          *available.entry(goal.user_id).or_insert(balance)
+        }
+      };
+      if balance < goal.target {
+        continue;
+      }
+      let before = super::audit::snapshot::<model::Goal>(&mut tx, "goal", goal.id).await?;
+      goal.completed_at = sqlx::query_scalar::<_, chrono::DateTime<Local>>(r#"UPDATE goal
+        SET completed_at = datetime('subsec') WHERE id = ? RETURNING completed_at"#)
+        .bind(goal.id).fetch_optional(&mut *tx).await?;
+      super::audit::record::<model::Goal>(&mut tx, "goal", goal.id, model::AuditAction::Update,
+        before).await?;
+      completed.push(goal);
+    }
+    tx.commit().await?;
+    Ok::<_, sqlx::Error>(completed)
+  }.await;
+  match result {
+    Ok(mut completed) => {
+      progress(db, &mut completed).await?;
+      Ok(completed)
+    },
+    Err(e) => {
+      let msg = "Error completing goals";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+// Fill in the progress and projected completion of the goals
+//
+// - the earning rate is the approved points that occurred over the last `GOAL_RATE_DAYS` days
+//   including penalties, spending isn't taken off as the user is saving rather than spending
+async fn progress(db: &SqlitePool, goals: &mut [model::Goal]) -> errors::Result<()>
+{
+  let since = Utc::now() - chrono::Duration::days(model::GOAL_RATE_DAYS);
+  let today = Local::now().date_naive();
+  let mut totals = HashMap::new();
+  for goal in goals.iter_mut() {
+    let (available, earned) = match totals.get(&goal.user_id) {
+      Some(totals) => *totals,
+      None => {
+        let available = super::ledger::balance(db, goal.user_id).await?.available;
+        let result = sqlx::query_scalar::<_, i64>(r#"SELECT COALESCE(SUM(value), 0) FROM point
+          WHERE user_id = ? AND status = 'approved' AND deleted_at IS NULL
+          AND datetime(occurred_at) >= datetime(?)"#)
+          .bind(goal.user_id).bind(since).fetch_one(db).await;
+        let earned = result.map_err(|e| {
+          let msg = format!("Error fetching earning rate for user with id '{}'", goal.user_id);
+          log::error!("{msg}");
+          errors::Error::from_sqlx(e, &msg)
+        })?;
+        *totals.entry(goal.user_id).or_insert((available, earned))
+      }
+    };
+    goal.progress = available.clamp(0, goal.target);
+    let remaining = goal.target - available;
+    goal.projected_on = match remaining > 0 && earned > 0 {
+      true => u64::try_from((remaining * model::GOAL_RATE_DAYS + earned - 1) / earned).ok()
+        .and_then(|x| today.checked_add_days(Days::new(x))),
+      false => None,
+    };
+  }
+  Ok(())
+}
+
+// Get the goal's target defaulting to the cost of the linked item
+//
+// - error on item not found
+// - error on invalid goal see `validate`
+async fn target(db: &SqlitePool, goal: &model::GoalPartial) -> errors::Result<i64>
+{
+  let cost = match goal.item_id {
+    Some(item_id) => Some(super::item::fetch_by_id(db, item_id).await?.cost),
+    None => None,
+  };
+  let target = goal.target.or(cost);
+  validate(goal, target)?;
+  Ok(target.unwrap_or_default())
+}
+
+// Ensure the goal values make sense before writing them
+//
+// - error on empty name
+// - error on no target given without an item to take it from
+// - error on target less than one
+fn validate(goal: &model::GoalPartial, target: Option<i64>) -> errors::Result<()>
+{
+  let msg = match target {
+    _ if goal.name.trim().is_empty() => "Goal name is required",
+    None => "Goal target is required when no item_id is given",
+    Some(x) if x < 1 => "Goal target must be at least 1",
+    _ => return Ok(()),
+  };
+  log::warn!("{msg}");
+  Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  #[tokio::test]
+  async fn test_insert_failure_invalid()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    for (goal, msg) in [
+      (model::GoalPartial::new(" ", Some(10)), "Goal name is required"),
+      (model::GoalPartial::new("bike", None), "Goal target is required when no item_id is given"),
+      (model::GoalPartial::new("bike", Some(0)), "Goal target must be at least 1"),
+    ] {
+      let err = insert(state.db(), user_id, &goal).await.unwrap_err().to_http();
+      assert_eq!((err.status, err.msg.as_str()), (StatusCode::UNPROCESSABLE_ENTITY, msg));
+    }
+    let err = insert(state.db(), user_id, &model::GoalPartial::new("bike", None).with_item_id(99))
+      .await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+    assert!(fetch_by_user_id(state.db(), user_id).await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_progress_and_projection()
+  {
+    let state = state::test().await;
+    let today = Local::now().date_naive();
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let item_id = db::item::insert(state.db(), &model::ItemPartial::new("bike", 100)).await
+      .unwrap();
+    let id = insert(state.db(), user_id, &model::GoalPartial::new("bike", None)
+      .with_item_id(item_id)).await.unwrap();
+
+    let goal = fetch_by_id(state.db(), user_id, id).await.unwrap();
+    assert_eq!((goal.target, goal.progress, goal.projected_on), (100, 0, None));
+
+    // 28 points over the trailing 28 days is one a day, spending slows progress but not the rate
+    db::point::insert(state.db(), 28, user_id, 1).await.unwrap();
+    db::reward::insert(state.db(), 8, user_id).await.unwrap();
+    let goal = fetch_by_id(state.db(), user_id, id).await.unwrap();
+    assert_eq!((goal.progress, goal.projected_on), (20, Some(today + Days::new(80))));
+
+    // Goals of other users aren't found
+    let err = fetch_by_id(state.db(), 1, id).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_complete_once()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let id = insert(state.db(), user_id, &model::GoalPartial::new("bike", Some(10))).await
+      .unwrap();
+    insert(state.db(), user_id, &model::GoalPartial::new("car", Some(1000))).await.unwrap();
+    assert!(complete(state.db(), None).await.unwrap().is_empty());
+
+    db::point::insert(state.db(), 12, user_id, 1).await.unwrap();
+    assert!(complete(state.db(), Some(1)).await.unwrap().is_empty());
+    let completed = complete(state.db(), Some(user_id)).await.unwrap();
+    assert_eq!(completed.iter().map(|x| (x.id, x.progress)).collect::<Vec<_>>(), vec![(id, 10)]);
+    assert!(completed[0].completed_at.is_some());
+    assert!(complete(state.db(), None).await.unwrap().is_empty());
+
+    // Raising the target opens the goal again
+    update_by_id(state.db(), user_id, id, &model::GoalPartial::new("bike", Some(20))).await
+      .unwrap();
+    let goal = fetch_by_id(state.db(), user_id, id).await.unwrap();
+    assert_eq!((goal.target, goal.progress, goal.completed_at), (20, 12, None));
+
+    delete_by_id(state.db(), user_id, id).await.unwrap();
+    assert_eq!(fetch_by_user_id(state.db(), user_id).await.unwrap().len(), 1);
+  }
+}
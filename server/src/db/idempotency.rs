@@ -0,0 +1,249 @@
+use sqlx::SqlitePool;
+use axum::http::StatusCode;
+use crate::{ errors, model };
+
+/// Reserve the given idempotency key for a new request
+///
+/// - expired keys are purged first so they can be reused
+/// - keys are scoped to the caller so different callers may use the same key
+/// - error on duplicate key for the caller
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***key*** - client supplied Idempotency-Key header value
+/// - ***user_id*** - caller sending the key, None for anonymous callers
+/// - ***request_hash*** - hash of the request method, uri and body
+/// - ***ttl*** - seconds to keep keys before they expire
+///
+/// #### Returns
+/// - ***id*** - id of the idempotency entry
+pub async fn insert(db: &SqlitePool, key: &str, user_id: Option<i64>, request_hash: &str,
+  ttl: u64) -> errors::Result<i64>
+{
+  validate_key(key)?;
+  delete_expired(db, ttl).await?;
+
+  let result = sqlx::query(r#"INSERT INTO idempotency (key, user_id, request_hash)
+    VALUES (?, ?, ?)"#)
+    .bind(key).bind(user_id).bind(request_hash).execute(db).await;
+  match result {
+    Ok(query) => Ok(query.last_insert_rowid()),
+    Err(e) => {
+      if errors::Error::is_sqlx_unique_violation(&e) {
+        let msg = format!("Idempotency key '{key}' already exists");
+        log::debug!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error inserting idempotency key '{key}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get the caller's idempotency entry by key from the database
+///
+/// - error on not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***key*** - client supplied Idempotency-Key header value
+/// - ***user_id*** - caller sending the key, None for anonymous callers
+///
+/// #### Returns
+/// - ***idempotency*** - the idempotency entry
+pub async fn fetch_by_key(db: &SqlitePool, key: &str, user_id: Option<i64>)
+  -> errors::Result<model::Idempotency>
+{
+  let result = sqlx::query_as::<_, model::Idempotency>(r#"SELECT * FROM idempotency
+    WHERE key = ? AND user_id IS ?"#)
+    .bind(key).bind(user_id).fetch_one(db).await;
+  match result {
+    Ok(entry) => Ok(entry),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Idempotency key '{key}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching idempotency key '{key}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Store the response for the given idempotency key
+///
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the idempotency entry
+/// - ***status*** - response status code
+/// - ***content_type*** - optional response content type
+/// - ***body*** - response body bytes
+pub async fn update_response(db: &SqlitePool, id: i64, status: u16, content_type: Option<&str>,
+  body: &[u8]) -> errors::Result<()>
+{
+  let result = sqlx::query(r#"UPDATE idempotency SET status = ?, content_type = ?, body = ? WHERE id = ?"#)
+    .bind(status).bind(content_type).bind(body).bind(id).execute(db).await;
+  if let Err(e) = result {
+    let msg = format!("Error updating idempotency with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Delete an idempotency entry in the database
+///
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the idempotency entry
+pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = sqlx::query(r#"DELETE from idempotency WHERE id = ?"#).bind(id).execute(db).await;
+  if let Err(e) = result {
+    let msg = format!("Error deleting idempotency with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Delete all idempotency entries older than the given ttl
+///
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***ttl*** - seconds to keep keys before they expire
+///
+/// #### Returns
+/// - ***count*** - number of entries deleted
+pub async fn delete_expired(db: &SqlitePool, ttl: u64) -> errors::Result<u64>
+{
+  let result = sqlx::query(
+    r#"DELETE from idempotency WHERE datetime(created_at) <= datetime('now', ?)"#)
+    .bind(format!("-{ttl} seconds")).execute(db).await;
+  match result {
+    Ok(query) => Ok(query.rows_affected()),
+    Err(e) => {
+      let msg = "Error deleting expired idempotency keys";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+// Keys are client generated so keep them to something reasonable
+fn validate_key(key: &str) -> errors::Result<()>
+{
+  if key.is_empty() || key.len() > 255 {
+    let msg = "Idempotency-Key must be between 1 and 255 characters";
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  #[tokio::test]
+  async fn test_insert_and_update_success()
+  {
+    let state = state::test().await;
+    let key = "key1";
+    let hash = "hash1";
+
+    let id = insert(state.db(), key, None, hash, 60).await.unwrap();
+    let entry = fetch_by_key(state.db(), key, None).await.unwrap();
+    assert_eq!(entry.id, id);
+    assert_eq!(entry.request_hash, hash);
+    assert_eq!(entry.status, None);
+    assert_eq!(entry.body, None);
+
+    update_response(state.db(), id, 201, Some("application/json"), b"{}").await.unwrap();
+    let entry = fetch_by_key(state.db(), key, None).await.unwrap();
+    assert_eq!(entry.status, Some(201));
+    assert_eq!(entry.content_type.as_deref(), Some("application/json"));
+    assert_eq!(entry.body.as_deref(), Some(&b"{}"[..]));
+  }
+
+  #[tokio::test]
+  async fn test_insert_failure_duplicate()
+  {
+    let state = state::test().await;
+    let key = "key1";
+
+    insert(state.db(), key, None, "hash1", 60).await.unwrap();
+    let err = insert(state.db(), key, None, "hash2", 60).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotUnique);
+  }
+
+  #[tokio::test]
+  async fn test_insert_same_key_for_other_user()
+  {
+    let state = state::test().await;
+    let key = "key1";
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    let id = insert(state.db(), key, Some(1), "hash1", 60).await.unwrap();
+    let other_id = insert(state.db(), key, Some(user_id), "hash2", 60).await.unwrap();
+    assert_ne!(id, other_id);
+    let entry = fetch_by_key(state.db(), key, Some(user_id)).await.unwrap();
+    assert_eq!((entry.id, entry.user_id), (other_id, Some(user_id)));
+    let err = fetch_by_key(state.db(), key, None).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_insert_failure_invalid_key()
+  {
+    let state = state::test().await;
+
+    let err = insert(state.db(), "", None, "hash1", 60).await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let err = insert(state.db(), &"k".repeat(256), None, "hash1", 60).await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+  }
+
+  #[tokio::test]
+  async fn test_insert_reuses_expired_key()
+  {
+    let state = state::test().await;
+    let key = "key1";
+
+    // Backdate the existing key beyond the ttl
+    let id = insert(state.db(), key, None, "hash1", 60).await.unwrap();
+    sqlx::query(r#"UPDATE idempotency SET created_at = datetime('now', '-2 minutes') WHERE id = ?"#)
+      .bind(id).execute(state.db()).await.unwrap();
+
+    let id = insert(state.db(), key, None, "hash2", 60).await.unwrap();
+    let entry = fetch_by_key(state.db(), key, None).await.unwrap();
+    assert_eq!(entry.id, id);
+    assert_eq!(entry.request_hash, "hash2");
+  }
+
+  #[tokio::test]
+  async fn test_delete_by_id_success()
+  {
+    let state = state::test().await;
+    let key = "key1";
+
+    let id = insert(state.db(), key, None, "hash1", 60).await.unwrap();
+    delete_by_id(state.db(), id).await.unwrap();
+
+    let err = fetch_by_key(state.db(), key, None).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+}
@@ -0,0 +1,407 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, TimeZone, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+/// Insert a new catalog item into the database
+///
+/// - error on invalid item see `validate`
+/// - error on duplicate name
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***item*** - item to insert
+///
+/// #### Returns
+/// - ***id*** - id of the item
+pub async fn insert(db: &SqlitePool, item: &model::ItemPartial) -> errors::Result<i64>
+{
+  validate(item)?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO item (name, desc, cost, stock, period_limit, period, active)
+      VALUES (?, ?, ?, ?, ?, ?, ?)"#)
+      .bind(&item.name).bind(&item.desc).bind(item.cost).bind(item.stock)
+      .bind(item.period_limit).bind(item.period).bind(item.active)
+      .execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Item>(&mut tx, "item", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
+  match result {
+    Ok(id) => Ok(id),
+    Err(e) => {
+      if errors::Error::is_sqlx_unique_violation(&e) {
+        let msg = format!("Item '{}' already exists", item.name);
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error inserting item '{}'", item.name);
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get a catalog item by id from the database
+///
+/// - error on not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the item
+///
+/// #### Returns
+/// - ***item*** - the item entry
+pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Item>
+{
+  let result = sqlx::query_as::<_, model::Item>(r#"SELECT * FROM item WHERE id = ?"#)
+    .bind(id).fetch_one(db).await;
+  match result {
+    Ok(item) => Ok(item),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Item with id '{id}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching item with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get all catalog items from the database
+///
+/// - orders the items by name ignoring case
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+///
+/// #### Returns
+/// - ***items*** - the item entries
+pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Item>>
+{
+  let result = sqlx::query_as::<_, model::Item>(r#"SELECT * FROM item ORDER BY LOWER(name)"#)
+    .fetch_all(db).await;
+  match result {
+    Ok(items) => Ok(items),
+    Err(e) => {
+      let msg = "Error fetching items";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Update a catalog item in the database
+///
+/// - all fields are replaced with the given values
+/// - error on not found
+/// - error on invalid item see `validate`
+/// - error on duplicate name
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the item
+/// - ***item*** - new values for the item
+pub async fn update_by_id(db: &SqlitePool, id: i64, item: &model::ItemPartial)
+  -> errors::Result<()>
+{
+  fetch_by_id(db, id).await?;
+  validate(item)?;
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Item>(&mut tx, "item", id).await?;
+    sqlx::query(r#"UPDATE item SET name = ?, desc = ?, cost = ?, stock = ?, period_limit = ?,
+      period = ?, active = ? WHERE id = ?"#)
+      .bind(&item.name).bind(&item.desc).bind(item.cost).bind(item.stock)
+      .bind(item.period_limit).bind(item.period).bind(item.active).bind(id)
+      .execute(&mut *tx).await?;
+    super::audit::record::<model::Item>(&mut tx, "item", id, model::AuditAction::Update, before)
+      .await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    if errors::Error::is_sqlx_unique_violation(&e) {
+      let msg = format!("Item '{}' already exists", item.name);
+      log::warn!("{msg}");
+      return Err(errors::Error::from_sqlx(e, &msg));
+    }
+    let msg = format!("Error updating item with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Delete a catalog item from the database
+///
+/// - does nothing if the item is not found
+/// - error on the item having been redeemed or requested, deactivate it instead to keep the history
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the item
+pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Item>(&mut tx, "item", id).await?;
+    let query = sqlx::query(r#"DELETE FROM item WHERE id = ?"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::insert(&mut tx, "item", id, model::AuditAction::Delete, before, None).await?;
+    }
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    if errors::Error::is_sqlx_foreign_key_constraint_failed(&e) {
+      let msg = format!("Item with id '{id}' has been redeemed or requested, deactivate it instead");
+      log::warn!("{msg}");
+      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+    }
+    let msg = format!("Error deleting item with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+/// Redeem a catalog item for the given user
+///
+/// - creates a reward for the item's cost linked to the item and takes one from its stock
+/// - see `db::reward::spend` for how concurrent redemptions are kept from overdrawing
+/// - error on item or user not found
+/// - error on the item being inactive or out of stock
+/// - error on the user having reached the item's limit for the current period
+/// - error on the user's available balance not covering the cost
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the item
+/// - ***user_id*** - id of the user redeeming the item
+///
+/// #### Returns
+/// - ***id*** - id of the reward created
+pub async fn redeem(db: &SqlitePool, id: i64, user_id: i64) -> errors::Result<i64>
+{
+  let item = fetch_by_id(db, id).await?;
+  super::user::fetch_by_id(db, user_id).await?;
+
+  // Rejections roll back by dropping the transaction before commit
+  let result = async {
+    let mut tx = db.begin().await?;
+    let reward_id = match super::reward::spend(&mut tx, user_id, item.cost, Some(&item)).await? {
+      Ok(reward_id) => reward_id,
+      Err(msg) => return Ok(Err(msg)),
+    };
+    tx.commit().await?;
+    Ok(Ok(reward_id))
+  }.await;
+  match result {
+    Ok(Ok(reward_id)) => Ok(reward_id),
+    Ok(Err(msg)) => {
+      log::warn!("{msg}");
+      Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg))
+    },
+    Err(e) => {
+      let msg = format!("Error redeeming item with id '{id}' for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Take one of the item for the given user once the reward for it has been written
+///
+/// - must be called with the transaction writing the reward so that the checks see it
+/// - rejects the item being inactive, out of stock or over the user's limit for the period
+///
+/// #### Parameters
+/// - ***conn*** - transaction the reward was written in
+/// - ***item*** - the item being redeemed
+/// - ***user_id*** - id of the user redeeming the item
+///
+/// #### Returns
+/// - ***rejection*** - why the item can't be taken if it can't
+pub(crate) async fn take(conn: &mut SqliteConnection, item: &model::Item, user_id: i64)
+  -> Result<Result<(), String>, sqlx::Error>
+{
+  if !item.active {
+    return Ok(Err(format!("Item '{}' is not active", item.name)));
+  }
+
+  if item.stock.is_some() {
+    let before = super::audit::snapshot::<model::Item>(conn, "item", item.id).await?;
+    let query = sqlx::query(r#"UPDATE item SET stock = stock - 1 WHERE id = ? AND stock > 0"#)
+      .bind(item.id).execute(&mut *conn).await?;
+    if query.rows_affected() == 0 {
+      return Ok(Err(format!("Item '{}' is out of stock", item.name)));
+    }
+    super::audit::record::<model::Item>(conn, "item", item.id, model::AuditAction::Update, before)
+      .await?;
+  }
+
+  if let (Some(limit), Some(period)) = (item.period_limit, item.period) {
+    let count = sqlx::query_scalar::<_, i64>(r#"SELECT COUNT(*) FROM reward
+      WHERE user_id = ? AND item_id = ? AND deleted_at IS NULL
+      AND datetime(created_at) >= datetime(?)"#)
+      .bind(user_id).bind(item.id).bind(period_start(period, Local::now()))
+      .fetch_one(&mut *conn).await?;
+    if count > limit {
+      return Ok(Err(format!("Item '{}' can only be redeemed {limit} times per {}",
+        item.name, period_name(period))));
+    }
+  }
+  Ok(Ok(()))
+}
+
+// Get the start of the period containing the given time in UTC to compare against the database
+//
+// - midnight is used as is unless daylight saving skips it in which case the hour after is used
+pub(crate) fn period_start(period: model::ItemPeriod, now: DateTime<Local>) -> DateTime<Utc>
+{
+  let today = now.date_naive();
+  let date = match period {
+    model::ItemPeriod::Day => today,
+    model::ItemPeriod::Week => today - Days::new(today.weekday().num_days_from_monday() as u64),
+    model::ItemPeriod::Month => today.with_day(1).unwrap_or(today),
+  };
+  let midnight = date.and_time(NaiveTime::MIN);
+  Local.from_local_datetime(&midnight).earliest()
+    .or_else(|| Local.from_local_datetime(&(midnight + chrono::Duration::hours(1))).earliest())
+    .unwrap_or(now).to_utc()
+}
+
+// Name of the period for messages
+fn period_name(period: model::ItemPeriod) -> &'static str
+{
+  match period {
+    model::ItemPeriod::Day => "day",
+    model::ItemPeriod::Week => "week",
+    model::ItemPeriod::Month => "month",
+  }
+}
+
+// Ensure the item values make sense before writing them
+//
+// - error on empty name
+// - error on negative cost or stock
+// - error on a limit without a period or the other way around
+// - error on a limit less than one
+fn validate(item: &model::ItemPartial) -> errors::Result<()>
+{
+  let msg = if item.name.is_empty() {
+    "Item name value is required"
+  } else if item.cost < 0 {
+    "Item cost can't be negative"
+  } else if item.stock.is_some_and(|x| x < 0) {
+    "Item stock can't be negative"
+  } else if item.period_limit.is_some() != item.period.is_some() {
+    "Item period_limit and period must be given together"
+  } else if item.period_limit.is_some_and(|x| x < 1) {
+    "Item period_limit must be at least 1"
+  } else {
+    return Ok(());
+  };
+  log::warn!("{msg}");
+  Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  #[test]
+  fn test_period_start()
+  {
+    // Sunday the 18th of October 2026
+    let now = Local.with_ymd_and_hms(2026, 10, 18, 15, 30, 0).unwrap();
+    let start = |period| period_start(period, now).with_timezone(&Local).naive_local();
+    assert_eq!(start(model::ItemPeriod::Day).to_string(), "2026-10-18 00:00:00");
+    assert_eq!(start(model::ItemPeriod::Week).to_string(), "2026-10-12 00:00:00");
+    assert_eq!(start(model::ItemPeriod::Month).to_string(), "2026-10-01 00:00:00");
+  }
+
+  #[tokio::test]
+  async fn test_insert_failure_invalid()
+  {
+    let state = state::test().await;
+    for item in [
+      model::ItemPartial::new("", 5),
+      model::ItemPartial::new("item1", -1),
+      model::ItemPartial::new("item1", 5).with_stock(-1),
+      model::ItemPartial::new("item1", 5).with_limit(0, model::ItemPeriod::Day),
+      model::ItemPartial { period_limit: Some(1), ..model::ItemPartial::new("item1", 5) },
+    ] {
+      let err = insert(state.db(), &item).await.unwrap_err().to_http();
+      assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY, "{item:?}");
+    }
+  }
+
+  #[tokio::test]
+  async fn test_redeem_success()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let id = insert(state.db(), &model::ItemPartial::new("item1", 4).with_stock(2)).await.unwrap();
+
+    let reward_id = redeem(state.db(), id, user_id).await.unwrap();
+    let reward = db::reward::fetch_by_id(state.db(), reward_id).await.unwrap();
+    assert_eq!((reward.value, reward.user_id, reward.item_id), (4, user_id, Some(id)));
+    assert_eq!(fetch_by_id(state.db(), id).await.unwrap().stock, Some(1));
+    assert_eq!(db::ledger::balance(state.db(), user_id).await.unwrap().available, 6);
+  }
+
+  #[tokio::test]
+  async fn test_redeem_failure_leaves_nothing_behind()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let pricey = insert(state.db(), &model::ItemPartial::new("item1", 11)).await.unwrap();
+    let inactive = insert(state.db(), &model::ItemPartial::new("item2", 1).with_active(false))
+      .await.unwrap();
+    let sold_out = insert(state.db(), &model::ItemPartial::new("item3", 1).with_stock(0))
+      .await.unwrap();
+    let limited = insert(state.db(), &model::ItemPartial::new("item4", 1)
+      .with_limit(1, model::ItemPeriod::Week)).await.unwrap();
+    redeem(state.db(), limited, user_id).await.unwrap();
+
+    for id in [pricey, inactive, sold_out, limited] {
+      let err = redeem(state.db(), id, user_id).await.unwrap_err().to_http();
+      assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", err.msg);
+    }
+    assert_eq!(db::reward::fetch_by_user_id(state.db(), user_id).await.unwrap().len(), 1);
+    assert_eq!(fetch_by_id(state.db(), sold_out).await.unwrap().stock, Some(0));
+
+    let err = redeem(state.db(), -1, user_id).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_delete_failure_when_redeemed()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let id = insert(state.db(), &model::ItemPartial::new("item1", 1)).await.unwrap();
+    let unused = insert(state.db(), &model::ItemPartial::new("item2", 1)).await.unwrap();
+    redeem(state.db(), id, user_id).await.unwrap();
+
+    let err = delete_by_id(state.db(), id).await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    delete_by_id(state.db(), unused).await.unwrap();
+    assert_eq!(fetch_all(state.db()).await.unwrap().len(), 1);
+  }
+}
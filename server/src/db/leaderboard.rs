@@ -0,0 +1,203 @@
+use chrono::{DateTime, Duration, Local, Utc};
+use sqlx::SqlitePool;
+use crate::{ errors, model };
+
+// Row of point totals for a user in the current and previous periods
+#[derive(sqlx::FromRow)]
+struct Totals {
+  user_id: i64,
+  username: String,
+  total: i64,
+  previous: i64,
+}
+
+/// Rank users by their points over the given period
+///
+/// - counts approved points that aren't in the trash, penalties taking away from the total
+/// - users with the same total share a rank and the next rank skips past them e.g. 1, 1, 3
+/// - every user not in the trash is ranked including those without any points in the period
+/// - week and month periods are compared against the period before for the rank deltas
+/// - error on category not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***query*** - period, category, roles to leave out and whether to hide totals
+///
+/// #### Returns
+/// - ***leaderboard*** - users best first
+pub async fn fetch(db: &SqlitePool, query: &model::LeaderboardQuery)
+  -> errors::Result<model::Leaderboard>
+{
+  if let Some(category_id) = query.category_id {
+    super::category::fetch_by_id(db, category_id).await?;
+  }
+  let (start, previous) = window(query.period, Local::now());
+
+  // Previous totals are only meaningful for week and month, all time leaves them at zero
+  let result = sqlx::query_as::<_, Totals>(r#"SELECT user.id AS user_id, user.username,
+      COALESCE(SUM(CASE WHEN ?1 IS NULL OR datetime(point.occurred_at) >= datetime(?1)
+        THEN point.value END), 0) AS total,
+      COALESCE(SUM(CASE WHEN datetime(point.occurred_at) >= datetime(?2)
+        AND datetime(point.occurred_at) < datetime(?1) THEN point.value END), 0) AS previous
+    FROM user
+    LEFT JOIN point ON point.user_id = user.id AND point.status = 'approved'
+      AND point.deleted_at IS NULL
+      AND (?3 IS NULL OR point.action_id IN (SELECT id FROM action WHERE category_id = ?3))
+    WHERE user.deleted_at IS NULL
+      AND (?4 IS NULL OR NOT EXISTS (SELECT 1 FROM user_role
+        JOIN role ON role.id = user_role.role_id
+        WHERE user_role.user_id = user.id AND role.name = ?4))
+      AND (?5 IS NULL OR NOT EXISTS (SELECT 1 FROM user_role
+        WHERE user_role.user_id = user.id AND user_role.role_id = ?5))
+    GROUP BY user.id"#)
+    .bind(start).bind(previous).bind(query.category_id).bind(&query.role_name_ne)
+    .bind(query.role_id_ne).fetch_all(db).await;
+  let mut totals = match result {
+    Ok(totals) => totals,
+    Err(e) => {
+      let msg = "Error fetching leaderboard";
+      log::error!("{msg}");
+      return Err(errors::Error::from_sqlx(e, msg));
+    }
+  };
+
+  // Rank the previous period first so the current ranking is left in order
+  let previous_ranks = previous.map(|_| rank(&mut totals, |x| x.previous));
+  let ranks = rank(&mut totals, |x| x.total);
+  let entries = totals.into_iter().zip(ranks).map(|(totals, (_, rank))| {
+    let previous_rank = previous_ranks.as_ref().and_then(|x| {
+      x.iter().find(|(user_id, _)| *user_id == totals.user_id).map(|(_, rank)| *rank)
+    });
+    model::LeaderboardEntry {
+      rank,
+      user_id: totals.user_id,
+      username: totals.username,
+      total: (!query.ranks_only).then_some(totals.total),
+      previous_rank,
+      rank_delta: previous_rank.map(|x| x - rank),
+    }
+  }).collect();
+
+  Ok(model::Leaderboard {
+    period: query.period,
+    start: start.map(|x| x.with_timezone(&Local)),
+    entries,
+  })
+}
+
+// Get the start of the period containing now and of the period before it in UTC
+//
+// - all time has neither
+fn window(period: model::LeaderboardPeriod, now: DateTime<Local>)
+  -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>)
+{
+  let period = match period {
+    model::LeaderboardPeriod::Week => model::ItemPeriod::Week,
+    model::LeaderboardPeriod::Month => model::ItemPeriod::Month,
+    model::LeaderboardPeriod::All => return (None, None),
+  };
+  let start = super::item::period_start(period, now);
+  let before = start.with_timezone(&Local) - Duration::days(1);
+  let previous = super::item::period_start(period, before);
+  (Some(start), Some(previous))
+}
+
+// Sort the totals best first by the given value then username and get each user's rank
+//
+// - ties share the rank of the first user with that value
+fn rank(totals: &mut [Totals], value: impl Fn(&Totals) -> i64) -> Vec<(i64, i64)>
+{
+  totals.sort_by(|a, b| value(b).cmp(&value(a))
+    .then_with(|| a.username.to_lowercase().cmp(&b.username.to_lowercase())));
+  let mut ranks: Vec<(i64, i64)> = Vec::with_capacity(totals.len());
+  for (i, x) in totals.iter().enumerate() {
+    let rank = match i {
+      0 => 1,
+      _ if value(x) == value(&totals[i - 1]) => ranks[i - 1].1,
+      _ => i as i64 + 1,
+    };
+    ranks.push((x.user_id, rank));
+  }
+  ranks
+}
+
+#[cfg(test)]
+mod tests
+{
+  use chrono::Local;
+  use crate::{db, model, state};
+
+  #[tokio::test]
+  async fn test_fetch_ties_and_deltas()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user1 = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let user2 = db::user::insert(db, "user2", "user2@foo.com").await.unwrap();
+
+    // Last week admin led and user2 trailed
+    let (start, _) = super::window(model::LeaderboardPeriod::Week, Local::now());
+    let last_week = start.unwrap().with_timezone(&Local) - chrono::Duration::days(2);
+    for (user_id, value) in [(1, 10), (user1, 5), (user2, 1)] {
+      let points = model::CreatePoints { value, user_id, action_id: 1,
+        occurred_at: Some(last_week), kind: None };
+      db::point::insert_with(db, &points, None, model::PointsStatus::Approved).await.unwrap();
+    }
+
+    // This week user1 and user2 tie ahead of admin
+    db::point::insert(db, 4, user1, 1).await.unwrap();
+    db::point::insert(db, 4, user2, 1).await.unwrap();
+    db::point::insert(db, 1, 1, 1).await.unwrap();
+
+    let query = model::LeaderboardQuery::new(model::LeaderboardPeriod::Week);
+    let board = super::fetch(db, &query).await.unwrap();
+    let ranks: Vec<_> = board.entries.iter()
+      .map(|x| (x.user_id, x.rank, x.total, x.previous_rank, x.rank_delta)).collect();
+    assert_eq!(ranks, vec![
+      (user1, 1, Some(4), Some(2), Some(1)),
+      (user2, 1, Some(4), Some(3), Some(2)),
+      (1, 3, Some(1), Some(1), Some(-2)),
+    ]);
+
+    // All time has no previous period
+    let query = model::LeaderboardQuery::new(model::LeaderboardPeriod::All).with_ranks_only();
+    let board = super::fetch(db, &query).await.unwrap();
+    let ranks: Vec<_> = board.entries.iter()
+      .map(|x| (x.user_id, x.rank, x.total, x.rank_delta)).collect();
+    assert_eq!(ranks, vec![(1, 1, None, None), (user1, 2, None, None), (user2, 3, None, None)]);
+    assert!(board.start.is_none());
+  }
+
+  #[tokio::test]
+  async fn test_fetch_category_and_role()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user1 = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let category_id = db::category::insert(db, "Chores").await.unwrap();
+    let action_id = db::action::insert(db, &model::CreateAction::new().with_desc("Dishes")
+      .with_category_id(category_id)).await.unwrap();
+    db::point::insert(db, 5, 1, 1).await.unwrap();
+    db::point::insert(db, 2, user1, action_id).await.unwrap();
+
+    // Only points for actions in the category count
+    let query = model::LeaderboardQuery::new(model::LeaderboardPeriod::Month)
+      .with_category_id(category_id);
+    let board = super::fetch(db, &query).await.unwrap();
+    let ranks: Vec<_> = board.entries.iter().map(|x| (x.user_id, x.total)).collect();
+    assert_eq!(ranks, vec![(user1, Some(2)), (1, Some(0))]);
+
+    // Users holding the role are left out
+    let query = model::LeaderboardQuery::new(model::LeaderboardPeriod::Month)
+      .with_role_name_ne("admin");
+    let board = super::fetch(db, &query).await.unwrap();
+    let ranks: Vec<_> = board.entries.iter().map(|x| (x.user_id, x.rank)).collect();
+    assert_eq!(ranks, vec![(user1, 1)]);
+
+    // Unknown category
+    let query = model::LeaderboardQuery::new(model::LeaderboardPeriod::Month)
+      .with_category_id(999);
+    assert!(super::fetch(db, &query).await.is_err());
+  }
+}
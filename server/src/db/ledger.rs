@@ -0,0 +1,243 @@
+use axum::http::StatusCode;
+use chrono::NaiveDateTime;
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+// Points, rewards and expiries merged into one chronological list of signed entries. Entries are
+// ordered by the second they occurred then kind then id so that the order is total and the running
+// balance is stable across pages. Trashed entries and points that aren't approved are left out
+// along with the expiries of those points.
+const LEDGER: &str = r#"WITH entry AS (
+    SELECT 'points' AS kind, id, value, action_id, occurred_at FROM point
+      WHERE user_id = ?1 AND status = 'approved' AND deleted_at IS NULL
+    UNION ALL
+    SELECT 'reward' AS kind, id, -value AS value, NULL AS action_id, created_at AS occurred_at
+      FROM reward WHERE user_id = ?1 AND deleted_at IS NULL
+    UNION ALL
+    SELECT 'expiry' AS kind, expiry.id, -expiry.value AS value, NULL AS action_id,
+      expiry.expired_at AS occurred_at FROM expiry JOIN point ON point.id = expiry.point_id
+      WHERE expiry.user_id = ?1 AND point.status = 'approved' AND point.deleted_at IS NULL
+  ), ledger AS (
+    SELECT *, datetime(occurred_at) AS sort_date,
+      SUM(value) OVER (ORDER BY datetime(occurred_at), kind, id) AS balance FROM entry
+  )"#;
+
+// A user's totals, points and rewards in the trash and points that aren't approved are left out
+// along with the expiries of those points
+const TOTALS: &str = r#"SELECT
+    (SELECT COALESCE(SUM(value), 0) FROM point
+      WHERE user_id = ?1 AND status = 'approved' AND deleted_at IS NULL) AS earned,
+    (SELECT COALESCE(SUM(value), 0) FROM reward WHERE user_id = ?1 AND deleted_at IS NULL) AS spent,
+    (SELECT COALESCE(SUM(value), 0) FROM redemption WHERE user_id = ?1 AND status = 'requested')
+      AS held,
+    (SELECT COALESCE(SUM(expiry.value), 0) FROM expiry JOIN point ON point.id = expiry.point_id
+      WHERE expiry.user_id = ?1 AND point.status = 'approved' AND point.deleted_at IS NULL)
+      AS expired"#;
+
+// Cursor date format, kept free of spaces so that cursors can be used in a query string as is
+const CURSOR_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Get the earned, spent, held, expired and available points for the given user
+///
+/// - excludes points and rewards in the trash and points that aren't approved
+/// - error on user not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+///
+/// #### Returns
+/// - ***balance*** - the user's totals
+pub async fn balance(db: &SqlitePool, user_id: i64) -> errors::Result<model::Balance>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+
+  let result = sqlx::query_as::<_, model::Balance>(&format!(r#"SELECT ?1 AS user_id, earned,
+      spent, held, expired, earned - spent - held - expired AS available FROM ({TOTALS})"#))
+    .bind(user_id).fetch_one(db).await;
+  match result {
+    Ok(balance) => Ok(balance),
+    Err(e) => {
+      let msg = format!("Error fetching balance for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get the points the given user has available to spend
+///
+/// - used to check spending inside the transaction making it, after the spending has been written
+///
+/// #### Parameters
+/// - ***conn*** - connection or transaction to check with
+/// - ***user_id*** - id of the user
+///
+/// #### Returns
+/// - ***available*** - earned less spent, held and expired
+pub(crate) async fn available(conn: &mut SqliteConnection, user_id: i64)
+  -> Result<i64, sqlx::Error>
+{
+  sqlx::query_scalar::<_, i64>(&format!("SELECT earned - spent - held - expired FROM ({TOTALS})"))
+    .bind(user_id).fetch_one(&mut *conn).await
+}
+
+/// Get a page of the given user's ledger
+///
+/// - merges points, rewards and expiries newest first with the running balance after each entry
+/// - excludes points and rewards in the trash and points that aren't approved
+/// - error on user not found
+/// - error on invalid cursor
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+/// - ***query*** - cursor and page size
+///
+/// #### Returns
+/// - ***page*** - the entries and the cursor for the next page if any
+pub async fn fetch_by_user_id(db: &SqlitePool, user_id: i64, query: &model::LedgerQuery)
+  -> errors::Result<model::LedgerPage>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let limit = query.limit.unwrap_or(model::LEDGER_PAGE_LIMIT).clamp(1, model::LEDGER_PAGE_MAX);
+  let cursor = query.cursor.as_deref().map(decode_cursor).transpose()?;
+
+  let mut sql = format!("{LEDGER}
+    SELECT kind, id, value, action_id, occurred_at, balance FROM ledger");
+  if cursor.is_some() {
+    sql.push_str(" WHERE (sort_date, kind, id) < (?2, ?3, ?4)");
+  }
+  sql.push_str(" ORDER BY sort_date DESC, kind DESC, id DESC LIMIT ?5");
+
+  let (date, kind, id) = cursor.unwrap_or_default();
+  let result = sqlx::query_as::<_, model::LedgerEntry>(&sql)
+    .bind(user_id).bind(date).bind(kind).bind(id).bind(limit + 1).fetch_all(db).await;
+  match result {
+    Ok(mut entries) => {
+      let mut next_cursor = None;
+      if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        next_cursor = entries.last().map(encode_cursor);
+      }
+      Ok(model::LedgerPage { entries, next_cursor })
+    },
+    Err(e) => {
+      let msg = format!("Error fetching ledger for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+// Build the cursor that continues after the given entry
+//
+// - the date matches the ledger's sort date i.e. `datetime(occurred_at)` in UTC to the second
+fn encode_cursor(entry: &model::LedgerEntry) -> String
+{
+  let kind = match entry.kind {
+    model::LedgerKind::Points => "points",
+    model::LedgerKind::Reward => "reward",
+    model::LedgerKind::Expiry => "expiry",
+  };
+  format!("{}_{kind}_{}", entry.occurred_at.naive_utc().format(CURSOR_DATE_FORMAT), entry.id)
+}
+
+// Split a cursor back into its sort date, kind and id
+fn decode_cursor(cursor: &str) -> errors::Result<(String, String, i64)>
+{
+  let invalid = || {
+    let msg = format!("Invalid ledger cursor '{cursor}'");
+    log::warn!("{msg}");
+    errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg)
+  };
+  let mut parts = cursor.splitn(3, '_');
+  let (Some(date), Some(kind), Some(id)) = (parts.next(), parts.next(), parts.next()) else {
+    return Err(invalid());
+  };
+  let date = NaiveDateTime::parse_from_str(date, CURSOR_DATE_FORMAT).map_err(|_| invalid())?;
+  if !["points", "reward", "expiry"].contains(&kind) {
+    return Err(invalid());
+  }
+  let id = id.parse::<i64>().map_err(|_| invalid())?;
+  Ok((date.format("%Y-%m-%d %H:%M:%S").to_string(), kind.to_string(), id))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  // Helper to create points that occurred the given number of days ago
+  async fn points_days_ago(db: &SqlitePool, value: i64, user_id: i64, days: i64) -> i64
+  {
+    let occurred_at = chrono::Local::now() - chrono::Duration::days(days);
+    db::point::insert_with(db, &model::CreatePoints { value, user_id, action_id: 1,
+      occurred_at: Some(occurred_at), kind: None }, None, model::PointsStatus::Approved).await
+      .unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_balance()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 10, user_id, 1).await.unwrap();
+    db::point::insert(state.db(), 5, user_id, 1).await.unwrap();
+    let trashed_id = db::point::insert(state.db(), 100, user_id, 1).await.unwrap();
+    db::point::delete_by_id(state.db(), trashed_id).await.unwrap();
+    db::reward::insert(state.db(), 4, user_id).await.unwrap();
+    db::point::insert(state.db(), 50, 1, 1).await.unwrap();
+
+    let totals = balance(state.db(), user_id).await.unwrap();
+    assert_eq!((totals.user_id, totals.earned, totals.spent, totals.available),
+      (user_id, 15, 4, 11));
+
+    let totals = balance(state.db(), 1).await.unwrap();
+    assert_eq!((totals.earned, totals.spent, totals.available), (50, 0, 50));
+
+    let err = balance(state.db(), -1).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_by_user_id_pages_with_running_balance()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    // Back filled points slot in by when they occurred rather than when they were recorded
+    points_days_ago(state.db(), 10, user_id, 3).await;
+    db::reward::insert(state.db(), 4, user_id).await.unwrap();
+    points_days_ago(state.db(), 20, user_id, 2).await;
+    points_days_ago(state.db(), 1, user_id, 5).await;
+
+    let query = model::LedgerQuery { cursor: None, limit: Some(3) };
+    let page = fetch_by_user_id(state.db(), user_id, &query).await.unwrap();
+    let entries = page.entries.iter().map(|x| (x.kind, x.value, x.balance)).collect::<Vec<_>>();
+    assert_eq!(entries, vec![(model::LedgerKind::Reward, -4, 27),
+      (model::LedgerKind::Points, 20, 31), (model::LedgerKind::Points, 10, 11)]);
+
+    let query = model::LedgerQuery { cursor: page.next_cursor, limit: Some(3) };
+    let page = fetch_by_user_id(state.db(), user_id, &query).await.unwrap();
+    let entries = page.entries.iter().map(|x| (x.kind, x.value, x.balance)).collect::<Vec<_>>();
+    assert_eq!(entries, vec![(model::LedgerKind::Points, 1, 1)]);
+    assert!(page.next_cursor.is_none());
+  }
+
+  #[tokio::test]
+  async fn test_fetch_by_user_id_failure_invalid_cursor()
+  {
+    let state = state::test().await;
+    for cursor in ["", "2026-10-18T12:00:00_points", "2026-10-18_points_1",
+      "2026-10-18T12:00:00_other_1", "2026-10-18T12:00:00_reward_x"]
+    {
+      let query = model::LedgerQuery { cursor: Some(cursor.to_string()), limit: None };
+      let err = fetch_by_user_id(state.db(), 1, &query).await.unwrap_err().to_http();
+      assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY, "{cursor}");
+    }
+  }
+}
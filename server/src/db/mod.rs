@@ -2,10 +2,26 @@
  * DB business logic
  */
 pub mod apikey;
+pub mod audit;
+pub mod backup;
+pub mod badge;
+pub mod bonus;
 pub mod user;
 pub mod action;
 pub mod category;
+pub mod chore;
+pub mod expiry;
+pub mod goal;
+pub mod idempotency;
+pub mod item;
+pub mod leaderboard;
+pub mod ledger;
+pub mod redemption;
 pub mod reward;
 pub mod password;
 pub mod role;
-pub mod point;
\ No newline at end of file
+pub mod point;
+pub mod search;
+pub mod streak;
+pub mod transfer;
+pub mod trash;
\ No newline at end of file
@@ -37,10 +37,19 @@ pub async fn insert(db: &SqlitePool, user_id: i64, salt: &str, hash: &str)
   }
 
   // Insert the new password
-  let result = sqlx::query(r#"INSERT INTO password (salt, hash, user_id) VALUES (?, ?, ?)"#)
-    .bind(salt).bind(hash).bind(user_id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO password (salt, hash, user_id) VALUES (?, ?, ?)"#)
+      .bind(salt).bind(hash).bind(user_id).execute(&mut *tx).await?.last_insert_rowid();
+
+    // Never record the salt or hash
+    super::audit::insert(&mut tx, "password", id, model::AuditAction::Create, None,
+      Some(serde_json::json!({ "user_id": user_id }))).await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
   match result {
-    Ok(query) => Ok(query.last_insert_rowid()),
+    Ok(id) => Ok(id),
     Err(e) => {
       let msg = format!("Error inserting password for user_id '{}'", user_id);
       log::error!("{msg}");
@@ -150,7 +159,16 @@ pub(crate) async fn fetch_by_user_id(db: &SqlitePool, user_id: i64) ->
 /// - ***id*** - password id
 pub(crate) async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
 {
-  let result = sqlx::query(r#"DELETE from password WHERE id = ?"#).bind(id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let user_id = sqlx::query_scalar::<_, i64>(r#"DELETE from password WHERE id = ? RETURNING user_id"#)
+      .bind(id).fetch_optional(&mut *tx).await?;
+    if let Some(user_id) = user_id {
+      super::audit::insert(&mut tx, "password", id, model::AuditAction::Delete,
+        Some(serde_json::json!({ "user_id": user_id })), None).await?;
+    }
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error deleting password with id '{id}'");
     log::error!("{msg}");
@@ -1,8 +1,15 @@
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, SqliteConnection, SqlitePool};
+use axum::http::StatusCode;
 use crate::{ errors, model };
 
+// Points are joined with their action to support category and approval filtering
+const SELECT_POINTS: &str = r#"SELECT point.* FROM point
+  INNER JOIN action ON action.id = point.action_id"#;
+
 /// Insert a new points entry into the database
 /// 
+/// - test only shorthand for anonymously awarded approved points that occurred now
+/// - production code creates points with `insert_with` instead
 /// - error on user not found
 /// - error on action not found
 /// - error on other SQL errors
@@ -15,16 +22,68 @@ use crate::{ errors, model };
 /// 
 /// #### Returns
 /// - ***id*** - id of the points
+#[cfg(test)]
 pub async fn insert(db: &SqlitePool, value: i64, user_id: i64, action_id: i64)
   -> errors::Result<i64>
 {
+  let points = model::CreatePoints { value, user_id, action_id, occurred_at: None, kind: None };
+  insert_with(db, &points, None, model::PointsStatus::Approved).await
+}
+
+/// Insert a new user 'user1' with the given approved points for tests
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***value*** - points value
+/// 
+/// #### Returns
+/// - ***user_id*** - id of the new user
+#[cfg(test)]
+pub async fn user_with_points(db: &SqlitePool, value: i64) -> i64
+{
+  let user_id = super::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+  insert(db, value, user_id, 1).await.unwrap();
+  user_id
+}
+
+/// Insert a new points entry into the database recording who awarded it and when it occurred
+/// 
+/// - the entry point for creating points, see `insert_in` to insert within a transaction
+/// - ***occurred_at*** defaults to now when not given
+/// - the value is multiplied by the bonus in effect when the points occurred see `bonus::find`
+/// - ***kind*** defaults to award or penalty by the sign of the value
+/// - pending points don't count towards sums and balances until approved see `review`
+/// - error on value not allowed for the kind
+/// - error on user not found
+/// - error on action not found
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***points*** - CreatePoints struct containing the points data
+/// - ***awarded_by*** - user awarding the points if known
+/// - ***status*** - review status to start the points in
+/// 
+/// #### Returns
+/// - ***id*** - id of the points
+pub async fn insert_with(db: &SqlitePool, points: &model::CreatePoints, awarded_by: Option<i64>,
+  status: model::PointsStatus) -> errors::Result<i64>
+{
+  let (value, user_id, action_id) = (points.value, points.user_id, points.action_id);
+  let kind = validate_kind(points.kind.unwrap_or(model::PointsKind::from_value(value)), value)?;
   super::user::fetch_by_id(db, user_id).await?;
   super::action::fetch_by_id(db, action_id).await?;
 
-  let result = sqlx::query(r#"INSERT INTO point (value, user_id, action_id) VALUES (?, ?, ?)"#)
-    .bind(value).bind(user_id).bind(action_id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let points = model::CreatePoints { value, user_id, action_id, occurred_at: points.occurred_at,
+      kind: Some(kind) };
+    let id = insert_in(&mut tx, &points, awarded_by, status, None, None).await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
   match result {
-    Ok(query) => Ok(query.last_insert_rowid()),
+    Ok(id) => Ok(id),
     Err(e) => {
       let msg = format!("Error inserting points '{value}'");
       log::error!("{msg}");
@@ -33,9 +92,55 @@ pub async fn insert(db: &SqlitePool, value: i64, user_id: i64, action_id: i64)
   }
 }
 
+/// Insert a new points entry inside the given transaction
+///
+/// - shared by `insert_with` and imports so that new points are always audited and counted
+///   toward streaks and badges the same way
+/// - the points are expected to be validated and their user and action to exist
+/// - the value is multiplied by the given ***multiplier*** or else by the bonus in effect when
+///   the points occurred see `bonus::find`
+///
+/// #### Parameters
+/// - ***conn*** - transaction to insert with
+/// - ***points*** - CreatePoints struct containing the points data
+/// - ***awarded_by*** - user awarding the points if known
+/// - ***status*** - review status to start the points in
+/// - ***created_at*** - when the points were recorded, defaults to now
+/// - ***multiplier*** - multiplier to apply instead of looking up the bonus
+///
+/// #### Returns
+/// - ***id*** - id of the points
+pub(crate) async fn insert_in(conn: &mut SqliteConnection, points: &model::CreatePoints,
+  awarded_by: Option<i64>, status: model::PointsStatus,
+  created_at: Option<chrono::DateTime<chrono::Local>>, multiplier: Option<f64>)
+  -> Result<i64, sqlx::Error>
+{
+  let multiplier = match multiplier {
+    Some(multiplier) => multiplier,
+    None => super::bonus::find(conn, points).await?.map_or(1.0, |x| x.multiplier),
+  };
+  let kind = points.kind.unwrap_or(model::PointsKind::from_value(points.value));
+  let created_at = created_at.map(|x| x.naive_utc());
+  let id = sqlx::query(r#"INSERT INTO point (value, base_value, multiplier, kind, user_id,
+    action_id, awarded_by, occurred_at, status, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?,
+    ?, COALESCE(?, datetime('subsec')), ?, COALESCE(?, datetime('subsec')),
+    COALESCE(?, datetime('subsec')))"#)
+    .bind(super::bonus::apply(points.value, multiplier)).bind(points.value).bind(multiplier)
+    .bind(kind).bind(points.user_id).bind(points.action_id).bind(awarded_by)
+    .bind(points.occurred_at.map(|x| x.naive_utc())).bind(status)
+    .bind(created_at).bind(created_at)
+    .execute(&mut *conn).await?.last_insert_rowid();
+  super::audit::record::<model::Points>(conn, "point", id, model::AuditAction::Create, None)
+    .await?;
+  let logged = super::streak::counted(conn, id).await?;
+  super::streak::changed(conn, None, logged).await?;
+  super::badge::evaluate_by_point(conn, id).await?;
+  Ok(id)
+}
+
 /// Get a points entry by ID from the database
 /// 
-/// - error on not found
+/// - error on not found or in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -46,7 +151,8 @@ pub async fn insert(db: &SqlitePool, value: i64, user_id: i64, action_id: i64)
 /// - ***points*** - points entry
 pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Points>
 {
-  let result = sqlx::query_as::<_, model::Points>(r#"SELECT * FROM point WHERE id = ?"#)
+  let result = sqlx::query_as::<_, model::Points>(
+    r#"SELECT * FROM point WHERE id = ? AND deleted_at IS NULL"#)
     .bind(id).fetch_one(db).await;
   match result {
     Ok(points) => Ok(points),
@@ -63,36 +169,31 @@ pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Poin
   }
 }
 
-/// Sum all points for the given user and or action
+/// Sum all approved points for the given filter
 /// 
-/// - Start defines the oldest date to include in the sum
-/// - End defines the newest date to include in the sum
+/// - pending and rejected points are never included
+/// - Start defines the oldest date the points occurred to include in the sum
+/// - End defines the newest date the points occurred to include in the sum
 /// 
-/// - error on user not found if user_id is provided
-/// - error on action not found if action_id is provided
+/// - error on user not found if user_id or user_ids is provided
+/// - error on action not found if action_id or action_ids is provided
+/// - error on category not found if category_id is provided
 /// - error on other SQL errors
 /// 
 /// #### Parameters
 /// - ***db*** - database connection pool
 /// - ***filter*** supports filter params:
-///   - ***user_id=***, ***action_id=***, ***start_date=***, ***end_date=***
+///   - ***user_id=***, ***user_ids=***, ***action_id=***, ***action_ids=***, ***category_id=***
+///   - ***approved=***, ***status=***, ***value_gt=***, ***value_lt=***, ***start_date=***,
+///     ***end_date=***
 pub async fn sum_by_filter(db: &SqlitePool, filter: model::Filter) -> errors::Result<i64>
 {
-  let where_clause = filter.to_points_where_clause(db).await?;
-  let query_str = format!("SELECT SUM(value) as total FROM point {where_clause}");
-  let mut query = sqlx::query_as::<_, (Option<i64>,)>(&query_str);
-  
-  if let Some(user_id) = filter.user_id {
-    query = query.bind(user_id);
-  }
-  if let Some(action_id) = filter.action_id {
-    query = query.bind(action_id);
-  }
-  if let Some((start, end)) = filter.date_range() {
-    query = query.bind(start).bind(end);
-  }
+  let mut query = QueryBuilder::new(r#"SELECT SUM(point.value) as total FROM point
+    INNER JOIN action ON action.id = point.action_id"#);
+  filter.push_points_where_clause(db, &mut query).await?;
+  query.push(" AND point.status = 'approved'");
 
-  let result = query.fetch_one(db).await;
+  let result = query.build_query_as::<(Option<i64>,)>().fetch_one(db).await;
   match result {
     Ok((total,)) => Ok(total.unwrap_or(0)),
     Err(e) => {
@@ -103,37 +204,31 @@ pub async fn sum_by_filter(db: &SqlitePool, filter: model::Filter) -> errors::Re
   }
 }
 
-/// Get all points for the given user and or action
+/// Get all points for the given filter
 /// 
-/// - Start defines the oldest date to include in the sum
-/// - End defines the newest date to include in the sum
+/// - orders the points by the date they occurred
+/// - Start defines the oldest date the points occurred to include
+/// - End defines the newest date the points occurred to include
 /// 
-/// - error on user not found if user_id is provided
-/// - error on action not found if action_id is provided
+/// - error on user not found if user_id or user_ids is provided
+/// - error on action not found if action_id or action_ids is provided
+/// - error on category not found if category_id is provided
 /// - error on other SQL errors
 /// 
 /// #### Parameters
 /// - ***db*** - database connection pool
 /// - ***filter*** supports filter params:
-///   - ***user_id=***, ***action_id=***, ***start_date=***, ***end_date=***
+///   - ***user_id=***, ***user_ids=***, ***action_id=***, ***action_ids=***, ***category_id=***
+///   - ***approved=***, ***status=***, ***value_gt=***, ***value_lt=***, ***start_date=***,
+///     ***end_date=***
 pub async fn fetch_by_filter(db: &SqlitePool, filter: model::Filter)
   -> errors::Result<Vec<model::Points>>
 {
-  let where_clause = filter.to_points_where_clause(db).await?;
-  let query_str = format!("SELECT * FROM point {where_clause} ORDER BY created_at");
-  let mut query = sqlx::query_as::<_, model::Points>(&query_str);
-  
-  if let Some(user_id) = filter.user_id {
-    query = query.bind(user_id);
-  }
-  if let Some(action_id) = filter.action_id {
-    query = query.bind(action_id);
-  }
-  if let Some((start, end)) = filter.date_range() {
-    query = query.bind(start).bind(end);
-  }
+  let mut query = QueryBuilder::new(SELECT_POINTS);
+  filter.push_points_where_clause(db, &mut query).await?;
+  query.push(" ORDER BY point.occurred_at");
 
-  let result = query.fetch_all(db).await;
+  let result = query.build_query_as::<model::Points>().fetch_all(db).await;
   match result {
     Ok(points) => Ok(points),
     Err(e) => {
@@ -149,7 +244,8 @@ pub async fn fetch_by_filter(db: &SqlitePool, filter: model::Filter)
 /// - ***user_id*** owner of the points
 pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Points>>
 {
-  let result = sqlx::query_as::<_, model::Points>(r#"SELECT * FROM point"#)
+  let result = sqlx::query_as::<_, model::Points>(
+    r#"SELECT * FROM point WHERE deleted_at IS NULL"#)
     .fetch_all(db).await;
   match result {
     Ok(points) => Ok(points),
@@ -164,16 +260,47 @@ pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Points>>
 /// Update a points in the database
 /// 
 /// - only the value field can be updated
+/// - the value given is the base value, multiplied by the multiplier the points were given
+/// - awards and penalties are reclassified by the sign of the new value, other kinds are kept
+/// - changing the value to be reviewed again clears the previous review see `review`
 /// - error on not found
+/// - error on value not allowed for the kind
 /// - error on other SQL errors
-pub async fn update_by_id(db: &SqlitePool, id: i64, value: i64) -> errors::Result<()>
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the points
+/// - ***value*** - new base value of the points
+/// - ***status*** - review status to move the points to when the value changes, kept if not given
+pub async fn update_by_id(db: &SqlitePool, id: i64, value: i64,
+  status: Option<model::PointsStatus>) -> errors::Result<()>
 {
   let points = fetch_by_id(db, id).await?;
+  let kind = match points.kind {
+    model::PointsKind::Award | model::PointsKind::Penalty => model::PointsKind::from_value(value),
+    kind => validate_kind(kind, value)?,
+  };
 
   // Update points value if changed
-  if points.value != value {
-    let result = sqlx::query(r#"UPDATE point SET value = ? WHERE id = ?"#)
-      .bind(&value).bind(&id).execute(db).await;
+  if points.base_value != value {
+    let result = async {
+      let mut tx = db.begin().await?;
+      let before = super::audit::snapshot::<model::Points>(&mut tx, "point", id).await?;
+      let logged = super::streak::counted(&mut tx, id).await?;
+      sqlx::query(r#"UPDATE point SET value = ?1, base_value = ?2, kind = ?3,
+        status = COALESCE(?4, status),
+        note = CASE WHEN ?4 IS NULL THEN note END,
+        reviewed_by = CASE WHEN ?4 IS NULL THEN reviewed_by END,
+        reviewed_at = CASE WHEN ?4 IS NULL THEN reviewed_at END WHERE id = ?5"#)
+        .bind(super::bonus::apply(value, points.multiplier)).bind(&value).bind(kind).bind(status)
+        .bind(&id).execute(&mut *tx).await?;
+      super::audit::record::<model::Points>(&mut tx, "point", id, model::AuditAction::Update,
+        before).await?;
+      let now_logged = super::streak::counted(&mut tx, id).await?;
+      super::streak::changed(&mut tx, logged, now_logged).await?;
+      super::badge::evaluate_by_point(&mut tx, id).await?;
+      tx.commit().await
+    }.await;
     if let Err(e) = result {
       let msg = format!("Error updating points with id '{id}'");
       log::error!("{msg}");
@@ -183,12 +310,100 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, value: i64) -> errors::Resul
   Ok(())
 }
 
-/// Delete a points in the database
+/// Approve or reject pending points in bulk
+///
+/// - all of the points are reviewed or none of them are
+/// - error on status not being approved or rejected
+/// - error on no ids given
+/// - error on any of the points not being found, in the trash or not pending
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***ids*** - ids of the points
+/// - ***status*** - approved or rejected
+/// - ***reviewed_by*** - user reviewing the points
+/// - ***note*** - note for the owner of the points e.g. why they were rejected
+pub async fn review(db: &SqlitePool, ids: &[i64], status: model::PointsStatus,
+  reviewed_by: Option<i64>, note: Option<&str>) -> errors::Result<()>
+{
+  if status == model::PointsStatus::Pending {
+    let msg = "Points can only be reviewed as approved or rejected";
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg));
+  }
+  if ids.is_empty() {
+    let msg = "At least one points id is required for a review";
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg));
+  }
+
+  // Skipped points roll back the whole review by dropping the transaction before commit
+  let result = async {
+    let mut tx = db.begin().await?;
+    let mut skipped = vec![];
+    for id in ids.iter() {
+      let before = super::audit::snapshot::<model::Points>(&mut tx, "point", *id).await?;
+      let logged = super::streak::counted(&mut tx, *id).await?;
+      let query = sqlx::query(r#"UPDATE point SET status = ?, note = ?, reviewed_by = ?,
+        reviewed_at = datetime('subsec')
+        WHERE id = ? AND status = 'pending' AND deleted_at IS NULL"#)
+        .bind(status).bind(note).bind(reviewed_by).bind(id).execute(&mut *tx).await?;
+      if query.rows_affected() == 0 {
+        skipped.push(id.to_string());
+        continue;
+      }
+      super::audit::record::<model::Points>(&mut tx, "point", *id, model::AuditAction::Update,
+        before).await?;
+      let now_logged = super::streak::counted(&mut tx, *id).await?;
+      super::streak::changed(&mut tx, logged, now_logged).await?;
+      super::badge::evaluate_by_point(&mut tx, *id).await?;
+    }
+    if !skipped.is_empty() {
+      return Ok(skipped);
+    }
+    tx.commit().await?;
+    Ok(skipped)
+  }.await;
+  match result {
+    Ok(skipped) if skipped.is_empty() => Ok(()),
+    Ok(skipped) => {
+      let msg = format!("Points with ids '{}' were not found or are not pending",
+        skipped.join(", "));
+      log::warn!("{msg}");
+      Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg))
+    },
+    Err(e) => {
+      let msg = "Error reviewing points";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Move a points to the trash
 /// 
+/// - does nothing if the points is not found or already in the trash
 /// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the points
 pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
 {
-  let result = sqlx::query(r#"DELETE from point WHERE id = ?"#).bind(id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Points>(&mut tx, "point", id).await?;
+    let logged = super::streak::counted(&mut tx, id).await?;
+    let query = sqlx::query(r#"UPDATE point SET deleted_at = datetime('subsec')
+      WHERE id = ? AND deleted_at IS NULL"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::record::<model::Points>(&mut tx, "point", id, model::AuditAction::Delete,
+        before).await?;
+      super::streak::changed(&mut tx, logged, None).await?;
+    }
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error deleting points with id '{id}'");
     log::error!("{msg}");
@@ -197,6 +412,69 @@ pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
   Ok(())
 }
 
+/// Restore a points from the trash
+/// 
+/// - error on not found in the trash
+/// - error on the owning user being in the trash, restore the user instead
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the points
+pub async fn restore_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = sqlx::query_scalar::<_, i64>(r#"SELECT user_id FROM point
+    WHERE id = ? AND deleted_at IS NOT NULL"#).bind(id).fetch_one(db).await;
+  let user_id = match result {
+    Ok(user_id) => user_id,
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Points with id '{id}' was not found in the trash");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching points with id '{id}' from the trash");
+      log::error!("{msg}");
+      return Err(errors::Error::from_sqlx(e, &msg));
+    }
+  };
+  if super::user::fetch_by_id(db, user_id).await.is_err() {
+    let msg = format!("User with id '{user_id}' must be restored before points with id '{id}'");
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Points>(&mut tx, "point", id).await?;
+    sqlx::query(r#"UPDATE point SET deleted_at = NULL WHERE id = ?"#)
+      .bind(id).execute(&mut *tx).await?;
+    super::audit::record::<model::Points>(&mut tx, "point", id, model::AuditAction::Restore,
+      before).await?;
+    let logged = super::streak::counted(&mut tx, id).await?;
+    super::streak::changed(&mut tx, None, logged).await?;
+    super::badge::evaluate_by_point(&mut tx, id).await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error restoring points with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
+// Ensure the value is allowed for the kind e.g. awards can't take points away
+fn validate_kind(kind: model::PointsKind, value: i64) -> errors::Result<model::PointsKind>
+{
+  if kind.allows(value) {
+    return Ok(kind);
+  }
+  let msg = format!("Points value '{value}' is not allowed for kind '{}'", kind.as_str());
+  log::warn!("{msg}");
+  Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg))
+}
+
 #[cfg(test)]
 mod tests
 {
@@ -237,7 +515,7 @@ mod tests
       .with_desc(action1)).await.unwrap();
     let id = insert(state.db(), points1, user_id, action_id).await.unwrap();
 
-    update_by_id(state.db(), id, points2).await.unwrap();
+    update_by_id(state.db(), id, points2, None).await.unwrap();
 
     let points = fetch_by_id(state.db(), id).await.unwrap();
     assert_eq!(points.id, 1);
@@ -250,11 +528,43 @@ mod tests
   {
     let state = state::test().await;
 
-    let err = update_by_id(state.db(), -1, 10).await.unwrap_err().to_http();
+    let err = update_by_id(state.db(), -1, 10, None).await.unwrap_err().to_http();
     assert_eq!(err.status, StatusCode::NOT_FOUND);
     assert_eq!(err.msg, format!("Points with id '-1' was not found"));
   }
 
+  #[tokio::test]
+  async fn test_kinds_classify_validate_and_filter()
+  {
+    let state = state::test().await;
+    let db = state.db();
+    let user_id = db::user::insert(db, "user1", "user1@foo.com").await.unwrap();
+    let award = insert(db, 10, user_id, 1).await.unwrap();
+    let penalty = insert(db, -3, user_id, 1).await.unwrap();
+    let kinded = |value: i64, kind: model::PointsKind| model::CreatePoints { value, user_id,
+      action_id: 1, occurred_at: None, kind: Some(kind) };
+    insert_with(db, &kinded(-2, model::PointsKind::Adjustment), None,
+      model::PointsStatus::Approved).await.unwrap();
+    assert_eq!(fetch_by_id(db, penalty).await.unwrap().kind, model::PointsKind::Penalty);
+
+    // Values must agree with the kind given
+    let err = insert_with(db, &kinded(-5, model::PointsKind::Award), None,
+      model::PointsStatus::Approved).await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Sums can leave out or pick kinds
+    let filter = model::Filter::new().with_user_id(user_id);
+    assert_eq!(sum_by_filter(db, filter.clone()).await.unwrap(), 5);
+    let without = filter.clone().with_kinds_ne(vec![model::PointsKind::Adjustment]);
+    assert_eq!(sum_by_filter(db, without).await.unwrap(), 7);
+    let penalties = filter.clone().with_kinds(vec![model::PointsKind::Penalty]);
+    assert_eq!(sum_by_filter(db, penalties.clone()).await.unwrap(), -3);
+
+    // Awards turn into penalties when their value goes negative
+    update_by_id(db, award, -1, None).await.unwrap();
+    assert_eq!(fetch_by_filter(db, penalties).await.unwrap().len(), 2);
+  }
+
   #[tokio::test]
   async fn test_sum_by_filter_by_date_range_success()
   {
@@ -510,6 +820,97 @@ mod tests
     assert_eq!(points.len(), 0);
   }
 
+  #[tokio::test]
+  async fn test_fetch_by_filter_by_start_date_only_success()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    insert(state.db(), 10, user_id, action_id).await.unwrap();
+    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    let start = chrono::Local::now();
+    insert(state.db(), 20, user_id, action_id).await.unwrap();
+
+    // Open ended range includes everything from the start date on
+    let points = fetch_by_filter(state.db(), model::Filter::default()
+      .with_start_date(start)).await.unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].value, 20);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_by_filter_by_user_ids_and_value_success()
+  {
+    let state = state::test().await;
+    let user_id_1 = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let user_id_2 = db::user::insert(state.db(), "user2", "user2@foo.com").await.unwrap();
+    let user_id_3 = db::user::insert(state.db(), "user3", "user3@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    insert(state.db(), 5, user_id_1, action_id).await.unwrap();
+    insert(state.db(), 15, user_id_2, action_id).await.unwrap();
+    insert(state.db(), 15, user_id_3, action_id).await.unwrap();
+    insert(state.db(), 25, user_id_1, action_id).await.unwrap();
+    insert(state.db(), 35, user_id_2, action_id).await.unwrap();
+
+    let points = fetch_by_filter(state.db(), model::Filter::default()
+      .with_user_ids(vec![user_id_1, user_id_2])
+      .with_value_gt(10).with_value_lt(30)).await.unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].value, 15);
+    assert_eq!(points[0].user_id, user_id_2);
+    assert_eq!(points[1].value, 25);
+    assert_eq!(points[1].user_id, user_id_1);
+
+    let total = sum_by_filter(state.db(), model::Filter::default()
+      .with_user_ids(vec![user_id_1, user_id_2])
+      .with_value_gt(10)).await.unwrap();
+    assert_eq!(total, 75);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_by_filter_by_category_and_approved_success()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let category_id = db::category::insert(state.db(), "category1").await.unwrap();
+    let action_id_1 = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1").with_category_id(category_id).with_approved(true)).await.unwrap();
+    let action_id_2 = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action2").with_category_id(category_id)).await.unwrap();
+    let action_id_3 = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action3").with_approved(true)).await.unwrap();
+
+    insert(state.db(), 10, user_id, action_id_1).await.unwrap();
+    insert(state.db(), 20, user_id, action_id_2).await.unwrap();
+    insert(state.db(), 30, user_id, action_id_3).await.unwrap();
+
+    let points = fetch_by_filter(state.db(), model::Filter::default()
+      .with_category_id(category_id)).await.unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].action_id, action_id_1);
+    assert_eq!(points[1].action_id, action_id_2);
+
+    let points = fetch_by_filter(state.db(), model::Filter::default()
+      .with_category_id(category_id).with_approved(true)).await.unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].action_id, action_id_1);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_by_filter_failure_user_id_and_user_ids()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    let err = fetch_by_filter(state.db(), model::Filter::default()
+      .with_user_id(user_id).with_user_ids(vec![user_id])).await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+  }
+
   #[tokio::test]
   async fn test_fetch_by_user_id_failure_not_found()
   {
@@ -597,4 +998,42 @@ mod tests
     assert!(points.created_at <= chrono::Local::now());
     assert!(points.updated_at <= chrono::Local::now());
   }
+
+  #[tokio::test]
+  async fn test_review_pending_points()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let pending = |value: i64| model::CreatePoints { value, user_id, action_id: 1,
+      occurred_at: None, kind: None };
+    let id1 = insert_with(state.db(), &pending(10), None, model::PointsStatus::Pending)
+      .await.unwrap();
+    let id2 = insert_with(state.db(), &pending(5), None, model::PointsStatus::Pending)
+      .await.unwrap();
+    insert(state.db(), 1, user_id, 1).await.unwrap();
+
+    // Pending points don't count until approved
+    let filter = model::Filter::new().with_user_id(user_id);
+    assert_eq!(sum_by_filter(state.db(), filter.clone()).await.unwrap(), 1);
+    assert_eq!(db::ledger::balance(state.db(), user_id).await.unwrap().available, 1);
+
+    review(state.db(), &[id1], model::PointsStatus::Approved, Some(1), Some("nice")).await.unwrap();
+    let points = fetch_by_id(state.db(), id1).await.unwrap();
+    assert_eq!(points.status, model::PointsStatus::Approved);
+    assert_eq!((points.note.as_deref(), points.reviewed_by), (Some("nice"), Some(1)));
+    assert!(points.reviewed_at.is_some());
+    assert_eq!(sum_by_filter(state.db(), filter).await.unwrap(), 11);
+
+    // Reviews are all or nothing
+    let err = review(state.db(), &[id2, id1], model::PointsStatus::Rejected, Some(1), None)
+      .await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(err.msg, format!("Points with ids '{id1}' were not found or are not pending"));
+    let points = fetch_by_id(state.db(), id2).await.unwrap();
+    assert_eq!(points.status, model::PointsStatus::Pending);
+
+    let err = review(state.db(), &[id2], model::PointsStatus::Pending, Some(1), None)
+      .await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+  }
 }
\ No newline at end of file
@@ -0,0 +1,365 @@
+use axum::http::StatusCode;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use crate::{ errors, model };
+
+/// Request a redemption holding its value from the user's available balance
+///
+/// - the value is the item's cost when an item is requested
+/// - error on neither or both of item_id and value given
+/// - error on a value less than one
+/// - error on user or item not found
+/// - error on the item being inactive or out of stock
+/// - error on the user's available balance not covering the value
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user requesting
+/// - ***redemption*** - the item or value requested and why
+///
+/// #### Returns
+/// - ***id*** - id of the redemption
+pub async fn insert(db: &SqlitePool, user_id: i64, redemption: &model::CreateRedemption)
+  -> errors::Result<i64>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let value = match (redemption.item_id, redemption.value) {
+    (Some(item_id), None) => {
+      let item = super::item::fetch_by_id(db, item_id).await?;
+      if !item.active || item.stock == Some(0) {
+        return Err(unprocessable(&format!("Item '{}' is not available", item.name)));
+      }
+      item.cost
+    },
+    (None, Some(value)) if value > 0 => value,
+    (None, Some(_)) => return Err(unprocessable("Redemption value must be at least 1")),
+    _ => return Err(unprocessable("Either item_id or value is required for a redemption")),
+  };
+
+  // Rejections roll back by dropping the transaction before commit
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO redemption (user_id, item_id, value, reason)
+      VALUES (?, ?, ?, ?)"#)
+      .bind(user_id).bind(redemption.item_id).bind(value).bind(&redemption.reason)
+      .execute(&mut *tx).await?.last_insert_rowid();
+    let available = super::ledger::available(&mut tx, user_id).await?;
+    if available < 0 {
+      return Ok(Err(format!("Redemption of {value} points is more than the {} points available",
+        available + value)));
+    }
+    super::audit::record::<model::Redemption>(&mut tx, "redemption", id,
+      model::AuditAction::Create, None).await?;
+    tx.commit().await?;
+    Ok(Ok(id))
+  }.await;
+  match result {
+    Ok(Ok(id)) => Ok(id),
+    Ok(Err(msg)) => Err(unprocessable(&msg)),
+    Err(e) => {
+      let msg = format!("Error inserting redemption for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get a redemption by id from the database
+///
+/// - error on not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the redemption
+///
+/// #### Returns
+/// - ***redemption*** - the redemption entry
+pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Redemption>
+{
+  let result = sqlx::query_as::<_, model::Redemption>(r#"SELECT * FROM redemption WHERE id = ?"#)
+    .bind(id).fetch_one(db).await;
+  match result {
+    Ok(redemption) => Ok(redemption),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Redemption with id '{id}' was not found");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching redemption with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get the redemptions matching the given query
+///
+/// - orders the redemptions newest first
+/// - error on user not found if user_id is provided
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***query*** - supports ***user_id*** and ***status***
+///
+/// #### Returns
+/// - ***redemptions*** - the redemption entries
+pub async fn fetch_by_query(db: &SqlitePool, query: &model::RedemptionQuery)
+  -> errors::Result<Vec<model::Redemption>>
+{
+  if let Some(user_id) = query.user_id {
+    super::user::fetch_by_id(db, user_id).await?;
+  }
+
+  let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM redemption WHERE 1 = 1");
+  if let Some(user_id) = query.user_id {
+    select.push(" AND user_id = ").push_bind(user_id);
+  }
+  if let Some(status) = query.status {
+    select.push(" AND status = ").push_bind(status);
+  }
+  select.push(" ORDER BY id DESC");
+
+  let result = select.build_query_as::<model::Redemption>().fetch_all(db).await;
+  match result {
+    Ok(redemptions) => Ok(redemptions),
+    Err(e) => {
+      let msg = "Error fetching redemptions";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Move a redemption on to the given status
+///
+/// - ***approved***, ***denied*** and ***cancelled*** are only reached from ***requested*** and
+///   record who decided, when and the note if any
+/// - ***fulfilled*** is only reached from ***approved***
+/// - approving spends the held value on a reward linked to the redemption and its item, checking
+///   the item's stock and limit the same as redeeming it directly
+/// - error on not found
+/// - error on the redemption not being in the status the given one is reached from
+/// - error on the reward being rejected see `db::reward::spend`
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the redemption
+/// - ***status*** - status to move to, ***requested*** and ***expired*** can't be moved to
+/// - ***decided_by*** - id of the user making the decision
+/// - ***note*** - note on the decision
+pub async fn update_status(db: &SqlitePool, id: i64, status: model::RedemptionStatus,
+  decided_by: Option<i64>, note: Option<&str>) -> errors::Result<()>
+{
+  let redemption = fetch_by_id(db, id).await?;
+  let Some(from) = status.reached_from() else {
+    return Err(unprocessable(&format!("Redemptions can't be moved to '{}'", status.as_str())));
+  };
+  let item = match (status, redemption.item_id) {
+    (model::RedemptionStatus::Approved, Some(item_id)) =>
+      Some(super::item::fetch_by_id(db, item_id).await?),
+    _ => None,
+  };
+
+  // Rejections roll back by dropping the transaction before commit
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Redemption>(&mut tx, "redemption", id).await?;
+    let query = match status {
+      model::RedemptionStatus::Fulfilled => sqlx::query(r#"UPDATE redemption SET status = ?
+        WHERE id = ? AND status = ?"#).bind(status).bind(id).bind(from),
+      _ => sqlx::query(r#"UPDATE redemption SET status = ?, decided_by = ?, note = ?,
+        decided_at = datetime('subsec') WHERE id = ? AND status = ?"#)
+        .bind(status).bind(decided_by).bind(note).bind(id).bind(from),
+    };
+    if query.execute(&mut *tx).await?.rows_affected() == 0 {
+      let current = sqlx::query_scalar::<_, model::RedemptionStatus>(
+        r#"SELECT status FROM redemption WHERE id = ?"#).bind(id).fetch_one(&mut *tx).await?;
+      return Ok(Err(format!("Redemption with id '{id}' is '{}' and can't become '{}'",
+        current.as_str(), status.as_str())));
+    }
+
+    // The hold is released by the status change above so the reward is checked against the rest
+    if status == model::RedemptionStatus::Approved {
+      let reward_id = match super::reward::spend(&mut tx, redemption.user_id, redemption.value,
+        item.as_ref()).await?
+      {
+        Ok(reward_id) => reward_id,
+        Err(msg) => return Ok(Err(msg)),
+      };
+      sqlx::query(r#"UPDATE redemption SET reward_id = ? WHERE id = ?"#)
+        .bind(reward_id).bind(id).execute(&mut *tx).await?;
+    }
+    super::audit::record::<model::Redemption>(&mut tx, "redemption", id,
+      model::AuditAction::Update, before).await?;
+    tx.commit().await?;
+    Ok(Ok(()))
+  }.await;
+  match result {
+    Ok(Ok(())) => Ok(()),
+    Ok(Err(msg)) => Err(unprocessable(&msg)),
+    Err(e) => {
+      let msg = format!("Error updating redemption with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Expire the redemptions that have been waiting on a decision since before the given time
+///
+/// - releases the held value of each
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***before*** - requests made before this time are expired
+///
+/// #### Returns
+/// - ***count*** - number of redemptions expired
+pub async fn expire(db: &SqlitePool, before: chrono::DateTime<chrono::Utc>)
+  -> errors::Result<u64>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let ids = sqlx::query_scalar::<_, i64>(r#"SELECT id FROM redemption
+      WHERE status = 'requested' AND datetime(created_at) < datetime(?)"#)
+      .bind(before.naive_utc()).fetch_all(&mut *tx).await?;
+    for id in ids.iter() {
+      let before = super::audit::snapshot::<model::Redemption>(&mut tx, "redemption", *id).await?;
+      sqlx::query(r#"UPDATE redemption SET status = 'expired', decided_at = datetime('subsec')
+        WHERE id = ?"#).bind(id).execute(&mut *tx).await?;
+      super::audit::record::<model::Redemption>(&mut tx, "redemption", *id,
+        model::AuditAction::Update, before).await?;
+    }
+    tx.commit().await?;
+    Ok(ids.len() as u64)
+  }.await;
+  match result {
+    Ok(count) => Ok(count),
+    Err(e) => {
+      let msg = "Error expiring redemptions";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+// Log and build an unprocessable entity error
+fn unprocessable(msg: &str) -> errors::Error
+{
+  log::warn!("{msg}");
+  errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  // Helper to request the given value
+  async fn request(db: &SqlitePool, user_id: i64, value: i64) -> errors::Result<i64>
+  {
+    insert(db, user_id, &model::CreateRedemption { value: Some(value), ..Default::default() })
+      .await
+  }
+
+  #[tokio::test]
+  async fn test_requests_hold_points_until_decided()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let denied = request(state.db(), user_id, 6).await.unwrap();
+
+    // The held points can't be requested or spent again
+    let err = request(state.db(), user_id, 5).await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let balance = db::ledger::balance(state.db(), user_id).await.unwrap();
+    assert_eq!((balance.spent, balance.held, balance.available), (0, 6, 4));
+
+    update_status(state.db(), denied, model::RedemptionStatus::Denied, Some(1), Some("not today"))
+      .await.unwrap();
+    let redemption = fetch_by_id(state.db(), denied).await.unwrap();
+    assert_eq!((redemption.decided_by, redemption.note.as_deref()), (Some(1), Some("not today")));
+    assert!(redemption.decided_at.is_some() && redemption.reward_id.is_none());
+
+    let approved = request(state.db(), user_id, 5).await.unwrap();
+    update_status(state.db(), approved, model::RedemptionStatus::Approved, Some(1), None)
+      .await.unwrap();
+    let redemption = fetch_by_id(state.db(), approved).await.unwrap();
+    let reward = db::reward::fetch_by_id(state.db(), redemption.reward_id.unwrap()).await.unwrap();
+    assert_eq!((reward.value, reward.user_id), (5, user_id));
+    let balance = db::ledger::balance(state.db(), user_id).await.unwrap();
+    assert_eq!((balance.spent, balance.held, balance.available), (5, 0, 5));
+  }
+
+  #[tokio::test]
+  async fn test_update_status_enforces_lifecycle()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let id = request(state.db(), user_id, 2).await.unwrap();
+
+    let err = update_status(state.db(), id, model::RedemptionStatus::Fulfilled, Some(1), None)
+      .await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    update_status(state.db(), id, model::RedemptionStatus::Approved, Some(1), None).await.unwrap();
+    for status in [model::RedemptionStatus::Approved, model::RedemptionStatus::Denied,
+      model::RedemptionStatus::Cancelled, model::RedemptionStatus::Requested,
+      model::RedemptionStatus::Expired]
+    {
+      let err = update_status(state.db(), id, status, Some(1), None).await.unwrap_err().to_http();
+      assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY, "{status:?}");
+    }
+    update_status(state.db(), id, model::RedemptionStatus::Fulfilled, Some(1), None).await.unwrap();
+    assert_eq!(fetch_by_id(state.db(), id).await.unwrap().status,
+      model::RedemptionStatus::Fulfilled);
+  }
+
+  #[tokio::test]
+  async fn test_approve_item_checks_stock()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let item_id = db::item::insert(state.db(), &model::ItemPartial::new("item1", 3).with_stock(1))
+      .await.unwrap();
+    let request = model::CreateRedemption { item_id: Some(item_id), ..Default::default() };
+    let first = insert(state.db(), user_id, &request).await.unwrap();
+    let second = insert(state.db(), user_id, &request).await.unwrap();
+
+    update_status(state.db(), first, model::RedemptionStatus::Approved, Some(1), None)
+      .await.unwrap();
+    let err = update_status(state.db(), second, model::RedemptionStatus::Approved, Some(1), None)
+      .await.unwrap_err().to_http();
+    assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(fetch_by_id(state.db(), second).await.unwrap().status,
+      model::RedemptionStatus::Requested);
+
+    let rewards = db::reward::fetch_by_user_id(state.db(), user_id).await.unwrap();
+    assert_eq!(rewards.iter().map(|x| (x.value, x.item_id)).collect::<Vec<_>>(),
+      vec![(3, Some(item_id))]);
+  }
+
+  #[tokio::test]
+  async fn test_expire()
+  {
+    let state = state::test().await;
+    let user_id = db::point::user_with_points(state.db(), 10).await;
+    let id = request(state.db(), user_id, 2).await.unwrap();
+    let decided = request(state.db(), user_id, 2).await.unwrap();
+    update_status(state.db(), decided, model::RedemptionStatus::Cancelled, Some(user_id), None)
+      .await.unwrap();
+
+    assert_eq!(expire(state.db(), chrono::Utc::now() - chrono::Duration::hours(1)).await.unwrap(),
+      0);
+    assert_eq!(expire(state.db(), chrono::Utc::now() + chrono::Duration::hours(1)).await.unwrap(),
+      1);
+    assert_eq!(fetch_by_id(state.db(), id).await.unwrap().status,
+      model::RedemptionStatus::Expired);
+    assert_eq!(db::ledger::balance(state.db(), user_id).await.unwrap().held, 0);
+  }
+}
@@ -1,4 +1,5 @@
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, SqliteConnection, SqlitePool};
+use axum::http::StatusCode;
 use crate::{ errors, model };
 
 /// Insert a new reward into the database
@@ -17,10 +18,17 @@ pub async fn insert(db: &SqlitePool, value: i64, user_id: i64) -> errors::Result
 {
   super::user::fetch_by_id(db, user_id).await?;
 
-  let result = sqlx::query(r#"INSERT INTO reward (value, user_id) VALUES (?, ?)"#)
-    .bind(value).bind(user_id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO reward (value, user_id) VALUES (?, ?)"#)
+      .bind(value).bind(user_id).execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Reward>(&mut tx, "reward", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
   match result {
-    Ok(query) => Ok(query.last_insert_rowid()),
+    Ok(id) => Ok(id),
     Err(e) => {
       let msg = format!("Error inserting reward '{value}'");
       log::error!("{msg}");
@@ -29,9 +37,46 @@ pub async fn insert(db: &SqlitePool, value: i64, user_id: i64) -> errors::Result
   }
 }
 
+/// Spend the given user's points on a reward
+///
+/// - the reward is written before the checks so that the transaction holds the write lock and
+///   concurrent spending is serialized rather than overdrawing the balance together
+/// - rejects the value being more than the user's available balance
+/// - rejects the item being unavailable see `db::item::take`
+///
+/// #### Parameters
+/// - ***conn*** - transaction to spend in, rejections must roll it back
+/// - ***user_id*** - id of the user
+/// - ***value*** - value of the reward
+/// - ***item*** - catalog item the reward is for if any
+///
+/// #### Returns
+/// - ***id*** - id of the reward or why it was rejected
+pub(crate) async fn spend(conn: &mut SqliteConnection, user_id: i64, value: i64,
+  item: Option<&model::Item>) -> Result<Result<i64, String>, sqlx::Error>
+{
+  let id = sqlx::query(r#"INSERT INTO reward (value, user_id, item_id) VALUES (?, ?, ?)"#)
+    .bind(value).bind(user_id).bind(item.map(|x| x.id)).execute(&mut *conn).await?
+    .last_insert_rowid();
+  if let Some(item) = item {
+    if let Err(msg) = super::item::take(conn, item, user_id).await? {
+      return Ok(Err(msg));
+    }
+  }
+
+  let available = super::ledger::available(conn, user_id).await?;
+  if available < 0 {
+    return Ok(Err(format!("Reward of {value} points is more than the {} points available",
+      available + value)));
+  }
+  super::audit::record::<model::Reward>(conn, "reward", id, model::AuditAction::Create, None)
+    .await?;
+  Ok(Ok(id))
+}
+
 /// Get a reward by ID from the database
 /// 
-/// - error on reward not found
+/// - error on reward not found or in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -42,7 +87,8 @@ pub async fn insert(db: &SqlitePool, value: i64, user_id: i64) -> errors::Result
 /// - ***reward*** - the reward entry
 pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Reward>
 {
-  let result = sqlx::query_as::<_, model::Reward>(r#"SELECT * FROM reward WHERE id = ?"#)
+  let result = sqlx::query_as::<_, model::Reward>(
+    r#"SELECT * FROM reward WHERE id = ? AND deleted_at IS NULL"#)
     .bind(id).fetch_one(db).await;
   match result {
     Ok(reward) => Ok(reward),
@@ -70,11 +116,13 @@ pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::Rewa
 /// 
 /// #### Returns
 /// - ***rewards*** - the rewards entries
+#[cfg(test)]
 pub async fn fetch_by_user_id(db: &SqlitePool, user_id: i64) -> errors::Result<Vec<model::Reward>>
 {
   super::user::fetch_by_id(db, user_id).await?;
 
-  let result = sqlx::query_as::<_, model::Reward>(r#"SELECT * FROM reward WHERE user_id = ?"#)
+  let result = sqlx::query_as::<_, model::Reward>(r#"SELECT * FROM reward
+    WHERE user_id = ? AND deleted_at IS NULL"#)
     .bind(user_id).fetch_all(db).await;
   match result {
     Ok(rewards) => Ok(rewards),
@@ -98,7 +146,8 @@ pub async fn fetch_by_user_id(db: &SqlitePool, user_id: i64) -> errors::Result<V
 /// - ***rewards*** - the rewards entries
 pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Reward>>
 {
-  let result = sqlx::query_as::<_, model::Reward>(r#"SELECT * FROM reward"#)
+  let result = sqlx::query_as::<_, model::Reward>(
+    r#"SELECT * FROM reward WHERE deleted_at IS NULL"#)
     .fetch_all(db).await;
   match result {
     Ok(rewards) => Ok(rewards),
@@ -110,33 +159,58 @@ pub async fn fetch_all(db: &SqlitePool) -> errors::Result<Vec<model::Reward>>
   }
 }
 
-/// Sum all rewards for the given user and or action
+/// Get all rewards for the given filter
+/// 
+/// - Start defines the oldest date to include
+/// - End defines the newest date to include
+/// 
+/// - error on user not found if user_id or user_ids is provided
+/// - error on item not found if item_id is provided
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***filter*** supports filter params:
+///   - ***user_id=***, ***user_ids=***, ***item_id=***, ***value_gt=***, ***value_lt=***
+///   - ***start_date=***, ***end_date=***
+pub async fn fetch_by_filter(db: &SqlitePool, filter: model::Filter)
+  -> errors::Result<Vec<model::Reward>>
+{
+  let mut query = QueryBuilder::new("SELECT reward.* FROM reward");
+  filter.push_rewards_where_clause(db, &mut query).await?;
+  query.push(" ORDER BY reward.created_at");
+
+  let result = query.build_query_as::<model::Reward>().fetch_all(db).await;
+  match result {
+    Ok(rewards) => Ok(rewards),
+    Err(e) => {
+      let msg = "Error fetching rewards by filter";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+/// Sum all rewards for the given filter
 /// 
 /// - Start defines the oldest date to include in the sum
 /// - End defines the newest date to include in the sum
 /// 
-/// - error on user not found if user_id is provided
-/// - error on action not found if action_id is provided
+/// - error on user not found if user_id or user_ids is provided
+/// - error on item not found if item_id is provided
 /// - error on other SQL errors
 /// 
 /// #### Parameters
 /// - ***db*** - database connection pool
 /// - ***filter*** supports filter params:
-///   - ***user_id=***, ***action_id=***, ***start_date=***, ***end_date=***
+///   - ***user_id=***, ***user_ids=***, ***item_id=***, ***value_gt=***, ***value_lt=***
+///   - ***start_date=***, ***end_date=***
 pub async fn sum_by_filter(db: &SqlitePool, filter: model::Filter) -> errors::Result<i64>
 {
-  let where_clause = filter.to_rewards_where_clause(db).await?;
-  let query_str = format!("SELECT SUM(value) as total FROM reward {where_clause}");
-  let mut query = sqlx::query_as::<_, (Option<i64>,)>(&query_str);
-  
-  if let Some(user_id) = filter.user_id {
-    query = query.bind(user_id);
-  }
-  if let Some((start, end)) = filter.date_range() {
-    query = query.bind(start).bind(end);
-  }
+  let mut query = QueryBuilder::new("SELECT SUM(reward.value) as total FROM reward");
+  filter.push_rewards_where_clause(db, &mut query).await?;
 
-  let result = query.fetch_one(db).await;
+  let result = query.build_query_as::<(Option<i64>,)>().fetch_one(db).await;
   match result {
     Ok((total,)) => Ok(total.unwrap_or(0)),
     Err(e) => {
@@ -163,8 +237,15 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, value: i64) -> errors::Resul
 
   // Update reward value if changed
   if reward.value != value {
-    let result = sqlx::query(r#"UPDATE reward SET value = ? WHERE id = ?"#)
-      .bind(&value).bind(&id).execute(db).await;
+    let result = async {
+      let mut tx = db.begin().await?;
+      let before = super::audit::snapshot::<model::Reward>(&mut tx, "reward", id).await?;
+      sqlx::query(r#"UPDATE reward SET value = ? WHERE id = ?"#)
+        .bind(&value).bind(&id).execute(&mut *tx).await?;
+      super::audit::record::<model::Reward>(&mut tx, "reward", id, model::AuditAction::Update,
+        before).await?;
+      tx.commit().await
+    }.await;
     if let Err(e) = result {
       let msg = format!("Error updating reward with id '{id}'");
       log::error!("{msg}");
@@ -174,8 +255,9 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, value: i64) -> errors::Resul
   Ok(())
 }
 
-/// Delete a reward in the database
+/// Move a reward to the trash
 /// 
+/// - does nothing if the reward is not found or already in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -183,7 +265,17 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, value: i64) -> errors::Resul
 /// - ***id*** - id of the reward
 pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
 {
-  let result = sqlx::query(r#"DELETE from reward WHERE id = ?"#).bind(id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Reward>(&mut tx, "reward", id).await?;
+    let query = sqlx::query(r#"UPDATE reward SET deleted_at = datetime('subsec')
+      WHERE id = ? AND deleted_at IS NULL"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::record::<model::Reward>(&mut tx, "reward", id, model::AuditAction::Delete,
+        before).await?;
+    }
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error deleting reward with id '{id}'");
     log::error!("{msg}");
@@ -192,6 +284,55 @@ pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
   Ok(())
 }
 
+/// Restore a reward from the trash
+/// 
+/// - error on not found in the trash
+/// - error on the owning user being in the trash, restore the user instead
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***id*** - id of the reward
+pub async fn restore_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
+{
+  let result = sqlx::query_scalar::<_, i64>(r#"SELECT user_id FROM reward
+    WHERE id = ? AND deleted_at IS NOT NULL"#).bind(id).fetch_one(db).await;
+  let user_id = match result {
+    Ok(user_id) => user_id,
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("Reward with id '{id}' was not found in the trash");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error fetching reward with id '{id}' from the trash");
+      log::error!("{msg}");
+      return Err(errors::Error::from_sqlx(e, &msg));
+    }
+  };
+  if super::user::fetch_by_id(db, user_id).await.is_err() {
+    let msg = format!("User with id '{user_id}' must be restored before reward with id '{id}'");
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Reward>(&mut tx, "reward", id).await?;
+    sqlx::query(r#"UPDATE reward SET deleted_at = NULL WHERE id = ?"#)
+      .bind(id).execute(&mut *tx).await?;
+    super::audit::record::<model::Reward>(&mut tx, "reward", id, model::AuditAction::Restore,
+      before).await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error restoring reward with id '{id}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
+  Ok(())
+}
+
 #[cfg(test)]
 mod tests
 {
@@ -17,10 +17,17 @@ pub async fn insert(db: &SqlitePool, name: &str) -> errors::Result<i64>
   validate_name_given(&name)?;
 
   // Create new role in the database
-  let result = sqlx::query(r#"INSERT INTO role (name) VALUES (?)"#)
-    .bind(name).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO role (name) VALUES (?)"#)
+      .bind(name).execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::Role>(&mut tx, "role", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
   match result {
-    Ok(query) => Ok(query.last_insert_rowid()),
+    Ok(id) => Ok(id),
     Err(e) => {
       if errors::Error::is_sqlx_unique_violation(&e) {
         let msg = format!("Role '{name}' already exists");
@@ -137,8 +144,15 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, name: &str) -> errors::Resul
     validate_name_given(&name)?;
 
     // Update role in database
-    let result = sqlx::query(r#"UPDATE role SET name = ? WHERE id = ?"#)
-      .bind(&name).bind(&id).execute(db).await;
+    let result = async {
+      let mut tx = db.begin().await?;
+      let before = super::audit::snapshot::<model::Role>(&mut tx, "role", id).await?;
+      sqlx::query(r#"UPDATE role SET name = ? WHERE id = ?"#)
+        .bind(&name).bind(&id).execute(&mut *tx).await?;
+      super::audit::record::<model::Role>(&mut tx, "role", id, model::AuditAction::Update,
+        before).await?;
+      tx.commit().await
+    }.await;
     if let Err(e) = result {
       let msg = format!("Error updating role with id '{id}'");
       log::error!("{msg}");
@@ -164,7 +178,15 @@ pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
     return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
   }
 
-  let result = sqlx::query(r#"DELETE from role WHERE id = ?"#).bind(id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::Role>(&mut tx, "role", id).await?;
+    let query = sqlx::query(r#"DELETE from role WHERE id = ?"#).bind(id).execute(&mut *tx).await?;
+    if query.rows_affected() > 0 {
+      super::audit::insert(&mut tx, "role", id, model::AuditAction::Delete, before, None).await?;
+    }
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error deleting role with id '{id}'");
     log::error!("{msg}");
@@ -0,0 +1,164 @@
+use sqlx::SqlitePool;
+use axum::http::StatusCode;
+use crate::{ errors, model };
+
+// Default and maximum number of hits returned by a search
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// Search actions, categories and users for the given text
+///
+/// - each word in the query is prefix matched so partial input works for search-as-you-type
+/// - hits of all kinds are ranked together with the best matches first
+/// - actions and users in the trash are not searched
+/// - error on query without any searchable words
+/// - error on limit out of range
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***query*** - text to search for and optional limit
+///
+/// #### Returns
+/// - ***hits*** - ranked search hits
+pub async fn search(db: &SqlitePool, query: &model::SearchQuery)
+  -> errors::Result<Vec<model::SearchHit>>
+{
+  let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+  if !(1..=MAX_LIMIT).contains(&limit) {
+    let msg = format!("Search limit must be between 1 and {MAX_LIMIT}");
+    log::warn!("{msg}");
+    return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+  let expr = to_match_expr(&query.q).ok_or_else(|| {
+    let msg = "Search query must contain at least one word";
+    log::warn!("{msg}");
+    errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg)
+  })?;
+
+  let result = sqlx::query_as::<_, model::SearchHit>(r#"
+    SELECT 'action' AS kind, rowid AS id, desc AS text, bm25(action_fts) AS rank
+      FROM action_fts WHERE action_fts MATCH ?1
+        AND rowid IN (SELECT id FROM action WHERE deleted_at IS NULL)
+    UNION ALL
+    SELECT 'category' AS kind, rowid AS id, name AS text, bm25(category_fts) AS rank
+      FROM category_fts WHERE category_fts MATCH ?1
+    UNION ALL
+    SELECT 'user' AS kind, rowid AS id, username AS text, bm25(user_fts) AS rank
+      FROM user_fts WHERE user_fts MATCH ?1
+        AND rowid IN (SELECT id FROM user WHERE deleted_at IS NULL)
+    ORDER BY rank, text COLLATE NOCASE LIMIT ?2"#)
+    .bind(&expr).bind(limit).fetch_all(db).await;
+  match result {
+    Ok(hits) => Ok(hits),
+    Err(e) => {
+      let msg = format!("Error searching for '{}'", query.q);
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+// Convert free text into an FTS5 match expression
+//
+// - every word is quoted so FTS5 operators and punctuation in the input are never interpreted
+// - every word is prefix matched e.g. `bru te` becomes `"bru"* "te"*`
+fn to_match_expr(text: &str) -> Option<String>
+{
+  let words = text.split(|c: char| !c.is_alphanumeric())
+    .filter(|x| !x.is_empty())
+    .map(|x| format!("\"{x}\"*"))
+    .collect::<Vec<_>>();
+  if words.is_empty() {
+    return None;
+  }
+  Some(words.join(" "))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  // Helper to build a search query
+  fn query(q: &str) -> model::SearchQuery
+  {
+    model::SearchQuery { q: q.to_string(), limit: None }
+  }
+
+  #[test]
+  fn test_to_match_expr()
+  {
+    assert_eq!(to_match_expr("bru te").unwrap(), r#""bru"* "te"*"#);
+    assert_eq!(to_match_expr(r#"a" OR b*"#).unwrap(), r#""a"* "OR"* "b"*"#);
+    assert!(to_match_expr(" \"*- ").is_none());
+  }
+
+  #[tokio::test]
+  async fn test_search_prefix_matches_all_kinds()
+  {
+    let state = state::test().await;
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("Brush teeth")).await.unwrap();
+    let category_id = db::category::insert(state.db(), "Bathroom").await.unwrap();
+    let user_id = db::user::insert(state.db(), "bruce", "bruce@foo.com").await.unwrap();
+    db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("Make bed")).await.unwrap();
+
+    let hits = search(state.db(), &query("br")).await.unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().any(|x| x.kind == model::SearchKind::Action && x.id == action_id));
+    assert!(hits.iter().any(|x| x.kind == model::SearchKind::User && x.id == user_id));
+
+    let hits = search(state.db(), &query("bath")).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, model::SearchKind::Category);
+    assert_eq!(hits[0].id, category_id);
+    assert_eq!(hits[0].text, "Bathroom");
+
+    // All words must match
+    let hits = search(state.db(), &query("brush te")).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].text, "Brush teeth");
+  }
+
+  #[tokio::test]
+  async fn test_search_tracks_updates_and_deletes()
+  {
+    let state = state::test().await;
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("Feed dog")).await.unwrap();
+    db::action::update_by_id(state.db(), action_id, &model::UpdateAction::new()
+      .with_desc("Feed cat")).await.unwrap();
+
+    assert_eq!(search(state.db(), &query("dog")).await.unwrap().len(), 0);
+    assert_eq!(search(state.db(), &query("cat")).await.unwrap().len(), 1);
+
+    db::action::delete_by_id(state.db(), action_id).await.unwrap();
+    assert_eq!(search(state.db(), &query("cat")).await.unwrap().len(), 0);
+  }
+
+  #[tokio::test]
+  async fn test_search_finds_seeded_rows()
+  {
+    let state = state::test().await;
+    let hits = search(state.db(), &query("unspec")).await.unwrap();
+    assert_eq!(hits.len(), 2);
+    let hits = search(state.db(), &query("adm")).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, model::SearchKind::User);
+  }
+
+  #[tokio::test]
+  async fn test_search_failure_invalid_query()
+  {
+    let state = state::test().await;
+    let err = search(state.db(), &query("  ")).await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let err = search(state.db(), &model::SearchQuery { q: "a".to_string(), limit: Some(0) })
+      .await.unwrap_err();
+    assert_eq!(err.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+  }
+}
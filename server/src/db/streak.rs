@@ -0,0 +1,457 @@
+use chrono::{Days, Local, NaiveDate};
+use sqlx::{SqliteConnection, SqlitePool};
+use crate::{ errors, model };
+
+// Points that count toward streaks, only approved points that aren't penalties or in the trash
+// the same as badges, a review counts or uncounts the points
+const COUNTED: &str = r#"point.value > 0 AND point.status = 'approved'
+  AND point.deleted_at IS NULL"#;
+
+/// Day and action the given points were logged for, when they count toward streaks
+///
+/// - ***day*** is the day the points occurred on in the household timezone
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+pub(crate) struct Logged {
+  pub user_id: i64,
+  pub action_id: i64,
+  pub category_id: i64,
+  pub day: NaiveDate,
+}
+
+/// Get the streaks of the given user for every action and category they logged points for
+///
+/// - streaks that were dropped since the last call are rebuilt from the per day counts
+/// - ***current*** is reported as zero once a day or scheduled occurrence was missed, today is
+///   never missed as it isn't over yet
+/// - error on user not found
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***user_id*** - id of the user
+///
+/// #### Returns
+/// - ***streaks*** - action streaks then category streaks ordered by id
+pub async fn fetch_by_user_id(db: &SqlitePool, user_id: i64) -> errors::Result<Vec<model::Streak>>
+{
+  super::user::fetch_by_id(db, user_id).await?;
+  let today = Local::now().date_naive();
+
+  let result = async {
+    let mut tx = db.begin().await?;
+    let keys = sqlx::query_as::<_, (model::StreakKind, i64)>(r#"SELECT DISTINCT 'action',
+      action_id FROM point_day WHERE user_id = ?1
+      UNION SELECT DISTINCT 'category', action.category_id FROM point_day
+      JOIN action ON action.id = point_day.action_id WHERE point_day.user_id = ?1
+      ORDER BY 1, 2"#).bind(user_id).fetch_all(&mut *tx).await?;
+
+    let mut streaks = vec![];
+    for (kind, ref_id) in keys {
+      let chores = schedule(&mut tx, user_id, kind, ref_id).await?;
+      let existing = sqlx::query_as::<_, model::Streak>(r#"SELECT * FROM streak
+        WHERE user_id = ? AND kind = ? AND ref_id = ?"#)
+        .bind(user_id).bind(kind).bind(ref_id).fetch_optional(&mut *tx).await?;
+      let streak = match existing {
+        Some(streak) => Some(streak),
+        None => rebuild(&mut tx, user_id, kind, ref_id, &chores).await?,
+      };
+      let Some(mut streak) = streak else { continue };
+      if missed_between(&chores, streak.last_on, today) {
+        streak.current = 0;
+      }
+      streak.scheduled = !chores.is_empty();
+      streaks.push(streak);
+    }
+    tx.commit().await?;
+    Ok::<_, sqlx::Error>(streaks)
+  }.await;
+  match result {
+    Ok(streaks) => Ok(streaks),
+    Err(e) => {
+      let msg = format!("Error fetching streaks for user with id '{user_id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+/// Get what the given points count toward
+///
+/// - used inside the transaction changing the points, before and after the change
+///
+/// #### Parameters
+/// - ***conn*** - connection or transaction to check with
+/// - ***point_id*** - id of the points
+///
+/// #### Returns
+/// - ***logged*** - the user, action, category and day or nothing when the points don't count
+pub(crate) async fn counted(conn: &mut SqliteConnection, point_id: i64)
+  -> Result<Option<Logged>, sqlx::Error>
+{
+  sqlx::query_as::<_, Logged>(&format!(r#"SELECT point.user_id, point.action_id,
+    action.category_id, date(point.occurred_at, 'localtime') AS day FROM point
+    JOIN action ON action.id = point.action_id WHERE point.id = ? AND {COUNTED}"#))
+    .bind(point_id).fetch_optional(&mut *conn).await
+}
+
+/// Keep the per day counts and streaks up to date after points changed
+///
+/// - does nothing when the points count toward the same thing before and after
+///
+/// #### Parameters
+/// - ***conn*** - transaction that changed the points
+/// - ***before*** - what the points counted toward before the change, see `counted`
+/// - ***after*** - what the points count toward after the change
+pub(crate) async fn changed(conn: &mut SqliteConnection, before: Option<Logged>,
+  after: Option<Logged>) -> Result<(), sqlx::Error>
+{
+  if before == after {
+    return Ok(());
+  }
+  if let Some(logged) = before {
+    remove(conn, &logged).await?;
+  }
+  if let Some(logged) = after {
+    add(conn, &logged).await?;
+  }
+  Ok(())
+}
+
+/// Get the longest streak of the given user for the given action or category
+///
+/// - used by badge rules inside the transaction that changed the points, building the streak
+///   when it was dropped
+///
+/// #### Parameters
+/// - ***conn*** - connection or transaction to read with
+/// - ***user_id*** - id of the user
+/// - ***kind*** - action or category
+/// - ***ref_id*** - id of the action or category
+///
+/// #### Returns
+/// - ***longest*** - the longest run or zero when nothing was logged
+pub(crate) async fn longest(conn: &mut SqliteConnection, user_id: i64, kind: model::StreakKind,
+  ref_id: i64) -> Result<i64, sqlx::Error>
+{
+  let longest = sqlx::query_scalar::<_, i64>(r#"SELECT longest FROM streak
+    WHERE user_id = ? AND kind = ? AND ref_id = ?"#)
+    .bind(user_id).bind(kind).bind(ref_id).fetch_optional(&mut *conn).await?;
+  if let Some(longest) = longest {
+    return Ok(longest);
+  }
+  let chores = schedule(conn, user_id, kind, ref_id).await?;
+  let streak = rebuild(conn, user_id, kind, ref_id, &chores).await?;
+  Ok(streak.map(|x| x.longest).unwrap_or_default())
+}
+
+/// Drop the streaks of all users for the given action or category
+///
+/// - used when a chore schedule or the category of an action changes, the streaks are rebuilt
+///   the next time they are fetched
+///
+/// #### Parameters
+/// - ***conn*** - transaction making the change
+/// - ***kind*** - action or category
+/// - ***ref_id*** - id of the action or category
+pub(crate) async fn invalidate(conn: &mut SqliteConnection, kind: model::StreakKind,
+  ref_id: i64) -> Result<(), sqlx::Error>
+{
+  sqlx::query(r#"DELETE FROM streak WHERE kind = ? AND ref_id = ?"#)
+    .bind(kind).bind(ref_id).execute(&mut *conn).await?;
+  Ok(())
+}
+
+/// Recount the per day counts from the points and drop the streaks
+///
+/// - used after many points changed at once e.g. a user moved to the trash or a backup restored
+///
+/// #### Parameters
+/// - ***conn*** - transaction making the change
+/// - ***user_id*** - id of the user to recount or all users if not given
+pub(crate) async fn reset(conn: &mut SqliteConnection, user_id: Option<i64>)
+  -> Result<(), sqlx::Error>
+{
+  for table in ["point_day", "streak"] {
+    sqlx::query(&format!("DELETE FROM {table} WHERE ?1 IS NULL OR user_id = ?1"))
+      .bind(user_id).execute(&mut *conn).await?;
+  }
+  sqlx::query(&format!(r#"INSERT INTO point_day (user_id, action_id, day, count)
+    SELECT user_id, action_id, date(occurred_at, 'localtime'), COUNT(*) FROM point
+    WHERE (?1 IS NULL OR user_id = ?1) AND {COUNTED}
+    GROUP BY user_id, action_id, date(occurred_at, 'localtime')"#))
+    .bind(user_id).execute(&mut *conn).await?;
+  Ok(())
+}
+
+// Count the points toward their day extending the streaks when the day is newly logged
+async fn add(conn: &mut SqliteConnection, logged: &Logged) -> Result<(), sqlx::Error>
+{
+  let count = sqlx::query_scalar::<_, i64>(r#"INSERT INTO point_day (user_id, action_id, day,
+    count) VALUES (?, ?, ?, 1) ON CONFLICT DO UPDATE SET count = count + 1 RETURNING count"#)
+    .bind(logged.user_id).bind(logged.action_id).bind(logged.day)
+    .fetch_one(&mut *conn).await?;
+  if count == 1 {
+    extend(conn, logged.user_id, model::StreakKind::Action, logged.action_id, logged.day).await?;
+  }
+  if category_count(conn, logged).await? == 1 {
+    extend(conn, logged.user_id, model::StreakKind::Category, logged.category_id, logged.day)
+      .await?;
+  }
+  Ok(())
+}
+
+// Take the points off their day dropping the streaks when the day is no longer logged
+async fn remove(conn: &mut SqliteConnection, logged: &Logged) -> Result<(), sqlx::Error>
+{
+  let count = sqlx::query_scalar::<_, i64>(r#"UPDATE point_day SET count = count - 1
+    WHERE user_id = ? AND action_id = ? AND day = ? RETURNING count"#)
+    .bind(logged.user_id).bind(logged.action_id).bind(logged.day)
+    .fetch_optional(&mut *conn).await?;
+  if count.unwrap_or_default() > 0 {
+    return Ok(());
+  }
+  sqlx::query(r#"DELETE FROM point_day WHERE user_id = ? AND action_id = ? AND day = ?"#)
+    .bind(logged.user_id).bind(logged.action_id).bind(logged.day).execute(&mut *conn).await?;
+  drop_streak(conn, logged.user_id, model::StreakKind::Action, logged.action_id).await?;
+  if category_count(conn, logged).await? == 0 {
+    drop_streak(conn, logged.user_id, model::StreakKind::Category, logged.category_id).await?;
+  }
+  Ok(())
+}
+
+// Extend the streak with a newly logged day
+//
+// - streaks that don't exist yet are left to be built when fetched
+// - days before the end of the streak split or join runs so the streak is dropped instead
+async fn extend(conn: &mut SqliteConnection, user_id: i64, kind: model::StreakKind, ref_id: i64,
+  day: NaiveDate) -> Result<(), sqlx::Error>
+{
+  let existing = sqlx::query_as::<_, model::Streak>(r#"SELECT * FROM streak
+    WHERE user_id = ? AND kind = ? AND ref_id = ?"#)
+    .bind(user_id).bind(kind).bind(ref_id).fetch_optional(&mut *conn).await?;
+  let Some(mut streak) = existing else { return Ok(()) };
+  let chores = schedule(conn, user_id, kind, ref_id).await?;
+  if !is_expected(&chores, day) {
+    return Ok(());
+  }
+  if day <= streak.last_on {
+    return drop_streak(conn, user_id, kind, ref_id).await;
+  }
+
+  streak.current = if missed_between(&chores, streak.last_on, day) { 1 } else { streak.current + 1 };
+  sqlx::query(r#"UPDATE streak SET current = ?, longest = MAX(longest, ?), last_on = ?
+    WHERE user_id = ? AND kind = ? AND ref_id = ?"#)
+    .bind(streak.current).bind(streak.current).bind(day)
+    .bind(user_id).bind(kind).bind(ref_id).execute(&mut *conn).await?;
+  Ok(())
+}
+
+// Build the streak from the per day counts
+//
+// - returns nothing and leaves no streak when no expected day was logged
+async fn rebuild(conn: &mut SqliteConnection, user_id: i64, kind: model::StreakKind, ref_id: i64,
+  chores: &[model::Chore]) -> Result<Option<model::Streak>, sqlx::Error>
+{
+  let days = match kind {
+    model::StreakKind::Action => sqlx::query_scalar::<_, NaiveDate>(r#"SELECT day FROM point_day
+      WHERE user_id = ? AND action_id = ? ORDER BY day"#),
+    model::StreakKind::Category => sqlx::query_scalar::<_, NaiveDate>(r#"SELECT DISTINCT day
+      FROM point_day JOIN action ON action.id = point_day.action_id
+      WHERE point_day.user_id = ? AND action.category_id = ? ORDER BY day"#),
+  }.bind(user_id).bind(ref_id).fetch_all(&mut *conn).await?;
+
+  let mut streak: Option<model::Streak> = None;
+  for day in days.into_iter().filter(|x| is_expected(chores, *x)) {
+    match streak.as_mut() {
+      Some(x) => {
+        x.current = if missed_between(chores, x.last_on, day) { 1 } else { x.current + 1 };
+        x.longest = x.longest.max(x.current);
+        x.last_on = day;
+      },
+      None => streak = Some(model::Streak { user_id, kind, ref_id, current: 1, longest: 1,
+        last_on: day, scheduled: false }),
+    }
+  }
+  if let Some(x) = streak.as_ref() {
+    sqlx::query(r#"INSERT INTO streak (user_id, kind, ref_id, current, longest, last_on)
+      VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT DO UPDATE SET current = excluded.current,
+      longest = excluded.longest, last_on = excluded.last_on"#)
+      .bind(user_id).bind(kind).bind(ref_id).bind(x.current).bind(x.longest).bind(x.last_on)
+      .execute(&mut *conn).await?;
+  }
+  Ok(streak)
+}
+
+// Drop the user's streak so that it's rebuilt the next time it's fetched
+async fn drop_streak(conn: &mut SqliteConnection, user_id: i64, kind: model::StreakKind,
+  ref_id: i64) -> Result<(), sqlx::Error>
+{
+  sqlx::query(r#"DELETE FROM streak WHERE user_id = ? AND kind = ? AND ref_id = ?"#)
+    .bind(user_id).bind(kind).bind(ref_id).execute(&mut *conn).await?;
+  Ok(())
+}
+
+// Get the number of points the user logged in the category on the day
+async fn category_count(conn: &mut SqliteConnection, logged: &Logged) -> Result<i64, sqlx::Error>
+{
+  sqlx::query_scalar::<_, i64>(r#"SELECT COALESCE(SUM(point_day.count), 0) FROM point_day
+    JOIN action ON action.id = point_day.action_id
+    WHERE point_day.user_id = ? AND action.category_id = ? AND point_day.day = ?"#)
+    .bind(logged.user_id).bind(logged.category_id).bind(logged.day)
+    .fetch_one(&mut *conn).await
+}
+
+// Get the chores that schedule the streak
+//
+// - action streaks follow the active chores for the action assigned to the user if any
+// - category streaks and action streaks without chores are counted in days
+async fn schedule(conn: &mut SqliteConnection, user_id: i64, kind: model::StreakKind, ref_id: i64)
+  -> Result<Vec<model::Chore>, sqlx::Error>
+{
+  match kind {
+    model::StreakKind::Action => super::chore::fetch_assigned(conn, user_id, ref_id).await,
+    model::StreakKind::Category => Ok(vec![]),
+  }
+}
+
+// Check if the day is one the streak counts, every day when not scheduled by chores
+fn is_expected(chores: &[model::Chore], day: NaiveDate) -> bool
+{
+  chores.is_empty() || chores.iter().any(|x| super::chore::is_due(x, day))
+}
+
+// Check if an expected day lies strictly between the two days
+fn missed_between(chores: &[model::Chore], from: NaiveDate, to: NaiveDate) -> bool
+{
+  let Some(next) = from.checked_add_days(Days::new(1)) else { return false };
+  if chores.is_empty() {
+    return next < to;
+  }
+  next.iter_days().take_while(|x| *x < to).any(|x| is_expected(chores, x))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  // Helper to create points for the given action that occurred the given number of days ago
+  async fn points_days_ago(db: &SqlitePool, user_id: i64, action_id: i64, days: u64) -> i64
+  {
+    let occurred_at = (Local::now().date_naive() - Days::new(days)).and_hms_opt(12, 0, 0).unwrap()
+      .and_local_timezone(Local).unwrap();
+    db::point::insert_with(db, &model::CreatePoints { value: 1, user_id, action_id,
+      occurred_at: Some(occurred_at), kind: None }, None, model::PointsStatus::Approved).await
+      .unwrap()
+  }
+
+  // Helper to get the current and longest streak for the given action or category
+  async fn streak(db: &SqlitePool, user_id: i64, kind: model::StreakKind, ref_id: i64)
+    -> Option<(i64, i64)>
+  {
+    fetch_by_user_id(db, user_id).await.unwrap().into_iter()
+      .find(|x| x.kind == kind && x.ref_id == ref_id).map(|x| (x.current, x.longest))
+  }
+
+  #[tokio::test]
+  async fn test_consecutive_days()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    assert!(fetch_by_user_id(state.db(), user_id).await.unwrap().is_empty());
+
+    for days in [5, 4, 2, 1] {
+      points_days_ago(state.db(), user_id, 1, days).await;
+    }
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((2, 2)));
+
+    // Extended in place, more points on the same day don't count twice
+    points_days_ago(state.db(), user_id, 1, 0).await;
+    points_days_ago(state.db(), user_id, 1, 0).await;
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((3, 3)));
+
+    // Back filling the gap joins the runs
+    points_days_ago(state.db(), user_id, 1, 3).await;
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((6, 6)));
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Category, 1).await, Some((6, 6)));
+
+    let err = fetch_by_user_id(state.db(), -1).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_delete_splits_and_misses_decay()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let mut ids = vec![];
+    for days in [4, 3, 2, 1] {
+      ids.push(points_days_ago(state.db(), user_id, 1, days).await);
+    }
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((4, 4)));
+
+    db::point::delete_by_id(state.db(), ids[1]).await.unwrap();
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((2, 2)));
+    db::point::restore_by_id(state.db(), ids[1]).await.unwrap();
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((4, 4)));
+
+    // Missing yesterday ends the current streak but not the longest
+    db::point::update_by_id(state.db(), ids[3], -1, None).await.unwrap();
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((0, 3)));
+
+    // Pending points only count once approved
+    let pending = db::point::insert_with(state.db(), &model::CreatePoints { value: 1, user_id,
+      action_id: 1, occurred_at: None, kind: None }, None, model::PointsStatus::Pending).await
+      .unwrap();
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((0, 3)));
+    db::point::review(state.db(), &[pending], model::PointsStatus::Approved, None, None).await
+      .unwrap();
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((1, 3)));
+
+    // Moving the user to the trash takes their points and streaks with them
+    db::user::delete_by_id(state.db(), user_id).await.unwrap();
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM point_day WHERE user_id = ?")
+      .bind(user_id).fetch_one(state.db()).await.unwrap();
+    assert_eq!(count, 0);
+  }
+
+  #[tokio::test]
+  async fn test_category_across_actions()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new().with_desc("bed"))
+      .await.unwrap();
+    points_days_ago(state.db(), user_id, 1, 2).await;
+    points_days_ago(state.db(), user_id, action_id, 1).await;
+    points_days_ago(state.db(), user_id, 1, 0).await;
+
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((1, 1)));
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, action_id).await,
+      Some((1, 1)));
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Category, 1).await, Some((3, 3)));
+  }
+
+  #[tokio::test]
+  async fn test_scheduled_by_chore()
+  {
+    let state = state::test().await;
+    let today = Local::now().date_naive();
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    for days in [6, 4, 2, 1] {
+      points_days_ago(state.db(), user_id, 1, days).await;
+    }
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((2, 2)));
+
+    // Every other day ending today, the day in between doesn't count
+    db::chore::insert(state.db(), &model::ChorePartial::new(1, model::ChoreSchedule::Interval)
+      .with_every(2).with_starts_on(today - Days::new(6)).with_user_ids(vec![user_id]))
+      .await.unwrap();
+    let streaks = fetch_by_user_id(state.db(), user_id).await.unwrap();
+    assert!(streaks.iter().any(|x| x.kind == model::StreakKind::Action && x.scheduled));
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((3, 3)));
+    points_days_ago(state.db(), user_id, 1, 0).await;
+    assert_eq!(streak(state.db(), user_id, model::StreakKind::Action, 1).await, Some((4, 4)));
+  }
+}
@@ -0,0 +1,465 @@
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Sqlite, SqlitePool};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use crate::{ errors, model };
+
+// Number of rows fetched ahead of a slow export client
+const EXPORT_BUFFER: usize = 64;
+
+// Points are joined with their user and action so that exports are readable and re-importable
+const SELECT_POINTS_RECORDS: &str = r#"SELECT point.id, point.value, point.kind, point.status,
+    point.user_id, user.username, point.action_id, action.desc AS action, point.occurred_at,
+    point.created_at
+  FROM point
+  INNER JOIN action ON action.id = point.action_id
+  INNER JOIN user ON user.id = point.user_id"#;
+
+// Rewards are joined with their user so that exports are readable and re-importable
+const SELECT_REWARD_RECORDS: &str = r#"SELECT reward.id, reward.value, reward.user_id,
+    user.username, reward.created_at FROM reward
+  INNER JOIN user ON user.id = reward.user_id"#;
+
+/// Stream points records for export
+///
+/// - orders the records by the date the deeds occurred
+/// - excludes points in the trash
+/// - the filter is validated up front so that errors are reported before streaming starts
+/// - error on invalid filter
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***filter*** - same filter params as `point::fetch_by_filter` all of which are optional
+///
+/// #### Returns
+/// - ***records*** - stream of points records
+pub async fn export_points(db: &SqlitePool, filter: model::Filter)
+  -> errors::Result<ReceiverStream<errors::Result<model::PointsRecord>>>
+{
+  let mut query = QueryBuilder::new(SELECT_POINTS_RECORDS);
+  if filter.any_points_filters() {
+    filter.push_points_where_clause(db, &mut query).await?;
+  } else {
+    query.push(" WHERE point.deleted_at IS NULL");
+  }
+  query.push(" ORDER BY point.occurred_at");
+  Ok(stream(db, query, "points"))
+}
+
+/// Stream reward records for export
+///
+/// - orders the records by creation date
+/// - excludes rewards in the trash
+/// - the filter is validated up front so that errors are reported before streaming starts
+/// - error on invalid filter
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***filter*** - same filter params as `reward::fetch_by_filter` all of which are optional
+///
+/// #### Returns
+/// - ***records*** - stream of reward records
+pub async fn export_rewards(db: &SqlitePool, filter: model::Filter)
+  -> errors::Result<ReceiverStream<errors::Result<model::RewardRecord>>>
+{
+  let mut query = QueryBuilder::new(SELECT_REWARD_RECORDS);
+  if filter.any_rewards_filters() {
+    filter.push_rewards_where_clause(db, &mut query).await?;
+  } else {
+    query.push(" WHERE reward.deleted_at IS NULL");
+  }
+  query.push(" ORDER BY reward.created_at");
+  Ok(stream(db, query, "rewards"))
+}
+
+/// Import points records
+///
+/// - every row is validated and its user and action resolved before anything is written
+/// - nothing is written for dry runs or when any row has errors
+/// - all rows are committed in a single transaction
+/// - values are taken as given without applying bonuses as exports already include them
+/// - rows keep their exported ***status***, pending points don't count towards sums and
+///   balances until approved see `point::review`
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***rows*** - parsed rows or the reason the row couldn't be parsed
+/// - ***awarded_by*** - user importing the points if any
+/// - ***status*** - review status for rows that don't give one
+/// - ***dry_run*** - validate and resolve the rows only
+///
+/// #### Returns
+/// - ***report*** - resolved or committed rows and any row errors
+pub async fn import_points(db: &SqlitePool, rows: Vec<Result<model::PointsRecord, String>>,
+  awarded_by: Option<i64>, status: model::PointsStatus, dry_run: bool)
+  -> errors::Result<model::ImportReport<model::PointsRecord>>
+{
+  let mut report = new_report(dry_run, rows.len());
+  for (i, row) in rows.into_iter().enumerate() {
+    match resolve_points(db, row).await {
+      Ok(record) => report.rows.push(record),
+      Err(msg) => report.errors.push(model::ImportError { row: i + 1, msg }),
+    }
+  }
+  if dry_run || !report.errors.is_empty() {
+    return Ok(report);
+  }
+
+  let mut tx = begin(db).await?;
+  for record in report.rows.iter_mut() {
+    let points = model::CreatePoints { value: record.value,
+      user_id: record.user_id.unwrap_or_default(), action_id: record.action_id.unwrap_or_default(),
+      occurred_at: record.occurred_at.or(record.created_at), kind: record.kind };
+    let status = *record.status.get_or_insert(status);
+
+    // Values were recorded with any bonus already applied so they are taken as given
+    let result = super::point::insert_in(&mut tx, &points, awarded_by, status,
+      record.created_at, Some(1.0)).await;
+    match result {
+      Ok(id) => record.id = Some(id),
+      Err(e) => {
+        let msg = format!("Error importing points '{}'", record.value);
+        log::error!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+    }
+  }
+  commit(tx, "points").await?;
+  report.imported = report.rows.len();
+  Ok(report)
+}
+
+/// Import reward records
+///
+/// - every row is validated and its user resolved before anything is written
+/// - nothing is written for dry runs or when any row has errors
+/// - all rows are committed in a single transaction
+/// - error on other SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***rows*** - parsed rows or the reason the row couldn't be parsed
+/// - ***dry_run*** - validate and resolve the rows only
+///
+/// #### Returns
+/// - ***report*** - resolved or committed rows and any row errors
+pub async fn import_rewards(db: &SqlitePool, rows: Vec<Result<model::RewardRecord, String>>,
+  dry_run: bool) -> errors::Result<model::ImportReport<model::RewardRecord>>
+{
+  let mut report = new_report(dry_run, rows.len());
+  for (i, row) in rows.into_iter().enumerate() {
+    match resolve_reward(db, row).await {
+      Ok(record) => report.rows.push(record),
+      Err(msg) => report.errors.push(model::ImportError { row: i + 1, msg }),
+    }
+  }
+  if dry_run || !report.errors.is_empty() {
+    return Ok(report);
+  }
+
+  let mut tx = begin(db).await?;
+  for record in report.rows.iter_mut() {
+    let result = sqlx::query(r#"INSERT INTO reward (value, user_id, created_at, updated_at)
+      VALUES (?, ?, COALESCE(?, datetime('subsec')), COALESCE(?, datetime('subsec')))"#)
+      .bind(record.value).bind(record.user_id)
+      .bind(created_at(record.created_at)).bind(created_at(record.created_at))
+      .execute(&mut *tx).await;
+    let result = match result {
+      Ok(query) => {
+        let id = query.last_insert_rowid();
+        record.id = Some(id);
+        super::audit::record::<model::Reward>(&mut tx, "reward", id, model::AuditAction::Create,
+          None).await
+      },
+      Err(e) => Err(e),
+    };
+    match result {
+      Ok(()) => (),
+      Err(e) => {
+        let msg = format!("Error importing reward '{}'", record.value);
+        log::error!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+    }
+  }
+  commit(tx, "rewards").await?;
+  report.imported = report.rows.len();
+  Ok(report)
+}
+
+// Run the query in the background feeding the rows through a bounded channel
+fn stream<T>(db: &SqlitePool, mut query: QueryBuilder<'static, Sqlite>, name: &'static str)
+  -> ReceiverStream<errors::Result<T>>
+where
+  T: for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin + 'static,
+{
+  let db = db.clone();
+  let (tx, rx) = tokio::sync::mpsc::channel(EXPORT_BUFFER);
+  tokio::spawn(async move {
+    let mut rows = query.build_query_as::<T>().fetch(&db);
+    while let Some(row) = rows.next().await {
+      let row = row.map_err(|e| {
+        let msg = format!("Error exporting {name}");
+        log::error!("{msg}");
+        errors::Error::from_sqlx(e, &msg)
+      });
+      let failed = row.is_err();
+
+      // Stop early if the client went away or the query failed
+      if tx.send(row).await.is_err() || failed {
+        break;
+      }
+    }
+  });
+  ReceiverStream::new(rx)
+}
+
+// Validate the points row and resolve its user and action
+async fn resolve_points(db: &SqlitePool, row: Result<model::PointsRecord, String>)
+  -> Result<model::PointsRecord, String>
+{
+  let mut record = row?;
+  let kind = record.kind.unwrap_or(model::PointsKind::from_value(record.value));
+  if !kind.allows(record.value) {
+    return Err(format!("Points value '{}' is not allowed for kind '{}'", record.value,
+      kind.as_str()));
+  }
+  let user = resolve_user(db, record.user_id, record.username.as_deref()).await?;
+  let action = match (record.action_id, record.action.as_deref().map(str::trim)) {
+    (Some(id), desc) => {
+      let action = super::action::fetch_by_id(db, id).await.map_err(|e| e.msg)?;
+      if desc.is_some_and(|x| x != action.desc) {
+        return Err(format!("Action id '{id}' does not match action '{}'", desc.unwrap()));
+      }
+      action
+    },
+    (None, Some(desc)) => super::action::fetch_by_desc(db, desc).await.map_err(|e| e.msg)?,
+    (None, None) => super::action::fetch_by_id(db, 1).await.map_err(|e| e.msg)?,
+  };
+
+  record.id = None;
+  record.kind = Some(kind);
+  record.user_id = Some(user.id);
+  record.username = Some(user.username);
+  record.action_id = Some(action.id);
+  record.action = Some(action.desc);
+  Ok(record)
+}
+
+// Validate the reward row and resolve its user
+async fn resolve_reward(db: &SqlitePool, row: Result<model::RewardRecord, String>)
+  -> Result<model::RewardRecord, String>
+{
+  let mut record = row?;
+  let user = resolve_user(db, record.user_id, record.username.as_deref()).await?;
+
+  record.id = None;
+  record.user_id = Some(user.id);
+  record.username = Some(user.username);
+  Ok(record)
+}
+
+// Resolve the user by id or handle ensuring the two agree when both are given
+async fn resolve_user(db: &SqlitePool, id: Option<i64>, handle: Option<&str>)
+  -> Result<model::User, String>
+{
+  match (id, handle.map(str::trim)) {
+    (Some(id), handle) => {
+      let user = super::user::fetch_by_id(db, id).await.map_err(|e| e.msg)?;
+      if let Some(handle) = handle.filter(|x| *x != user.username && *x != user.email) {
+        return Err(format!("User id '{id}' does not match user '{handle}'"));
+      }
+      Ok(user)
+    },
+    (None, Some(handle)) => super::user::fetch_by_handle(db, handle).await.map_err(|e| e.msg),
+    (None, None) => Err("Either user_id or username is required".to_string()),
+  }
+}
+
+// Store imported dates the same way the database defaults do i.e. in UTC
+fn created_at(value: Option<chrono::DateTime<chrono::Local>>) -> Option<chrono::NaiveDateTime>
+{
+  value.map(|x| x.naive_utc())
+}
+
+// Create an empty report for the given number of rows
+fn new_report<T>(dry_run: bool, total: usize) -> model::ImportReport<T>
+{
+  model::ImportReport { dry_run, total, imported: 0, rows: vec![], errors: vec![] }
+}
+
+// Start the import transaction
+async fn begin(db: &SqlitePool) -> errors::Result<sqlx::Transaction<'static, Sqlite>>
+{
+  db.begin().await.map_err(|e| {
+    let msg = "Error starting import transaction";
+    log::error!("{msg}");
+    errors::Error::from_sqlx(e, msg)
+  })
+}
+
+// Commit the import transaction
+async fn commit(tx: sqlx::Transaction<'static, Sqlite>, name: &str) -> errors::Result<()>
+{
+  tx.commit().await.map_err(|e| {
+    let msg = format!("Error committing {name} import");
+    log::error!("{msg}");
+    errors::Error::from_sqlx(e, &msg)
+  })
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  #[tokio::test]
+  async fn test_export_points_by_filter()
+  {
+    let state = state::test().await;
+    let user_id_1 = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let user_id_2 = db::user::insert(state.db(), "user2", "user2@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    db::point::insert(state.db(), 10, user_id_1, action_id).await.unwrap();
+    db::point::insert(state.db(), 20, user_id_2, action_id).await.unwrap();
+
+    let records: Vec<_> = export_points(state.db(), model::Filter::default()).await.unwrap()
+      .collect::<Vec<_>>().await.into_iter().map(Result::unwrap).collect();
+    assert_eq!(records.len(), 2);
+
+    let records: Vec<_> = export_points(state.db(), model::Filter::default()
+      .with_user_id(user_id_2)).await.unwrap()
+      .collect::<Vec<_>>().await.into_iter().map(Result::unwrap).collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].value, 20);
+    assert_eq!(records[0].username.as_deref(), Some("user2"));
+    assert_eq!(records[0].action.as_deref(), Some("action1"));
+  }
+
+  #[tokio::test]
+  async fn test_import_points_success()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    let created_at = chrono::Local::now() - chrono::Duration::days(30);
+
+    let rows = vec![
+      Ok(model::PointsRecord { value: 10, username: Some("user1".into()),
+        action: Some("action1".into()), created_at: Some(created_at), ..Default::default() }),
+      Ok(model::PointsRecord { value: 20, user_id: Some(user_id), ..Default::default() }),
+    ];
+    let report = import_points(state.db(), rows, None, model::PointsStatus::Approved, false).await
+      .unwrap();
+    assert!(report.errors.is_empty());
+    assert_eq!(report.imported, 2);
+
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].user_id, user_id);
+    assert_eq!(points[0].action_id, action_id);
+    assert_eq!(points[0].created_at.timestamp(), created_at.timestamp());
+    assert_eq!(points[1].action_id, 1);
+  }
+
+  #[tokio::test]
+  async fn test_import_points_pending_not_counted()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    let rows = vec![Ok(model::PointsRecord { value: 10, user_id: Some(user_id),
+      ..Default::default() })];
+    let report = import_points(state.db(), rows, Some(user_id), model::PointsStatus::Pending,
+      false).await.unwrap();
+    assert_eq!(report.imported, 1);
+
+    // Pending points wait on review before they count
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points[0].status, model::PointsStatus::Pending);
+    let filter = model::Filter::new().with_user_id(user_id);
+    assert_eq!(db::point::sum_by_filter(state.db(), filter).await.unwrap(), 0);
+    assert_eq!(db::ledger::balance(state.db(), user_id).await.unwrap().available, 0);
+    let page = db::ledger::fetch_by_user_id(state.db(), user_id, &model::LedgerQuery::default())
+      .await.unwrap();
+    assert!(page.entries.is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_import_points_keeps_exported_status()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 10, user_id, 1).await.unwrap();
+    db::point::insert_with(state.db(), &model::CreatePoints { value: 5, user_id, action_id: 1,
+      occurred_at: None, kind: None }, None, model::PointsStatus::Rejected).await.unwrap();
+    let records: Vec<_> = export_points(state.db(), model::Filter::default()).await.unwrap()
+      .collect::<Vec<_>>().await.into_iter().map(Result::unwrap).collect();
+    assert_eq!(records.iter().map(|x| x.status).collect::<Vec<_>>(),
+      vec![Some(model::PointsStatus::Approved), Some(model::PointsStatus::Rejected)]);
+
+    // Rejected points don't come back as spendable balance
+    let other = state::test().await;
+    let user_id = db::user::insert(other.db(), "user1", "user1@foo.com").await.unwrap();
+    let report = import_points(other.db(), records.into_iter().map(Ok).collect(), None,
+      model::PointsStatus::Approved, false).await.unwrap();
+    assert_eq!(report.imported, 2);
+    let points = db::point::fetch_all(other.db()).await.unwrap();
+    assert_eq!(points.iter().map(|x| x.status).collect::<Vec<_>>(),
+      vec![model::PointsStatus::Approved, model::PointsStatus::Rejected]);
+    assert_eq!(db::ledger::balance(other.db(), user_id).await.unwrap().available, 10);
+  }
+
+  #[tokio::test]
+  async fn test_import_points_dry_run_and_errors_write_nothing()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    // Dry run resolves the rows without writing them
+    let rows = vec![Ok(model::PointsRecord { value: 10, username: Some("user1@foo.com".into()),
+      ..Default::default() })];
+    let report = import_points(state.db(), rows, None, model::PointsStatus::Approved, true).await
+      .unwrap();
+    assert!(report.errors.is_empty());
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.rows[0].user_id, Some(user_id));
+    assert_eq!(report.rows[0].action.as_deref(), Some("Unspecified"));
+
+    // A single bad row rejects the whole import
+    let rows = vec![
+      Ok(model::PointsRecord { value: 10, user_id: Some(user_id), ..Default::default() }),
+      Ok(model::PointsRecord { value: 20, username: Some("nobody".into()), ..Default::default() }),
+      Ok(model::PointsRecord { value: 30, user_id: Some(user_id), username: Some("other".into()),
+        ..Default::default() }),
+      Err("invalid digit found in string".to_string()),
+    ];
+    let report = import_points(state.db(), rows, None, model::PointsStatus::Approved, false).await
+      .unwrap();
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.errors.iter().map(|x| x.row).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+    assert_eq!(db::point::fetch_all(state.db()).await.unwrap().len(), 0);
+  }
+
+  #[tokio::test]
+  async fn test_import_rewards_success()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    let rows = vec![Ok(model::RewardRecord { value: 5, username: Some("user1".into()),
+      ..Default::default() })];
+    let report = import_rewards(state.db(), rows, false).await.unwrap();
+    assert_eq!(report.imported, 1);
+    assert_eq!(report.rows[0].id, Some(1));
+
+    let records: Vec<_> = export_rewards(state.db(), model::Filter::default()).await.unwrap()
+      .collect::<Vec<_>>().await.into_iter().map(Result::unwrap).collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].user_id, Some(user_id));
+    assert_eq!(records[0].value, 5);
+  }
+}
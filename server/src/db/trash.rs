@@ -0,0 +1,162 @@
+use sqlx::{sqlite::SqliteRow, SqlitePool};
+use crate::{ errors, model };
+
+/// Get everything in the trash
+///
+/// - orders each kind by most recently deleted first
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+///
+/// #### Returns
+/// - ***trash*** - the entries in the trash
+pub async fn fetch(db: &SqlitePool) -> errors::Result<model::Trash>
+{
+  Ok(model::Trash {
+    users: fetch_deleted(db, "user").await?,
+    actions: fetch_deleted(db, "action").await?,
+    points: fetch_deleted(db, "point").await?,
+    rewards: fetch_deleted(db, "reward").await?,
+  })
+}
+
+/// Permanently delete entries that have been in the trash since before the given time
+///
+/// - deleting a user also deletes its points, rewards and passwords
+/// - actions still referenced by points or chores are kept so that history is never re-pointed
+/// - error on SQL errors
+///
+/// #### Parameters
+/// - ***db*** - database connection pool
+/// - ***before*** - entries deleted before this time are purged
+///
+/// #### Returns
+/// - ***count*** - number of entries purged
+pub async fn purge(db: &SqlitePool, before: chrono::DateTime<chrono::Utc>) -> errors::Result<u64>
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let mut count = 0;
+    for (table, condition) in [("point", ""), ("reward", ""),
+      ("action", " AND id NOT IN (SELECT action_id FROM point)
+        AND id NOT IN (SELECT action_id FROM chore)"), ("user", "")]
+    {
+      count += sqlx::query(&format!(r#"DELETE FROM {table}
+        WHERE datetime(deleted_at) < datetime(?){condition}"#))
+        .bind(before.naive_utc()).execute(&mut *tx).await?.rows_affected();
+    }
+    tx.commit().await?;
+    Ok(count)
+  }.await;
+
+  match result {
+    Ok(count) => Ok(count),
+    Err(e) => {
+      let msg = "Error purging the trash";
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, msg))
+    }
+  }
+}
+
+// Get the deleted rows of the given table most recently deleted first
+async fn fetch_deleted<T>(db: &SqlitePool, table: &str) -> errors::Result<Vec<T>>
+where
+  T: for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+{
+  let result = sqlx::query_as::<_, T>(&format!(r#"SELECT * FROM {table}
+    WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC"#))
+    .fetch_all(db).await;
+  match result {
+    Ok(rows) => Ok(rows),
+    Err(e) => {
+      let msg = format!("Error fetching {table} from the trash");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::{db, state};
+
+  #[tokio::test]
+  async fn test_delete_fetch_and_restore()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    let points_id = db::point::insert(state.db(), 10, user_id, action_id).await.unwrap();
+    let kept_id = db::point::insert(state.db(), 20, user_id, action_id).await.unwrap();
+    db::reward::insert(state.db(), 5, user_id).await.unwrap();
+
+    // Points deleted on their own before the user stay in the trash when the user is restored as
+    // they were deleted at a different time
+    db::point::delete_by_id(state.db(), kept_id).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    db::action::delete_by_id(state.db(), action_id).await.unwrap();
+    db::user::delete_by_id(state.db(), user_id).await.unwrap();
+
+    let trash = fetch(state.db()).await.unwrap();
+    assert_eq!(trash.users.len(), 1);
+    assert_eq!(trash.actions.len(), 1);
+    assert_eq!(trash.points.len(), 2);
+    assert_eq!(trash.rewards.len(), 1);
+    assert!(db::user::fetch_by_handle(state.db(), "user1").await.is_err());
+    assert!(db::point::fetch_all(state.db()).await.unwrap().is_empty());
+    assert!(db::action::fetch_all(state.db(), model::Filter::default()).await.unwrap()
+      .iter().all(|x| x.id != action_id));
+
+    // Points can't be restored while their user is in the trash
+    let err = db::point::restore_by_id(state.db(), points_id).await.unwrap_err();
+    assert_eq!(err.to_http().status, axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    db::user::restore_by_id(state.db(), user_id).await.unwrap();
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.iter().map(|x| x.id).collect::<Vec<_>>(), vec![points_id]);
+    assert_eq!(points[0].action_id, action_id);
+    assert_eq!(db::reward::fetch_all(state.db()).await.unwrap().len(), 1);
+
+    db::point::restore_by_id(state.db(), kept_id).await.unwrap();
+    db::action::restore_by_id(state.db(), action_id).await.unwrap();
+    let trash = fetch(state.db()).await.unwrap();
+    assert!(trash.users.is_empty() && trash.actions.is_empty() && trash.points.is_empty());
+
+    let err = db::action::restore_by_id(state.db(), action_id).await.unwrap_err();
+    assert_eq!(err.kind, errors::ErrorKind::NotFound);
+  }
+
+  #[tokio::test]
+  async fn test_purge()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    let unused_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action2")).await.unwrap();
+    db::point::insert(state.db(), 10, 1, action_id).await.unwrap();
+    db::reward::insert(state.db(), 5, user_id).await.unwrap();
+    db::user::delete_by_id(state.db(), user_id).await.unwrap();
+    db::action::delete_by_id(state.db(), action_id).await.unwrap();
+    db::action::delete_by_id(state.db(), unused_id).await.unwrap();
+
+    // Nothing has been in the trash long enough
+    let before = chrono::Utc::now() - chrono::Duration::hours(1);
+    assert_eq!(purge(state.db(), before).await.unwrap(), 0);
+
+    // The user and its reward are purged but the action still referenced by points is kept
+    let count = purge(state.db(), chrono::Utc::now() + chrono::Duration::seconds(1)).await.unwrap();
+    assert_eq!(count, 3);
+    let trash = fetch(state.db()).await.unwrap();
+    assert!(trash.users.is_empty() && trash.rewards.is_empty());
+    assert_eq!(trash.actions.iter().map(|x| x.id).collect::<Vec<_>>(), vec![action_id]);
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points[0].action_id, action_id);
+  }
+}
@@ -1,4 +1,4 @@
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, SqlitePool};
 use regex;
 use axum::http::StatusCode;
 use crate::{ errors, model };
@@ -21,10 +21,17 @@ pub async fn insert(db: &SqlitePool, username: &str, email: &str) -> errors::Res
   validate_email(&email)?;
 
   // Create new user in database
-  let result = sqlx::query(r#"INSERT INTO user (username, email) VALUES (?, ?)"#)
-    .bind(username).bind(email).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query(r#"INSERT INTO user (username, email) VALUES (?, ?)"#)
+      .bind(username).bind(email).execute(&mut *tx).await?.last_insert_rowid();
+    super::audit::record::<model::User>(&mut tx, "user", id, model::AuditAction::Create, None)
+      .await?;
+    tx.commit().await?;
+    Ok(id)
+  }.await;
   match result {
-    Ok(query) => Ok(query.last_insert_rowid()),
+    Ok(id) => Ok(id),
     Err(e) => {
       if errors::Error::is_sqlx_unique_violation(&e) {
         let msg = format!("User '{username}' already exists");
@@ -47,19 +54,33 @@ pub async fn insert(db: &SqlitePool, username: &str, email: &str) -> errors::Res
 pub async fn assign_roles(db: &SqlitePool, user_id: i64, role_ids: Vec<i64>) -> errors::Result<()> 
 {
   let user = super::user::fetch_by_id(db, user_id).await?.username;
+  let before = roles(db, user_id).await?.iter().map(|x| x.id).collect::<Vec<_>>();
+  let mut after = before.clone();
 
-  for role_id in role_ids {
-    let role = super::role::fetch_by_id(db, role_id).await?.name;
+  // Ensure the roles exist
+  for role_id in &role_ids {
+    super::role::fetch_by_id(db, *role_id).await?;
+  }
 
-    let result = sqlx::query(r#"INSERT INTO user_role (user_id, role_id) VALUES (?, ?)"#)
-      .bind(user_id).bind(role_id).execute(db).await;
-    if let Err(e) = result {
-      let msg = format!("Error assigning role '{role}' to user '{user}'");
-      log::error!("{msg}");
-      return Err(errors::Error::from_sqlx(e, &msg));
+  let result = async {
+    let mut tx = db.begin().await?;
+    for role_id in &role_ids {
+      sqlx::query(r#"INSERT INTO user_role (user_id, role_id) VALUES (?, ?)"#)
+        .bind(user_id).bind(role_id).execute(&mut *tx).await?;
+      after.push(*role_id);
     }
-  }
 
+    // Record the roles as a change to the user
+    super::audit::insert(&mut tx, "user", user_id, model::AuditAction::Update,
+      Some(serde_json::json!({ "role_ids": before })),
+      Some(serde_json::json!({ "role_ids": after }))).await?;
+    tx.commit().await
+  }.await;
+  if let Err(e) = result {
+    let msg = format!("Error assigning roles {role_ids:?} to user '{user}'");
+    log::error!("{msg}");
+    return Err(errors::Error::from_sqlx(e, &msg));
+  }
   Ok(())
 }
 
@@ -94,6 +115,7 @@ pub async fn roles(db: &SqlitePool, user_id: i64) -> errors::Result<Vec<model::R
 
 /// Check if there are any users existing
 /// 
+/// - includes users in the trash
 /// - error on other SQL errors
 /// 
 /// #### Returns
@@ -115,6 +137,7 @@ pub async fn any(db: &SqlitePool) -> errors::Result<bool>
 /// Get users by filter
 ///
 /// - orders the users by username ignoring case
+/// - excludes users in the trash
 /// - error on SQL errors
 /// - error on invalid filter
 ///
@@ -131,28 +154,18 @@ pub async fn fetch_all(db: &SqlitePool, filter: model::Filter) ->
   let result = if !filter.any_user_filters() {
 
     // Get all users when no filter options are specified
-    sqlx::query_as::<_, model::User>(r#"SELECT * FROM user ORDER BY LOWER(username)"#)
+    sqlx::query_as::<_, model::User>(r#"SELECT * FROM user WHERE deleted_at IS NULL
+      ORDER BY LOWER(username)"#)
       .fetch_all(db).await
   } else {
 
     // Get users with the given filter
-    let where_clause = filter.to_users_where_clause(db).await?;
-    let query_str = format!(r#"SELECT DISTINCT user.* FROM user
+    let mut query = QueryBuilder::new(r#"SELECT DISTINCT user.* FROM user
       LEFT JOIN user_role ON user.id = user_role.user_id
-      LEFT JOIN role ON role.id = user_role.role_id
-      {where_clause} ORDER BY LOWER(user.username)"#);
-    let mut query = sqlx::query_as::<_, model::User>(&query_str);
-    if let Some(role_id) = filter.role_id {
-      query = query.bind(role_id);
-    } else if let Some(role_name) = filter.role_name {
-      query = query.bind(role_name);
-    } else if let Some(role_id_ne) = filter.role_id_ne {
-      query = query.bind(role_id_ne);
-    } else if let Some(role_name_ne) = filter.role_name_ne {
-      query = query.bind(role_name_ne);
-    }
-
-    query.fetch_all(db).await
+      LEFT JOIN role ON role.id = user_role.role_id"#);
+    filter.push_users_where_clause(db, &mut query).await?;
+    query.push(" ORDER BY LOWER(user.username)");
+    query.build_query_as::<model::User>().fetch_all(db).await
   };
 
   match result {
@@ -167,7 +180,7 @@ pub async fn fetch_all(db: &SqlitePool, filter: model::Filter) ->
 
 /// Get a user by ID from the database
 /// 
-/// - error on not found
+/// - error on not found or in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -177,7 +190,7 @@ pub async fn fetch_all(db: &SqlitePool, filter: model::Filter) ->
 /// - ***user*** - the user entry
 pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::User> 
 {
-  let result = sqlx::query_as::<_, model::User>(r#"SELECT * FROM user WHERE id = ?"#)
+  let result = sqlx::query_as::<_, model::User>(r#"SELECT * FROM user WHERE id = ? AND deleted_at IS NULL"#)
     .bind(id).fetch_one(db).await;
   match result {
     Ok(user) => Ok(user),
@@ -196,7 +209,7 @@ pub async fn fetch_by_id(db: &SqlitePool, id: i64) -> errors::Result<model::User
 
 /// Get a user by username or email from the database
 /// 
-/// - error on not found
+/// - error on not found or in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
@@ -208,7 +221,7 @@ pub async fn fetch_by_handle(db: &SqlitePool, handle: &str) -> errors::Result<mo
 {
   let field = if handle.contains('@') { "email" } else { "username" };
 
-  let result = sqlx::query_as::<_, model::User>(&format!("SELECT * FROM user WHERE {field} = ?"))
+  let result = sqlx::query_as::<_, model::User>(&format!("SELECT * FROM user WHERE {field} = ? AND deleted_at IS NULL"))
     .bind(handle).fetch_one(db).await;
   match result {
     Ok(user) => Ok(user),
@@ -246,8 +259,15 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, username: Option<&str>,
   validate_email(&email)?;
 
   // Update user in database
-  let result = sqlx::query(r#"UPDATE user SET username = ?, email = ? WHERE id = ?"#)
-    .bind(&username).bind(email).bind(&id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::User>(&mut tx, "user", id).await?;
+    sqlx::query(r#"UPDATE user SET username = ?, email = ? WHERE id = ?"#)
+      .bind(&username).bind(email).bind(&id).execute(&mut *tx).await?;
+    super::audit::record::<model::User>(&mut tx, "user", id, model::AuditAction::Update, before)
+      .await?;
+    tx.commit().await
+  }.await;
   if let Err(e) = result {
     let msg = format!("Error updating user with id '{id}'");
     log::error!("{msg}");
@@ -256,16 +276,38 @@ pub async fn update_by_id(db: &SqlitePool, id: i64, username: Option<&str>,
   Ok(())
 }
 
-/// Delete a user in the database
+/// Move a user to the trash
 /// 
+/// - the user's points and rewards are moved to the trash along with the user
+/// - does nothing if the user is not found or already in the trash
 /// - error on other SQL errors
 /// 
 /// #### Parameters
 /// - ***id*** user id
 pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()> 
 {
-  let result = sqlx::query(r#"DELETE from user WHERE id = ?"#)
-    .bind(id).execute(db).await;
+  let result = async {
+    let mut tx = db.begin().await?;
+    let before = super::audit::snapshot::<model::User>(&mut tx, "user", id).await?;
+    let deleted_at = sqlx::query_scalar::<_, String>(r#"UPDATE user
+      SET deleted_at = datetime('subsec') WHERE id = ? AND deleted_at IS NULL
+      RETURNING deleted_at"#)
+      .bind(id).fetch_optional(&mut *tx).await?;
+
+    // Stamp the history with the same time so that it can be restored with the user
+    if let Some(deleted_at) = deleted_at {
+      for table in ["point", "reward"] {
+        sqlx::query(&format!(r#"UPDATE {table} SET deleted_at = ?
+          WHERE user_id = ? AND deleted_at IS NULL"#))
+          .bind(&deleted_at).bind(id).execute(&mut *tx).await?;
+      }
+      super::audit::record::<model::User>(&mut tx, "user", id, model::AuditAction::Delete, before)
+        .await?;
+      super::streak::reset(&mut tx, Some(id)).await?;
+    }
+    tx.commit().await
+  }.await;
+
   if let Err(e) = result {
     let msg = format!("Error deleting user with id '{id}'");
     log::error!("{msg}");
@@ -274,6 +316,49 @@ pub async fn delete_by_id(db: &SqlitePool, id: i64) -> errors::Result<()>
   Ok(())
 }
 
+/// Restore a user from the trash
+/// 
+/// - the points and rewards moved to the trash along with the user are restored with it
+/// - error on not found in the trash
+/// - error on other SQL errors
+/// 
+/// #### Parameters
+/// - ***id*** user id
+pub async fn restore_by_id(db: &SqlitePool, id: i64) -> errors::Result<()> 
+{
+  let result = async {
+    let mut tx = db.begin().await?;
+    let deleted_at = sqlx::query_scalar::<_, String>(r#"SELECT deleted_at FROM user
+      WHERE id = ? AND deleted_at IS NOT NULL"#)
+      .bind(id).fetch_one(&mut *tx).await?;
+    let before = super::audit::snapshot::<model::User>(&mut tx, "user", id).await?;
+    for table in ["user", "point", "reward"] {
+      let column = if table == "user" { "id" } else { "user_id" };
+      sqlx::query(&format!(r#"UPDATE {table} SET deleted_at = NULL
+        WHERE {column} = ? AND deleted_at = ?"#))
+        .bind(id).bind(&deleted_at).execute(&mut *tx).await?;
+    }
+    super::audit::record::<model::User>(&mut tx, "user", id, model::AuditAction::Restore, before)
+      .await?;
+    super::streak::reset(&mut tx, Some(id)).await?;
+    tx.commit().await
+  }.await;
+
+  match result {
+    Ok(()) => Ok(()),
+    Err(e) => {
+      if errors::Error::is_sqlx_not_found(&e) {
+        let msg = format!("User with id '{id}' was not found in the trash");
+        log::warn!("{msg}");
+        return Err(errors::Error::from_sqlx(e, &msg));
+      }
+      let msg = format!("Error restoring user with id '{id}'");
+      log::error!("{msg}");
+      Err(errors::Error::from_sqlx(e, &msg))
+    }
+  }
+}
+
 // Ensure the username is following the constraints we need it to
 fn validate_username(username: &str) -> errors::Result<()> 
 {
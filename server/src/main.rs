@@ -11,16 +11,77 @@ const APP_NAME: &str = "OneUp";
 // Keeping non-async for configuration and observability
 fn main() -> anyhow::Result<()> 
 {
+  // Handle help before anything else is initialized
+  let args = std::env::args().skip(1).collect::<Vec<_>>();
+  if args.iter().any(|x| x == "--help" || x == "-h") {
+    usage();
+    return Ok(());
+  }
+
   // Init configuration and observability
   let config = state::config::init()?;
   utils::observe::init(APP_NAME, &config);
 
-  // Start the api server
-  serve(config)?;
+  // Run the requested command defaulting to the api server
+  match args.first().map(String::as_str) {
+    None | Some("run") => serve(config)?,
+    Some("backup") => backup(config, &args[1..])?,
+    Some("restore") => restore(config, &args[1..])?,
+    Some(cmd) => {
+      usage();
+      return Err(anyhow::anyhow!("Unknown command '{cmd}'"));
+    }
+  }
 
   Ok(())
 }
 
+/// Command line usage
+fn usage()
+{
+  println!("Usage: ./oneup [command]");
+  println!("Commands:");
+  println!("  run                           Run the api server, the default");
+  println!("  backup [--passwords] <file>   Write a full JSON backup of the database to file");
+  println!("  restore <file>                Replace all data with the JSON backup from file");
+}
+
+/// Write a full backup of the database to the given file
+///
+/// - Password hashes are only included with `--passwords`
+fn backup(config: model::Config, args: &[String]) -> anyhow::Result<()>
+{
+  let passwords = args.iter().any(|x| x == "--passwords");
+  let path = args.iter().find(|x| !x.starts_with("--"))
+    .ok_or_else(|| anyhow::anyhow!("Missing backup file, see --help"))?;
+
+  tokio::runtime::Builder::new_current_thread().enable_all().build()?
+    .block_on(async move {
+      let state = state::init(config).await?;
+      let backup = db::backup::fetch(state.db(), state::schema_version(), passwords).await?;
+      std::fs::write(path, serde_json::to_vec_pretty(&backup)?)?;
+      log::info!("Backup version '{}' written to: {path}", backup.version);
+      state.close_db().await?;
+      Ok(())
+    })
+}
+
+/// Replace all data in the database with the backup from the given file
+fn restore(config: model::Config, args: &[String]) -> anyhow::Result<()>
+{
+  let path = args.first().ok_or_else(|| anyhow::anyhow!("Missing backup file, see --help"))?;
+  let backup: model::Backup = serde_json::from_slice(&std::fs::read(path)?)?;
+
+  tokio::runtime::Builder::new_current_thread().enable_all().build()?
+    .block_on(async move {
+      let state = state::init(config).await?;
+      db::backup::restore(state.db(), state::schema_version(), &backup).await?;
+      log::info!("Backup version '{}' restored from: {path}", backup.version);
+      state.close_db().await?;
+      Ok(())
+    })
+}
+
 /// Start the tokio runtime manually to allow for:
 /// * Customizing the runtime (e.g., number of threads)
 /// * Handling configuration and observability setup before starting the server
@@ -38,6 +99,12 @@ fn serve(config: model::Config) -> anyhow::Result<()>
     {
       let addr = format!("{}:{}", &config.ip, config.port);
       let state = state::init(config).await?;
+      state::backup::spawn(&state);
+      state::trash::spawn(&state);
+      state::redemption::spawn(&state);
+      state::chore::spawn(&state);
+      state::goal::spawn(&state);
+      state::expiry::spawn(&state);
       let router = routes::init(std::sync::Arc::new(state.clone()));
       log::info!("Server started at: {}", addr);
 
@@ -84,38 +151,3 @@ fn serve(config: model::Config) -> anyhow::Result<()>
 
   Ok(())
 }
-
-// use std::env;
-
-// fn main() {
-//   // Handle help command
-//   if env::args().len() == 1 || env::args().any(|x| x == "--help" || x == "-h") {
-//     usage();
-//     return;
-//   }
-
-//   // Handle other commands
-//   if let Some(cmd) = std::env::args().nth(1) {
-//     match cmd.as_str() {
-//       "token" => {
-//         log::info!("Generate a new api token...");
-//       }
-//       "run" => {
-//         log::info!("Running the project...");
-//         // Add run logic here
-//       }
-//       _ => {
-//         usage();
-//         return;
-//       }
-//     }
-//   }
-// }
-
-// /// Command line usage
-// fn usage() {
-//   println!("Usage: ./oneup [command]");
-//   println!("Commands:");
-//   println!("  token   Generate a new api token");
-//   println!("  run     Run the api server");
-// } 
\ No newline at end of file
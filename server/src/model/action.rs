@@ -81,8 +81,22 @@ impl UpdateAction {
     }
 }
 
+/// Used during posts to approve or reject a proposed Action
+///
+/// - ***value*** and ***category_id*** may be changed while approving
+/// - ***note*** is shown to the proposer e.g. why the action was rejected
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ReviewAction {
+  pub value: Option<i64>,
+  pub category_id: Option<i64>,
+  pub note: Option<String>,
+}
 
 /// Full Action object from database
+///
+/// - ***proposed_by*** is the user who proposed the action if known
+/// - ***reviewed_at*** is set once an admin approved or rejected the proposal
+/// - ***deleted_at*** is set while in the trash
 #[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Action {
   pub id: i64,
@@ -90,6 +104,11 @@ pub struct Action {
   pub value: i64,
   pub category_id: i64,
   pub approved: bool,
+  pub proposed_by: Option<i64>,
+  pub note: Option<String>,
+  pub reviewed_by: Option<i64>,
+  pub reviewed_at: Option<chrono::DateTime<chrono::Local>>,
   pub created_at: chrono::DateTime<chrono::Local>,
   pub updated_at: chrono::DateTime<chrono::Local>,
+  pub deleted_at: Option<chrono::DateTime<chrono::Local>>,
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{ Deserialize, Serialize};
+
+/// Default number of audit entries returned per page
+pub const AUDIT_PAGE_LIMIT: i64 = 50;
+
+/// Most audit entries that can be requested in a single page
+pub const AUDIT_PAGE_MAX: i64 = 500;
+
+/// Kinds of changes recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum AuditAction {
+  Create,
+  Update,
+  Delete,
+  Restore,
+}
+
+/// Full audit object from database
+///
+/// - ***actor_id*** is the user that made the change, None for anonymous requests
+/// - ***before*** and ***after*** hold only the fields that changed, ***before*** is None for
+///   creates and ***after*** is None for hard deletes
+/// - ***request_id*** ties the entry to the request logs, None for changes made outside a request
+#[derive(Debug, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Audit {
+  pub id: i64,
+  pub actor_id: Option<i64>,
+  pub entity: String,
+  pub entity_id: i64,
+  pub action: AuditAction,
+  #[sqlx(json(nullable))]
+  pub before: Option<serde_json::Value>,
+  #[sqlx(json(nullable))]
+  pub after: Option<serde_json::Value>,
+  pub request_id: Option<String>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Who is making the changes for the current request
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+  pub actor_id: Option<i64>,
+  pub request_id: Option<String>,
+}
+
+/// Query parameters for the audit log
+///
+/// - ***actor_id*** matches the user that made the change
+/// - ***entity*** matches the kind of entry changed e.g. `point` optionally narrowed by ***entity_id***
+/// - ***start_date*** and ***end_date*** may be given together or alone for open ended ranges
+/// - ***offset*** and ***limit*** page through the newest entries first
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct AuditQuery {
+  pub actor_id: Option<i64>,
+  pub entity: Option<String>,
+  pub entity_id: Option<i64>,
+  pub start_date: Option<DateTime<Utc>>,
+  pub end_date: Option<DateTime<Utc>>,
+  pub offset: Option<i64>,
+  pub limit: Option<i64>,
+}
+
+/// A page of audit entries
+///
+/// - ***total*** is the number of entries matching the query across all pages
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuditPage {
+  pub total: i64,
+  pub offset: i64,
+  pub limit: i64,
+  pub entries: Vec<Audit>,
+}
@@ -31,6 +31,14 @@ pub struct JwtClaims {
   pub exp: usize,                  // Expiration time in seconds
 }
 
+impl JwtClaims {
+
+  /// Check if the user has the given role
+  pub fn has_role(&self, name: &str) -> bool {
+    self.roles.iter().any(|x| x.name == name)
+  }
+}
+
 /// Used during posts to login a user
 #[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
 pub struct ApiKey {
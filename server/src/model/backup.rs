@@ -0,0 +1,103 @@
+use serde::{ Deserialize, Serialize};
+
+/// Version of the backup layout, bumped on incompatible changes to the backup itself
+pub const BACKUP_FORMAT: u32 = 1;
+
+/// Full snapshot of the household data
+///
+/// - ***format*** is the backup layout version see `BACKUP_FORMAT`
+/// - ***version*** is the latest database migration the data was taken at
+/// - ***passwords*** are only included when asked for as they contain password hashes
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Backup {
+  pub format: u32,
+  pub version: i64,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub users: Vec<super::User>,
+  pub roles: Vec<super::Role>,
+  pub user_roles: Vec<UserRole>,
+  pub categories: Vec<super::Category>,
+  pub category_parents: Vec<CategoryParent>,
+  pub actions: Vec<super::Action>,
+  pub points: Vec<super::Points>,
+  pub items: Vec<super::Item>,
+  pub rewards: Vec<super::Reward>,
+  pub redemptions: Vec<super::Redemption>,
+  pub chores: Vec<super::Chore>,
+  pub chore_users: Vec<super::ChoreUser>,
+  pub chore_misses: Vec<super::ChoreMiss>,
+  pub goals: Vec<super::Goal>,
+  pub badges: Vec<super::Badge>,
+  pub user_badges: Vec<super::UserBadge>,
+  pub bonuses: Vec<super::Bonus>,
+  pub expiries: Vec<super::Expiry>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub passwords: Option<Vec<super::Password>>,
+}
+
+/// Backup query parameters
+///
+/// - ***passwords*** includes the password hashes in the backup
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BackupQuery {
+  #[serde(default)]
+  pub passwords: bool,
+}
+
+/// Full user role assignment object from database
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct UserRole {
+  pub id: i64,
+  pub user_id: i64,
+  pub role_id: i64,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Full category parent object from database
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct CategoryParent {
+  pub id: i64,
+  pub category_id: i64,
+  pub parent_id: i64,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Status of the scheduled database snapshots
+///
+/// - ***enabled*** is false when no backup directory is configured
+/// - ***last_path*** and ***last_success_at*** are for the last verified snapshot
+/// - ***last_error*** is set when the last attempt failed and cleared on the next success
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct BackupStatus {
+  pub enabled: bool,
+  pub last_attempt_at: Option<chrono::DateTime<chrono::Local>>,
+  pub last_success_at: Option<chrono::DateTime<chrono::Local>>,
+  pub last_path: Option<String>,
+  pub last_error: Option<String>,
+}
+
+/// Summary of the scheduled database snapshots that is safe to share publicly
+///
+/// - leaves out the snapshot path and error which are only shown to admins
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct BackupHealth {
+  pub enabled: bool,
+  pub last_attempt_at: Option<chrono::DateTime<chrono::Local>>,
+  pub last_success_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl From<BackupStatus> for BackupHealth {
+  fn from(status: BackupStatus) -> Self {
+    Self { enabled: status.enabled, last_attempt_at: status.last_attempt_at,
+      last_success_at: status.last_success_at }
+  }
+}
+
+/// Health check response
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Health {
+  pub message: String,
+  pub backup: BackupHealth,
+}
@@ -0,0 +1,95 @@
+use serde::{ Deserialize, Serialize};
+
+/// What a badge rule measures
+///
+/// - ***points*** the total value of the user's approved points
+/// - ***count*** the number of approved points the user logged
+/// - ***streak*** the user's longest streak, see `Streak`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeMetric {
+  Points,
+  Count,
+  Streak,
+}
+
+/// Rule a user has to meet to earn a badge
+///
+/// - ***threshold*** is the least the metric has to reach
+/// - ***action_id*** or ***category_id*** limits the rule to an action or a category, streaks
+///   without either take the longest streak of any action
+/// - ***days*** limits points and counts to those that occurred over the trailing number of days
+/// - penalties, pending or rejected points and points in the trash never count
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BadgeRule {
+  pub metric: BadgeMetric,
+  pub threshold: i64,
+  pub action_id: Option<i64>,
+  pub category_id: Option<i64>,
+  pub days: Option<i64>,
+}
+
+#[cfg(test)]
+impl BadgeRule {
+  /// Create a new rule for the given metric and threshold
+  pub fn new(metric: BadgeMetric, threshold: i64) -> Self {
+    Self { metric, threshold, action_id: None, category_id: None, days: None }
+  }
+
+  /// Limit the rule to the given action
+  pub fn with_action_id(mut self, action_id: i64) -> Self {
+    self.action_id = Some(action_id);
+    self
+  }
+
+  /// Limit the rule to the given category
+  pub fn with_category_id(mut self, category_id: i64) -> Self {
+    self.category_id = Some(category_id);
+    self
+  }
+
+  /// Limit the rule to the given number of trailing days
+  pub fn with_days(mut self, days: i64) -> Self {
+    self.days = Some(days);
+    self
+  }
+}
+
+/// Used during posts and updates to create or change a badge
+///
+/// - ***icon*** is a key the clients map to an image
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BadgePartial {
+  pub name: String,
+  pub icon: String,
+  pub rule: BadgeRule,
+}
+
+/// Full badge object from database
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Badge {
+  pub id: i64,
+  pub name: String,
+  pub icon: String,
+  pub rule: sqlx::types::Json<BadgeRule>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Award of a badge to a user
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct UserBadge {
+  pub id: i64,
+  pub badge_id: i64,
+  pub user_id: i64,
+  pub earned_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Badge a user has earned along with when
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct EarnedBadge {
+  pub badge_id: i64,
+  pub name: String,
+  pub icon: String,
+  pub earned_at: chrono::DateTime<chrono::Local>,
+}
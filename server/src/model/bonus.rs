@@ -0,0 +1,66 @@
+use serde::{ Deserialize, Serialize};
+
+/// Used during posts and updates to create or change a bonus
+///
+/// - ***multiplier*** is applied to points that occur from ***starts_at*** up to ***ends_at***
+/// - ***category_id***, ***action_id*** and ***user_id*** narrow the bonus down, all given must
+///   match and without any the bonus applies to everyone's points
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BonusPartial {
+  pub name: String,
+  pub multiplier: f64,
+  pub starts_at: chrono::DateTime<chrono::Local>,
+  pub ends_at: chrono::DateTime<chrono::Local>,
+  pub category_id: Option<i64>,
+  pub action_id: Option<i64>,
+  pub user_id: Option<i64>,
+}
+
+#[cfg(test)]
+impl BonusPartial {
+  /// Create a new global bonus for the given window
+  pub fn new(name: &str, multiplier: f64, starts_at: chrono::DateTime<chrono::Local>,
+    ends_at: chrono::DateTime<chrono::Local>) -> Self
+  {
+    Self { name: name.to_string(), multiplier, starts_at, ends_at, category_id: None,
+      action_id: None, user_id: None }
+  }
+
+  /// Limit the bonus to the given category
+  pub fn with_category_id(mut self, category_id: i64) -> Self {
+    self.category_id = Some(category_id);
+    self
+  }
+
+  /// Limit the bonus to the given user
+  pub fn with_user_id(mut self, user_id: i64) -> Self {
+    self.user_id = Some(user_id);
+    self
+  }
+}
+
+/// Full bonus object from database
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Bonus {
+  pub id: i64,
+  pub name: String,
+  pub multiplier: f64,
+  pub starts_at: chrono::DateTime<chrono::Local>,
+  pub ends_at: chrono::DateTime<chrono::Local>,
+  pub category_id: Option<i64>,
+  pub action_id: Option<i64>,
+  pub user_id: Option<i64>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Value points would be given with the bonuses in effect
+///
+/// - ***bonus_id*** is the bonus whose ***multiplier*** applied, None when no bonus applied
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BonusPreview {
+  pub base_value: i64,
+  pub multiplier: f64,
+  pub value: i64,
+  pub bonus_id: Option<i64>,
+}
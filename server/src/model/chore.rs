@@ -0,0 +1,133 @@
+use serde::{ Deserialize, Serialize};
+
+/// How often a chore falls due
+///
+/// - ***daily*** every day
+/// - ***weekly*** on the given ***weekdays*** where Monday is 1 and Sunday is 7
+/// - ***interval*** ***every*** given number of days counting from ***starts_on***
+/// - ***monthly*** on the given ***day_of_month*** or the last day of shorter months
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum ChoreSchedule {
+  Daily,
+  Weekly,
+  Interval,
+  Monthly,
+}
+
+/// Used during posts and updates to create or change a chore
+///
+/// - ***weekdays***, ***every*** and ***day_of_month*** are given for the schedule that uses them
+/// - ***starts_on*** defaults to today
+/// - ***user_ids*** are the users the chore is assigned to, replacing any existing assignments
+/// - ***active*** chores fall due, defaults to true
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChorePartial {
+  pub action_id: i64,
+  pub schedule: ChoreSchedule,
+  pub weekdays: Option<Vec<u32>>,
+  pub every: Option<i64>,
+  pub day_of_month: Option<u32>,
+  pub starts_on: Option<chrono::NaiveDate>,
+  #[serde(default)]
+  pub user_ids: Vec<i64>,
+  #[serde(default = "default_active")]
+  pub active: bool,
+}
+
+#[cfg(test)]
+impl ChorePartial {
+  /// Create a new active chore for the given action and schedule starting today
+  pub fn new(action_id: i64, schedule: ChoreSchedule) -> Self {
+    Self { action_id, schedule, weekdays: None, every: None, day_of_month: None, starts_on: None,
+      user_ids: vec![], active: true }
+  }
+
+  /// Set the weekdays
+  pub fn with_weekdays(mut self, weekdays: Vec<u32>) -> Self {
+    self.weekdays = Some(weekdays);
+    self
+  }
+
+  /// Set the number of days between occurrences
+  pub fn with_every(mut self, every: i64) -> Self {
+    self.every = Some(every);
+    self
+  }
+
+  /// Set the day of the month
+  pub fn with_day_of_month(mut self, day_of_month: u32) -> Self {
+    self.day_of_month = Some(day_of_month);
+    self
+  }
+
+  /// Set the start date
+  pub fn with_starts_on(mut self, starts_on: chrono::NaiveDate) -> Self {
+    self.starts_on = Some(starts_on);
+    self
+  }
+
+  /// Set the assigned users
+  pub fn with_user_ids(mut self, user_ids: Vec<i64>) -> Self {
+    self.user_ids = user_ids;
+    self
+  }
+}
+
+// Chores are active unless said otherwise
+fn default_active() -> bool {
+  true
+}
+
+/// Full chore object from database
+///
+/// - ***user_ids*** are the assigned users, filled in from `ChoreUser` entries
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Chore {
+  pub id: i64,
+  pub action_id: i64,
+  pub schedule: ChoreSchedule,
+  pub weekdays: Option<sqlx::types::Json<Vec<u32>>>,
+  pub every: Option<i64>,
+  pub day_of_month: Option<u32>,
+  pub starts_on: chrono::NaiveDate,
+  pub active: bool,
+  #[sqlx(skip)]
+  #[serde(default)]
+  pub user_ids: Vec<i64>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Assignment of a chore to a user
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct ChoreUser {
+  pub id: i64,
+  pub chore_id: i64,
+  pub user_id: i64,
+  pub created_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Occurrence of a chore a user missed
+///
+/// - ***point_id*** is the penalty points taken for it if any
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct ChoreMiss {
+  pub id: i64,
+  pub chore_id: i64,
+  pub user_id: i64,
+  pub due_on: chrono::NaiveDate,
+  pub point_id: Option<i64>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Chore a user still has to do today
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct ChoreDue {
+  pub chore_id: i64,
+  pub action_id: i64,
+  pub desc: String,
+  pub value: i64,
+  pub due_on: chrono::NaiveDate,
+}
@@ -7,8 +7,67 @@ pub struct Config {
   pub ip: String,
   pub port: u16,
   pub database_url: String,
-  pub web_app_dir: String,
+
+  /// Directory containing the Flutter web build
+  ///
+  /// - optional when the web build is embedded into the binary
+  #[serde(default)]
+  pub web_app_dir: Option<String>,
   pub rust_log: LevelFilter,
+
+  /// Seconds to keep Idempotency-Key responses around for replay
+  #[serde(default = "default_idempotency_ttl")]
+  pub idempotency_ttl: u64,
+
+  /// Directory to write scheduled database snapshots to
+  ///
+  /// - scheduled snapshots are disabled when not set
+  #[serde(default)]
+  pub backup_dir: Option<String>,
+
+  /// Seconds between scheduled database snapshots
+  #[serde(default = "default_backup_interval")]
+  pub backup_interval: u64,
+
+  /// Number of most recent days to keep a daily snapshot for
+  #[serde(default = "default_backup_keep_daily")]
+  pub backup_keep_daily: usize,
+
+  /// Number of most recent weeks to keep a weekly snapshot for
+  #[serde(default = "default_backup_keep_weekly")]
+  pub backup_keep_weekly: usize,
+
+  /// Seconds to keep deleted entries in the trash before they are purged for good
+  #[serde(default = "default_trash_retention")]
+  pub trash_retention: u64,
+
+  /// Seconds a redemption request can wait on a decision before it expires
+  ///
+  /// - requests never expire when not set
+  #[serde(default)]
+  pub redemption_expiry: Option<u64>,
+
+  /// Points taken for each missed chore occurrence
+  ///
+  /// - misses are still recorded but nothing is taken when not set
+  #[serde(default)]
+  pub chore_penalty: Option<i64>,
+
+  /// Household timezone days are counted in e.g. for streaks, such as `America/Denver`
+  ///
+  /// - the system timezone is used when not set
+  #[serde(default)]
+  pub household_tz: Option<String>,
+
+  /// Days points can go unspent before they expire
+  ///
+  /// - points never expire when not set
+  #[serde(default)]
+  pub points_expiry_days: Option<i64>,
+
+  /// How unspent points expire, see `ExpiryPolicy`
+  #[serde(default)]
+  pub points_expiry_policy: super::ExpiryPolicy,
 }
 
 impl Config {
@@ -20,8 +79,44 @@ impl Config {
       ip: "127.0.0.1".to_string(),
       port: 8080,
       database_url: "sqlite::memory:".to_string(),
-      web_app_dir: "web".to_string(),
+      web_app_dir: Some("web".to_string()),
       rust_log: LevelFilter::Off,
+      idempotency_ttl: default_idempotency_ttl(),
+      backup_dir: None,
+      backup_interval: default_backup_interval(),
+      backup_keep_daily: default_backup_keep_daily(),
+      backup_keep_weekly: default_backup_keep_weekly(),
+      trash_retention: default_trash_retention(),
+      redemption_expiry: None,
+      chore_penalty: None,
+      household_tz: None,
+      points_expiry_days: None,
+      points_expiry_policy: super::ExpiryPolicy::default(),
     }
   }
-}
\ No newline at end of file
+}
+
+// Default to keeping idempotency keys for 24 hours
+fn default_idempotency_ttl() -> u64 {
+  86400
+}
+
+// Default to a snapshot every 24 hours
+fn default_backup_interval() -> u64 {
+  86400
+}
+
+// Default to keeping a week of daily snapshots
+fn default_backup_keep_daily() -> usize {
+  7
+}
+
+// Default to keeping a month of weekly snapshots
+fn default_backup_keep_weekly() -> usize {
+  4
+}
+
+// Default to keeping deleted entries for 30 days
+fn default_trash_retention() -> u64 {
+  2592000
+}
@@ -0,0 +1,90 @@
+use serde::{ Deserialize, Serialize};
+
+/// Kinds of domain events published to connected clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+  PointsCreated,
+  PointsUpdated,
+  PointsDeleted,
+  RewardCreated,
+  RewardUpdated,
+  RewardDeleted,
+  RedemptionCreated,
+  RedemptionUpdated,
+  GoalCompleted,
+  PointsExpired,
+  ActionApproved,
+  ActionRejected,
+  UserCreated,
+  UserUpdated,
+  UserDeleted,
+}
+
+impl EventKind {
+
+  /// Get the event name as sent over the wire
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      EventKind::PointsCreated => "points_created",
+      EventKind::PointsUpdated => "points_updated",
+      EventKind::PointsDeleted => "points_deleted",
+      EventKind::RewardCreated => "reward_created",
+      EventKind::RewardUpdated => "reward_updated",
+      EventKind::RewardDeleted => "reward_deleted",
+      EventKind::RedemptionCreated => "redemption_created",
+      EventKind::RedemptionUpdated => "redemption_updated",
+      EventKind::GoalCompleted => "goal_completed",
+      EventKind::PointsExpired => "points_expired",
+      EventKind::ActionApproved => "action_approved",
+      EventKind::ActionRejected => "action_rejected",
+      EventKind::UserCreated => "user_created",
+      EventKind::UserUpdated => "user_updated",
+      EventKind::UserDeleted => "user_deleted",
+    }
+  }
+}
+
+/// Domain event describing a change to an entity
+///
+/// - ***id*** is the id of the entity that changed
+/// - ***user_id*** is the user the change belongs to if any e.g. the owner of the points
+/// - ***data*** is the entity after the change, absent for deletes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Event {
+  pub kind: EventKind,
+  pub id: i64,
+  pub user_id: Option<i64>,
+  pub data: Option<serde_json::Value>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+}
+
+impl Event {
+
+  /// Create a new event for the given entity id
+  pub fn new(kind: EventKind, id: i64) -> Self {
+    Self { kind, id, user_id: None, data: None, created_at: chrono::Local::now() }
+  }
+
+  /// Set the user the change belongs to
+  pub fn with_user_id(mut self, user_id: i64) -> Self {
+    self.user_id = Some(user_id);
+    self
+  }
+
+  /// Set the entity data
+  pub fn with_data(mut self, data: impl Serialize) -> Self {
+    self.data = serde_json::to_value(data).ok();
+    self
+  }
+
+  /// Check if the event should be delivered for the given user filter
+  ///
+  /// - events that don't belong to a user e.g. action approvals are always delivered
+  pub fn is_for_user(&self, user_id: Option<i64>) -> bool {
+    match (user_id, self.user_id) {
+      (Some(filter), Some(owner)) => filter == owner,
+      _ => true,
+    }
+  }
+}
@@ -0,0 +1,49 @@
+use serde::{ Deserialize, Serialize};
+
+/// Default number of days ahead the expiring soon report looks
+pub const EXPIRING_DAYS: i64 = 14;
+
+/// How unspent points expire once they reach the configured lifetime
+///
+/// - ***fifo*** spending uses up the oldest points first so only what is left of an entry expires
+/// - ***fixed*** the whole entry expires no matter what was spent, never taking more than the
+///   user has available
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryPolicy {
+  #[default]
+  Fifo,
+  Fixed,
+}
+
+/// Full expiry object from database
+///
+/// - ***value*** is the number of points of the entry that expired
+/// - ***expired_at*** is when the points reached their lifetime
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Expiry {
+  pub id: i64,
+  pub user_id: i64,
+  pub point_id: i64,
+  pub value: i64,
+  pub expired_at: chrono::DateTime<chrono::Local>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Points that will expire unless spent first
+///
+/// - ***value*** is how many points of the entry will expire if nothing else changes
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExpiringPoints {
+  pub point_id: i64,
+  pub value: i64,
+  pub expires_at: chrono::DateTime<chrono::Local>,
+}
+
+/// Query parameters for the expiring soon report
+///
+/// - ***days*** is how far ahead to look, defaults to `EXPIRING_DAYS`
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ExpiringQuery {
+  pub days: Option<i64>,
+}
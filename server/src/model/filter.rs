@@ -1,20 +1,43 @@
 use axum::http::StatusCode;
 use chrono::{DateTime, Local, Utc};
-use sqlx::SqlitePool;
+use serde::{de::IntoDeserializer, Deserialize, Deserializer, Serializer};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use crate::{db, errors};
 
 /// Query parameter filters for various endpoints
+///
+/// - ***start_date*** and ***end_date*** may be given together or alone for open ended ranges
+///   as RFC 3339 timestamps, an unencoded `+` offset decoded as a space is accepted as a `+`
+/// - ***user_ids*** and ***action_ids*** are comma separated lists e.g. `user_ids=1,2,3`
+/// - ***value_gt*** and ***value_lt*** are exclusive bounds on the entry value
+/// - ***kinds*** and ***kinds_ne*** are comma separated lists of points kinds to include or
+///   exclude e.g. `kinds_ne=adjustment,penalty`
 #[derive(Debug, Default, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Filter {
   pub user_id: Option<i64>,
+  #[serde(default, deserialize_with = "deserialize_ids", serialize_with = "serialize_ids")]
+  pub user_ids: Option<Vec<i64>>,
   pub action_id: Option<i64>,
+  #[serde(default, deserialize_with = "deserialize_ids", serialize_with = "serialize_ids")]
+  pub action_ids: Option<Vec<i64>>,
+  pub category_id: Option<i64>,
+  pub item_id: Option<i64>,
   pub role_id: Option<i64>,
   pub role_id_ne: Option<i64>,
   pub role_name: Option<String>,
   pub role_name_ne: Option<String>,
+  #[serde(default, deserialize_with = "deserialize_date")]
   pub start_date: Option<DateTime<Utc>>,
+  #[serde(default, deserialize_with = "deserialize_date")]
   pub end_date: Option<DateTime<Utc>>,
+  pub value_gt: Option<i64>,
+  pub value_lt: Option<i64>,
   pub approved: Option<bool>,
+  pub status: Option<super::PointsStatus>,
+  #[serde(default, deserialize_with = "deserialize_kinds", serialize_with = "serialize_kinds")]
+  pub kinds: Option<Vec<super::PointsKind>>,
+  #[serde(default, deserialize_with = "deserialize_kinds", serialize_with = "serialize_kinds")]
+  pub kinds_ne: Option<Vec<super::PointsKind>>,
 }
 
 impl Filter {
@@ -31,18 +54,54 @@ impl Filter {
     self
   }
 
+  /// Set the oldest date to include
+  pub fn with_start_date(mut self, start: DateTime<Local>) -> Self {
+    self.start_date = Some(start.to_utc());
+    self
+  }
+
+  /// Set the newest date to include
+  pub fn with_end_date(mut self, end: DateTime<Local>) -> Self {
+    self.end_date = Some(end.to_utc());
+    self
+  }
+
   /// Set the user id
   pub fn with_user_id(mut self, user_id: i64) -> Self {
     self.user_id = Some(user_id);
     self
   }
 
+  /// Set the list of user ids to match any of
+  pub fn with_user_ids(mut self, user_ids: Vec<i64>) -> Self {
+    self.user_ids = Some(user_ids);
+    self
+  }
+
   /// Set the action id
   pub fn with_action_id(mut self, action_id: i64) -> Self {
     self.action_id = Some(action_id);
     self
   }
 
+  /// Set the list of action ids to match any of
+  pub fn with_action_ids(mut self, action_ids: Vec<i64>) -> Self {
+    self.action_ids = Some(action_ids);
+    self
+  }
+
+  /// Set the category id
+  pub fn with_category_id(mut self, category_id: i64) -> Self {
+    self.category_id = Some(category_id);
+    self
+  }
+
+  /// Set the catalog item id
+  pub fn with_item_id(mut self, item_id: i64) -> Self {
+    self.item_id = Some(item_id);
+    self
+  }
+
   /// Set the role id
   pub fn with_role_id(mut self, role_id: i64) -> Self {
     self.role_id = Some(role_id);
@@ -67,21 +126,40 @@ impl Filter {
     self
   }
 
+  /// Set the exclusive lower bound for values
+  pub fn with_value_gt(mut self, value: i64) -> Self {
+    self.value_gt = Some(value);
+    self
+  }
+
+  /// Set the exclusive upper bound for values
+  pub fn with_value_lt(mut self, value: i64) -> Self {
+    self.value_lt = Some(value);
+    self
+  }
+
   /// Set the action approved status
   pub fn with_approved(mut self, approved: bool) -> Self {
     self.approved = Some(approved);
     self
   }
 
-  /// Set the role name
-  /// Get the date range as DateTime objects if both dates are present
-  pub fn date_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
-    match (&self.start_date, &self.end_date) {
-      (Some(start), Some(end)) => {
-        Some((*start, *end))
-      }
-      _ => None,
-    }
+  /// Set the points review status
+  pub fn with_status(mut self, status: super::PointsStatus) -> Self {
+    self.status = Some(status);
+    self
+  }
+
+  /// Set the list of points kinds to match any of
+  pub fn with_kinds(mut self, kinds: Vec<super::PointsKind>) -> Self {
+    self.kinds = Some(kinds);
+    self
+  }
+
+  /// Set the list of points kinds to leave out
+  pub fn with_kinds_ne(mut self, kinds: Vec<super::PointsKind>) -> Self {
+    self.kinds_ne = Some(kinds);
+    self
   }
 
   /// Are any of the user filter values set?
@@ -92,197 +170,438 @@ impl Filter {
 
   /// Are any of the points filter values set?
   pub fn any_points_filters(&self) -> bool {
-    self.user_id.is_some() || self.action_id.is_some() || self.start_date.is_some()
-      || self.end_date.is_some()
+    self.user_id.is_some() || self.user_ids.is_some() || self.action_id.is_some()
+      || self.action_ids.is_some() || self.category_id.is_some() || self.approved.is_some()
+      || self.status.is_some() || self.kinds.is_some() || self.kinds_ne.is_some()
+      || self.any_value_filters() || self.any_date_filters()
+  }
+
+  /// Are any of the rewards filter values set?
+  pub fn any_rewards_filters(&self) -> bool {
+    self.user_id.is_some() || self.user_ids.is_some() || self.item_id.is_some()
+      || self.any_value_filters() || self.any_date_filters()
   }
 
   /// Are any of the action filter values set?
   pub fn any_action_filters(&self) -> bool {
-    self.approved.is_some()
+    self.approved.is_some() || self.category_id.is_some()
+  }
+
+  /// Are any of the value range filter values set?
+  pub fn any_value_filters(&self) -> bool {
+    self.value_gt.is_some() || self.value_lt.is_some()
+  }
+
+  /// Are any of the date range filter values set?
+  pub fn any_date_filters(&self) -> bool {
+    self.start_date.is_some() || self.end_date.is_some()
   }
 
-  /// Convert the filter to a where clause for filtering users
-  /// 
+  /// Push the where clause for filtering users onto the given query
+  ///
+  /// - excludes users in the trash
+  /// - expects `role` to be joined into the query
   /// - error on no valid filter options provided
   /// - error on both role_id and role_id_ne provided
   /// - error on both role_name and role_name_ne provided
   /// - error on both role_id and role_name provided
-  /// - error on other SQL errors
-  /// 
+  ///
   /// #### Parameters
-  /// - ***db*** - database connection pool 
-  /// 
-  /// #### Returns
-  /// - ***String*** - where clause for query
-  ///   - e.g. `WHERE user_id = ? AND action_id = ?`
-  pub async fn to_users_where_clause(&self, _db: &SqlitePool) ->
-    errors::Result<String>
+  /// - ***db*** - database connection pool
+  /// - ***query*** - query to push the where clause onto
+  pub async fn push_users_where_clause(&self, _db: &SqlitePool,
+    query: &mut QueryBuilder<'_, Sqlite>) -> errors::Result<()>
   {
     // Error out if no filter values are provided
-    if !self.any_user_filters()
-    {
-      let msg = format!("No valid filter options provided for users.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+    if !self.any_user_filters() {
+      return Err(unprocessable("No valid filter options provided for users."));
     }
-
-    // Error out if both role_id and role_id_ne are provided
     if self.role_id.is_some() && self.role_id_ne.is_some() {
-      let msg = format!("Both role_id and role_id_ne filter options cannot be provided for users.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
-   }
-
-   // Error out if both role_name and role_name_ne are provided
+      return Err(unprocessable(
+        "Both role_id and role_id_ne filter options cannot be provided for users."));
+    }
     if self.role_name.is_some() && self.role_name_ne.is_some() {
-      let msg = format!("Both role_name and role_name_ne filter options cannot be provided for users.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+      return Err(unprocessable(
+        "Both role_name and role_name_ne filter options cannot be provided for users."));
     }
- 
-    // Error out if both role_id and role_name are provided
     if self.role_id.is_some() && self.role_name.is_some() {
-      let msg = format!("Both role_id and role_name filter options cannot be provided for users.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+      return Err(unprocessable(
+        "Both role_id and role_name filter options cannot be provided for users."));
     }
 
-    // Construct where clause and ensure the user and action exist if provided 
-    let mut where_clause = "WHERE ".to_string();
-
-    if self.role_id.is_some() {
-      where_clause.push_str(&format!("role.id = ?"));
-    } else if self.role_name.is_some() {
-      where_clause.push_str(&format!("role.name = ?"));
-    } else if self.role_id_ne.is_some() {
-      where_clause.push_str(&format!("(role.id != ? OR role.id IS NULL)"));
-    } else if self.role_name_ne.is_some() {
-      where_clause.push_str(&format!("(role.name != ? OR role.name IS NULL)"));
+    let mut clause = WhereClause::new(query);
+    clause.and().push("user.deleted_at IS NULL");
+    if let Some(role_id) = self.role_id {
+      clause.and().push("role.id = ").push_bind(role_id);
+    } else if let Some(role_name) = &self.role_name {
+      clause.and().push("role.name = ").push_bind(role_name.clone());
+    } else if let Some(role_id_ne) = self.role_id_ne {
+      clause.and().push("(role.id != ").push_bind(role_id_ne).push(" OR role.id IS NULL)");
+    } else if let Some(role_name_ne) = &self.role_name_ne {
+      clause.and().push("(role.name != ").push_bind(role_name_ne.clone())
+        .push(" OR role.name IS NULL)");
     }
-
-    Ok(where_clause)
+    Ok(())
   }
 
-  /// Convert the filter to a where clause for filtering points
-  /// 
+  /// Push the where clause for filtering points onto the given query
+  ///
+  /// - excludes points in the trash
+  /// - expects `action` to be joined into the query for category and approved filtering
   /// - error on no valid filter options provided
-  /// - error on user not found if user_id is provided
-  /// - error on action not found if action_id is provided
+  /// - error on user not found if user_id or user_ids are provided
+  /// - error on action not found if action_id or action_ids are provided
+  /// - error on category not found if category_id is provided
+  /// - error on empty kinds or kinds_ne
   /// - error on other SQL errors
-  /// 
+  ///
   /// #### Parameters
-  /// - ***db*** - database connection pool 
-  /// 
-  /// #### Returns
-  /// - ***String*** - where clause for query
-  ///   - e.g. `WHERE user_id = ? AND action_id = ?`
-  pub async fn to_points_where_clause(&self, db: &SqlitePool) ->
-    errors::Result<String>
+  /// - ***db*** - database connection pool
+  /// - ***query*** - query to push the where clause onto
+  pub async fn push_points_where_clause(&self, db: &SqlitePool,
+    query: &mut QueryBuilder<'_, Sqlite>) -> errors::Result<()>
   {
-    // Error out if no filter values are provided
     if !self.any_points_filters() {
-      let msg = format!("No valid filter options provided for points.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+      return Err(unprocessable("No valid filter options provided for points."));
     }
-
-    // Construct where clause and ensure the user and action exist if provided 
-    let mut where_clause = "WHERE ".to_string();
-    let mut first_condition = true;
-
-    if let Some(user_id) = self.user_id {
-      db::user::fetch_by_id(db, user_id).await?;
-      where_clause.push_str(&format!("user_id = ?"));
-      first_condition = false;
+    self.validate_users(db).await?;
+    self.validate_actions(db).await?;
+    if let Some(category_id) = self.category_id {
+      db::category::fetch_by_id(db, category_id).await?;
     }
-    
-    if let Some(action_id) = self.action_id {
-      db::action::fetch_by_id(db, action_id).await?;
-      if !first_condition {
-        where_clause.push_str(" AND ");
-      }
-      where_clause.push_str(&format!("action_id = ?"));
-      first_condition = false;
+    if self.kinds.as_ref().is_some_and(|x| x.is_empty())
+      || self.kinds_ne.as_ref().is_some_and(|x| x.is_empty())
+    {
+      return Err(unprocessable(
+        "The kinds and kinds_ne filter options require at least one kind."));
     }
 
-    if let Some((_, _)) = self.date_range() {
-      if !first_condition {
-        where_clause.push_str(" AND ");
-      }
-      where_clause.push_str("datetime(created_at) >= datetime(?) AND datetime(created_at) <= datetime(?)");
+    let mut clause = WhereClause::new(query);
+    clause.and().push("point.deleted_at IS NULL");
+    self.push_user_conditions(&mut clause, "point.user_id");
+    if let Some(action_id) = self.action_id {
+      clause.and().push("point.action_id = ").push_bind(action_id);
+    }
+    if let Some(action_ids) = &self.action_ids {
+      push_in_list(clause.and(), "point.action_id", action_ids);
+    }
+    if let Some(category_id) = self.category_id {
+      clause.and().push("action.category_id = ").push_bind(category_id);
+    }
+    if let Some(approved) = self.approved {
+      clause.and().push("action.approved = ").push_bind(approved);
     }
-    Ok(where_clause)
+    if let Some(status) = self.status {
+      clause.and().push("point.status = ").push_bind(status);
+    }
+    if let Some(kinds) = &self.kinds {
+      push_kinds(clause.and(), "point.kind IN (", kinds);
+    }
+    if let Some(kinds) = &self.kinds_ne {
+      push_kinds(clause.and(), "point.kind NOT IN (", kinds);
+    }
+    self.push_value_conditions(&mut clause, "point.value");
+    self.push_date_conditions(&mut clause, "point.occurred_at");
+    Ok(())
   }
 
-  /// Convert the filter to a where clause for filtering rewards
-  /// 
+  /// Push the where clause for filtering rewards onto the given query
+  ///
+  /// - excludes rewards in the trash
   /// - error on no valid filter options provided
-  /// - error on user not found if user_id is provided
+  /// - error on user not found if user_id or user_ids are provided
+  /// - error on item not found if item_id is provided
   /// - error on other SQL errors
-  /// 
+  ///
   /// #### Parameters
-  /// - ***db*** - database connection pool 
-  /// 
-  /// #### Returns
-  /// - ***String*** - where clause for query
-  ///   - e.g. `WHERE user_id = ? AND action_id = ?`
-  pub async fn to_rewards_where_clause(&self, db: &SqlitePool) ->
-    errors::Result<String>
+  /// - ***db*** - database connection pool
+  /// - ***query*** - query to push the where clause onto
+  pub async fn push_rewards_where_clause(&self, db: &SqlitePool,
+    query: &mut QueryBuilder<'_, Sqlite>) -> errors::Result<()>
   {
-    // Error out if no filter values are provided
-    if self.user_id.is_none() && self.date_range().is_none() {
-      let msg = format!("No valid filter options provided for rewards.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+    if !self.any_rewards_filters() {
+      return Err(unprocessable("No valid filter options provided for rewards."));
     }
-
-    // Construct where clause and ensure the user and action exist if provided 
-    let mut where_clause = "WHERE ".to_string();
-    let mut first_condition = true;
-
-    if let Some(user_id) = self.user_id {
-      db::user::fetch_by_id(db, user_id).await?;
-      where_clause.push_str(&format!("user_id = ?"));
-      first_condition = false;
+    self.validate_users(db).await?;
+    if let Some(item_id) = self.item_id {
+      db::item::fetch_by_id(db, item_id).await?;
     }
-    
-    if let Some((_, _)) = self.date_range() {
-      if !first_condition {
-        where_clause.push_str(" AND ");
-      }
-      where_clause.push_str("datetime(created_at) >= datetime(?) AND datetime(created_at) <= datetime(?)");
+
+    let mut clause = WhereClause::new(query);
+    clause.and().push("reward.deleted_at IS NULL");
+    self.push_user_conditions(&mut clause, "reward.user_id");
+    if let Some(item_id) = self.item_id {
+      clause.and().push("reward.item_id = ").push_bind(item_id);
     }
-    Ok(where_clause)
+    self.push_value_conditions(&mut clause, "reward.value");
+    self.push_date_conditions(&mut clause, "reward.created_at");
+    Ok(())
   }
 
-  /// Convert the filter to a where clause for filtering actions
-  /// 
+  /// Push the where clause for filtering actions onto the given query
+  ///
+  /// - excludes actions in the trash
   /// - error on no valid filter options provided
   /// - error on category not found if category_id is provided
   /// - error on other SQL errors
-  /// 
+  ///
   /// #### Parameters
-  /// - ***db*** - database connection pool 
-  /// 
-  /// #### Returns
-  /// - ***String*** - where clause for query
-  ///   - e.g. `WHERE approved = ?`
-  pub async fn to_actions_where_clause(&self, _db: &SqlitePool) ->
-    errors::Result<String>
+  /// - ***db*** - database connection pool
+  /// - ***query*** - query to push the where clause onto
+  pub async fn push_actions_where_clause(&self, db: &SqlitePool,
+    query: &mut QueryBuilder<'_, Sqlite>) -> errors::Result<()>
   {
-    // Error out if no filter values are provided
     if !self.any_action_filters() {
-      let msg = format!("No valid filter options provided for actions.");
-      log::error!("{msg}");
-      return Err(errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+      return Err(unprocessable("No valid filter options provided for actions."));
+    }
+    if let Some(category_id) = self.category_id {
+      db::category::fetch_by_id(db, category_id).await?;
     }
 
-    // Construct where clause and ensure the approved status is set if provided 
-    let mut where_clause = "WHERE ".to_string();
+    let mut clause = WhereClause::new(query);
+    clause.and().push("action.deleted_at IS NULL");
+    if let Some(approved) = self.approved {
+      clause.and().push("action.approved = ").push_bind(approved);
+    }
+    if let Some(category_id) = self.category_id {
+      clause.and().push("action.category_id = ").push_bind(category_id);
+    }
+    Ok(())
+  }
 
-    if self.approved.is_some() {
-      where_clause.push_str(&format!("approved = ?"));
+  // Ensure the user filters are consistent and the users exist
+  async fn validate_users(&self, db: &SqlitePool) -> errors::Result<()>
+  {
+    if self.user_id.is_some() && self.user_ids.is_some() {
+      return Err(unprocessable("Both user_id and user_ids filter options cannot be provided."));
+    }
+    if self.user_ids.as_ref().is_some_and(|x| x.is_empty()) {
+      return Err(unprocessable("The user_ids filter option requires at least one id."));
+    }
+    for user_id in self.user_id.iter().chain(self.user_ids.iter().flatten()) {
+      db::user::fetch_by_id(db, *user_id).await?;
+    }
+    Ok(())
+  }
+
+  // Ensure the action filters are consistent and the actions exist
+  async fn validate_actions(&self, db: &SqlitePool) -> errors::Result<()>
+  {
+    if self.action_id.is_some() && self.action_ids.is_some() {
+      return Err(unprocessable("Both action_id and action_ids filter options cannot be provided."));
+    }
+    if self.action_ids.as_ref().is_some_and(|x| x.is_empty()) {
+      return Err(unprocessable("The action_ids filter option requires at least one id."));
+    }
+    for action_id in self.action_id.iter().chain(self.action_ids.iter().flatten()) {
+      db::action::fetch_by_id(db, *action_id).await?;
+    }
+    Ok(())
+  }
+
+  // Push user id conditions for the given column
+  fn push_user_conditions(&self, clause: &mut WhereClause<'_, '_>, column: &str)
+  {
+    if let Some(user_id) = self.user_id {
+      clause.and().push(column).push(" = ").push_bind(user_id);
+    }
+    if let Some(user_ids) = &self.user_ids {
+      push_in_list(clause.and(), column, user_ids);
+    }
+  }
+
+  // Push exclusive value range conditions for the given column
+  fn push_value_conditions(&self, clause: &mut WhereClause<'_, '_>, column: &str)
+  {
+    if let Some(value) = self.value_gt {
+      clause.and().push(column).push(" > ").push_bind(value);
+    }
+    if let Some(value) = self.value_lt {
+      clause.and().push(column).push(" < ").push_bind(value);
     }
-    Ok(where_clause)
   }
-} 
\ No newline at end of file
+
+  // Push inclusive date range conditions for the given column
+  fn push_date_conditions(&self, clause: &mut WhereClause<'_, '_>, column: &str)
+  {
+    if let Some(start) = self.start_date {
+      clause.and().push(format!("datetime({column}) >= datetime(")).push_bind(start).push(")");
+    }
+    if let Some(end) = self.end_date {
+      clause.and().push(format!("datetime({column}) <= datetime(")).push_bind(end).push(")");
+    }
+  }
+}
+
+// Joins conditions onto a query with WHERE for the first and AND for the rest
+struct WhereClause<'q, 'args> {
+  query: &'q mut QueryBuilder<'args, Sqlite>,
+  is_empty: bool,
+}
+
+impl<'q, 'args> WhereClause<'q, 'args> {
+  fn new(query: &'q mut QueryBuilder<'args, Sqlite>) -> Self {
+    Self { query, is_empty: true }
+  }
+
+  // Start the next condition
+  fn and(&mut self) -> &mut QueryBuilder<'args, Sqlite> {
+    self.query.push(if self.is_empty { " WHERE " } else { " AND " });
+    self.is_empty = false;
+    self.query
+  }
+}
+
+// Push a `column IN (?, ?, ...)` condition with each id bound
+fn push_in_list(query: &mut QueryBuilder<'_, Sqlite>, column: &str, ids: &[i64])
+{
+  query.push(column).push(" IN (");
+  let mut list = query.separated(", ");
+  for id in ids {
+    list.push_bind(*id);
+  }
+  list.push_unseparated(")");
+}
+
+// Push a `column IN (?, ?, ...)` style condition with each kind bound
+fn push_kinds(query: &mut QueryBuilder<'_, Sqlite>, condition: &str, kinds: &[super::PointsKind])
+{
+  query.push(condition);
+  let mut list = query.separated(", ");
+  for kind in kinds {
+    list.push_bind(*kind);
+  }
+  list.push_unseparated(")");
+}
+
+// Log and build an unprocessable entity error
+fn unprocessable(msg: &str) -> errors::Error
+{
+  log::error!("{msg}");
+  errors::Error::http(StatusCode::UNPROCESSABLE_ENTITY, msg)
+}
+
+// Deserialize an RFC 3339 timestamp restoring a `+` offset the query string decoded as a space
+// e.g. `2026-10-19T03:04:05 07:00`
+fn deserialize_date<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let value = Option::<String>::deserialize(deserializer)?;
+  value.map(|x| DateTime::parse_from_rfc3339(&x.trim_start().replace(' ', "+"))
+    .map(|x| x.to_utc()).map_err(serde::de::Error::custom)).transpose()
+}
+
+// Deserialize a comma separated list of ids e.g. `1,2,3`
+fn deserialize_ids<'de, D>(deserializer: D) -> Result<Option<Vec<i64>>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let value = Option::<String>::deserialize(deserializer)?;
+  value.map(|x| x.split(',').map(str::trim).filter(|x| !x.is_empty())
+    .map(|x| x.parse::<i64>().map_err(serde::de::Error::custom))
+    .collect()).transpose()
+}
+
+// Serialize a list of ids as a comma separated string e.g. `1,2,3`
+fn serialize_ids<S>(value: &Option<Vec<i64>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  match value {
+    Some(ids) => serializer.serialize_str(
+      &ids.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")),
+    None => serializer.serialize_none(),
+  }
+}
+
+// Deserialize a comma separated list of points kinds e.g. `award,adjustment`
+fn deserialize_kinds<'de, D>(deserializer: D) -> Result<Option<Vec<super::PointsKind>>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let value = Option::<String>::deserialize(deserializer)?;
+  value.map(|x| x.split(',').map(str::trim).filter(|x| !x.is_empty())
+    .map(|x| super::PointsKind::deserialize(x.into_deserializer()))
+    .collect()).transpose()
+}
+
+// Serialize a list of points kinds as a comma separated string e.g. `award,adjustment`
+fn serialize_kinds<S>(value: &Option<Vec<super::PointsKind>>, serializer: S)
+  -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  match value {
+    Some(kinds) => serializer.serialize_str(
+      &kinds.iter().map(|x| x.as_str()).collect::<Vec<_>>().join(",")),
+    None => serializer.serialize_none(),
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use axum::{extract::Query, http::Uri};
+  use crate::model::PointsKind;
+
+  #[test]
+  fn test_deserialize_id_lists()
+  {
+    let uri: Uri = "/api/points?user_ids=1,2,%203&action_ids=4".parse().unwrap();
+    let Query(filter) = Query::<Filter>::try_from_uri(&uri).unwrap();
+    assert_eq!(filter.user_ids, Some(vec![1, 2, 3]));
+    assert_eq!(filter.action_ids, Some(vec![4]));
+    assert!(filter.any_points_filters());
+
+    let uri: Uri = "/api/points?user_ids=1,foo".parse().unwrap();
+    assert!(Query::<Filter>::try_from_uri(&uri).is_err());
+
+    let uri: Uri = "/api/points?kinds_ne=adjustment,penalty".parse().unwrap();
+    let Query(filter) = Query::<Filter>::try_from_uri(&uri).unwrap();
+    assert_eq!(filter.kinds_ne, Some(vec![PointsKind::Adjustment, PointsKind::Penalty]));
+    assert!(filter.any_points_filters());
+    let uri: Uri = "/api/points?kinds=award,foo".parse().unwrap();
+    assert!(Query::<Filter>::try_from_uri(&uri).is_err());
+  }
+
+  #[test]
+  fn test_deserialize_dates_with_unencoded_offset()
+  {
+    let expected = "2026-10-18T20:04:05Z".parse::<DateTime<Utc>>().unwrap();
+    let uri: Uri = "/api/points?start_date=2026-10-19T03:04:05+07:00\
+      &end_date=2026-10-18T20:04:05Z".parse().unwrap();
+    let Query(filter) = Query::<Filter>::try_from_uri(&uri).unwrap();
+    assert_eq!(filter.start_date, Some(expected));
+    assert_eq!(filter.end_date, Some(expected));
+
+    let uri: Uri = "/api/points?start_date=2026-10-19T03:04:05%2B07:00".parse().unwrap();
+    let Query(filter) = Query::<Filter>::try_from_uri(&uri).unwrap();
+    assert_eq!(filter.start_date, Some(expected));
+
+    let uri: Uri = "/api/points?start_date=yesterday".parse().unwrap();
+    assert!(Query::<Filter>::try_from_uri(&uri).is_err());
+  }
+
+  #[test]
+  fn test_open_ended_date_range_is_a_filter()
+  {
+    let filter = Filter::new().with_start_date(Local::now());
+    assert!(filter.any_points_filters());
+    assert!(filter.any_rewards_filters());
+    assert!(!filter.any_user_filters());
+  }
+
+  #[tokio::test]
+  async fn test_points_where_clause_binds_in_order()
+  {
+    let state = crate::state::test().await;
+    let filter = Filter::new().with_value_gt(1).with_value_lt(5).with_category_id(1)
+      .with_start_date(Local::now());
+    let mut query = QueryBuilder::new("SELECT point.* FROM point");
+    filter.push_points_where_clause(state.db(), &mut query).await.unwrap();
+    assert_eq!(query.sql(), "SELECT point.* FROM point WHERE point.deleted_at IS NULL \
+      AND action.category_id = ? AND point.value > ? AND point.value < ? AND datetime(point.occurred_at) >= datetime(?)");
+  }
+}
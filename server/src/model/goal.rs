@@ -0,0 +1,55 @@
+use serde::{ Deserialize, Serialize};
+
+/// Number of days the earning rate for projections is taken over
+pub const GOAL_RATE_DAYS: i64 = 28;
+
+/// Used during posts and updates to create or change a savings goal
+///
+/// - ***target*** defaults to the cost of the linked catalog ***item_id*** when not given
+/// - ***deadline*** is the day the user hopes to reach the target by
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GoalPartial {
+  pub name: String,
+  pub target: Option<i64>,
+  pub deadline: Option<chrono::NaiveDate>,
+  pub item_id: Option<i64>,
+}
+
+#[cfg(test)]
+impl GoalPartial {
+  /// Create a new goal with the given name and target
+  pub fn new(name: impl Into<String>, target: Option<i64>) -> Self {
+    Self { name: name.into(), target, deadline: None, item_id: None }
+  }
+
+  /// Set the linked catalog item
+  pub fn with_item_id(mut self, item_id: i64) -> Self {
+    self.item_id = Some(item_id);
+    self
+  }
+}
+
+/// Full savings goal object from database
+///
+/// - ***completed_at*** is when the target was first reached, cleared when the target changes
+/// - ***progress*** is the user's available points up to the target
+/// - ***projected_on*** is when the target is expected to be reached at the user's earning rate
+///   over the last `GOAL_RATE_DAYS` days, missing when the goal is reached or nothing is earned
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Goal {
+  pub id: i64,
+  pub user_id: i64,
+  pub name: String,
+  pub target: i64,
+  pub deadline: Option<chrono::NaiveDate>,
+  pub item_id: Option<i64>,
+  pub completed_at: Option<chrono::DateTime<chrono::Local>>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+  #[sqlx(skip)]
+  #[serde(default)]
+  pub progress: i64,
+  #[sqlx(skip)]
+  #[serde(default)]
+  pub projected_on: Option<chrono::NaiveDate>,
+}
@@ -0,0 +1,17 @@
+use serde::{ Deserialize, Serialize};
+
+/// Full idempotency object from database
+///
+/// - ***user_id*** is the caller the key belongs to, None for anonymous callers
+/// - ***status*** is None while the original request is still being processed
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Idempotency {
+  pub id: i64,
+  pub key: String,
+  pub user_id: Option<i64>,
+  pub request_hash: String,
+  pub status: Option<u16>,
+  pub content_type: Option<String>,
+  pub body: Option<Vec<u8>>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+}
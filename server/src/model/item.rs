@@ -0,0 +1,87 @@
+use serde::{ Deserialize, Serialize};
+
+/// Periods a per user redemption limit can apply to
+///
+/// - periods follow the server's local calendar with weeks starting on Monday
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum ItemPeriod {
+  Day,
+  Week,
+  Month,
+}
+
+/// Used during posts and updates to create or change a catalog item
+///
+/// - ***stock*** is how many are left, omit for unlimited
+/// - ***period_limit*** and ***period*** are given together to limit redemptions per user
+/// - ***active*** items can be redeemed, defaults to true
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ItemPartial {
+  pub name: String,
+  #[serde(default)]
+  pub desc: String,
+  pub cost: i64,
+  pub stock: Option<i64>,
+  pub period_limit: Option<i64>,
+  pub period: Option<ItemPeriod>,
+  #[serde(default = "default_active")]
+  pub active: bool,
+}
+
+#[cfg(test)]
+impl ItemPartial {
+  /// Create a new active item with the given name and cost
+  pub fn new(name: impl Into<String>, cost: i64) -> Self {
+    Self { name: name.into(), desc: String::new(), cost, stock: None, period_limit: None,
+      period: None, active: true }
+  }
+
+  /// Set the stock
+  pub fn with_stock(mut self, stock: i64) -> Self {
+    self.stock = Some(stock);
+    self
+  }
+
+  /// Set the per user redemption limit for the given period
+  pub fn with_limit(mut self, limit: i64, period: ItemPeriod) -> Self {
+    self.period_limit = Some(limit);
+    self.period = Some(period);
+    self
+  }
+
+  /// Set the active status
+  pub fn with_active(mut self, active: bool) -> Self {
+    self.active = active;
+    self
+  }
+}
+
+// Items are active unless said otherwise
+fn default_active() -> bool {
+  true
+}
+
+/// Used during posts to redeem a catalog item
+///
+/// - ***user_id*** defaults to the caller
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Redeem {
+  pub user_id: Option<i64>,
+}
+
+/// Full catalog item object from database
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Item {
+  pub id: i64,
+  pub name: String,
+  pub desc: String,
+  pub cost: i64,
+  pub stock: Option<i64>,
+  pub period_limit: Option<i64>,
+  pub period: Option<ItemPeriod>,
+  pub active: bool,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
@@ -0,0 +1,84 @@
+use serde::{ Deserialize, Serialize};
+
+/// Window of time a leaderboard ranks points over
+///
+/// - ***week*** starts on Monday and ***month*** on the first, both at local midnight
+/// - ***all*** ranks all points and so has no previous period to compare against
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardPeriod {
+  #[default]
+  Week,
+  Month,
+  All,
+}
+
+/// Query parameters for the leaderboard
+///
+/// - ***period*** defaults to the current week
+/// - ***category_id*** only counts points for actions in the category
+/// - ***role_name_ne*** and ***role_id_ne*** leave out users holding the role
+/// - ***ranks_only*** hides the point totals so only the ranking is shown
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct LeaderboardQuery {
+  #[serde(default)]
+  pub period: LeaderboardPeriod,
+  pub category_id: Option<i64>,
+  pub role_name_ne: Option<String>,
+  pub role_id_ne: Option<i64>,
+  #[serde(default)]
+  pub ranks_only: bool,
+}
+
+#[cfg(test)]
+impl LeaderboardQuery {
+  /// Create a new query for the given period
+  pub fn new(period: LeaderboardPeriod) -> Self {
+    Self { period, ..Default::default() }
+  }
+
+  /// Set the category id
+  pub fn with_category_id(mut self, category_id: i64) -> Self {
+    self.category_id = Some(category_id);
+    self
+  }
+
+  /// Set the name of the role to leave out
+  pub fn with_role_name_ne(mut self, role_name_ne: &str) -> Self {
+    self.role_name_ne = Some(role_name_ne.to_string());
+    self
+  }
+
+  /// Hide the point totals
+  pub fn with_ranks_only(mut self) -> Self {
+    self.ranks_only = true;
+    self
+  }
+}
+
+/// User's place on the leaderboard
+///
+/// - ***rank*** is shared by users with the same total, the next rank skipping past them
+/// - ***total*** is the user's points in the period, left out in ranks only mode
+/// - ***previous_rank*** is the user's rank in the period before
+/// - ***rank_delta*** is the places gained since the previous period, negative for places lost
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LeaderboardEntry {
+  pub rank: i64,
+  pub user_id: i64,
+  pub username: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub total: Option<i64>,
+  pub previous_rank: Option<i64>,
+  pub rank_delta: Option<i64>,
+}
+
+/// Users ranked by points over a period
+///
+/// - ***start*** is when the period began, not set for all time
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Leaderboard {
+  pub period: LeaderboardPeriod,
+  pub start: Option<chrono::DateTime<chrono::Local>>,
+  pub entries: Vec<LeaderboardEntry>,
+}
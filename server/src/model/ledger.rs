@@ -0,0 +1,70 @@
+use serde::{ Deserialize, Serialize};
+
+/// Default number of ledger entries returned per page
+pub const LEDGER_PAGE_LIMIT: i64 = 50;
+
+/// Most ledger entries that can be requested in a single page
+pub const LEDGER_PAGE_MAX: i64 = 500;
+
+/// Point totals for a user
+///
+/// - ***earned*** is the sum of the user's points
+/// - ***spent*** is the sum of the user's rewards
+/// - ***held*** is the sum of the user's redemption requests waiting on a decision
+/// - ***expired*** is the sum of the user's points that went unspent past their lifetime
+/// - ***available*** is what is left to spend i.e. earned less spent, held and expired
+#[derive(Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Balance {
+  pub user_id: i64,
+  pub earned: i64,
+  pub spent: i64,
+  pub held: i64,
+  pub expired: i64,
+  pub available: i64,
+}
+
+/// Kinds of entries in the ledger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum LedgerKind {
+  Points,
+  Reward,
+  Expiry,
+}
+
+/// Points, reward or expiry entry in a user's ledger
+///
+/// - ***id*** is the id of the points, reward or expiry entry
+/// - ***value*** is positive for points earned and negative for rewards spent and points expired
+/// - ***occurred_at*** is when the points occurred, the reward was cashed out or the points
+///   expired
+/// - ***balance*** is the running balance including this entry
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct LedgerEntry {
+  pub kind: LedgerKind,
+  pub id: i64,
+  pub value: i64,
+  pub action_id: Option<i64>,
+  pub occurred_at: chrono::DateTime<chrono::Local>,
+  pub balance: i64,
+}
+
+/// Query parameters for the ledger
+///
+/// - ***cursor*** continues from the ***next_cursor*** of the previous page
+/// - ***limit*** is the number of entries per page
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct LedgerQuery {
+  pub cursor: Option<String>,
+  pub limit: Option<i64>,
+}
+
+/// A page of ledger entries newest first
+///
+/// - ***next_cursor*** is set when there are older entries to fetch
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LedgerPage {
+  pub entries: Vec<LedgerEntry>,
+  pub next_cursor: Option<String>,
+}
@@ -3,24 +3,58 @@
  */
 pub mod user;
 pub mod action;
+pub mod audit;
+pub mod backup;
+pub mod badge;
+pub mod bonus;
 pub mod category;
+pub mod chore;
 pub mod config;
+pub mod event;
+pub mod expiry;
 pub mod filter;
+pub mod goal;
+pub mod idempotency;
+pub mod item;
+pub mod leaderboard;
+pub mod ledger;
 pub mod auth;
 pub mod password;
 pub mod point;
+pub mod redemption;
 pub mod reward;
 pub mod role;
+pub mod search;
+pub mod streak;
 pub mod simple;
+pub mod transfer;
+pub mod trash;
 
 pub use user::*;
 pub use action::*;
+pub use audit::*;
+pub use backup::*;
+pub use badge::*;
+pub use bonus::*;
 pub use category::*;
+pub use chore::*;
 pub use config::*;
+pub use event::*;
+pub use expiry::*;
 pub use filter::*;
+pub use goal::*;
+pub use idempotency::*;
+pub use item::*;
+pub use leaderboard::*;
+pub use ledger::*;
 pub use auth::*;
 pub use password::*;
 pub use point::*;
+pub use redemption::*;
 pub use reward::*;
 pub use role::*;
+pub use search::*;
+pub use streak::*;
 pub use simple::*;
+pub use transfer::*;
+pub use trash::*;
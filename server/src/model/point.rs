@@ -1,11 +1,80 @@
 use serde::{ Deserialize, Serialize};
 
 /// Used during posts to create a new points entry
-#[derive(Debug, Deserialize, Serialize)]
+///
+/// - ***occurred_at*** is when the deed happened for back filling, defaults to now when not given
+/// - ***kind*** defaults to award or penalty by the sign of the value
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct CreatePoints {
   pub value: i64,
   pub user_id: i64,
   pub action_id: i64,
+  #[serde(default)]
+  pub occurred_at: Option<chrono::DateTime<chrono::Local>>,
+  #[serde(default)]
+  pub kind: Option<PointsKind>,
+}
+
+/// Kind of a points entry
+///
+/// - ***award*** adds points and ***penalty*** takes them away
+/// - ***adjustment*** may go either way e.g. corrections
+/// - the action of a penalty is the reason for it e.g. the chore that was missed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum PointsKind {
+  Award,
+  Penalty,
+  Adjustment,
+}
+
+impl PointsKind {
+
+  /// Get the kind name as stored in the database
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      PointsKind::Award => "award",
+      PointsKind::Penalty => "penalty",
+      PointsKind::Adjustment => "adjustment",
+    }
+  }
+
+  /// Get the kind for the given value when none is given, penalty when negative else award
+  pub fn from_value(value: i64) -> Self {
+    if value < 0 { PointsKind::Penalty } else { PointsKind::Award }
+  }
+
+  /// Is the given value allowed for this kind?
+  pub fn allows(&self, value: i64) -> bool {
+    match self {
+      PointsKind::Award => value >= 0,
+      PointsKind::Penalty => value <= 0,
+      PointsKind::Adjustment => true,
+    }
+  }
+}
+
+/// Review status of a points entry
+///
+/// - points created by admins are approved straight away, everyone else's wait as pending
+/// - only approved points count towards sums and balances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum PointsStatus {
+  Pending,
+  Approved,
+  Rejected,
+}
+
+/// Used during posts to approve or reject pending points in bulk
+///
+/// - ***note*** is shown to the owner of the points e.g. why they were rejected
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ReviewPoints {
+  pub ids: Vec<i64>,
+  pub note: Option<String>,
 }
 
 /// Used during updates to change a points entry
@@ -16,12 +85,30 @@ pub struct UpdatePoints {
 }
 
 /// Full points object from database
+///
+/// - ***value*** is the ***base_value*** times the ***multiplier*** of any bonus that applied
+/// - ***kind*** is what the entry is for e.g. an award or a penalty
+/// - ***awarded_by*** is the user that awarded the points, None when awarded anonymously
+/// - ***occurred_at*** is when the deed happened which date ranges and sums operate on
+/// - ***status*** is the review status, ***note*** ***reviewed_by*** and ***reviewed_at*** are set
+///   once reviewed
+/// - ***deleted_at*** is set while in the trash
 #[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Points {
   pub id: i64,
   pub value: i64,
+  pub base_value: i64,
+  pub multiplier: f64,
+  pub kind: PointsKind,
   pub user_id: i64,
   pub action_id: i64,
+  pub awarded_by: Option<i64>,
+  pub occurred_at: chrono::DateTime<chrono::Local>,
+  pub status: PointsStatus,
+  pub note: Option<String>,
+  pub reviewed_by: Option<i64>,
+  pub reviewed_at: Option<chrono::DateTime<chrono::Local>>,
   pub created_at: chrono::DateTime<chrono::Local>,
   pub updated_at: chrono::DateTime<chrono::Local>,
+  pub deleted_at: Option<chrono::DateTime<chrono::Local>>,
 }
\ No newline at end of file
@@ -0,0 +1,90 @@
+use serde::{ Deserialize, Serialize};
+
+/// Stages of a redemption request
+///
+/// - ***requested*** holds the value from the user's available balance until decided
+/// - ***approved*** creates the reward and ***fulfilled*** marks it as handed over
+/// - ***denied***, ***cancelled*** and ***expired*** release the held value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum RedemptionStatus {
+  Requested,
+  Approved,
+  Denied,
+  Cancelled,
+  Expired,
+  Fulfilled,
+}
+
+impl RedemptionStatus {
+
+  /// Get the status name as stored in the database
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      RedemptionStatus::Requested => "requested",
+      RedemptionStatus::Approved => "approved",
+      RedemptionStatus::Denied => "denied",
+      RedemptionStatus::Cancelled => "cancelled",
+      RedemptionStatus::Expired => "expired",
+      RedemptionStatus::Fulfilled => "fulfilled",
+    }
+  }
+
+  /// Get the status a redemption must be in to be moved to this status by a decision
+  ///
+  /// - None for ***requested*** and ***expired*** as decisions can't move a redemption to them
+  pub fn reached_from(&self) -> Option<RedemptionStatus> {
+    match self {
+      RedemptionStatus::Requested | RedemptionStatus::Expired => None,
+      RedemptionStatus::Fulfilled => Some(RedemptionStatus::Approved),
+      _ => Some(RedemptionStatus::Requested),
+    }
+  }
+}
+
+/// Used during posts to request a redemption
+///
+/// - ***user_id*** defaults to the caller
+/// - either ***item_id*** to request a catalog item at its cost or ***value*** is given
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CreateRedemption {
+  pub user_id: Option<i64>,
+  pub item_id: Option<i64>,
+  pub value: Option<i64>,
+  #[serde(default)]
+  pub reason: String,
+}
+
+/// Used during posts to approve or deny a redemption
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RedemptionDecision {
+  pub note: Option<String>,
+}
+
+/// Redemption query parameters
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RedemptionQuery {
+  pub user_id: Option<i64>,
+  pub status: Option<RedemptionStatus>,
+}
+
+/// Full redemption object from database
+///
+/// - ***reward_id*** is set once approved
+/// - ***decided_by*** and ***decided_at*** are set once approved, denied or cancelled
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Redemption {
+  pub id: i64,
+  pub user_id: i64,
+  pub item_id: Option<i64>,
+  pub value: i64,
+  pub reason: String,
+  pub status: RedemptionStatus,
+  pub note: Option<String>,
+  pub reward_id: Option<i64>,
+  pub decided_by: Option<i64>,
+  pub decided_at: Option<chrono::DateTime<chrono::Local>>,
+  pub created_at: chrono::DateTime<chrono::Local>,
+  pub updated_at: chrono::DateTime<chrono::Local>,
+}
@@ -14,11 +14,16 @@ pub struct UpdateReward {
 }
 
 /// Full reward object from database
+///
+/// - ***item_id*** is the catalog item redeemed if any
+/// - ***deleted_at*** is set while in the trash
 #[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
 pub struct Reward {
   pub id: i64,
   pub value: i64,
   pub user_id: i64,
+  pub item_id: Option<i64>,
   pub created_at: chrono::DateTime<chrono::Local>,
   pub updated_at: chrono::DateTime<chrono::Local>,
+  pub deleted_at: Option<chrono::DateTime<chrono::Local>>,
 }
\ No newline at end of file
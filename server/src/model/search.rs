@@ -0,0 +1,34 @@
+use serde::{ Deserialize, Serialize};
+
+/// Kinds of entities that can be found by searching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum SearchKind {
+  Action,
+  Category,
+  User,
+}
+
+/// Search query parameters
+///
+/// - ***q*** is the text to search for, each word is prefix matched
+/// - ***limit*** is the maximum number of hits to return
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SearchQuery {
+  pub q: String,
+  pub limit: Option<i64>,
+}
+
+/// Single ranked search hit
+///
+/// - ***id*** is the id of the matching entity of the given kind
+/// - ***text*** is the matching text e.g. the action description
+/// - ***rank*** orders the hits with lower values being better matches
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct SearchHit {
+  pub kind: SearchKind,
+  pub id: i64,
+  pub text: String,
+  pub rank: f64,
+}
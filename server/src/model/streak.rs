@@ -0,0 +1,29 @@
+use serde::{ Deserialize, Serialize};
+
+/// What a streak is counted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum StreakKind {
+  Action,
+  Category,
+}
+
+/// Full streak object from database
+///
+/// - ***ref_id*** is the id of the action or category depending on the ***kind***
+/// - ***current*** is the run ending on ***last_on***, zero once a day or occurrence was missed
+/// - ***scheduled*** streaks count the occurrences of the user's chores for the action rather
+///   than days
+#[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Streak {
+  pub user_id: i64,
+  pub kind: StreakKind,
+  pub ref_id: i64,
+  pub current: i64,
+  pub longest: i64,
+  pub last_on: chrono::NaiveDate,
+  #[sqlx(skip)]
+  #[serde(default)]
+  pub scheduled: bool,
+}
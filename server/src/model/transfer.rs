@@ -0,0 +1,107 @@
+use serde::{ Deserialize, Serialize};
+
+/// Data formats supported for export and import
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferFormat {
+  #[default]
+  Csv,
+  Json,
+}
+
+impl TransferFormat {
+
+  /// Get the content type for the format
+  pub fn content_type(&self) -> &'static str {
+    match self {
+      TransferFormat::Csv => "text/csv",
+      TransferFormat::Json => "application/json",
+    }
+  }
+
+  /// Get the file extension for the format
+  pub fn extension(&self) -> &'static str {
+    match self {
+      TransferFormat::Csv => "csv",
+      TransferFormat::Json => "json",
+    }
+  }
+}
+
+/// Export query parameters
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ExportQuery {
+  #[serde(default)]
+  pub format: TransferFormat,
+}
+
+/// Import query parameters
+///
+/// - ***dry_run*** validates and resolves the rows without committing them
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ImportQuery {
+  #[serde(default)]
+  pub dry_run: bool,
+}
+
+/// Points row as exported and imported
+///
+/// - ***id*** is ignored on import and set to the new id once committed
+/// - users are resolved by ***user_id*** or ***username*** which may be a username or email
+/// - actions are resolved by ***action_id*** or ***action*** description, defaulting to the
+///   Unspecified action when neither is given
+/// - ***kind*** defaults to award or penalty by the sign of the value
+/// - ***status*** is kept so that rejected and pending points aren't imported as approved,
+///   defaulting to approved when not given
+/// - ***occurred_at*** defaults to ***created_at*** and ***created_at*** to now when not given
+#[derive(Debug, Default, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct PointsRecord {
+  pub id: Option<i64>,
+  pub value: i64,
+  #[serde(default)]
+  pub kind: Option<super::PointsKind>,
+  #[serde(default)]
+  pub status: Option<super::PointsStatus>,
+  pub user_id: Option<i64>,
+  pub username: Option<String>,
+  pub action_id: Option<i64>,
+  pub action: Option<String>,
+  pub occurred_at: Option<chrono::DateTime<chrono::Local>>,
+  pub created_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+/// Reward row as exported and imported
+///
+/// - ***id*** is ignored on import and set to the new id once committed
+/// - users are resolved by ***user_id*** or ***username*** which may be a username or email
+/// - ***created_at*** defaults to now when not given
+#[derive(Debug, Default, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct RewardRecord {
+  pub id: Option<i64>,
+  pub value: i64,
+  pub user_id: Option<i64>,
+  pub username: Option<String>,
+  pub created_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+/// Problem with a single import row
+///
+/// - ***row*** is the 1 based position of the row in the data excluding any CSV header
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ImportError {
+  pub row: usize,
+  pub msg: String,
+}
+
+/// Outcome of an import
+///
+/// - ***rows*** are the resolved rows for previewing or the committed rows with their new ids
+/// - ***imported*** is zero for dry runs and imports with errors as nothing is committed
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportReport<T> {
+  pub dry_run: bool,
+  pub total: usize,
+  pub imported: usize,
+  pub rows: Vec<T>,
+  pub errors: Vec<ImportError>,
+}
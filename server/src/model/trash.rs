@@ -0,0 +1,12 @@
+use serde::{ Deserialize, Serialize};
+
+/// Soft deleted entries waiting to be restored or purged
+///
+/// - points and rewards of a deleted user are in the trash along with the user
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Trash {
+  pub users: Vec<super::User>,
+  pub actions: Vec<super::Action>,
+  pub points: Vec<super::Points>,
+  pub rewards: Vec<super::Reward>,
+}
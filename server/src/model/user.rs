@@ -15,6 +15,8 @@ pub struct UpdateUser {
 }
 
 /// Full user object from database
+///
+/// - ***deleted_at*** is set while in the trash
 #[derive(Debug, Deserialize, Serialize, sqlx::FromRow)]
 pub struct User {
   pub id: i64,
@@ -22,4 +24,5 @@ pub struct User {
   pub email: String,
   pub created_at: chrono::DateTime<chrono::Local>,
   pub updated_at: chrono::DateTime<chrono::Local>,
+  pub deleted_at: Option<chrono::DateTime<chrono::Local>>,
 }
\ No newline at end of file
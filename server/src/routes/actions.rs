@@ -1,21 +1,25 @@
 use std::sync::Arc;
-use axum::{extract::{Path, Query, State}, http::StatusCode, response::IntoResponse};
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::IntoResponse, Extension};
 use crate::{db, state, model, routes::Json, errors::Error};
 
+// Reviewing actions is for admins
+const ADMIN_ONLY: &str = "review actions";
+
 /// Create a new Action
 /// 
 /// - POST handler for `/actions`
+/// - The proposer is recorded when a Bearer token is sent
+/// - Only admins can create approved actions, everyone else's proposals wait on a review
 pub async fn create(State(state): State<Arc<state::State>>,
-  Json(action): Json<model::CreateAction>) -> Result<impl IntoResponse, Error>
+  claims: Option<Extension<model::JwtClaims>>, Json(mut action): Json<model::CreateAction>)
+  -> Result<impl IntoResponse, Error>
 {
-  // Only allow actions to be created in the approved=false state
-  if action.approved.unwrap_or(false) {
-    let msg = format!("Actions can only be created in the approved=false state");
-    log::error!("{msg}");
-    return Err(Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  if !matches!(&claims, Some(Extension(x)) if x.has_role("admin")) {
+    action.approved = Some(false);
   }
 
-  let id = db::action::insert(state.db(), &action).await?;
+  let proposed_by = claims.map(|Extension(x)| x.sub);
+  let id = db::action::insert_with(state.db(), &action, proposed_by).await?;
   let action = db::action::fetch_by_id(state.db(), id).await?;
 
   Ok((StatusCode::CREATED, Json(serde_json::json!(action))))
@@ -42,26 +46,107 @@ pub async fn get_by_id(State(state): State<Arc<state::State>>,
 /// Update specific action by id
 /// 
 /// - PUT handler for `/actions/{id}`
-pub async fn update_by_id(State(state): State<Arc<state::State>>, Path(id): Path<i64>,
+/// - error on caller changing ***approved*** without being an admin
+pub async fn update_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
   Json(action): Json<model::UpdateAction>) -> Result<impl IntoResponse, Error>
 {
+  if action.approved.is_some() {
+    super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  }
+  let was_approved = db::action::fetch_by_id(state.db(), id).await?.approved;
   db::action::update_by_id(state.db(), id, &action).await?;
+
+  // Let clients know a newly approved action is available
+  let action = db::action::fetch_by_id(state.db(), id).await?;
+  if action.approved && !was_approved {
+    state.publish(model::Event::new(model::EventKind::ActionApproved, id).with_data(&action));
+  }
+
   Ok(Json(serde_json::json!({})))
 }
 
+/// Get the actions waiting on a review
+/// 
+/// - GET handler for `/actions/pending`
+/// - Oldest proposals first
+pub async fn get_pending(State(state): State<Arc<state::State>>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::action::fetch_pending(state.db()).await?))
+}
+
+/// Approve specific proposed action by id
+/// 
+/// - POST handler for `/actions/{id}/approve`
+/// - error on caller not being an admin
+/// - error on the action not pending a review
+/// 
+/// #### Parameters
+/// - ***review*** - supports ***value***, ***category_id*** and ***note***
+pub async fn approve(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(review): Json<model::ReviewAction>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::action::review(state.db(), id, true, &review, Some(claims.sub)).await?;
+  let action = db::action::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::ActionApproved, id).with_data(&action));
+  Ok(Json(action))
+}
+
+/// Reject specific proposed action by id
+/// 
+/// - POST handler for `/actions/{id}/reject`
+/// - The action is kept so the proposer can see the note
+/// - error on caller not being an admin
+/// - error on the action not pending a review
+/// 
+/// #### Parameters
+/// - ***review*** - supports ***note***
+pub async fn reject(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(review): Json<model::ReviewAction>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::action::review(state.db(), id, false, &review, Some(claims.sub)).await?;
+  let action = db::action::fetch_by_id(state.db(), id).await?;
+  let mut event = model::Event::new(model::EventKind::ActionRejected, id).with_data(&action);
+  if let Some(user_id) = action.proposed_by {
+    event = event.with_user_id(user_id);
+  }
+  state.publish(event);
+  Ok(Json(action))
+}
+
 /// Delete specific action by id
 /// 
 /// - DELETE handler for `/actions/{id}`
+/// - Moves the action to the trash, points for the action are unchanged
 pub async fn delete_by_id(State(state): State<Arc<state::State>>,
   Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
 {
   Ok(Json(db::action::delete_by_id(state.db(), id).await?))
 }
 
+/// Restore specific action from the trash
+/// 
+/// - POST handler for `/actions/{id}/restore`
+/// - error on caller not being an admin
+/// - error on not found in the trash
+pub async fn restore_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, super::trash::ADMIN_ONLY)?;
+  db::action::restore_by_id(state.db(), id).await?;
+  Ok(Json(db::action::fetch_by_id(state.db(), id).await?))
+}
+
 #[cfg(test)]
 mod tests
 {
-  use super::{*, super::tests::login_as_admin};
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
   use axum::{
     body::Body,
     http::{header, Response, Request, Method, StatusCode}
@@ -70,6 +155,92 @@ mod tests
   use tower::ServiceExt;
   use crate::{errors, routes, state};
 
+  // Helper to post the given body with the given token
+  fn post(uri: &str, token: &str, body: serde_json::Value) -> Request<Body>
+  {
+    Request::builder().method(Method::POST)
+      .uri(uri)
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {token}"))
+      .body(Body::from(serde_json::to_vec(&body).unwrap())).unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_propose_and_approve()
+  {
+    let state = state::test().await;
+    let (user_id, user_token) = login_as_user(state.clone(), "user1", None).await;
+    let (admin, admin_token) = login_as_admin(state.clone()).await;
+
+    // Proposals from non-admins can't approve themselves
+    let body = serde_json::json!(model::CreateAction::new().with_desc("dishes").with_value(10)
+      .with_approved(true));
+    let res = routes::init(state.clone()).oneshot(post("/api/actions", &user_token, body))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let action: model::Action = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((action.approved, action.proposed_by), (false, Some(user_id)));
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/actions/pending")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let actions: Vec<model::Action> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(actions.iter().map(|x| x.id).collect::<Vec<_>>(), vec![action.id]);
+
+    // Only admins approve, whether through the queue or an update
+    let uri = format!("/api/actions/{}/approve", action.id);
+    let res = routes::init(state.clone()).oneshot(post(&uri, &user_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let req = Request::builder().method(Method::PUT)
+      .uri(format!("/api/actions/{}", action.id))
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {user_token}"))
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::UpdateAction::new().with_approved(true)
+      )).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let body = serde_json::json!({"value": 5, "note": "half for now"});
+    let res = routes::init(state.clone()).oneshot(post(&uri, &admin_token, body)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let action: model::Action = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((action.approved, action.value, action.reviewed_by), (true, 5, Some(admin.id)));
+
+    let res = routes::init(state.clone()).oneshot(post(&uri, &admin_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  }
+
+  #[tokio::test]
+  async fn test_reject_notifies_proposer()
+  {
+    let state = state::test().await;
+    let (user_id, _) = login_as_user(state.clone(), "user1", None).await;
+    let (_, admin_token) = login_as_admin(state.clone()).await;
+    let id = db::action::insert_with(state.db(), &model::CreateAction::new().with_desc("dishes"),
+      Some(user_id)).await.unwrap();
+    let mut events = state.subscribe();
+
+    let uri = format!("/api/actions/{id}/reject");
+    let body = serde_json::json!({"note": "already a chore"});
+    let res = routes::init(state.clone()).oneshot(post(&uri, &admin_token, body)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let action: model::Action = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((action.approved, action.note.as_deref()), (false, Some("already a chore")));
+
+    let event = events.try_recv().unwrap();
+    assert_eq!((event.kind, event.id, event.user_id),
+      (model::EventKind::ActionRejected, id, Some(user_id)));
+  }
+
   #[tokio::test]
   async fn test_delete_by_id()
   {
@@ -125,6 +296,35 @@ mod tests
     assert_eq!(action.desc, action2);
   }
 
+  #[tokio::test]
+  async fn test_update_by_id_publishes_action_approved()
+  {
+    let state = state::test().await;
+    let id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    let mut events = state.subscribe();
+
+    // Approving the action publishes the event but only on the transition
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    for _ in 0..2 {
+      let req = Request::builder().method(Method::PUT)
+        .uri(format!("/api/actions/{}", id))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
+        .body(Body::from(serde_json::to_vec(&serde_json::json!(
+          model::UpdateAction::new().with_approved(true)
+        )).unwrap())).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.kind, model::EventKind::ActionApproved);
+    assert_eq!(event.id, id);
+    assert_eq!(event.user_id, None);
+    assert!(events.try_recv().is_err());
+  }
+
   #[tokio::test]
   async fn test_get_all_not_approved() {
     let state = state::test().await;
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Query, Request, State}, middleware::Next,
+  response::{IntoResponse, Response}, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+/// Middleware to attribute changes made while handling the request in the audit log
+///
+/// - The actor is the caller identified by their Bearer token
+/// - Requests without a valid token are recorded as anonymous
+/// - The request id is the one given to the request by the request id middleware
+///
+/// #### Parameters:
+/// - ***req*** is the incoming request
+/// - ***next*** is the next middleware or handler to call
+pub async fn context(req: Request, next: Next) -> Response
+{
+  let context = model::AuditContext {
+    actor_id: req.extensions().get::<model::JwtClaims>().map(|x| x.sub),
+    request_id: req.headers().get(super::REQUEST_ID).and_then(|x| x.to_str().ok())
+      .map(|x| x.to_string()),
+  };
+  db::audit::scope(context, next.run(req)).await
+}
+
+/// Get a page of the audit log
+///
+/// - GET handler for `/audit?actor_id={id}&entity={name}&entity_id={id}&start_date={date}&end_date={date}&offset={n}&limit={n}`
+/// - Returns the newest entries first along with the total matching entries
+/// - error on caller not being an admin
+///
+/// #### Parameters
+/// - ***query*** - supports ***actor_id***, ***entity***, ***entity_id***, ***start_date***,
+///   ***end_date***, ***offset*** and ***limit***
+pub async fn get(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Query(query): Query<model::AuditQuery>)
+  -> Result<impl IntoResponse, Error>
+{
+  // The audit log exposes every change so it is limited to admins
+  super::auth::require_admin(&claims, "view the audit log")?;
+  Ok(Json(db::audit::fetch_by_query(state.db(), &query).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::login_as_admin};
+  use axum::{body::Body, http::{header, Method, StatusCode}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  #[tokio::test]
+  async fn test_changes_are_audited()
+  {
+    let state = state::test().await;
+    let (admin, access_token) = login_as_admin(state.clone()).await;
+
+    // Create points anonymously then delete them as the admin through the same public routes
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::CreatePoints { value: 10, user_id: 1, action_id: 1, occurred_at: None, kind: None }
+      )).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let points: model::Points = serde_json::from_slice(&bytes).unwrap();
+
+    let req = Request::builder().method(Method::DELETE)
+      .uri(format!("/api/points/{}", points.id))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .header(routes::REQUEST_ID, "req-1")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(routes::REQUEST_ID).unwrap(), "req-1");
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/audit?entity=point&entity_id={}", points.id))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let page: model::AuditPage = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(page.total, 2);
+
+    let (delete, create) = (&page.entries[0], &page.entries[1]);
+    assert_eq!(delete.action, model::AuditAction::Delete);
+    assert_eq!(delete.actor_id, Some(admin.id));
+    assert_eq!(delete.request_id.as_deref(), Some("req-1"));
+    assert_eq!(create.action, model::AuditAction::Create);
+    assert_eq!(create.actor_id, None);
+    assert!(create.request_id.is_some());
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/audit?actor_id={}&limit=1", admin.id))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let page: model::AuditPage = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((page.total, page.entries.len()), (1, 1));
+  }
+
+  #[tokio::test]
+  async fn test_get_failure_not_logged_in()
+  {
+    let state = state::test().await;
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/audit")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+  }
+}
@@ -28,11 +28,29 @@ pub async fn login(State(state): State<Arc<state::State>>,
 
   log::info!("User [{}, {}] logged in...", user.username, user.email);
 
-  Ok((StatusCode::OK, Json(serde_json::json!(
+  // Tokens must never be cached or stored for replay
+  Ok((StatusCode::OK, [(http::header::CACHE_CONTROL, "no-store")], Json(serde_json::json!(
     model::LoginResponse { access_token: token, token_type: "Bearer".to_string() }
   ))))
 }
 
+/// Ensure the caller is an admin
+///
+/// - error on caller not being an admin naming what they aren't allowed to do
+///
+/// #### Parameters
+/// - ***claims*** - claims of the caller
+/// - ***action*** - what only admins may do e.g. `manage badges`
+pub(crate) fn require_admin(claims: &model::JwtClaims, action: &str) -> Result<(), Error>
+{
+  if claims.has_role("admin") {
+    return Ok(());
+  }
+  let msg = format!("User '{}' is not allowed to {action}", claims.username);
+  log::warn!("{msg}");
+  Err(Error::http(StatusCode::FORBIDDEN, &msg))
+}
+
 /// Middleware to extract and validate a Bearer token from the request
 /// 
 /// - Requires the authorization header "Authorization: Bearer <token>"
@@ -44,11 +62,47 @@ pub async fn login(State(state): State<Arc<state::State>>,
 /// - ***next*** is the next middleware or handler to call
 pub async fn authorization(State(state): State<Arc<state::State>>,
   mut req: Request, next: Next) -> Result<impl IntoResponse, Error>
+{
+  let claims = bearer_claims(&state, req.headers()).await?;
+
+  // Insert the decoded claims into the request extensions
+  req.extensions_mut().insert(claims);
+  Ok(next.run(req).await)
+}
+
+/// Middleware to pass along the JWT claims when a valid Bearer token is sent
+/// 
+/// - Used on public routes so that a logged in caller is still known e.g. to award points
+/// - Requests without a valid token are passed along without claims rather than rejected
+/// 
+/// #### Parameters:
+/// - ***req*** is the incoming request
+/// - ***next*** is the next middleware or handler to call
+pub async fn identify(State(state): State<Arc<state::State>>, mut req: Request, next: Next)
+  -> impl IntoResponse
+{
+  if req.headers().contains_key(http::header::AUTHORIZATION) {
+    if let Ok(claims) = bearer_claims(&state, req.headers()).await {
+      req.extensions_mut().insert(claims);
+    }
+  }
+  next.run(req).await
+}
+
+/// Decode and validate the claims of the Bearer token sent with the request
+///
+/// - error on missing, invalid or expired token
+///
+/// #### Parameters:
+/// - ***state*** is the application state holding the API keys
+/// - ***headers*** are the incoming request headers
+pub(crate) async fn bearer_claims(state: &state::State, headers: &http::HeaderMap)
+  -> Result<model::JwtClaims, Error>
 {
   let forbidden = || Error::http(StatusCode::FORBIDDEN, "Access denied: user not logged in");
 
   // Get the authorization header from the request
-  let auth_header = match req.headers_mut().get(http::header::AUTHORIZATION) {
+  let auth_header = match headers.get(http::header::AUTHORIZATION) {
     Some(header) => header.to_str().map_err(|_| forbidden())?,
     None => return Err(forbidden()),
   };
@@ -65,10 +119,7 @@ pub async fn authorization(State(state): State<Arc<state::State>>,
   if claims.exp < chrono::Utc::now().timestamp() as usize {
     return Err(Error::http(StatusCode::FORBIDDEN, "Bearer token has expired"));
   }
-
-  // Insert the decoded claims into the request extensions
-  req.extensions_mut().insert(claims);
-  Ok(next.run(req).await)
+  Ok(claims)
 }
 
 #[cfg(test)]
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Query, State}, http::header, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// Backups expose and replace everything so they are limited to admins
+const ADMIN_ONLY: &str = "backup or restore";
+
+/// Download a full backup of the database
+///
+/// - GET handler for `/backup?passwords={bool}`
+/// - Returns a JSON attachment stamped with the database migration version
+/// - Password hashes are only included when ***passwords*** is set
+/// - error on caller not being an admin
+///
+/// #### Parameters
+/// - ***query*** - supports ***passwords***
+pub async fn get(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Query(query): Query<model::BackupQuery>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let backup = db::backup::fetch(state.db(), state::schema_version(), query.passwords).await?;
+  let disposition = format!("attachment; filename=\"oneup-{}.json\"",
+    backup.created_at.format("%Y%m%d%H%M%S"));
+  Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(backup)))
+}
+
+/// Get the status of the scheduled database snapshots
+///
+/// - GET handler for `/backup/status`
+/// - Includes the path of the last snapshot and the error of the last failed attempt
+/// - error on caller not being an admin
+pub async fn get_status(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(state.backup_status()))
+}
+
+/// Restore a full backup replacing all existing data
+///
+/// - POST handler for `/restore`
+/// - Body is a backup as produced by `/backup`
+/// - Existing passwords are kept by username when the backup doesn't include any
+/// - error on caller not being an admin
+/// - error on backup from a different database version or with broken references
+///
+/// #### Parameters
+/// - ***backup*** - the backup to restore
+pub async fn restore(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(backup): Json<model::Backup>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::backup::restore(state.db(), state::schema_version(), &backup).await?;
+  Ok(Json(model::Simple::new(&format!("Restored backup taken at {}", backup.created_at))))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::login_as_admin};
+  use axum::{body::Body, http::{Method, Request, StatusCode}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::{routes, security::auth};
+
+  #[tokio::test]
+  async fn test_backup_and_restore()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 10, user_id, 1).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/backup?passwords=true")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get(header::CONTENT_DISPOSITION).unwrap().to_str().unwrap()
+      .starts_with("attachment; filename=\"oneup-"));
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let backup: model::Backup = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(backup.version, state::schema_version());
+    assert_eq!(backup.users.len(), 2);
+    assert_eq!(backup.passwords.as_ref().unwrap().len(), 1);
+
+    // Restore into a fresh server and login with the restored admin
+    let target = state::test().await;
+    let (_, access_token) = login_as_admin(target.clone()).await;
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/restore")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(bytes)).unwrap();
+    let res = routes::init(target.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(db::point::fetch_all(target.db()).await.unwrap().len(), 1);
+    assert_eq!(db::user::fetch_by_id(target.db(), user_id).await.unwrap().username, "user1");
+    login_as_admin(target.clone()).await;
+  }
+
+  #[tokio::test]
+  async fn test_restore_failure_incompatible_version()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    let mut backup = db::backup::fetch(state.db(), state::schema_version(), false).await.unwrap();
+    backup.version -= 1;
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/restore")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(serde_json::to_vec(&backup).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  }
+
+  #[tokio::test]
+  async fn test_backup_failure_not_admin()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let creds = auth::hash_password("pass1").unwrap();
+    db::password::insert(state.db(), user_id, &creds.salt, &creds.hash).await.unwrap();
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/login")
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::LoginRequest { handle: "user1".to_string(), password: "pass1".to_string() }
+      )).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let login: model::LoginResponse = serde_json::from_slice(&bytes).unwrap();
+
+    for uri in ["/api/backup", "/api/backup/status"] {
+      let req = Request::builder().method(Method::GET)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", login.access_token))
+        .body(Body::empty()).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+  }
+
+  #[tokio::test]
+  async fn test_get_status()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    state.update_backup_status(|status| status.last_error = Some("disk full".into()));
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/backup/status")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let status: model::BackupStatus = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(status.last_error.as_deref(), Some("disk full"));
+  }
+}
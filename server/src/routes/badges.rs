@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Path, State}, http::StatusCode, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// Badges are defined by admins
+const ADMIN_ONLY: &str = "manage badges";
+
+/// Create a new badge
+///
+/// - POST handler for `/badges`
+/// - Users already meeting the rule are awarded the badge right away
+/// - error on caller not being an admin
+///
+/// #### Parameters
+/// - ***badge*** - the ***name***, ***icon*** and ***rule***
+pub async fn create(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(badge): Json<model::BadgePartial>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let id = db::badge::insert(state.db(), &badge).await?;
+  let badge = db::badge::fetch_by_id(state.db(), id).await?;
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(badge))))
+}
+
+/// Get all badges
+///
+/// - GET handler for `/badges`
+pub async fn get(State(state): State<Arc<state::State>>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::badge::fetch_all(state.db()).await?))
+}
+
+/// Get specific badge by id
+///
+/// - GET handler for `/badges/{id}`
+pub async fn get_by_id(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::badge::fetch_by_id(state.db(), id).await?))
+}
+
+/// Replace specific badge by id
+///
+/// - PUT handler for `/badges/{id}`
+/// - Badges already earned are kept
+/// - error on caller not being an admin
+pub async fn update_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(badge): Json<model::BadgePartial>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::badge::update_by_id(state.db(), id, &badge).await?;
+  Ok(Json(db::badge::fetch_by_id(state.db(), id).await?))
+}
+
+/// Delete specific badge by id
+///
+/// - DELETE handler for `/badges/{id}`
+/// - error on caller not being an admin
+pub async fn delete_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(db::badge::delete_by_id(state.db(), id).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::login_as_admin};
+  use axum::{body::Body, http::{header, Method, Request}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  #[tokio::test]
+  async fn test_create_and_get_user_badges()
+  {
+    let state = state::test().await;
+    let (admin, token) = login_as_admin(state.clone()).await;
+    db::point::insert(state.db(), 5, admin.id, 1).await.unwrap();
+
+    let body = serde_json::json!({"name": "first points", "icon": "star",
+      "rule": {"metric": "points", "threshold": 1}});
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/badges")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {token}"))
+      .body(Body::from(serde_json::to_vec(&body).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let badge: model::Badge = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(badge.rule.metric, model::BadgeMetric::Points);
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{}/badges", admin.id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let badges: Vec<model::EarnedBadge> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(badges.iter().map(|x| (x.badge_id, x.icon.as_str())).collect::<Vec<_>>(),
+      vec![(badge.id, "star")]);
+  }
+}
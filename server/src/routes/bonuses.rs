@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Path, Query, State}, http::StatusCode, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// Bonuses are run by admins
+const ADMIN_ONLY: &str = "manage bonuses";
+
+/// Create a new bonus
+///
+/// - POST handler for `/bonuses`
+/// - Applies to points created from then on
+/// - error on caller not being an admin
+///
+/// #### Parameters
+/// - ***bonus*** - the ***name***, ***multiplier***, ***starts_at***, ***ends_at*** and
+///   optionally ***category_id***, ***action_id*** and ***user_id***
+pub async fn create(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(bonus): Json<model::BonusPartial>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let id = db::bonus::insert(state.db(), &bonus).await?;
+  let bonus = db::bonus::fetch_by_id(state.db(), id).await?;
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(bonus))))
+}
+
+/// Get all bonuses
+///
+/// - GET handler for `/bonuses`
+pub async fn get(State(state): State<Arc<state::State>>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::bonus::fetch_all(state.db()).await?))
+}
+
+/// Get specific bonus by id
+///
+/// - GET handler for `/bonuses/{id}`
+pub async fn get_by_id(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::bonus::fetch_by_id(state.db(), id).await?))
+}
+
+/// Preview the value points would be created with
+///
+/// - GET handler for `/bonuses/preview?value={n}&user_id={id}&action_id={id}`
+/// - ***occurred_at*** defaults to now
+/// - error on caller not being an admin
+/// - error on user or action not found
+pub async fn preview(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Query(points): Query<model::CreatePoints>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(db::bonus::preview(state.db(), &points).await?))
+}
+
+/// Replace specific bonus by id
+///
+/// - PUT handler for `/bonuses/{id}`
+/// - Points already created keep the multiplier they were given
+/// - error on caller not being an admin
+pub async fn update_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(bonus): Json<model::BonusPartial>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::bonus::update_by_id(state.db(), id, &bonus).await?;
+  Ok(Json(db::bonus::fetch_by_id(state.db(), id).await?))
+}
+
+/// Delete specific bonus by id
+///
+/// - DELETE handler for `/bonuses/{id}`
+/// - error on caller not being an admin
+pub async fn delete_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(db::bonus::delete_by_id(state.db(), id).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::login_as_admin};
+  use axum::{body::Body, http::{header, Method, Request}};
+  use chrono::{Duration, Local};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  #[tokio::test]
+  async fn test_create_and_preview()
+  {
+    let state = state::test().await;
+    let (admin, token) = login_as_admin(state.clone()).await;
+
+    let now = Local::now();
+    let body = serde_json::json!({"name": "Double points weekend", "multiplier": 2.0,
+      "starts_at": now - Duration::hours(1), "ends_at": now + Duration::days(2)});
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/bonuses")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {token}"))
+      .body(Body::from(serde_json::to_vec(&body).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let bonus: model::Bonus = serde_json::from_slice(&bytes).unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/bonuses/preview?value=3&user_id={}&action_id=1", admin.id))
+      .header(header::AUTHORIZATION, format!("Bearer {token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let preview: model::BonusPreview = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((preview.base_value, preview.value, preview.bonus_id), (3, 6, Some(bonus.id)));
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/bonuses")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let bonuses: Vec<model::Bonus> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(bonuses.len(), 1);
+  }
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Path, State}, http::StatusCode, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// Chores are managed by admins
+const ADMIN_ONLY: &str = "manage chores";
+
+/// Create a new chore
+///
+/// - POST handler for `/chores`
+/// - error on caller not being an admin
+///
+/// #### Parameters
+/// - ***chore*** - the action, schedule and assigned ***user_ids***
+pub async fn create(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(chore): Json<model::ChorePartial>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let id = db::chore::insert(state.db(), &chore).await?;
+  let chore = db::chore::fetch_by_id(state.db(), id).await?;
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(chore))))
+}
+
+/// Get all chores
+///
+/// - GET handler for `/chores`
+pub async fn get(State(state): State<Arc<state::State>>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::chore::fetch_all(state.db()).await?))
+}
+
+/// Get specific chore by id
+///
+/// - GET handler for `/chores/{id}`
+pub async fn get_by_id(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::chore::fetch_by_id(state.db(), id).await?))
+}
+
+/// Replace specific chore by id
+///
+/// - PUT handler for `/chores/{id}`
+/// - error on caller not being an admin
+pub async fn update_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(chore): Json<model::ChorePartial>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::chore::update_by_id(state.db(), id, &chore).await?;
+  Ok(Json(db::chore::fetch_by_id(state.db(), id).await?))
+}
+
+/// Delete specific chore by id
+///
+/// - DELETE handler for `/chores/{id}`
+/// - error on caller not being an admin
+pub async fn delete_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(db::chore::delete_by_id(state.db(), id).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
+  use axum::{body::Body, http::{header, Method, Request}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  #[tokio::test]
+  async fn test_create_and_get_due()
+  {
+    let state = state::test().await;
+    let (user_id, user_token) = login_as_user(state.clone(), "user1", None).await;
+    let (_, admin_token) = login_as_admin(state.clone()).await;
+    let chore = model::ChorePartial::new(1, model::ChoreSchedule::Daily)
+      .with_user_ids(vec![user_id]);
+
+    for (token, status) in [(user_token, StatusCode::FORBIDDEN), (admin_token, StatusCode::CREATED)] {
+      let req = Request::builder().method(Method::POST)
+        .uri("/api/chores")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {token}"))
+        .body(Body::from(serde_json::to_vec(&chore).unwrap())).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), status);
+    }
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{user_id}/due"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let due: Vec<model::ChoreDue> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!((due[0].action_id, due[0].due_on), (1, chrono::Local::now().date_naive()));
+
+    // Doing the chore takes it off the list
+    db::point::insert(state.db(), 1, user_id, 1).await.unwrap();
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{user_id}/due"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let due: Vec<model::ChoreDue> = serde_json::from_slice(&bytes).unwrap();
+    assert!(due.is_empty());
+  }
+}
@@ -0,0 +1,162 @@
+use axum::{
+  body::Body, extract::Request, http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+  response::{IntoResponse, Response},
+};
+
+use super::web::{Files, IndexFallback};
+
+/// Flutter web build embedded into the binary at build time
+///
+/// - Staged at `web/` by `just flutter` before building with the `embed-web` feature
+/// - Allowed to be missing so that the feature builds without a web build staged
+#[derive(rust_embed::RustEmbed)]
+#[folder = "web/"]
+#[allow_missing = true]
+struct Assets;
+
+// Web app entry point also used as the fallback for client side routes
+const INDEX_HTML: &str = "index.html";
+
+// Precompressed variants to look for in order of preference
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// Serve the embedded web app
+///
+/// - Serves embedded files from memory with MIME types and ETags
+/// - Prefers embedded precompressed `.br` and `.gz` variants when accepted by the client
+/// - Falls back on the ***files*** directory for anything that wasn't embedded
+/// - Falls back to the embedded `index.html` so that deep links work on reload
+///
+/// #### Parameters
+/// - ***req*** - the incoming request
+/// - ***files*** - web app directory service if configured
+pub(crate) async fn serve(req: Request, files: Option<Files>) -> Response
+{
+  if req.method() != Method::GET && req.method() != Method::HEAD {
+    return StatusCode::METHOD_NOT_ALLOWED.into_response();
+  }
+
+  let path = req.uri().path().trim_start_matches('/');
+  let path = if path.is_empty() || path.ends_with('/') {
+    format!("{path}{INDEX_HTML}")
+  } else {
+    path.to_string()
+  };
+  if let Some(res) = respond(req.headers(), &path) {
+    return res;
+  }
+
+  // Serve from the web app directory which handles its own client side routing
+  if let Some(mut files) = files {
+    return match files.try_call(req).await {
+      Ok(res) => res.map(Body::new),
+      Err(e) => {
+        log::error!("Error serving web app file '{path}': {e}");
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+      }
+    };
+  }
+
+  match respond(req.headers(), INDEX_HTML) {
+    Some(mut res) => {
+      res.extensions_mut().insert(IndexFallback);
+      res
+    },
+    None => StatusCode::NOT_FOUND.into_response(),
+  }
+}
+
+// Build the response for the given embedded file if it exists
+fn respond(headers: &HeaderMap, path: &str) -> Option<Response>
+{
+  let file = Assets::get(path)?;
+  let mime = file.metadata.mimetype().to_string();
+
+  // Swap in a precompressed variant if the client accepts it
+  let (file, encoding) = ENCODINGS.iter()
+    .filter(|(encoding, _)| accepts_encoding(headers, encoding))
+    .find_map(|(encoding, ext)| Assets::get(&format!("{path}.{ext}")).map(|x| (x, Some(*encoding))))
+    .unwrap_or((file, None));
+
+  let etag = etag(&file.metadata.sha256_hash());
+  let mut res = if headers.get(header::IF_NONE_MATCH).is_some_and(|x| x == etag.as_str()) {
+    StatusCode::NOT_MODIFIED.into_response()
+  } else {
+    let mut res = Body::from(file.data).into_response();
+    if let Ok(value) = HeaderValue::from_str(&mime) {
+      res.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    if let Some(encoding) = encoding {
+      res.headers_mut().insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+    res
+  };
+  if let Ok(value) = HeaderValue::from_str(&etag) {
+    res.headers_mut().insert(header::ETAG, value);
+  }
+  res.headers_mut().insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+  Some(res)
+}
+
+// Check if the client listed the given encoding in Accept-Encoding without disabling it
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool
+{
+  headers.get_all(header::ACCEPT_ENCODING).iter()
+    .filter_map(|x| x.to_str().ok())
+    .flat_map(|x| x.split(','))
+    .any(|x| {
+      let mut parts = x.split(';').map(str::trim);
+      parts.next() == Some(encoding) && !parts.any(|x| x.replace(' ', "") == "q=0")
+    })
+}
+
+// Strong ETag for the given content hash
+fn etag(hash: &[u8]) -> String
+{
+  let hex = hash.iter().map(|x| format!("{x:02x}")).collect::<String>();
+  format!("\"{hex}\"")
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use http_body_util::BodyExt;
+
+  #[test]
+  fn test_accepts_encoding()
+  {
+    let mut headers = HeaderMap::new();
+    assert!(!accepts_encoding(&headers, "gzip"));
+
+    headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate, br;q=0"));
+    assert!(accepts_encoding(&headers, "gzip"));
+    assert!(!accepts_encoding(&headers, "br"));
+  }
+
+  #[test]
+  fn test_etag()
+  {
+    assert_eq!(etag(&[0x00, 0xab, 0x10]), "\"00ab10\"");
+  }
+
+  #[tokio::test]
+  async fn test_serve_falls_back_on_web_app_dir()
+  {
+    let dir = std::env::temp_dir().join(format!("oneup-embed-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("not-embedded.txt"), "from disk").unwrap();
+    let files = super::super::web::serve_dir(dir.to_str().unwrap());
+
+    let req = Request::builder().uri("/not-embedded.txt").body(Body::empty()).unwrap();
+    let res = serve(req, Some(files)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&bytes[..], b"from disk");
+
+    let req = Request::builder().method(Method::POST).uri("/").body(Body::empty()).unwrap();
+    assert_eq!(serve(req, None).await.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
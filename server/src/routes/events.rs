@@ -0,0 +1,136 @@
+use std::{convert::Infallible, sync::Arc};
+use axum::{
+  extract::{Query, State}, response::{sse::{Event, KeepAlive, Sse}, IntoResponse}, Extension
+};
+use tokio_stream::{wrappers::{errors::BroadcastStreamRecvError, BroadcastStream}, StreamExt};
+use crate::{state, model};
+
+/// Stream domain events to the caller as Server-Sent Events
+///
+/// - GET handler for `/events`
+/// - GET handler for `/events?user_id={id}`
+/// - Each SSE message is named after the event kind e.g. `points_created` with the JSON
+///   serialized `model::Event` as its data
+/// - Events that don't belong to a user e.g. `action_approved` are always delivered
+/// - A `lagged` message is sent if the client fell behind and missed events, in which case the
+///   client should refresh its data
+///
+/// #### Parameters
+/// - ***filter*** - supports ***user_id***
+pub async fn get(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Query(filter): Query<model::Filter>)
+  -> impl IntoResponse
+{
+  log::info!("User [{}] subscribed to events", claims.username);
+
+  let user_id = filter.user_id;
+  let stream = BroadcastStream::new(state.subscribe()).filter_map(move |x| match x {
+    Ok(event) if event.is_for_user(user_id) => Some(Ok::<_, Infallible>(
+      Event::default().event(event.kind.as_str()).json_data(&event)
+        .unwrap_or_else(|_| Event::default().event("error")))),
+    Ok(_) => None,
+    Err(BroadcastStreamRecvError::Lagged(count)) => {
+      log::warn!("Event subscriber lagged behind by {count} events");
+      Some(Ok(Event::default().event("lagged").data(count.to_string())))
+    },
+  });
+
+  Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::super::tests::login_as_admin;
+  use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode}
+  };
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::{db, model, routes, state};
+
+  // Helper to read the next SSE frame from the body as a string
+  async fn next_frame(body: &mut Body) -> String
+  {
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame()).await
+      .expect("timed out waiting for event").unwrap().unwrap();
+    String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_get_fails_without_login()
+  {
+    let state = state::test().await;
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/events")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+  }
+
+  #[tokio::test]
+  async fn test_get_streams_events()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/events")
+      .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "text/event-stream");
+
+    // Create points through the API which publishes the event
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::CreatePoints { value: 10, user_id, action_id: 1, occurred_at: None, kind: None }))
+      .unwrap())).unwrap();
+    let res2 = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res2.status(), StatusCode::CREATED);
+
+    let mut body = res.into_body();
+    let frame = next_frame(&mut body).await;
+    assert!(frame.starts_with("event: points_created\n"));
+    let data = frame.lines().find_map(|x| x.strip_prefix("data: ")).unwrap();
+    let event: model::Event = serde_json::from_str(data).unwrap();
+    assert_eq!(event.kind, model::EventKind::PointsCreated);
+    assert_eq!(event.id, 1);
+    assert_eq!(event.user_id, Some(user_id));
+    assert_eq!(event.data.unwrap()["value"], 10);
+  }
+
+  #[tokio::test]
+  async fn test_get_filters_by_user()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    let user_id_1 = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let user_id_2 = db::user::insert(state.db(), "user2", "user2@foo.com").await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/events?user_id={user_id_2}"))
+      .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Only the second user's event and the user agnostic event make it through
+    state.publish(model::Event::new(model::EventKind::PointsCreated, 1).with_user_id(user_id_1));
+    state.publish(model::Event::new(model::EventKind::PointsCreated, 2).with_user_id(user_id_2));
+    state.publish(model::Event::new(model::EventKind::ActionApproved, 3));
+
+    let mut body = res.into_body();
+    let frame = next_frame(&mut body).await;
+    assert!(frame.starts_with("event: points_created\n"));
+    assert!(frame.contains(r#""id":2"#));
+    let frame = next_frame(&mut body).await;
+    assert!(frame.starts_with("event: action_approved\n"));
+  }
+}
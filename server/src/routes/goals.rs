@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Path, State}, http::StatusCode, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+/// Create a new savings goal for specific user by id
+///
+/// - POST handler for `/users/{id}/goals`
+/// - Completes the goal right away when the user's available balance already covers it
+/// - error on caller not being the user or an admin
+///
+/// #### Parameters
+/// - ***goal*** - the ***name***, ***target***, ***deadline*** and ***item_id***
+pub async fn create(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(user_id): Path<i64>,
+  Json(goal): Json<model::GoalPartial>) -> Result<impl IntoResponse, Error>
+{
+  require_owner(&claims, user_id)?;
+  let id = db::goal::insert(state.db(), user_id, &goal).await?;
+  state::goal::check_user(&state, user_id).await;
+  let goal = db::goal::fetch_by_id(state.db(), user_id, id).await?;
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(goal))))
+}
+
+/// Get the savings goals of specific user by id
+///
+/// - GET handler for `/users/{id}/goals`
+/// - Goals include the user's progress and projected completion
+pub async fn get(State(state): State<Arc<state::State>>,
+  Path(user_id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::goal::fetch_by_user_id(state.db(), user_id).await?))
+}
+
+/// Get specific savings goal by id of specific user by id
+///
+/// - GET handler for `/users/{id}/goals/{goal_id}`
+pub async fn get_by_id(State(state): State<Arc<state::State>>,
+  Path((user_id, id)): Path<(i64, i64)>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::goal::fetch_by_id(state.db(), user_id, id).await?))
+}
+
+/// Replace specific savings goal by id of specific user by id
+///
+/// - PUT handler for `/users/{id}/goals/{goal_id}`
+/// - error on caller not being the user or an admin
+pub async fn update_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path((user_id, id)): Path<(i64, i64)>,
+  Json(goal): Json<model::GoalPartial>) -> Result<impl IntoResponse, Error>
+{
+  require_owner(&claims, user_id)?;
+  db::goal::update_by_id(state.db(), user_id, id, &goal).await?;
+  state::goal::check_user(&state, user_id).await;
+  Ok(Json(db::goal::fetch_by_id(state.db(), user_id, id).await?))
+}
+
+/// Delete specific savings goal by id of specific user by id
+///
+/// - DELETE handler for `/users/{id}/goals/{goal_id}`
+/// - error on caller not being the user or an admin
+pub async fn delete_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path((user_id, id)): Path<(i64, i64)>)
+  -> Result<impl IntoResponse, Error>
+{
+  require_owner(&claims, user_id)?;
+  Ok(Json(db::goal::delete_by_id(state.db(), user_id, id).await?))
+}
+
+// Goals are managed by the user saving up or an admin
+fn require_owner(claims: &model::JwtClaims, user_id: i64) -> Result<(), Error>
+{
+  if claims.sub == user_id || claims.has_role("admin") {
+    return Ok(());
+  }
+  let msg = format!("User '{}' is not allowed to manage goals of others", claims.username);
+  log::warn!("{msg}");
+  Err(Error::http(StatusCode::FORBIDDEN, &msg))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
+  use axum::{body::Body, http::{header, Method, Request}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  #[tokio::test]
+  async fn test_create_completes_and_get()
+  {
+    let state = state::test().await;
+    let (user_id, user_token) = login_as_user(state.clone(), "user1", None).await;
+    db::point::insert(state.db(), 15, user_id, 1).await.unwrap();
+    let mut events = state.subscribe();
+
+    // Users manage their own goals only
+    let (admin, _) = login_as_admin(state.clone()).await;
+    for (id, status) in [(admin.id, StatusCode::FORBIDDEN), (user_id, StatusCode::CREATED)] {
+      let req = Request::builder().method(Method::POST)
+        .uri(format!("/api/users/{id}/goals"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {user_token}"))
+        .body(Body::from(serde_json::to_vec(&serde_json::json!({"name": "kite", "target": 10}))
+          .unwrap())).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), status);
+    }
+
+    let event = events.try_recv().unwrap();
+    assert_eq!((event.kind, event.user_id), (model::EventKind::GoalCompleted, Some(user_id)));
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{user_id}/goals"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let goals: Vec<model::Goal> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(goals.iter().map(|x| (x.name.as_str(), x.progress)).collect::<Vec<_>>(),
+      vec![("kite", 10)]);
+    assert!(goals[0].completed_at.is_some());
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{}/goals/{}", admin.id, goals[0].id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  }
+
+  #[tokio::test]
+  async fn test_completes_when_points_awarded()
+  {
+    let state = state::test().await;
+    let (user_id, user_token) = login_as_user(state.clone(), "user1", None).await;
+    let (_, admin_token) = login_as_admin(state.clone()).await;
+
+    let req = Request::builder().method(Method::POST)
+      .uri(format!("/api/users/{user_id}/goals"))
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {user_token}"))
+      .body(Body::from(serde_json::to_vec(&serde_json::json!({"name": "kite", "target": 10}))
+        .unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let goal: model::Goal = serde_json::from_slice(&bytes).unwrap();
+    assert!(goal.completed_at.is_none());
+    let mut events = state.subscribe();
+
+    // Awarding the points completes the goal without waiting on the background check
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {admin_token}"))
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::CreatePoints { value: 10, user_id, action_id: 1, occurred_at: None, kind: None }))
+      .unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let kinds: Vec<_> = std::iter::from_fn(|| events.try_recv().ok()).map(|x| x.kind).collect();
+    assert!(kinds.contains(&model::EventKind::GoalCompleted));
+    let goal = db::goal::fetch_by_id(state.db(), user_id, goal.id).await.unwrap();
+    assert!(goal.completed_at.is_some());
+  }
+}
@@ -1,11 +1,15 @@
-use axum::{response::IntoResponse, Json};
-use crate::{model::Simple, APP_NAME};
+use std::sync::Arc;
+use axum::{extract::State, response::IntoResponse, Json};
+use crate::{model::Health, state, APP_NAME};
 
-pub async fn get() -> impl IntoResponse
+/// Health check
+///
+/// - GET handler for `/health`
+/// - Includes a summary of the scheduled database snapshots, see `/backup/status` for the details
+pub async fn get(State(state): State<Arc<state::State>>) -> impl IntoResponse
 {
   let msg = format!("{} API Services", APP_NAME);
-  let res = serde_json::json!(Simple::new(&msg));
-  Json(res)
+  Json(Health { message: msg, backup: state.backup_status().into() })
 }
 
 #[cfg(test)]
@@ -27,11 +31,17 @@ mod tests
     let req = Request::builder().method(Method::GET)
       .uri("/api/health")
       .body(Body::empty()).unwrap();
-    let res = routes::init(state).oneshot(req).await.unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
 
     assert_eq!(res.status(), StatusCode::OK);
     let bytes = res.into_body().collect().await.unwrap().to_bytes();
     let simple: model::Simple = serde_json::from_slice(&bytes).unwrap();
     assert_eq!(simple.message, "OneUp API Services");
+    let health: model::Health = serde_json::from_slice(&bytes).unwrap();
+    assert!(!health.backup.enabled);
+    assert!(health.backup.last_success_at.is_none());
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(json["backup"].get("last_path").is_none());
+    assert!(json["backup"].get("last_error").is_none());
   }
 }
\ No newline at end of file
@@ -0,0 +1,332 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Request, State}, middleware::Next,
+  http::{header, HeaderValue, Method, StatusCode}, response::Response,
+};
+use axum::body::HttpBody;
+use http_body_util::{BodyExt, Limited};
+use ring::digest;
+
+use crate::{db, state, model, errors::{Error, ErrorKind}};
+
+/// Request header clients use to mark a POST as safe to retry
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Response header set when a stored response is being replayed
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+// Largest request or response buffered for replay, the same as the default request body limit
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Middleware to honour the `Idempotency-Key` header on POST requests
+///
+/// - Requests without the header or that aren't POSTs are passed straight through
+/// - Keys are scoped to the caller identified by the token, anonymous callers share a scope
+/// - The first request reserves the key and stores the response once it completes
+/// - Retries with the same key and body get the original response replayed
+/// - Retries while the original is still in flight are rejected with 409
+/// - Reusing a key with a different request is rejected with 422
+/// - Server errors are not stored so that the client may retry them
+/// - Responses marked `Cache-Control: no-store` e.g. ones carrying credentials, and responses too
+///   large to buffer are passed along without being stored, releasing the key
+/// - Request bodies over 2MB are rejected with 413
+///
+/// #### Parameters:
+/// - ***req*** is the incoming request
+/// - ***next*** is the next middleware or handler to call
+pub async fn idempotency(State(state): State<Arc<state::State>>, req: Request, next: Next)
+  -> Result<Response, Error>
+{
+  if req.method() != Method::POST {
+    return Ok(next.run(req).await);
+  }
+  let key = match req.headers().get(IDEMPOTENCY_KEY) {
+    Some(value) => value.to_str().map_err(|_| Error::http(StatusCode::UNPROCESSABLE_ENTITY,
+      "Idempotency-Key must be a visible ASCII string"))?.to_string(),
+    None => return Ok(next.run(req).await),
+  };
+  let user_id = req.extensions().get::<model::JwtClaims>().map(|x| x.sub);
+
+  // Buffer the request body so that it can be hashed and then passed along
+  let (parts, body) = req.into_parts();
+  let bytes = match Limited::new(body, BODY_LIMIT).collect().await {
+    Ok(collected) => collected.to_bytes(),
+    Err(e) if e.is::<http_body_util::LengthLimitError>() => {
+      let msg = format!("Request body with Idempotency-Key '{key}' is too large");
+      log::warn!("{msg}");
+      return Err(Error::http(StatusCode::PAYLOAD_TOO_LARGE, &msg));
+    },
+    Err(_) => return Err(Error::http(StatusCode::BAD_REQUEST, "Failed to read request body")),
+  };
+  let hash = request_hash(&parts.method, &parts.uri, &bytes);
+
+  // Reserve the key or replay the response already stored for it
+  let ttl = state.config().idempotency_ttl;
+  let id = match db::idempotency::insert(state.db(), &key, user_id, &hash, ttl).await {
+    Ok(id) => id,
+    Err(e) if e.kind == ErrorKind::NotUnique => {
+      let entry = db::idempotency::fetch_by_key(state.db(), &key, user_id).await?;
+      return replay(entry, &hash);
+    },
+    Err(e) => return Err(e),
+  };
+
+  let res = next.run(Request::from_parts(parts, bytes.into())).await;
+
+  // Release the key on server errors so the client is free to try again, and on responses that
+  // must not be stored or are too large to buffer
+  if res.status().is_server_error() || !is_storable(&res) {
+    db::idempotency::delete_by_id(state.db(), id).await?;
+    return Ok(res);
+  }
+
+  // Store the response for later replay
+  let (parts, body) = res.into_parts();
+  let bytes = body.collect().await
+    .map_err(|_| Error::http(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read response body"))?
+    .to_bytes();
+  let content_type = parts.headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+  db::idempotency::update_response(state.db(), id, parts.status.as_u16(), content_type, &bytes).await?;
+
+  Ok(Response::from_parts(parts, bytes.into()))
+}
+
+// Check the response may be stored for replay, not marked no-store and of a known small size
+fn is_storable(res: &Response) -> bool
+{
+  let no_store = res.headers().get_all(header::CACHE_CONTROL).iter()
+    .filter_map(|x| x.to_str().ok())
+    .any(|x| x.split(',').any(|x| x.trim().eq_ignore_ascii_case("no-store")));
+  let size = res.body().size_hint().upper();
+  !no_store && size.is_some_and(|x| x <= BODY_LIMIT as u64)
+}
+
+// Build the stored response back up or explain why it can't be replayed
+fn replay(entry: model::Idempotency, hash: &str) -> Result<Response, Error>
+{
+  if entry.request_hash != hash {
+    let msg = format!("Idempotency-Key '{}' was already used for a different request", entry.key);
+    log::warn!("{msg}");
+    return Err(Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg));
+  }
+  let status = match entry.status {
+    Some(status) => StatusCode::from_u16(status)
+      .map_err(|_| Error::http(StatusCode::INTERNAL_SERVER_ERROR, "Invalid stored status"))?,
+    None => {
+      let msg = format!("Request with Idempotency-Key '{}' is still being processed", entry.key);
+      log::warn!("{msg}");
+      return Err(Error::http(StatusCode::CONFLICT, &msg));
+    }
+  };
+
+  log::info!("Replaying response for Idempotency-Key '{}'", entry.key);
+  let mut res = Response::new(entry.body.unwrap_or_default().into());
+  *res.status_mut() = status;
+  if let Some(value) = entry.content_type.and_then(|x| HeaderValue::from_str(&x).ok()) {
+    res.headers_mut().insert(header::CONTENT_TYPE, value);
+  }
+  res.headers_mut().insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+  Ok(res)
+}
+
+// Hash the parts of the request that make it unique
+fn request_hash(method: &Method, uri: &axum::http::Uri, body: &[u8]) -> String
+{
+  let mut ctx = digest::Context::new(&digest::SHA256);
+  ctx.update(method.as_str().as_bytes());
+  ctx.update(b"\n");
+  ctx.update(uri.to_string().as_bytes());
+  ctx.update(b"\n");
+  ctx.update(body);
+  base64::encode(ctx.finish().as_ref())
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
+  use axum::body::Body;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  // Helper to build a points create request with an optional idempotency key
+  fn create_points_req(key: Option<&str>, value: i64, user_id: i64, action_id: i64)
+    -> Request<Body>
+  {
+    let mut builder = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json");
+    if let Some(key) = key {
+      builder = builder.header(IDEMPOTENCY_KEY, key);
+    }
+    builder.body(Body::from(serde_json::to_vec(&serde_json::json!(
+      model::CreatePoints { value, user_id, action_id, occurred_at: None, kind: None }))
+      .unwrap())).unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_retry_replays_original_response()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    let res = routes::init(state.clone())
+      .oneshot(create_points_req(Some("key1"), 10, user_id, action_id)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert!(res.headers().get(IDEMPOTENT_REPLAYED).is_none());
+    let first = res.into_body().collect().await.unwrap().to_bytes();
+
+    let res = routes::init(state.clone())
+      .oneshot(create_points_req(Some("key1"), 10, user_id, action_id)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(res.headers().get(IDEMPOTENT_REPLAYED).unwrap(), "true");
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "application/json");
+    let second = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(first, second);
+
+    // Only a single points entry was created
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_reused_key_with_different_body_is_rejected()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    let res = routes::init(state.clone())
+      .oneshot(create_points_req(Some("key1"), 10, user_id, action_id)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    let res = routes::init(state.clone())
+      .oneshot(create_points_req(Some("key1"), 20, user_id, action_id)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].value, 10);
+  }
+
+  #[tokio::test]
+  async fn test_same_key_from_other_user_is_not_replayed()
+  {
+    let state = state::test().await;
+    let (admin, admin_token) = login_as_admin(state.clone()).await;
+    let (_, user_token) = login_as_user(state.clone(), "user1", None).await;
+
+    // Each caller gets their own response, never the other's and never a conflict
+    for token in [&admin_token, &user_token, &user_token] {
+      let mut req = create_points_req(Some("key1"), 10, admin.id, 1);
+      req.headers_mut().insert(header::AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {token}")).unwrap());
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), StatusCode::CREATED);
+    }
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].status, model::PointsStatus::Approved);
+    assert_eq!(points[1].status, model::PointsStatus::Pending);
+  }
+
+  #[tokio::test]
+  async fn test_in_flight_key_is_rejected()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    // Simulate the original request still being processed
+    let req = create_points_req(Some("key1"), 10, user_id, action_id);
+    let (parts, body) = req.into_parts();
+    let bytes = body.collect().await.unwrap().to_bytes();
+    let hash = request_hash(&parts.method, &parts.uri, &bytes);
+    db::idempotency::insert(state.db(), "key1", None, &hash, 60).await.unwrap();
+
+    let res = routes::init(state.clone())
+      .oneshot(create_points_req(Some("key1"), 10, user_id, action_id)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 0);
+  }
+
+  #[tokio::test]
+  async fn test_without_key_is_not_deduplicated()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    for _ in 0..2 {
+      let res = routes::init(state.clone())
+        .oneshot(create_points_req(None, 10, user_id, action_id)).await.unwrap();
+      assert_eq!(res.status(), StatusCode::CREATED);
+    }
+
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 2);
+  }
+
+  #[tokio::test]
+  async fn test_client_errors_are_replayed()
+  {
+    let state = state::test().await;
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+
+    // User doesn't exist so the request fails the same way each time
+    for replayed in [false, true] {
+      let res = routes::init(state.clone())
+        .oneshot(create_points_req(Some("key1"), 10, -1, action_id)).await.unwrap();
+      assert_eq!(res.status(), StatusCode::NOT_FOUND);
+      assert_eq!(res.headers().get(IDEMPOTENT_REPLAYED).is_some(), replayed);
+    }
+  }
+
+  #[tokio::test]
+  async fn test_credentials_and_large_bodies_are_not_stored()
+  {
+    let state = state::test().await;
+    let (admin, _) = login_as_admin(state.clone()).await;
+
+    // Logins are never buffered or stored even with a key
+    for _ in 0..2 {
+      let req = Request::builder().method(Method::POST)
+        .uri("/api/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(IDEMPOTENCY_KEY, "key1")
+        .body(Body::from(serde_json::to_vec(&serde_json::json!(model::LoginRequest {
+          handle: admin.username.clone(), password: "admin".to_string() })).unwrap())).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+      assert!(res.headers().get(IDEMPOTENT_REPLAYED).is_none());
+      assert_eq!(res.headers().get(header::CACHE_CONTROL).unwrap(), "no-store");
+    }
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM idempotency")
+      .fetch_one(state.db()).await.unwrap();
+    assert_eq!(count, 0);
+
+    // Responses marked no-store aren't kept either
+    let mut res = Response::new(axum::body::Body::from("{}"));
+    assert!(is_storable(&res));
+    res.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("private, no-store"));
+    assert!(!is_storable(&res));
+
+    // Bodies too large to buffer are turned away before reaching the handler
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(IDEMPOTENCY_KEY, "key2")
+      .body(Body::from(vec![b' '; BODY_LIMIT + 1])).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(db::point::fetch_all(state.db()).await.unwrap().is_empty());
+  }
+}
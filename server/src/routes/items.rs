@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Path, State}, http::StatusCode, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// The catalog is managed by admins
+const ADMIN_ONLY: &str = "manage the catalog";
+
+/// Create a new catalog item
+///
+/// - POST handler for `/items`
+/// - error on caller not being an admin
+pub async fn create(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(item): Json<model::ItemPartial>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let id = db::item::insert(state.db(), &item).await?;
+  let item = db::item::fetch_by_id(state.db(), id).await?;
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(item))))
+}
+
+/// Get the catalog of items
+///
+/// - GET handler for `/items`
+pub async fn get(State(state): State<Arc<state::State>>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::item::fetch_all(state.db()).await?))
+}
+
+/// Get specific catalog item by id
+///
+/// - GET handler for `/items/{id}`
+pub async fn get_by_id(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::item::fetch_by_id(state.db(), id).await?))
+}
+
+/// Replace specific catalog item by id
+///
+/// - PUT handler for `/items/{id}`
+/// - error on caller not being an admin
+pub async fn update_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(item): Json<model::ItemPartial>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  db::item::update_by_id(state.db(), id, &item).await?;
+  Ok(Json(db::item::fetch_by_id(state.db(), id).await?))
+}
+
+/// Delete specific catalog item by id
+///
+/// - DELETE handler for `/items/{id}`
+/// - error on caller not being an admin
+/// - error on the item having been redeemed, deactivate it instead
+pub async fn delete_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(db::item::delete_by_id(state.db(), id).await?))
+}
+
+/// Redeem specific catalog item by id
+///
+/// - POST handler for `/items/{id}/redeem`
+/// - Creates a reward for the item's cost linked to the item
+/// - Redeems for the caller unless ***user_id*** is given
+/// - error on caller redeeming for someone else without being an admin
+/// - error on the item being inactive, out of stock or over its limit for the period
+/// - error on the user's available balance not covering the cost
+///
+/// #### Parameters
+/// - ***redeem*** - supports ***user_id***
+pub async fn redeem(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(redeem): Json<model::Redeem>) -> Result<impl IntoResponse, Error>
+{
+  let user_id = redeem.user_id.unwrap_or(claims.sub);
+  if user_id != claims.sub && !claims.has_role("admin") {
+    let msg = format!("User '{}' is not allowed to redeem items for others", claims.username);
+    log::warn!("{msg}");
+    return Err(Error::http(StatusCode::FORBIDDEN, &msg));
+  }
+
+  let reward_id = db::item::redeem(state.db(), id, user_id).await?;
+  let reward = db::reward::fetch_by_id(state.db(), reward_id).await?;
+  state.publish(model::Event::new(model::EventKind::RewardCreated, reward_id)
+    .with_user_id(reward.user_id).with_data(&reward));
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(reward))))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
+  use axum::{body::Body, http::{header, Method, Request}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  #[tokio::test]
+  async fn test_create_admin_only()
+  {
+    let state = state::test().await;
+    let item = model::ItemPartial::new("item1", 5).with_stock(2);
+    let (_, user_token) = login_as_user(state.clone(), "user1", Some(0)).await;
+    let (_, admin_token) = login_as_admin(state.clone()).await;
+
+    for (token, status) in [(user_token, StatusCode::FORBIDDEN), (admin_token, StatusCode::CREATED)] {
+      let req = Request::builder().method(Method::POST)
+        .uri("/api/items")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, format!("Bearer {token}"))
+        .body(Body::from(serde_json::to_vec(&item).unwrap())).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), status);
+    }
+
+    let items = db::item::fetch_all(state.db()).await.unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!((items[0].cost, items[0].stock, items[0].active), (5, Some(2), true));
+  }
+
+  #[tokio::test]
+  async fn test_redeem_and_filter_rewards_by_item()
+  {
+    let state = state::test().await;
+    let (user_id, access_token) = login_as_user(state.clone(), "user1", Some(10)).await;
+    let id = db::item::insert(state.db(), &model::ItemPartial::new("item1", 4)).await.unwrap();
+    db::reward::insert(state.db(), 1, user_id).await.unwrap();
+
+    // Redeeming for someone else is only for admins
+    let redeem = |user_id: Option<i64>| Request::builder().method(Method::POST)
+      .uri(format!("/api/items/{id}/redeem"))
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from(serde_json::to_vec(&model::Redeem { user_id }).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(redeem(Some(1))).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = routes::init(state.clone()).oneshot(redeem(None)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let reward: model::Reward = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((reward.value, reward.user_id, reward.item_id), (4, user_id, Some(id)));
+
+    // Only 5 points are left so a second redemption fits but a third doesn't
+    let res = routes::init(state.clone()).oneshot(redeem(None)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let res = routes::init(state.clone()).oneshot(redeem(None)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/rewards?item_id={id}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let rewards: Vec<model::Reward> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(rewards.len(), 2);
+    assert!(rewards.iter().all(|x| x.item_id == Some(id)));
+  }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, response::IntoResponse};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+/// Get users ranked by their points over a period
+///
+/// - GET handler for `/leaderboard?period={week|month|all}`
+/// - GET handler for `/leaderboard?period={period}&category_id={id}&role_name_ne={name}`
+/// - Users with the same total share a rank with rank deltas against the period before
+/// - ***ranks_only=true*** hides the point totals
+/// - error on category not found
+///
+/// #### Parameters
+/// - ***query*** - supports ***period***, ***category_id***, ***role_name_ne***,
+///   ***role_id_ne*** and ***ranks_only***
+pub async fn get(State(state): State<Arc<state::State>>,
+  Query(query): Query<model::LeaderboardQuery>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::leaderboard::fetch(state.db(), &query).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode}
+  };
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::{db, model, routes, state};
+
+  #[tokio::test]
+  async fn test_get_ranks_only()
+  {
+    let state = state::test().await;
+    let user1 = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 3, user1, 1).await.unwrap();
+    db::point::insert(state.db(), 5, 1, 1).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/leaderboard?period=month&role_name_ne=admin&ranks_only=true")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(value["entries"][0].get("total").is_none());
+    let board: model::Leaderboard = serde_json::from_value(value).unwrap();
+    assert_eq!(board.period, model::LeaderboardPeriod::Month);
+    assert_eq!(board.entries.len(), 1);
+    assert_eq!(board.entries[0].user_id, user1);
+    assert_eq!(board.entries[0].rank, 1);
+  }
+
+  #[tokio::test]
+  async fn test_get_failure_invalid_period()
+  {
+    let state = state::test().await;
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/leaderboard?period=year")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+  }
+}
@@ -3,10 +3,10 @@
  */
 use std::{sync::Arc, time::Duration};
 use axum::{
-  extract::Request, http::header, middleware, response::Response, routing::{delete, get, post, put}, Router
+  extract::{DefaultBodyLimit, Request}, http::{header, HeaderValue}, middleware, response::Response, routing::{delete, get, post, put}, Router
 };
 use tower_http::{
-  cors, trace::TraceLayer, services::ServeDir,
+  cors, trace::TraceLayer,
 };
 use uuid::Uuid;
 use http_body_util::BodyExt;
@@ -15,14 +15,37 @@ use crate::state;
 
 // Exports
 mod health;
+mod audit;
 mod auth;
+mod backup;
+mod badges;
+mod bonuses;
+mod events;
+mod goals;
+mod idempotency;
+mod items;
+mod leaderboard;
 mod users;
 mod roles;
 mod passwords;
 mod actions;
 mod categories;
+mod chores;
 mod points;
+mod redemptions;
 mod rewards;
+mod search;
+mod transfer;
+mod trash;
+mod web;
+#[cfg(feature = "embed-web")]
+mod embed;
+
+/// Request and response header carrying the id used to correlate logs and audit entries
+pub const REQUEST_ID: &str = "x-request-id";
+
+// Backups carry the whole database so allow much larger bodies than the default 2MB
+const RESTORE_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
 /// Configure api routes
 pub(crate) fn init(state: Arc::<state::State>) -> Router 
@@ -37,42 +60,114 @@ pub(crate) fn init(state: Arc::<state::State>) -> Router
     //.allow_headers([header::CONTENT_TYPE]);
 
   // Static file serving for Flutter web app
-  let static_files = Router::new()
-    .fallback_service(ServeDir::new(&state.config().web_app_dir));
+  let static_files = web::init(state.config().web_app_dir.as_deref());
 
   // No authorization is required for these routes
   let public_routes = Router::new()
     .route("/api/health", get(health::get))
-    .route("/api/login", post(auth::login))
     .route("/api/actions", get(actions::get).post(actions::create))
+    .route("/api/actions/pending", get(actions::get_pending))
     .route("/api/actions/{opt}", get(actions::get_by_id))
+    .route("/api/badges", get(badges::get))
+    .route("/api/badges/{opt}", get(badges::get_by_id))
+    .route("/api/bonuses", get(bonuses::get))
+    .route("/api/bonuses/{opt}", get(bonuses::get_by_id))
     .route("/api/categories", get(categories::get))
     .route("/api/categories/{opt}", get(categories::get_by_id))
+    .route("/api/chores", get(chores::get))
+    .route("/api/chores/{opt}", get(chores::get_by_id))
     .route("/api/passwords", get(passwords::get))
     .route("/api/passwords/{opt}", get(passwords::get_by_id))
     .route("/api/roles", get(roles::get))
     .route("/api/roles/{opt}", get(roles::get_by_id))
+    .route("/api/search", get(search::get))
+    .route("/api/items", get(items::get))
+    .route("/api/items/{opt}", get(items::get_by_id))
+    .route("/api/leaderboard", get(leaderboard::get))
+    .route("/api/export/points", get(transfer::export_points))
+    .route("/api/export/rewards", get(transfer::export_rewards))
     .route("/api/points", get(points::get).post(points::create))
     .route("/api/points/{opt}", get(points::get_by_id).put(points::update_by_id).delete(points::delete_by_id))
     .route("/api/points/sum", get(points::sum))
     .route("/api/rewards", get(rewards::get).post(rewards::create))
     .route("/api/rewards/sum", get(rewards::sum))
     .route("/api/rewards/{opt}", get(rewards::get_by_id).put(rewards::update_by_id).delete(rewards::delete_by_id))
+    .route("/api/redemptions", get(redemptions::get))
+    .route("/api/redemptions/{opt}", get(redemptions::get_by_id))
     .route("/api/users",get(users::get))
     .route("/api/users/{opt}", get(users::get_by_id))
-    .route("/api/users/{opt}/roles", get(users::get_roles));
+    .route("/api/users/{opt}/roles", get(users::get_roles))
+    .route("/api/users/{opt}/balance", get(users::get_balance))
+    .route("/api/users/{opt}/ledger", get(users::get_ledger))
+    .route("/api/users/{opt}/due", get(users::get_due))
+    .route("/api/users/{opt}/streaks", get(users::get_streaks))
+    .route("/api/users/{opt}/badges", get(users::get_badges))
+    .route("/api/users/{opt}/expiring", get(users::get_expiring))
+    .route("/api/users/{opt}/goals", get(goals::get))
+    .route("/api/users/{opt}/goals/{goal_id}", get(goals::get_by_id))
+    .layer(middleware::from_fn(audit::context))
+    .layer(middleware::from_fn_with_state(state.clone(), idempotency::idempotency))
+
+    // Login is left out of idempotency so that access tokens are never stored for replay
+    .route("/api/login", post(auth::login))
+
+    // Callers are identified when they send a token so their changes can be attributed to them
+    .layer(middleware::from_fn_with_state(state.clone(), auth::identify));
 
   // Authorization is required for these routes
   let private_routes = Router::new()
+    .route("/api/events", get(events::get))
     .route("/api/users", post(users::create))
     .route("/api/users/{opt}", put(users::update_by_id).delete(users::delete_by_id))
+    .route("/api/users/{opt}/goals", post(goals::create))
+    .route("/api/users/{opt}/goals/{goal_id}", put(goals::update_by_id).delete(goals::delete_by_id))
     .route("/api/passwords", post(passwords::create))
     .route("/api/passwords/{opt}", delete(passwords::delete_by_id))
     .route("/api/roles", post(roles::create))
     .route("/api/roles/{opt}", put(roles::update_by_id).delete(roles::delete_by_id))
+    .route("/api/badges", post(badges::create))
+    .route("/api/badges/{opt}", put(badges::update_by_id).delete(badges::delete_by_id))
+    .route("/api/bonuses", post(bonuses::create))
+    .route("/api/bonuses/preview", get(bonuses::preview))
+    .route("/api/bonuses/{opt}", put(bonuses::update_by_id).delete(bonuses::delete_by_id))
     .route("/api/categories", post(categories::create))
     .route("/api/categories/{opt}", put(categories::update_by_id).delete(categories::delete_by_id))
     .route("/api/actions/{opt}", put(actions::update_by_id).delete(actions::delete_by_id))
+    .route("/api/actions/{opt}/approve", post(actions::approve))
+    .route("/api/actions/{opt}/reject", post(actions::reject))
+    .route("/api/points/approve", post(points::approve))
+    .route("/api/points/reject", post(points::reject))
+    .route("/api/chores", post(chores::create))
+    .route("/api/chores/{opt}", put(chores::update_by_id).delete(chores::delete_by_id))
+    .route("/api/items", post(items::create))
+    .route("/api/items/{opt}", put(items::update_by_id).delete(items::delete_by_id))
+    .route("/api/items/{opt}/redeem", post(items::redeem))
+    .route("/api/redemptions", post(redemptions::create))
+    .route("/api/redemptions/{opt}/approve", post(redemptions::approve))
+    .route("/api/redemptions/{opt}/deny", post(redemptions::deny))
+    .route("/api/redemptions/{opt}/cancel", post(redemptions::cancel))
+    .route("/api/redemptions/{opt}/fulfill", post(redemptions::fulfill))
+    .route("/api/import/points", post(transfer::import_points))
+    .route("/api/import/rewards", post(transfer::import_rewards))
+    .route("/api/trash", get(trash::get))
+    .route("/api/users/{opt}/restore", post(users::restore_by_id))
+    .route("/api/actions/{opt}/restore", post(actions::restore_by_id))
+    .route("/api/points/{opt}/restore", post(points::restore_by_id))
+    .route("/api/rewards/{opt}/restore", post(rewards::restore_by_id))
+    .route("/api/backup", get(backup::get))
+    .route("/api/backup/status", get(backup::get_status))
+    .route("/api/audit", get(audit::get))
+
+    // Audit context is layered inside authorization so changes are attributed to the caller
+    .layer(middleware::from_fn(audit::context))
+
+    // Idempotency is layered inside authorization so replays are never served unauthenticated
+    .layer(middleware::from_fn_with_state(state.clone(), idempotency::idempotency))
+
+    // Restore is left out of idempotency so that whole backups are never buffered and stored
+    .route("/api/restore", post(backup::restore)
+      .route_layer(middleware::from_fn(audit::context))
+      .layer(DefaultBodyLimit::max(RESTORE_BODY_LIMIT)))
     .layer(middleware::from_fn_with_state(state.clone(), auth::authorization));
 
   // Merge all routers into the final router
@@ -94,7 +189,8 @@ pub(crate) fn init(state: Arc::<state::State>) -> Router
       .make_span_with(|request: &Request| {
         let method = request.method();
         let uri = request.uri().path();
-        let request_id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        let request_id = request.headers().get(REQUEST_ID)
+          .and_then(|v| v.to_str().ok()).unwrap_or_default();
         tracing::info_span!("request", id = %request_id, method = %method, uri = %uri)
       })
       .on_request(|request: &Request, _span: &tracing::Span| {
@@ -108,10 +204,31 @@ pub(crate) fn init(state: Arc::<state::State>) -> Router
         tracing::info!("Response: {}, len: {}, in {:?}", response.status(), length, latency);
       })
     )
+
+    // Add the request id outside of tracing so the span and audit entries share it
+    .layer(middleware::from_fn(request_id))
+
     // Add the state layer to access application state
     .with_state(state)
 }
 
+// -------------------------------------------------------------------------------------------------
+// Custom middleware to give every request an id, keeping the one sent by the client if any
+// -------------------------------------------------------------------------------------------------
+async fn request_id(mut request: Request, next: middleware::Next) -> Response {
+  let id = match request.headers().get(REQUEST_ID) {
+    Some(id) if !id.is_empty() && id.len() <= 64 => id.clone(),
+    _ => {
+      let id = HeaderValue::from_str(&Uuid::new_v4().simple().to_string()[..8]).unwrap();
+      request.headers_mut().insert(REQUEST_ID, id.clone());
+      id
+    }
+  };
+  let mut response = next.run(request).await;
+  response.headers_mut().insert(REQUEST_ID, id);
+  response
+}
+
 // -------------------------------------------------------------------------------------------------
 // Custom middleware to log request and response bodies on debug level
 // -------------------------------------------------------------------------------------------------
@@ -214,4 +331,30 @@ mod tests
     let admin_user = db::user::fetch_by_handle(state.db(), "admin").await.unwrap();
     (admin_user, login_response.access_token)
   }
+
+  // Helper test function to login as a new non-admin user with the given username
+  // optionally seeding the user with the given points
+  pub async fn login_as_user(state: Arc<state::State>, username: &str, points: Option<i64>)
+    -> (i64, String)
+  {
+    let email = format!("{username}@foo.com");
+    let user_id = db::user::insert(state.db(), username, &email).await.unwrap();
+    let creds = crate::security::auth::hash_password("pass1").unwrap();
+    db::password::insert(state.db(), user_id, &creds.salt, &creds.hash).await.unwrap();
+    if let Some(value) = points {
+      db::point::insert(state.db(), value, user_id, 1).await.unwrap();
+    }
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/login")
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::LoginRequest { handle: username.to_string(), password: "pass1".to_string() }
+      )).unwrap())).unwrap();
+    let res = init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let login_response: model::LoginResponse = serde_json::from_slice(&bytes).unwrap();
+    (user_id, login_response.access_token)
+  }
 } 
\ No newline at end of file
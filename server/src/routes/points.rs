@@ -1,15 +1,27 @@
 use std::sync::Arc;
-use axum::{http::StatusCode, extract::{Path, Query, State}, response::IntoResponse};
+use axum::{http::StatusCode, extract::{Path, Query, State}, response::IntoResponse, Extension};
 use crate::{db, state, model, routes::Json, errors::Error};
 
 /// Create a new points
 /// 
 /// - POST handler for `/points`
+/// - Points are awarded by the caller when a Bearer token is sent
+/// - Points from admins are approved straight away, everyone else's are pending until reviewed
+/// - ***occurred_at*** may be given to back fill points, defaults to now
 pub async fn create(State(state): State<Arc<state::State>>,
-  Json(points): Json<model::CreatePoints>) -> Result<impl IntoResponse, Error>
+  claims: Option<Extension<model::JwtClaims>>, Json(points): Json<model::CreatePoints>)
+  -> Result<impl IntoResponse, Error>
 {
-  let id = db::point::insert(state.db(), points.value, points.user_id, points.action_id).await?;
+  let status = match &claims {
+    Some(Extension(x)) if x.has_role("admin") => model::PointsStatus::Approved,
+    _ => model::PointsStatus::Pending,
+  };
+  let awarded_by = claims.map(|Extension(x)| x.sub);
+  let id = db::point::insert_with(state.db(), &points, awarded_by, status).await?;
   let points = db::point::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::PointsCreated, id)
+    .with_user_id(points.user_id).with_data(&points));
+  state::goal::check_user(&state, points.user_id).await;
 
   Ok((StatusCode::CREATED, Json(serde_json::json!(points))))
 }
@@ -60,6 +72,56 @@ pub async fn sum(State(state): State<Arc<state::State>>,
   Ok(Json(db::point::sum_by_filter(state.db(), filter).await?))
 }
 
+/// Approve pending points in bulk
+///
+/// - POST handler for `/points/approve`
+/// - error on caller not being an admin
+/// - error on any of the points not being pending, in which case none are approved
+///
+/// #### Parameters
+/// - ***review*** - supports ***ids*** and ***note***
+pub async fn approve(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(review): Json<model::ReviewPoints>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(review_points(&state, &claims, &review, model::PointsStatus::Approved).await?))
+}
+
+/// Reject pending points in bulk
+///
+/// - POST handler for `/points/reject`
+/// - The ***note*** is visible to the owner of the points along with the rejected status
+/// - error on caller not being an admin
+/// - error on any of the points not being pending, in which case none are rejected
+///
+/// #### Parameters
+/// - ***review*** - supports ***ids*** and ***note***
+pub async fn reject(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(review): Json<model::ReviewPoints>)
+  -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(review_points(&state, &claims, &review, model::PointsStatus::Rejected).await?))
+}
+
+// Review the points as an admin and let clients know
+async fn review_points(state: &state::State, claims: &model::JwtClaims,
+  review: &model::ReviewPoints, status: model::PointsStatus) -> Result<Vec<model::Points>, Error>
+{
+  super::auth::require_admin(claims, "review points")?;
+  db::point::review(state.db(), &review.ids, status, Some(claims.sub), review.note.as_deref())
+    .await?;
+
+  let mut reviewed = vec![];
+  for id in review.ids.iter() {
+    let points = db::point::fetch_by_id(state.db(), *id).await?;
+    state.publish(model::Event::new(model::EventKind::PointsUpdated, points.id)
+      .with_user_id(points.user_id).with_data(&points));
+    state::goal::check_user(state, points.user_id).await;
+    reviewed.push(points);
+  }
+  Ok(reviewed)
+}
+
 /// Get specific points by id
 /// 
 /// - GET handler for `/points/{id}`
@@ -72,19 +134,59 @@ pub async fn get_by_id(State(state): State<Arc<state::State>>,
 /// Update specific points by id
 /// 
 /// - PUT handler for `/points/{id}`
+/// - Points edited by anyone other than an admin go back to pending for review
 pub async fn update_by_id(State(state): State<Arc<state::State>>,
-  Path(id): Path<i64>, Json(points): Json<model::UpdatePoints>) -> Result<impl IntoResponse, Error>
+  claims: Option<Extension<model::JwtClaims>>, Path(id): Path<i64>,
+  Json(points): Json<model::UpdatePoints>) -> Result<impl IntoResponse, Error>
 {
-  Ok(Json(db::point::update_by_id(state.db(), id, points.value).await?))
+  let status = match &claims {
+    Some(Extension(x)) if x.has_role("admin") => None,
+    _ => Some(model::PointsStatus::Pending),
+  };
+  db::point::update_by_id(state.db(), id, points.value, status).await?;
+  let points = db::point::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::PointsUpdated, id)
+    .with_user_id(points.user_id).with_data(&points));
+  state::goal::check_user(&state, points.user_id).await;
+
+  Ok(Json(()))
 }
 
 /// Delete specific points by id
 /// 
 /// - DELETE handler for `/points/{id}`
+/// - Moves the points to the trash where they can be restored from
 pub async fn delete_by_id(State(state): State<Arc<state::State>>,
   Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
 {
-  Ok(Json(db::point::delete_by_id(state.db(), id).await?))
+  let points = db::point::fetch_by_id(state.db(), id).await.ok();
+  db::point::delete_by_id(state.db(), id).await?;
+  if let Some(points) = points {
+    state.publish(model::Event::new(model::EventKind::PointsDeleted, id)
+      .with_user_id(points.user_id));
+    state::goal::check_user(&state, points.user_id).await;
+  }
+
+  Ok(Json(()))
+}
+
+/// Restore specific points from the trash
+/// 
+/// - POST handler for `/points/{id}/restore`
+/// - error on caller not being an admin
+/// - error on not found in the trash or the user still being in the trash
+pub async fn restore_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, super::trash::ADMIN_ONLY)?;
+  db::point::restore_by_id(state.db(), id).await?;
+  let points = db::point::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::PointsCreated, id)
+    .with_user_id(points.user_id).with_data(&points));
+  state::goal::check_user(&state, points.user_id).await;
+
+  Ok(Json(points))
 }
 
 #[cfg(test)]
@@ -98,6 +200,7 @@ mod tests
   use http_body_util::BodyExt;
   use tower::ServiceExt;
   use crate::{errors, routes, state};
+  use super::super::tests::login_as_admin;
 
   #[tokio::test]
   async fn test_delete_by_id() 
@@ -157,6 +260,40 @@ mod tests
     assert_eq!(points.value, points2);
   }
 
+  #[tokio::test]
+  async fn test_update_by_id_needs_review_unless_admin()
+  {
+    let state = state::test().await;
+    let (admin, access_token) = login_as_admin(state.clone()).await;
+    let id = db::point::insert(state.db(), 10, admin.id, 1).await.unwrap();
+
+    let update = |value: i64, token: Option<&str>| {
+      let mut req = Request::builder().method(Method::PUT)
+        .uri(format!("/api/points/{id}"))
+        .header(header::CONTENT_TYPE, "application/json");
+      if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+      }
+      req.body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::UpdatePoints { value, action_id: 1 })).unwrap())).unwrap()
+    };
+
+    // Admins keep the points approved
+    let res = routes::init(state.clone()).oneshot(update(20, Some(&access_token))).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let points = db::point::fetch_by_id(state.db(), id).await.unwrap();
+    assert_eq!((points.value, points.status), (20, model::PointsStatus::Approved));
+
+    // Anyone else sends the points back for review so they stop counting
+    let res = routes::init(state.clone()).oneshot(update(500, None)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let points = db::point::fetch_by_id(state.db(), id).await.unwrap();
+    assert_eq!((points.value, points.status), (500, model::PointsStatus::Pending));
+    assert_eq!(points.reviewed_by, None);
+    let filter = model::Filter::new().with_user_id(admin.id);
+    assert_eq!(db::point::sum_by_filter(state.db(), filter).await.unwrap(), 0);
+  }
+
   #[tokio::test]
   async fn test_get_all() 
   {
@@ -547,7 +684,8 @@ mod tests
       .uri("/api/points")
       .header(header::CONTENT_TYPE, "application/json")
       .body(Body::from(serde_json::to_vec(&serde_json::json!(
-        model::CreatePoints { value: points1, user_id: user_id, action_id: action_id }))
+        model::CreatePoints { value: points1, user_id: user_id, action_id: action_id,
+          occurred_at: None, kind: None }))
       .unwrap())).unwrap();
     let res = routes::init(state).oneshot(req).await.unwrap();
 
@@ -559,10 +697,139 @@ mod tests
     assert_eq!(points.value, points1);
     assert_eq!(points.user_id, user_id);
     assert_eq!(points.action_id, action_id);
+    assert_eq!(points.awarded_by, None);
+    assert!(points.occurred_at <= chrono::Local::now());
     assert!(points.created_at <= chrono::Local::now());
     assert!(points.updated_at <= chrono::Local::now());
   }
 
+  #[tokio::test]
+  async fn test_create_awarded_and_back_filled()
+  {
+    let state = state::test().await;
+    let (admin, access_token) = login_as_admin(state.clone()).await;
+    let yesterday = chrono::Local::now() - chrono::Duration::days(1);
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::CreatePoints { value: 10, user_id: admin.id, action_id: 1,
+          occurred_at: Some(yesterday), kind: None }))
+      .unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let points: model::Points = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(points.awarded_by, Some(admin.id));
+    assert_eq!(points.occurred_at.timestamp(), yesterday.timestamp());
+    assert!(points.created_at > yesterday);
+
+    // Date ranges sum by when the points occurred rather than when they were recorded
+    let start = yesterday - chrono::Duration::hours(1);
+    let end = yesterday + chrono::Duration::hours(1);
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/points/sum?user_id={}&start_date={}&end_date={}", admin.id,
+        start.to_rfc3339(), end.to_rfc3339()))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let sum: i64 = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(sum, 10);
+  }
+
+  #[tokio::test]
+  async fn test_create_pending_and_reject()
+  {
+    let state = state::test().await;
+    let (admin, access_token) = login_as_admin(state.clone()).await;
+
+    // Points without an admin token wait for review
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        model::CreatePoints { value: 10, user_id: admin.id, action_id: 1, occurred_at: None,
+          kind: None }))
+      .unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let points: model::Points = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(points.status, model::PointsStatus::Pending);
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/points/sum?user_id={}", admin.id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let sum: i64 = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(sum, 0);
+
+    let review = |token: Option<&str>| {
+      let mut req = Request::builder().method(Method::POST)
+        .uri("/api/points/reject")
+        .header(header::CONTENT_TYPE, "application/json");
+      if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+      }
+      req.body(Body::from(serde_json::to_vec(&serde_json::json!(
+        {"ids": [points.id], "note": "not this time"})).unwrap())).unwrap()
+    };
+    let res = routes::init(state.clone()).oneshot(review(None)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = routes::init(state.clone()).oneshot(review(Some(&access_token))).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/points?user_id={}&status=rejected", admin.id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let points: Vec<model::Points> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].note.as_deref(), Some("not this time"));
+    assert_eq!(points[0].reviewed_by, Some(admin.id));
+  }
+
+  #[tokio::test]
+  async fn test_create_with_kind_and_filter()
+  {
+    let state = state::test().await;
+    let (admin, access_token) = login_as_admin(state.clone()).await;
+
+    let create = |value: i64, kind: &str| Request::builder().method(Method::POST)
+      .uri("/api/points")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from(serde_json::to_vec(&serde_json::json!(
+        {"value": value, "user_id": admin.id, "action_id": 1, "kind": kind})).unwrap())).unwrap();
+    let res = routes::init(state.clone()).oneshot(create(5, "penalty")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let res = routes::init(state.clone()).oneshot(create(-5, "penalty")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let res = routes::init(state.clone()).oneshot(create(4, "adjustment")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    // Expiries, bonuses and transfers aren't kinds of points
+    for kind in ["expiry", "bonus", "transfer"] {
+      let res = routes::init(state.clone()).oneshot(create(-4, kind)).await.unwrap();
+      assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/points?user_id={}&kinds=penalty,award", admin.id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let points: Vec<model::Points> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!((points[0].value, points[0].kind), (-5, model::PointsKind::Penalty));
+  }
+
   #[tokio::test]
   async fn test_create_failure_no_body() 
   {
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use axum::{
+  extract::{Path, Query, State}, http::StatusCode, response::IntoResponse, Extension,
+};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// Decisions on redemptions are made by admins
+const ADMIN_ONLY: &str = "decide on redemptions";
+
+/// Request a redemption
+///
+/// - POST handler for `/redemptions`
+/// - Holds the value from the user's available balance until decided
+/// - Requests for the caller unless ***user_id*** is given
+/// - error on caller requesting for someone else without being an admin
+/// - error on the item being unavailable or the available balance not covering the value
+///
+/// #### Parameters
+/// - ***redemption*** - supports ***user_id***, ***item_id*** or ***value***, and ***reason***
+pub async fn create(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Json(redemption): Json<model::CreateRedemption>)
+  -> Result<impl IntoResponse, Error>
+{
+  let user_id = redemption.user_id.unwrap_or(claims.sub);
+  if user_id != claims.sub && !claims.has_role("admin") {
+    let msg = format!("User '{}' is not allowed to request redemptions for others",
+      claims.username);
+    log::warn!("{msg}");
+    return Err(Error::http(StatusCode::FORBIDDEN, &msg));
+  }
+
+  let id = db::redemption::insert(state.db(), user_id, &redemption).await?;
+  let redemption = db::redemption::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::RedemptionCreated, id)
+    .with_user_id(redemption.user_id).with_data(&redemption));
+
+  Ok((StatusCode::CREATED, Json(serde_json::json!(redemption))))
+}
+
+/// Get redemptions newest first
+///
+/// - GET handler for `/redemptions?user_id={id}&status={status}`
+///
+/// #### Parameters
+/// - ***query*** - supports ***user_id*** and ***status***
+pub async fn get(State(state): State<Arc<state::State>>,
+  Query(query): Query<model::RedemptionQuery>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::redemption::fetch_by_query(state.db(), &query).await?))
+}
+
+/// Get specific redemption by id
+///
+/// - GET handler for `/redemptions/{id}`
+pub async fn get_by_id(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::redemption::fetch_by_id(state.db(), id).await?))
+}
+
+/// Approve specific redemption by id
+///
+/// - POST handler for `/redemptions/{id}/approve`
+/// - Creates the reward for the held value
+/// - error on caller not being an admin
+/// - error on the redemption not being requested or the reward being rejected
+///
+/// #### Parameters
+/// - ***decision*** - supports ***note***
+pub async fn approve(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(decision): Json<model::RedemptionDecision>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let redemption = decide(&state, &claims, id, model::RedemptionStatus::Approved,
+    decision.note.as_deref()).await?;
+  if let Some(reward_id) = redemption.reward_id {
+    let reward = db::reward::fetch_by_id(state.db(), reward_id).await?;
+    state.publish(model::Event::new(model::EventKind::RewardCreated, reward_id)
+      .with_user_id(reward.user_id).with_data(&reward));
+  }
+  Ok(Json(redemption))
+}
+
+/// Deny specific redemption by id
+///
+/// - POST handler for `/redemptions/{id}/deny`
+/// - Releases the held value
+/// - error on caller not being an admin
+/// - error on the redemption not being requested
+///
+/// #### Parameters
+/// - ***decision*** - supports ***note***
+pub async fn deny(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>,
+  Json(decision): Json<model::RedemptionDecision>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(decide(&state, &claims, id, model::RedemptionStatus::Denied,
+    decision.note.as_deref()).await?))
+}
+
+/// Cancel specific redemption by id
+///
+/// - POST handler for `/redemptions/{id}/cancel`
+/// - Releases the held value
+/// - error on caller not being the requesting user or an admin
+/// - error on the redemption not being requested
+pub async fn cancel(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  let redemption = db::redemption::fetch_by_id(state.db(), id).await?;
+  if redemption.user_id != claims.sub && !claims.has_role("admin") {
+    let msg = format!("User '{}' is not allowed to cancel redemptions of others", claims.username);
+    log::warn!("{msg}");
+    return Err(Error::http(StatusCode::FORBIDDEN, &msg));
+  }
+  Ok(Json(decide(&state, &claims, id, model::RedemptionStatus::Cancelled, None).await?))
+}
+
+/// Fulfill specific redemption by id
+///
+/// - POST handler for `/redemptions/{id}/fulfill`
+/// - Marks the approved reward as handed over
+/// - error on caller not being an admin
+/// - error on the redemption not being approved
+pub async fn fulfill(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(decide(&state, &claims, id, model::RedemptionStatus::Fulfilled, None).await?))
+}
+
+// Move the redemption on to the given status and let clients know
+async fn decide(state: &state::State, claims: &model::JwtClaims, id: i64,
+  status: model::RedemptionStatus, note: Option<&str>) -> Result<model::Redemption, Error>
+{
+  db::redemption::update_status(state.db(), id, status, Some(claims.sub), note).await?;
+  let redemption = db::redemption::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::RedemptionUpdated, id)
+    .with_user_id(redemption.user_id).with_data(&redemption));
+  state::goal::check_user(state, redemption.user_id).await;
+  Ok(redemption)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
+  use axum::{body::Body, http::{header, Method, Request}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  // Helper to post the given body with the given token
+  fn post(uri: &str, token: &str, body: serde_json::Value) -> Request<Body>
+  {
+    Request::builder().method(Method::POST)
+      .uri(uri)
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {token}"))
+      .body(Body::from(serde_json::to_vec(&body).unwrap())).unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_request_approve_and_fulfill()
+  {
+    let state = state::test().await;
+    let (user_id, user_token) = login_as_user(state.clone(), "user1", Some(10)).await;
+    let (_, admin_token) = login_as_admin(state.clone()).await;
+
+    let body = serde_json::json!({"value": 4, "reason": "movie night"});
+    let res = routes::init(state.clone()).oneshot(post("/api/redemptions", &user_token, body))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let redemption: model::Redemption = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((redemption.user_id, redemption.value, redemption.reason.as_str()),
+      (user_id, 4, "movie night"));
+    assert_eq!(redemption.status, model::RedemptionStatus::Requested);
+
+    // Only admins decide
+    let uri = format!("/api/redemptions/{}/approve", redemption.id);
+    let res = routes::init(state.clone()).oneshot(post(&uri, &user_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let body = serde_json::json!({"note": "enjoy"});
+    let res = routes::init(state.clone()).oneshot(post(&uri, &admin_token, body)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let redemption: model::Redemption = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(redemption.status, model::RedemptionStatus::Approved);
+    assert!(redemption.reward_id.is_some());
+
+    let uri = format!("/api/redemptions/{}/fulfill", redemption.id);
+    let res = routes::init(state.clone()).oneshot(post(&uri, &admin_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/redemptions?user_id={user_id}&status=fulfilled"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let redemptions: Vec<model::Redemption> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(redemptions.len(), 1);
+  }
+
+  #[tokio::test]
+  async fn test_cancel_own_only()
+  {
+    let state = state::test().await;
+    let (_, user_token) = login_as_user(state.clone(), "user1", Some(10)).await;
+    let id = db::redemption::insert(state.db(), 1, &model::CreateRedemption {
+      value: Some(1), ..Default::default() }).await.unwrap_err();
+    assert_eq!(id.to_http().status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    db::point::insert(state.db(), 5, 1, 1).await.unwrap();
+    let admins = db::redemption::insert(state.db(), 1, &model::CreateRedemption {
+      value: Some(1), ..Default::default() }).await.unwrap();
+    let uri = format!("/api/redemptions/{admins}/cancel");
+    let res = routes::init(state.clone()).oneshot(post(&uri, &user_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = routes::init(state.clone()).oneshot(post("/api/redemptions", &user_token,
+      serde_json::json!({"value": 3}))).await.unwrap();
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let redemption: model::Redemption = serde_json::from_slice(&bytes).unwrap();
+    let uri = format!("/api/redemptions/{}/cancel", redemption.id);
+    let res = routes::init(state.clone()).oneshot(post(&uri, &user_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = routes::init(state.clone()).oneshot(post(&uri, &user_token, serde_json::json!({})))
+      .await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  }
+}
@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use axum::{http::StatusCode, extract::{Path, Query, State}, response::IntoResponse};
+use axum::{http::StatusCode, extract::{Path, Query, State}, response::IntoResponse, Extension};
 use crate::{db, state, model, routes::Json, errors::Error};
 
 /// Create a new reward
@@ -10,29 +10,38 @@ pub async fn create(State(state): State<Arc<state::State>>,
 {
   let id = db::reward::insert(state.db(), reward.value, reward.user_id).await?;
   let reward = db::reward::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::RewardCreated, id)
+    .with_user_id(reward.user_id).with_data(&reward));
 
   Ok((StatusCode::CREATED, Json(serde_json::json!(reward))))
 }
 
-/// Get all rewards or filter by user id
+/// Get all rewards or filter by criteria
 /// 
 /// - GET handler for `/rewards`
 /// - GET handler for `/rewards?user_id={id}`
+/// - GET handler for `/rewards?user_ids={id},{id}&value_gt={value}&start_date={start}`
+/// - GET handler for `/rewards?item_id={id}`
+/// - error on invalid filter
+/// 
+/// #### Parameters
+/// - ***filter*** supports ***user_id***, ***user_ids***, ***item_id***, ***value_gt***,
+///   ***value_lt***, ***start_date***, ***end_date***
 pub async fn get(State(state): State<Arc<state::State>>,
   Query(filter): Query<model::Filter>) -> Result<impl IntoResponse, Error>
 {
-  // Filter by user_id
-  if let Some(user_id) = filter.user_id {
-    return Ok(Json(db::reward::fetch_by_user_id(state.db(), user_id).await?));
+  // Filter based on the given filter params
+  if filter.any_rewards_filters() {
+    return Ok(Json(db::reward::fetch_by_filter(state.db(), filter).await?));
   }
 
-  // Fetch all rewards if no user_id is provided
+  // Fetch all rewards if no filter is provided
   Ok(Json(db::reward::fetch_all(state.db()).await?))
 }
 
 /// Get sum of rewards based on filter criteria
 /// 
-/// - GET handler for `/rewards/sum?user_id={id}&action_id={aid}&start_date={start}&end_date={end}`
+/// - GET handler for `/rewards/sum?user_id={id}&value_gt={value}&start_date={start}&end_date={end}`
 /// - Supports ISO 8601 date time range
 ///   - Start defines the oldest date to include in the sum
 ///   - End defines the newest date to include in the sum
@@ -65,16 +74,49 @@ pub async fn get_by_id(State(state): State<Arc<state::State>>,
 pub async fn update_by_id(State(state): State<Arc<state::State>>,
   Path(id): Path<i64>, Json(reward): Json<model::UpdateReward>) -> Result<impl IntoResponse, Error>
 {
-  Ok(Json(db::reward::update_by_id(state.db(), id, reward.value).await?))
+  db::reward::update_by_id(state.db(), id, reward.value).await?;
+  let reward = db::reward::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::RewardUpdated, id)
+    .with_user_id(reward.user_id).with_data(&reward));
+  state::goal::check_user(&state, reward.user_id).await;
+
+  Ok(Json(()))
 }
 
 /// Delete specific reward by id
 /// 
 /// - DELETE handler for `/rewards/{id}`
+/// - Moves the reward to the trash where it can be restored from
 pub async fn delete_by_id(State(state): State<Arc<state::State>>,
   Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
 {
-  Ok(Json(db::reward::delete_by_id(state.db(), id).await?))
+  let reward = db::reward::fetch_by_id(state.db(), id).await.ok();
+  db::reward::delete_by_id(state.db(), id).await?;
+  if let Some(reward) = reward {
+    state.publish(model::Event::new(model::EventKind::RewardDeleted, id)
+      .with_user_id(reward.user_id));
+    state::goal::check_user(&state, reward.user_id).await;
+  }
+
+  Ok(Json(()))
+}
+
+/// Restore specific reward from the trash
+/// 
+/// - POST handler for `/rewards/{id}/restore`
+/// - error on caller not being an admin
+/// - error on not found in the trash or the user still being in the trash
+pub async fn restore_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, super::trash::ADMIN_ONLY)?;
+  db::reward::restore_by_id(state.db(), id).await?;
+  let reward = db::reward::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::RewardCreated, id)
+    .with_user_id(reward.user_id).with_data(&reward));
+
+  Ok(Json(reward))
 }
 
 #[cfg(test)]
@@ -173,6 +215,35 @@ mod tests
     assert_eq!(err.kind, errors::ErrorKind::NotFound);
   }
 
+  #[tokio::test]
+  async fn test_delete_by_id_publishes_event()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let id = db::reward::insert(state.db(), 10, user_id).await.unwrap();
+    let mut events = state.subscribe();
+
+    let req = Request::builder().method(Method::DELETE)
+      .uri(format!("/api/rewards/{}", id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.kind, model::EventKind::RewardDeleted);
+    assert_eq!(event.id, id);
+    assert_eq!(event.user_id, Some(user_id));
+    assert!(event.data.is_none());
+
+    // Deleting again is still fine but there is nothing to announce
+    let req = Request::builder().method(Method::DELETE)
+      .uri(format!("/api/rewards/{}", id))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(events.try_recv().is_err());
+  }
+
   #[tokio::test]
   async fn test_update_by_id() 
   {
@@ -290,6 +361,34 @@ mod tests
     assert!(rewards[1].updated_at <= chrono::Local::now());
   }
 
+  #[tokio::test]
+  async fn test_get_by_value_and_user_ids() 
+  {
+    let state = state::test().await;
+    let user_id_1 = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let user_id_2 = db::user::insert(state.db(), "user2", "user2@foo.com").await.unwrap();
+    let user_id_3 = db::user::insert(state.db(), "user3", "user3@foo.com").await.unwrap();
+    db::reward::insert(state.db(), 10, user_id_1).await.unwrap();
+    db::reward::insert(state.db(), 30, user_id_2).await.unwrap();
+    db::reward::insert(state.db(), 40, user_id_3).await.unwrap();
+    db::reward::insert(state.db(), 50, user_id_1).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/rewards?user_ids={user_id_1},{user_id_2}&value_gt=20"))
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let rewards: Vec<model::Reward> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(rewards.len(), 2);
+    assert_eq!(rewards[0].value, 30);
+    assert_eq!(rewards[0].user_id, user_id_2);
+    assert_eq!(rewards[1].value, 50);
+    assert_eq!(rewards[1].user_id, user_id_1);
+  }
+
   #[tokio::test]
   async fn test_get_by_id_success() 
   {
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+use axum::{extract::{Query, State}, response::IntoResponse};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+/// Search actions, categories and users
+///
+/// - GET handler for `/search?q={text}`
+/// - GET handler for `/search?q={text}&limit={limit}`
+/// - Each word is prefix matched so partial input works for search-as-you-type
+/// - Returns hits of all kinds ranked together with the best matches first
+/// - error on empty query or invalid limit
+///
+/// #### Parameters
+/// - ***query*** - supports ***q*** and ***limit***
+pub async fn get(State(state): State<Arc<state::State>>,
+  Query(query): Query<model::SearchQuery>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::search::search(state.db(), &query).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode}
+  };
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::{db, model, routes, state};
+
+  #[tokio::test]
+  async fn test_get_success()
+  {
+    let state = state::test().await;
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("Brush teeth")).await.unwrap();
+    db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("Make bed")).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/search?q=tee")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let hits: Vec<model::SearchHit> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, model::SearchKind::Action);
+    assert_eq!(hits[0].id, action_id);
+    assert_eq!(hits[0].text, "Brush teeth");
+  }
+
+  #[tokio::test]
+  async fn test_get_failure_missing_query()
+  {
+    let state = state::test().await;
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/search")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+  }
+}
@@ -0,0 +1,356 @@
+use std::sync::Arc;
+use axum::{
+  body::{Body, Bytes}, extract::{Query, State}, http::{header, HeaderMap, StatusCode},
+  response::{IntoResponse, Response}, Extension,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio_stream::{Stream, StreamExt};
+use crate::{db, state, model, errors, routes::Json, errors::Error};
+
+// Imports write on behalf of any user so they are limited to admins
+const ADMIN_ONLY: &str = "import";
+
+/// Export points as CSV or JSON
+///
+/// - GET handler for `/export/points?format={csv|json}`
+/// - Rows are streamed as they are read from the database
+/// - Supports the same filter params as `/points` e.g. `user_id`, `start_date`
+/// - error on invalid filter
+///
+/// #### Parameters
+/// - ***filter*** - filter to apply
+/// - ***query*** - supports ***format*** which defaults to CSV
+pub async fn export_points(State(state): State<Arc<state::State>>,
+  Query(filter): Query<model::Filter>, Query(query): Query<model::ExportQuery>)
+  -> Result<impl IntoResponse, Error>
+{
+  let records = db::transfer::export_points(state.db(), filter).await?;
+  Ok(stream(records, query.format, "points"))
+}
+
+/// Export rewards as CSV or JSON
+///
+/// - GET handler for `/export/rewards?format={csv|json}`
+/// - Rows are streamed as they are read from the database
+/// - Supports the same filter params as `/rewards` e.g. `user_id`, `start_date`
+/// - error on invalid filter
+///
+/// #### Parameters
+/// - ***filter*** - filter to apply
+/// - ***query*** - supports ***format*** which defaults to CSV
+pub async fn export_rewards(State(state): State<Arc<state::State>>,
+  Query(filter): Query<model::Filter>, Query(query): Query<model::ExportQuery>)
+  -> Result<impl IntoResponse, Error>
+{
+  let records = db::transfer::export_rewards(state.db(), filter).await?;
+  Ok(stream(records, query.format, "rewards"))
+}
+
+/// Import points from CSV or JSON
+///
+/// - POST handler for `/import/points?dry_run={bool}`
+/// - Body is CSV with a header row when sent as `text/csv` else a JSON array of rows
+/// - Rows use the same fields as the export, users and actions are resolved by id or name
+/// - Imported points are awarded by the caller and keep the exported status, approved when the
+///   rows don't give one
+/// - error on caller not being an admin
+/// - Returns 201 with the committed rows, 200 with the resolved rows for a dry run or 422 with the
+///   row errors in which case nothing was committed
+///
+/// #### Parameters
+/// - ***query*** - supports ***dry_run***
+pub async fn import_points(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Query(query): Query<model::ImportQuery>,
+  headers: HeaderMap, body: Bytes)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let rows = parse::<model::PointsRecord>(&headers, &body)?;
+  let report = db::transfer::import_points(state.db(), rows, Some(claims.sub),
+    model::PointsStatus::Approved, query.dry_run).await?;
+  if report.imported > 0 {
+    for record in report.rows.iter() {
+      state.publish(model::Event::new(model::EventKind::PointsCreated, record.id.unwrap_or_default())
+        .with_user_id(record.user_id.unwrap_or_default()).with_data(record));
+    }
+    let user_ids: std::collections::BTreeSet<_> = report.rows.iter()
+      .filter_map(|x| x.user_id).collect();
+    for user_id in user_ids {
+      state::goal::check_user(&state, user_id).await;
+    }
+  }
+
+  Ok((status(&report), Json(report)))
+}
+
+/// Import rewards from CSV or JSON
+///
+/// - POST handler for `/import/rewards?dry_run={bool}`
+/// - Body is CSV with a header row when sent as `text/csv` else a JSON array of rows
+/// - Rows use the same fields as the export, users are resolved by id or name
+/// - error on caller not being an admin
+/// - Returns 201 with the committed rows, 200 with the resolved rows for a dry run or 422 with the
+///   row errors in which case nothing was committed
+///
+/// #### Parameters
+/// - ***query*** - supports ***dry_run***
+pub async fn import_rewards(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Query(query): Query<model::ImportQuery>,
+  headers: HeaderMap, body: Bytes) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  let rows = parse::<model::RewardRecord>(&headers, &body)?;
+  let report = db::transfer::import_rewards(state.db(), rows, query.dry_run).await?;
+  if report.imported > 0 {
+    for record in report.rows.iter() {
+      state.publish(model::Event::new(model::EventKind::RewardCreated, record.id.unwrap_or_default())
+        .with_user_id(record.user_id.unwrap_or_default()).with_data(record));
+    }
+  }
+
+  Ok((status(&report), Json(report)))
+}
+
+// Encode the records as they arrive into a streaming response body
+fn stream<T>(records: impl Stream<Item = errors::Result<T>> + Send + 'static,
+  format: model::TransferFormat, name: &str) -> Response
+where
+  T: Serialize + Send + 'static,
+{
+  let mut first = true;
+  let rows = records.map(move |record| {
+    let record = record.map_err(|e| std::io::Error::other(e.msg))?;
+    let chunk = encode(&record, format, first)?;
+    first = false;
+    Ok::<_, std::io::Error>(chunk)
+  });
+
+  // JSON rows are wrapped in an array while CSV rows are written as is
+  let body = match format {
+    model::TransferFormat::Csv => Body::from_stream(rows),
+    model::TransferFormat::Json => Body::from_stream(
+      tokio_stream::once(Ok(b"[".to_vec())).chain(rows).chain(tokio_stream::once(Ok(b"]".to_vec())))),
+  };
+  let disposition = format!("attachment; filename=\"{name}.{}\"", format.extension());
+  ([(header::CONTENT_TYPE, format.content_type().to_string()),
+    (header::CONTENT_DISPOSITION, disposition)], body).into_response()
+}
+
+// Encode a single record including the CSV header or JSON separator as needed
+fn encode<T: Serialize>(record: &T, format: model::TransferFormat, first: bool)
+  -> std::io::Result<Vec<u8>>
+{
+  match format {
+    model::TransferFormat::Csv => {
+      let mut writer = csv::WriterBuilder::new().has_headers(first).from_writer(vec![]);
+      writer.serialize(record).map_err(std::io::Error::other)?;
+      writer.into_inner().map_err(|e| std::io::Error::other(e.to_string()))
+    },
+    model::TransferFormat::Json => {
+      let mut chunk = if first { vec![] } else { b",".to_vec() };
+      serde_json::to_writer(&mut chunk, record)?;
+      Ok(chunk)
+    },
+  }
+}
+
+// Parse the body into rows keeping each row's problem rather than failing the whole body
+fn parse<T: DeserializeOwned>(headers: &HeaderMap, body: &[u8])
+  -> Result<Vec<Result<T, String>>, Error>
+{
+  let content_type = headers.get(header::CONTENT_TYPE).and_then(|x| x.to_str().ok())
+    .unwrap_or_default();
+  if content_type.starts_with(model::TransferFormat::Csv.content_type()) {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(body);
+    return Ok(reader.deserialize::<T>().map(|x| x.map_err(|e| e.to_string())).collect());
+  }
+  if content_type.starts_with(model::TransferFormat::Json.content_type()) {
+    let values: Vec<serde_json::Value> = serde_json::from_slice(body).map_err(|e| {
+      let msg = format!("Import body must be a JSON array of rows: {e}");
+      log::warn!("{msg}");
+      Error::http(StatusCode::UNPROCESSABLE_ENTITY, &msg)
+    })?;
+    return Ok(values.into_iter().map(|x| serde_json::from_value(x).map_err(|e| e.to_string()))
+      .collect());
+  }
+
+  let msg = format!("Import body must be sent as '{}' or '{}'",
+    model::TransferFormat::Csv.content_type(), model::TransferFormat::Json.content_type());
+  log::warn!("{msg}");
+  Err(Error::http(StatusCode::UNSUPPORTED_MEDIA_TYPE, &msg))
+}
+
+// Pick the response status for the import outcome
+fn status<T>(report: &model::ImportReport<T>) -> StatusCode
+{
+  if !report.errors.is_empty() {
+    StatusCode::UNPROCESSABLE_ENTITY
+  } else if report.dry_run {
+    StatusCode::OK
+  } else {
+    StatusCode::CREATED
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::{*, super::tests::{login_as_admin, login_as_user}};
+  use axum::http::{Method, Request};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  // Helper to read the response body as a string
+  async fn body(res: Response) -> String
+  {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn test_export_points_csv_and_json()
+  {
+    let state = state::test().await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let action_id = db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    db::point::insert(state.db(), 10, user_id, action_id).await.unwrap();
+    db::point::insert(state.db(), 20, user_id, 1).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/export/points?user_id={user_id}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "text/csv");
+    assert_eq!(res.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+      "attachment; filename=\"points.csv\"");
+    let csv = body(res).await;
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0],
+      "id,value,kind,status,user_id,username,action_id,action,occurred_at,created_at");
+    assert!(lines[1].starts_with(
+      &format!("1,10,award,approved,{user_id},user1,{action_id},action1,")));
+    assert!(lines[2].starts_with(&format!("2,20,award,approved,{user_id},user1,1,Unspecified,")));
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/export/points?format=json&value_gt=15")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "application/json");
+    let records: Vec<model::PointsRecord> = serde_json::from_str(&body(res).await).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].value, 20);
+  }
+
+  #[tokio::test]
+  async fn test_export_rewards_empty_json()
+  {
+    let state = state::test().await;
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/export/rewards?format=json")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body(res).await, "[]");
+  }
+
+  #[tokio::test]
+  async fn test_import_points_csv_round_trip()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::action::insert(state.db(), &model::CreateAction::new()
+      .with_desc("action1")).await.unwrap();
+    let csv = "value,username,action,created_at\n\
+      10,user1,action1,2025-01-02T03:04:05-07:00\n\
+      20, user1@foo.com ,,\n";
+
+    // Dry run previews the resolved rows
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/import/points?dry_run=true")
+      .header(header::CONTENT_TYPE, "text/csv")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from(csv)).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let report: model::ImportReport<model::PointsRecord> =
+      serde_json::from_str(&body(res).await).unwrap();
+    assert_eq!(report.total, 2);
+    assert_eq!(report.rows[1].user_id, Some(user_id));
+    assert_eq!(db::point::fetch_all(state.db()).await.unwrap().len(), 0);
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/import/points")
+      .header(header::CONTENT_TYPE, "text/csv")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from(csv)).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let points = db::point::fetch_all(state.db()).await.unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].created_at.to_rfc3339(),
+      chrono::DateTime::parse_from_rfc3339("2025-01-02T03:04:05-07:00").unwrap()
+        .with_timezone(&chrono::Local).to_rfc3339());
+  }
+
+  #[tokio::test]
+  async fn test_import_rewards_json_reports_row_errors()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/import/rewards")
+      .header(header::CONTENT_TYPE, "application/json")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from(r#"[{"value":5,"username":"user1"},{"value":"x","username":"user1"}]"#))
+      .unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let report: model::ImportReport<model::RewardRecord> =
+      serde_json::from_str(&body(res).await).unwrap();
+    assert_eq!(report.imported, 0);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].row, 2);
+    assert_eq!(db::reward::fetch_all(state.db()).await.unwrap().len(), 0);
+  }
+
+  #[tokio::test]
+  async fn test_import_failure_without_admin_or_content_type()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    let (_, user_token) = login_as_user(state.clone(), "user1", None).await;
+
+    // Anonymous callers and non-admins are both turned away
+    for (uri, token) in [("/api/import/points", None), ("/api/import/points", Some(&user_token)),
+      ("/api/import/rewards", Some(&user_token))]
+    {
+      let mut req = Request::builder().method(Method::POST)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "text/csv");
+      if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+      }
+      let res = routes::init(state.clone())
+        .oneshot(req.body(Body::from("value,user_id\n1,1\n")).unwrap()).await.unwrap();
+      assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+    assert!(db::point::fetch_all(state.db()).await.unwrap().is_empty());
+
+    let req = Request::builder().method(Method::POST)
+      .uri("/api/import/points")
+      .header(header::CONTENT_TYPE, "text/plain")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::from("value,user_id\n1,1\n")).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+  }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use axum::{extract::State, response::IntoResponse, Extension};
+use crate::{db, state, model, routes::Json, errors::Error};
+
+// Listing and restoring the trash reaches everyone's deleted records so it's for admins
+pub(crate) const ADMIN_ONLY: &str = "list or restore the trash";
+
+/// Get everything in the trash
+///
+/// - GET handler for `/trash`
+/// - Lists deleted users, actions, points and rewards most recently deleted first
+/// - Entries are restored with `POST /{kind}/{id}/restore` or purged after the trash retention
+/// - error on caller not being an admin
+pub async fn get(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>) -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, ADMIN_ONLY)?;
+  Ok(Json(db::trash::fetch(state.db()).await?))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::super::tests::{login_as_admin, login_as_user};
+  use axum::{body::Body, http::{header, Method, Request, StatusCode}};
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::{db, model, routes, state};
+
+  #[tokio::test]
+  async fn test_delete_list_and_restore()
+  {
+    let state = state::test().await;
+    let (_, access_token) = login_as_admin(state.clone()).await;
+    let user_id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    let points_id = db::point::insert(state.db(), 10, user_id, 1).await.unwrap();
+
+    let req = Request::builder().method(Method::DELETE)
+      .uri(format!("/api/users/{user_id}"))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/trash")
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let trash: model::Trash = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(trash.users.len(), 1);
+    assert!(trash.users[0].deleted_at.is_some());
+    assert_eq!(trash.points.len(), 1);
+    assert_eq!(trash.points[0].id, points_id);
+
+    // Points wait on their user
+    let req = Request::builder().method(Method::POST)
+      .uri(format!("/api/points/{points_id}/restore"))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = Request::builder().method(Method::POST)
+      .uri(format!("/api/users/{user_id}/restore"))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let user: model::User = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(user.id, user_id);
+    assert!(user.deleted_at.is_none());
+    assert_eq!(db::point::fetch_by_id(state.db(), points_id).await.unwrap().value, 10);
+
+    let req = Request::builder().method(Method::POST)
+      .uri(format!("/api/users/{user_id}/restore"))
+      .header(header::AUTHORIZATION, format!("Bearer {access_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  }
+
+  #[tokio::test]
+  async fn test_get_fails_without_login()
+  {
+    let state = state::test().await;
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/trash")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+  }
+
+  #[tokio::test]
+  async fn test_get_and_restore_fail_for_non_admin()
+  {
+    let state = state::test().await;
+    let (_, user_token) = login_as_user(state.clone(), "user1", None).await;
+    let other_id = db::user::insert(state.db(), "user2", "user2@foo.com").await.unwrap();
+    let points_id = db::point::insert(state.db(), 10, other_id, 1).await.unwrap();
+    db::point::delete_by_id(state.db(), points_id).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/trash")
+      .header(header::AUTHORIZATION, format!("Bearer {user_token}"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    for uri in [format!("/api/users/{other_id}/restore"), "/api/actions/1/restore".into(),
+      format!("/api/points/{points_id}/restore"), "/api/rewards/1/restore".into()]
+    {
+      let req = Request::builder().method(Method::POST)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {user_token}"))
+        .body(Body::empty()).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+    assert!(db::point::fetch_by_id(state.db(), points_id).await.is_err());
+  }
+}
@@ -13,6 +13,8 @@ pub async fn create(State(state): State<Arc<state::State>>,
 {
   let id = db::user::insert(state.db(), &user.username, &user.email).await?;
   let user = db::user::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::UserCreated, id)
+    .with_user_id(id).with_data(&user));
 
   Ok((StatusCode::CREATED, Json(serde_json::json!(user))))
 }
@@ -42,6 +44,80 @@ pub async fn get_roles(State(state): State<Arc<state::State>>,
   Ok(Json(db::user::roles(state.db(), id).await?))
 }
 
+/// Get the earned, spent and available points for specific user by id
+/// 
+/// - GET handler for `/users/{id}/balance`
+/// - Excludes points and rewards in the trash
+pub async fn get_balance(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::ledger::balance(state.db(), id).await?))
+}
+
+/// Get the ledger of points, rewards and expiries for specific user by id
+/// 
+/// - GET handler for `/users/{id}/ledger?cursor={cursor}&limit={n}`
+/// - Returns entries newest first with the running balance after each entry
+/// - The ***next_cursor*** of a page fetches the next older page
+/// - error on invalid cursor
+/// 
+/// #### Parameters
+/// - ***query*** - supports ***cursor*** and ***limit***
+pub async fn get_ledger(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>, Query(query): Query<model::LedgerQuery>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::ledger::fetch_by_user_id(state.db(), id, &query).await?))
+}
+
+/// Get the chores specific user by id still has to do today
+/// 
+/// - GET handler for `/users/{id}/due`
+/// - Chores are done once the user has points for the chore's action today
+pub async fn get_due(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  let today = chrono::Local::now().date_naive();
+  Ok(Json(db::chore::fetch_due(state.db(), id, today).await?))
+}
+
+/// Get the streaks of specific user by id
+/// 
+/// - GET handler for `/users/{id}/streaks`
+/// - Streaks are kept for each action and category the user logged points for
+/// - Days are counted in the household timezone
+pub async fn get_streaks(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::streak::fetch_by_user_id(state.db(), id).await?))
+}
+
+/// Get the badges specific user by id has earned
+/// 
+/// - GET handler for `/users/{id}/badges`
+/// - Badges are listed in the order they were earned
+pub async fn get_badges(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
+{
+  Ok(Json(db::badge::fetch_by_user_id(state.db(), id).await?))
+}
+
+/// Get the points of specific user by id that will expire soon unless spent first
+/// 
+/// - GET handler for `/users/{id}/expiring?days={n}`
+/// - Looks 14 days ahead by default, nothing expires when no points expiry is configured
+/// - error on days less than one
+/// 
+/// #### Parameters
+/// - ***query*** - supports ***days***
+pub async fn get_expiring(State(state): State<Arc<state::State>>,
+  Path(id): Path<i64>, Query(query): Query<model::ExpiringQuery>)
+  -> Result<impl IntoResponse, Error>
+{
+  let config = state.config();
+  Ok(Json(db::expiry::fetch_expiring(state.db(), id, config.points_expiry_days,
+    config.points_expiry_policy, &query).await?))
+}
+
 
 /// Get specific user by id
 /// 
@@ -58,17 +134,48 @@ pub async fn get_by_id(State(state): State<Arc<state::State>>,
 pub async fn update_by_id(State(state): State<Arc<state::State>>,
   Path(id): Path<i64>, Json(user): Json<model::UpdateUser>) -> Result<impl IntoResponse, Error>
 {
-  Ok(Json(db::user::update_by_id(state.db(), id, user.username.as_deref(),
-    user.email.as_deref()).await?))
+  db::user::update_by_id(state.db(), id, user.username.as_deref(), user.email.as_deref()).await?;
+  let user = db::user::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::UserUpdated, id)
+    .with_user_id(id).with_data(&user));
+
+  Ok(Json(()))
 }
 
 /// Delete specific user by id
 /// 
 /// - DELETE handler for `/users/{id}`
+/// - Moves the user along with their points and rewards to the trash where they can be restored
 pub async fn delete_by_id(State(state): State<Arc<state::State>>,
   Path(id): Path<i64>) -> Result<impl IntoResponse, Error>
 {
-  Ok(Json(db::user::delete_by_id(state.db(), id).await?))
+  let user = db::user::fetch_by_id(state.db(), id).await.ok();
+  db::user::delete_by_id(state.db(), id).await?;
+  if user.is_some() {
+    state.publish(model::Event::new(model::EventKind::UserDeleted, id).with_user_id(id));
+  }
+
+  Ok(Json(()))
+}
+
+/// Restore specific user from the trash
+/// 
+/// - POST handler for `/users/{id}/restore`
+/// - Restores the points and rewards deleted along with the user
+/// - error on caller not being an admin
+/// - error on not found in the trash
+pub async fn restore_by_id(State(state): State<Arc<state::State>>,
+  Extension(claims): Extension<model::JwtClaims>, Path(id): Path<i64>)
+  -> Result<impl IntoResponse, Error>
+{
+  super::auth::require_admin(&claims, super::trash::ADMIN_ONLY)?;
+  db::user::restore_by_id(state.db(), id).await?;
+  let user = db::user::fetch_by_id(state.db(), id).await?;
+  state.publish(model::Event::new(model::EventKind::UserCreated, id)
+    .with_user_id(id).with_data(&user));
+  state::goal::check_user(&state, id).await;
+
+  Ok(Json(user))
 }
 
 #[cfg(test)]
@@ -236,6 +343,100 @@ mod tests
     assert_eq!(roles[1].name, "user");
   }
 
+  #[tokio::test]
+  async fn test_get_balance_and_ledger() {
+    let state = state::test().await;
+    let id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 10, id, 1).await.unwrap();
+    db::point::insert(state.db(), 5, id, 1).await.unwrap();
+    db::reward::insert(state.db(), 3, id).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{id}/balance"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let balance: model::Balance = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!((balance.earned, balance.spent, balance.available), (15, 3, 12));
+
+    // Page through the ledger one entry at a time following the cursors
+    let mut cursor: Option<String> = None;
+    let mut balances = vec![];
+    loop {
+      let uri = match &cursor {
+        Some(cursor) => format!("/api/users/{id}/ledger?limit=1&cursor={cursor}"),
+        None => format!("/api/users/{id}/ledger?limit=1"),
+      };
+      let req = Request::builder().method(Method::GET).uri(uri).body(Body::empty()).unwrap();
+      let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+      assert_eq!(res.status(), StatusCode::OK);
+      let bytes = res.into_body().collect().await.unwrap().to_bytes();
+      let page: model::LedgerPage = serde_json::from_slice(&bytes).unwrap();
+      balances.extend(page.entries.iter().map(|x| x.balance));
+      cursor = page.next_cursor;
+      if cursor.is_none() {
+        break;
+      }
+    }
+    assert_eq!(balances, vec![12, 15, 10]);
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/users/-1/ledger")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  }
+
+  #[tokio::test]
+  async fn test_get_streaks() {
+    let state = state::test().await;
+    let id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 10, id, 1).await.unwrap();
+    db::point::insert(state.db(), -5, id, 1).await.unwrap();
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{id}/streaks"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let streaks: Vec<model::Streak> = serde_json::from_slice(&bytes).unwrap();
+    let streaks = streaks.iter().map(|x| (x.kind, x.ref_id, x.current, x.longest))
+      .collect::<Vec<_>>();
+    assert_eq!(streaks, vec![(model::StreakKind::Action, 1, 1, 1),
+      (model::StreakKind::Category, 1, 1, 1)]);
+
+    let req = Request::builder().method(Method::GET)
+      .uri("/api/users/-1/streaks")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+  }
+
+  #[tokio::test]
+  async fn test_get_expiring() {
+    let state = state::test().await;
+    let id = db::user::insert(state.db(), "user1", "user1@foo.com").await.unwrap();
+    db::point::insert(state.db(), 10, id, 1).await.unwrap();
+
+    // Points never expire without a configured lifetime
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{id}/expiring?days=30"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let expiring: Vec<model::ExpiringPoints> = serde_json::from_slice(&bytes).unwrap();
+    assert!(expiring.is_empty());
+
+    let req = Request::builder().method(Method::GET)
+      .uri(format!("/api/users/{id}/expiring?days=0"))
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  }
+
   #[tokio::test]
   async fn test_get_roles_empty() {
     let state = state::test().await;
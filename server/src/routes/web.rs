@@ -0,0 +1,250 @@
+use std::{path::Path, sync::Arc};
+use axum::{
+  extract::Request, http::{header, HeaderValue, StatusCode, Uri}, middleware::{self, Next},
+  response::Response, routing::any, Router,
+};
+use tower::util::MapResponse;
+use tower_http::services::{fs::ServeFileSystemResponseBody, ServeDir, ServeFile};
+
+use crate::{state, errors::Error};
+
+// Hashed assets never change so they can be cached for a year without revalidation
+const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+// Everything else must be revalidated so that new releases are picked up right away
+const CACHE_REVALIDATE: &str = "no-cache";
+
+// Web app directory used when none is configured and the web build isn't embedded
+#[cfg(not(feature = "embed-web"))]
+const DEFAULT_WEB_APP_DIR: &str = "web";
+
+// Minimum length of a hex segment in a file name to be considered a content hash
+const MIN_HASH_LEN: usize = 8;
+
+/// Static file service for a web app directory with `index.html` as the fallback
+pub(crate) type Files = ServeDir<Index>;
+
+/// `index.html` service marking its responses as the fallback, see `IndexFallback`
+pub(crate) type Index = MapResponse<ServeFile, fn(FileResponse) -> FileResponse>;
+
+// Response of the static file services
+type FileResponse = axum::http::Response<ServeFileSystemResponseBody>;
+
+/// Response extension marking `index.html` served in place of a file that wasn't found
+///
+/// - The fallback is never cached as immutable even when the path looks hashed e.g. a hex id
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IndexFallback;
+
+/// Configure static file serving for the Flutter web app
+///
+/// - Serves files from ***web_app_dir*** preferring precompressed `.br` and `.gz` variants
+/// - Serves the embedded web build first when built with the `embed-web` feature, falling back
+///   on ***web_app_dir*** if configured for anything that wasn't embedded
+/// - Falls back to `index.html` for any other path so that deep links work on reload
+/// - Unknown `/api` routes get a JSON 404 rather than the web app
+/// - Sets Cache-Control so hashed assets are immutable and everything else is revalidated
+///
+/// #### Parameters
+/// - ***web_app_dir*** - directory containing the Flutter web build, defaults to `web`
+///   when the web build isn't embedded
+pub(crate) fn init(web_app_dir: Option<&str>) -> Router<Arc<state::State>>
+{
+  let router = Router::new()
+    .route("/api", any(not_found))
+    .route("/api/{*path}", any(not_found));
+
+  #[cfg(feature = "embed-web")]
+  let router = {
+    let files = web_app_dir.map(serve_dir);
+    router.fallback(move |req: Request| super::embed::serve(req, files.clone()))
+  };
+
+  #[cfg(not(feature = "embed-web"))]
+  let router = router.fallback_service(serve_dir(web_app_dir.unwrap_or(DEFAULT_WEB_APP_DIR)));
+
+  router.layer(middleware::from_fn(cache_control))
+}
+
+/// Serve the given web app directory preferring precompressed variants
+///
+/// #### Parameters
+/// - ***web_app_dir*** - directory containing the Flutter web build
+pub(crate) fn serve_dir(web_app_dir: &str) -> Files
+{
+  let index = ServeFile::new(Path::new(web_app_dir).join("index.html"))
+    .precompressed_br()
+    .precompressed_gzip();
+  let index = MapResponse::new(index, mark_fallback as fn(FileResponse) -> FileResponse);
+  ServeDir::new(web_app_dir)
+    .precompressed_br()
+    .precompressed_gzip()
+    .fallback(index)
+}
+
+// Mark the response as the index fallback
+fn mark_fallback(mut res: FileResponse) -> FileResponse
+{
+  res.extensions_mut().insert(IndexFallback);
+  res
+}
+
+// Unknown API routes are reported as such rather than served the web app
+async fn not_found(uri: Uri) -> Error
+{
+  let msg = format!("Route '{}' was not found", uri.path());
+  log::warn!("{msg}");
+  Error::http(StatusCode::NOT_FOUND, &msg)
+}
+
+// Set the Cache-Control header on successful static file responses
+//
+// - only files actually served for a hashed path are immutable, never the index fallback
+async fn cache_control(req: Request, next: Next) -> Response
+{
+  let hashed = is_hashed(req.uri().path());
+  let mut res = next.run(req).await;
+  let fallback = res.extensions().get::<IndexFallback>().is_some();
+  let value = if hashed && !fallback { CACHE_IMMUTABLE } else { CACHE_REVALIDATE };
+  if res.status().is_success() || res.status() == StatusCode::NOT_MODIFIED {
+    res.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+  }
+  res
+}
+
+// Check if the file name carries a content hash e.g. `main.3f2a9c1b.js`
+//
+// - `index.html`, `flutter_service_worker.js` and other fixed names are never hashed
+fn is_hashed(path: &str) -> bool
+{
+  let name = path.rsplit('/').next().unwrap_or_default();
+  name.split(['.', '-', '_'])
+    .any(|x| x.len() >= MIN_HASH_LEN && x.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use axum::body::Body;
+  use http_body_util::BodyExt;
+  use tower::ServiceExt;
+  use crate::routes;
+
+  // Helper to create a web app directory with a few files in it
+  fn web_app_dir() -> std::path::PathBuf
+  {
+    let dir = std::env::temp_dir().join(format!("oneup-web-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("assets")).unwrap();
+    std::fs::write(dir.join("index.html"), "<html>index</html>").unwrap();
+    std::fs::write(dir.join("flutter_service_worker.js"), "worker").unwrap();
+    std::fs::write(dir.join("main.3f2a9c1b.js"), "main").unwrap();
+    std::fs::write(dir.join("main.3f2a9c1b.js.gz"), "gzipped").unwrap();
+    dir
+  }
+
+  // Helper to send a GET request to the web app router
+  async fn get(dir: &Path, uri: &str, encoding: Option<&str>) -> Response
+  {
+    let state = state::test().await;
+    let mut req = Request::builder().method("GET").uri(uri);
+    if let Some(encoding) = encoding {
+      req = req.header(header::ACCEPT_ENCODING, encoding);
+    }
+    init(dir.to_str()).with_state(state)
+      .oneshot(req.body(Body::empty()).unwrap()).await.unwrap()
+  }
+
+  // Helper to read the response body as a string
+  async fn body(res: Response) -> String
+  {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+  }
+
+  #[test]
+  fn test_is_hashed()
+  {
+    assert!(is_hashed("/main.3f2a9c1b.js"));
+    assert!(is_hashed("/assets/font-0123abcd.otf"));
+    assert!(!is_hashed("/index.html"));
+    assert!(!is_hashed("/flutter_service_worker.js"));
+    assert!(!is_hashed("/main.dart.js"));
+  }
+
+  #[tokio::test]
+  async fn test_deep_link_falls_back_to_index()
+  {
+    let dir = web_app_dir();
+
+    let res = get(&dir, "/settings/users", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CACHE_CONTROL).unwrap(), CACHE_REVALIDATE);
+    assert_eq!(body(res).await, "<html>index</html>");
+
+    let res = get(&dir, "/", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body(res).await, "<html>index</html>");
+
+    // Deep links that look hashed e.g. hex ids still get the index revalidated
+    for uri in ["/users/12345678", "/items/3f2a9c1b.js"] {
+      let res = get(&dir, uri, None).await;
+      assert_eq!(res.status(), StatusCode::OK);
+      assert_eq!(res.headers().get(header::CACHE_CONTROL).unwrap(), CACHE_REVALIDATE);
+      assert_eq!(body(res).await, "<html>index</html>");
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_cache_control_by_file()
+  {
+    let dir = web_app_dir();
+
+    let res = get(&dir, "/main.3f2a9c1b.js", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CACHE_CONTROL).unwrap(), CACHE_IMMUTABLE);
+    assert_eq!(body(res).await, "main");
+
+    let res = get(&dir, "/flutter_service_worker.js", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CACHE_CONTROL).unwrap(), CACHE_REVALIDATE);
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_precompressed_variant_is_served()
+  {
+    let dir = web_app_dir();
+
+    let res = get(&dir, "/main.3f2a9c1b.js", Some("gzip")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+    assert_eq!(body(res).await, "gzipped");
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_unknown_api_route_is_not_found()
+  {
+    let state = state::test().await;
+
+    let req = Request::builder().method("GET")
+      .uri("/api/does/not/exist")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "application/json");
+    assert!(res.headers().get(header::CACHE_CONTROL).is_none());
+
+    // Known routes are unaffected
+    let req = Request::builder().method("GET")
+      .uri("/api/health")
+      .body(Body::empty()).unwrap();
+    let res = routes::init(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+  }
+}
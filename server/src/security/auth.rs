@@ -138,6 +138,7 @@ mod tests
       email: email.to_string(),
       created_at: chrono::Utc::now().with_timezone(&chrono::Local),
       updated_at: chrono::Utc::now().with_timezone(&chrono::Local),
+      deleted_at: None,
     }, roles.clone()).unwrap();
     let claims = decode_jwt_token(private_key, &jwt).unwrap();
 
@@ -165,6 +166,7 @@ mod tests
       email: email.to_string(),
       created_at: chrono::Utc::now().with_timezone(&chrono::Local),
       updated_at: chrono::Utc::now().with_timezone(&chrono::Local),
+      deleted_at: None,
     }, roles).unwrap();
 
     let err = decode_jwt_token("bad key", &jwt).unwrap_err();
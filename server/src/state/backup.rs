@@ -0,0 +1,230 @@
+use std::{collections::HashSet, path::{Path, PathBuf}, time::Duration};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+
+use crate::db;
+use super::State;
+
+// Snapshot file names are stamped with the UTC time they were taken at
+const SNAPSHOT_FORMAT: &str = "oneup-%Y%m%dT%H%M%SZ.db";
+
+/// Start taking scheduled database snapshots in the background
+///
+/// - Does nothing when no backup directory is configured
+/// - The first snapshot is taken once the interval has passed since the newest existing snapshot
+///   so that restarts don't pile up extra snapshots
+pub(crate) fn spawn(state: &State)
+{
+  let Some(dir) = state.config().backup_dir.clone() else {
+    log::info!("Scheduled backups disabled, no backup directory configured");
+    return;
+  };
+  let state = state.clone();
+  tokio::spawn(async move {
+    let dir = PathBuf::from(dir);
+    let interval = Duration::from_secs(state.config().backup_interval.max(1));
+    log::info!("Scheduled backups every {interval:?} to: {}", dir.display());
+    loop {
+      let latest = list(&dir).unwrap_or_default().first().map(|(x, _)| *x);
+      tokio::time::sleep(due_in(latest, interval, Utc::now())).await;
+      run(&state, &dir).await;
+    }
+  });
+}
+
+/// Take a snapshot, apply retention and record the outcome in the backup status
+async fn run(state: &State, dir: &Path)
+{
+  let attempt_at = chrono::Local::now();
+  let result = take(state, dir).await;
+  if result.is_ok() {
+    let config = state.config();
+    if let Err(e) = prune(dir, config.backup_keep_daily, config.backup_keep_weekly) {
+      log::error!("Error pruning snapshots: {e:#}");
+    }
+  }
+
+  state.update_backup_status(|status| {
+    status.last_attempt_at = Some(attempt_at);
+    match result {
+      Ok(path) => {
+        status.last_success_at = Some(attempt_at);
+        status.last_path = Some(path.display().to_string());
+        status.last_error = None;
+      },
+      Err(e) => {
+        log::error!("Error taking snapshot: {e:#}");
+        status.last_error = Some(format!("{e:#}"));
+      },
+    }
+  });
+}
+
+// Write and verify a new snapshot only moving it into place once it is known to be good
+async fn take(state: &State, dir: &Path) -> Result<PathBuf>
+{
+  std::fs::create_dir_all(dir)
+    .with_context(|| format!("creating backup directory: {}", dir.display()))?;
+  let path = dir.join(Utc::now().format(SNAPSHOT_FORMAT).to_string());
+  let tmp = path.with_extension("db.tmp");
+  if tmp.exists() {
+    std::fs::remove_file(&tmp)?;
+  }
+
+  db::backup::snapshot(state.db(), &tmp).await?;
+  if let Err(e) = db::backup::verify(&tmp).await {
+    std::fs::remove_file(&tmp).ok();
+    return Err(e.into());
+  }
+  std::fs::rename(&tmp, &path)
+    .with_context(|| format!("moving snapshot into place: {}", path.display()))?;
+
+  log::info!("Snapshot written to: {}", path.display());
+  Ok(path)
+}
+
+// Delete the snapshots not kept by retention returning the deleted files
+fn prune(dir: &Path, daily: usize, weekly: usize) -> Result<Vec<PathBuf>>
+{
+  let snapshots = list(dir)?;
+  let times = snapshots.iter().map(|(x, _)| *x).collect::<Vec<_>>();
+  let keep = retain(&times, daily, weekly);
+
+  let mut deleted = vec![];
+  for (i, (_, path)) in snapshots.into_iter().enumerate() {
+    if !keep.contains(&i) {
+      std::fs::remove_file(&path).with_context(|| format!("deleting snapshot: {}", path.display()))?;
+      log::info!("Snapshot pruned: {}", path.display());
+      deleted.push(path);
+    }
+  }
+  Ok(deleted)
+}
+
+// Get the snapshots in the directory newest first ignoring anything else
+fn list(dir: &Path) -> Result<Vec<(DateTime<Utc>, PathBuf)>>
+{
+  let mut snapshots = vec![];
+  if !dir.exists() {
+    return Ok(snapshots);
+  }
+  for entry in std::fs::read_dir(dir).with_context(|| format!("listing: {}", dir.display()))? {
+    let path = entry?.path();
+    let name = path.file_name().and_then(|x| x.to_str()).unwrap_or_default();
+    if let Ok(time) = NaiveDateTime::parse_from_str(name, SNAPSHOT_FORMAT) {
+      snapshots.push((time.and_utc(), path));
+    }
+  }
+  snapshots.sort_by_key(|x| std::cmp::Reverse(x.0));
+  Ok(snapshots)
+}
+
+// Indexes of the snapshots to keep given their times newest first
+//
+// - keeps the newest snapshot of each of the most recent `daily` days and `weekly` ISO weeks
+// - always keeps the newest snapshot
+fn retain(times: &[DateTime<Utc>], daily: usize, weekly: usize) -> HashSet<usize>
+{
+  let (mut days, mut weeks) = (HashSet::new(), HashSet::new());
+  let mut keep = HashSet::new();
+  for (i, time) in times.iter().enumerate() {
+    if days.len() < daily && days.insert(time.date_naive()) {
+      keep.insert(i);
+    }
+    let week = time.iso_week();
+    if weeks.len() < weekly && weeks.insert((week.year(), week.week())) {
+      keep.insert(i);
+    }
+  }
+  if !times.is_empty() {
+    keep.insert(0);
+  }
+  keep
+}
+
+// Time to wait until the next snapshot is due
+fn due_in(latest: Option<DateTime<Utc>>, interval: Duration, now: DateTime<Utc>) -> Duration
+{
+  latest
+    .and_then(|x| (x + interval - now).to_std().ok())
+    .map(|x| x.min(interval))
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use chrono::TimeZone;
+
+  // Helper to create a UTC time
+  fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc>
+  {
+    Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+  }
+
+  #[test]
+  fn test_retain()
+  {
+    // Newest first, two on the 18th then one a day back to the 5th
+    let mut times = vec![utc(2026, 10, 18, 12), utc(2026, 10, 18, 0)];
+    times.extend((5..18).rev().map(|d| utc(2026, 10, d, 0)));
+
+    let keep = retain(&times, 3, 2);
+    let mut keep = keep.into_iter().map(|i| times[i]).collect::<Vec<_>>();
+    keep.sort_by(|a, b| b.cmp(a));
+
+    // Daily keeps the 18th at noon, the 17th and 16th. Weekly keeps the newest of the week of
+    // the 12th and the week of the 5th.
+    assert_eq!(keep, vec![utc(2026, 10, 18, 12), utc(2026, 10, 17, 0), utc(2026, 10, 16, 0),
+      utc(2026, 10, 11, 0)]);
+
+    assert_eq!(retain(&times, 0, 0), HashSet::from([0]));
+    assert!(retain(&[], 3, 2).is_empty());
+  }
+
+  #[test]
+  fn test_due_in()
+  {
+    let hour = Duration::from_secs(3600);
+    let now = utc(2026, 10, 18, 12);
+    assert_eq!(due_in(None, hour, now), Duration::ZERO);
+    assert_eq!(due_in(Some(utc(2026, 10, 18, 11)), hour, now), Duration::ZERO);
+    assert_eq!(due_in(Some(utc(2026, 10, 18, 10)), hour, now), Duration::ZERO);
+    assert_eq!(due_in(Some(now), hour, now), hour);
+
+    // Snapshots from the future e.g. after a clock change don't delay by more than an interval
+    assert_eq!(due_in(Some(utc(2026, 10, 19, 12)), hour, now), hour);
+  }
+
+  #[tokio::test]
+  async fn test_run_and_prune()
+  {
+    let state = super::super::test().await;
+    let dir = std::env::temp_dir().join(format!("oneup-backups-{}", uuid::Uuid::new_v4()));
+
+    // Snapshots from ten days in a row long ago
+    std::fs::create_dir_all(&dir).unwrap();
+    for d in 1..11 {
+      let name = utc(2025, 1, d, 0).format(SNAPSHOT_FORMAT).to_string();
+      std::fs::write(dir.join(name), "").unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+    run(&state, &dir).await;
+    let status = state.backup_status();
+    assert!(status.last_error.is_none());
+    let path = PathBuf::from(status.last_path.unwrap());
+    assert!(path.exists());
+    db::backup::verify(&path).await.unwrap();
+
+    // Default retention of 7 daily keeps the new snapshot and the 10th back to the 5th, those
+    // weeks are already covered by the daily snapshots so the 1st to the 4th are pruned
+    let snapshots = list(&dir).unwrap();
+    assert_eq!(snapshots.len(), 7);
+    assert_eq!(snapshots[6].0, utc(2025, 1, 5, 0));
+    assert!(dir.join("notes.txt").exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+}
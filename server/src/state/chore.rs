@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use chrono::Days;
+use crate::db;
+use super::State;
+
+// How often chores are checked for missed occurrences
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Number of days back that missed occurrences are looked for e.g. while the server was down
+const LOOKBACK_DAYS: u64 = 7;
+
+/// Start recording missed chore occurrences in the background
+///
+/// - Occurrences on the days before today that weren't done are recorded as missed, taking the
+///   configured penalty if any, checking on startup and then every hour
+pub(crate) fn spawn(state: &State)
+{
+  let state = state.clone();
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+      interval.tick().await;
+      run(&state).await;
+    }
+  });
+}
+
+// Record the missed occurrences within the lookback
+async fn run(state: &State)
+{
+  let today = chrono::Local::now().date_naive();
+  let from = today - Days::new(LOOKBACK_DAYS);
+  match db::chore::mark_missed(state.db(), from, today, state.config().chore_penalty).await {
+    Ok(0) => {},
+    Ok(count) => log::info!("Recorded {count} missed chores"),
+    Err(e) => log::error!("Error recording missed chores: {e}"),
+  }
+}
@@ -1,3 +1,4 @@
+use std::path::Path;
 use dotenvy::dotenv;
 use anyhow::{anyhow, Result};
 
@@ -14,8 +15,22 @@ pub(crate) fn init() -> Result<Config>
   dotenv().ok();
 
   // Load configuration from environment variables
-  match envy::from_env::<Config>() {
-    Ok(config) => return Ok(config),
-    Err(e) => Err(anyhow!("loading configuration: {}", e)),
+  let config = match envy::from_env::<Config>() {
+    Ok(config) => config,
+    Err(e) => return Err(anyhow!("loading configuration: {}", e)),
+  };
+
+  // Count days in the household timezone, both chrono and SQLite's localtime follow TZ. This runs
+  // before any other threads are started so changing the environment is safe.
+  if let Some(tz) = config.household_tz.as_deref() {
+    let dir = std::env::var("TZDIR").unwrap_or_else(|_| "/usr/share/zoneinfo".to_string());
+    if tz.is_empty() || tz.contains("..") || !Path::new(&dir).join(tz).is_file() {
+      return Err(anyhow!("loading configuration: unknown household timezone '{tz}'"));
+    }
+    std::env::set_var("TZ", tz);
+  }
+  if config.points_expiry_days.is_some_and(|x| x < 1) {
+    return Err(anyhow!("loading configuration: points expiry days must be at least 1"));
   }
+  Ok(config)
 }
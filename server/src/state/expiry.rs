@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use crate::{db, model};
+use super::State;
+
+// How often points are checked for having expired
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Start expiring unspent points in the background
+///
+/// - Does nothing when no points expiry is configured
+/// - Points unspent past their lifetime are expired following the configured policy, checking on
+///   startup and then every hour
+pub(crate) fn spawn(state: &State)
+{
+  let Some(lifetime) = state.config().points_expiry_days else {
+    log::info!("Points expiry disabled, no expiry configured");
+    return;
+  };
+  let state = state.clone();
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+    loop {
+      interval.tick().await;
+      run(&state, lifetime).await;
+    }
+  });
+}
+
+// Expire the points past their lifetime and let clients know
+async fn run(state: &State, lifetime: i64)
+{
+  match db::expiry::expire(state.db(), lifetime, state.config().points_expiry_policy).await {
+    Ok(expiries) => for expiry in expiries {
+      log::info!("Expired {} points of points with id '{}'", expiry.value, expiry.point_id);
+      state.publish(model::Event::new(model::EventKind::PointsExpired, expiry.id)
+        .with_user_id(expiry.user_id).with_data(&expiry));
+    },
+    Err(e) => log::error!("Error expiring points: {e}"),
+  }
+}
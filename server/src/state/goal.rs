@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use crate::{db, model};
+use super::State;
+
+// How often goals are checked for having been reached, as a backstop to the checks made when
+// points, rewards and redemptions change
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Start completing savings goals in the background
+///
+/// - Goals whose target the user's available balance covers are completed, checking on startup
+///   and then every minute
+/// - Goals are normally completed as soon as the balance changes see `check_user`, this catches
+///   anything else e.g. a restored backup
+pub(crate) fn spawn(state: &State)
+{
+  let state = state.clone();
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+      interval.tick().await;
+      check(&state).await;
+    }
+  });
+}
+
+/// Complete the goals of all users that were reached and let clients know
+pub(crate) async fn check(state: &State)
+{
+  complete(state, None).await;
+}
+
+/// Complete the goals of the given user that were reached and let clients know
+///
+/// - Called after goals or anything adding to the user's available balance changes so that the
+///   goal completes right away
+pub(crate) async fn check_user(state: &State, user_id: i64)
+{
+  complete(state, Some(user_id)).await;
+}
+
+// Complete the reached goals of the given user or all users publishing an event for each
+async fn complete(state: &State, user_id: Option<i64>)
+{
+  match db::goal::complete(state.db(), user_id).await {
+    Ok(goals) => for goal in goals {
+      log::info!("Goal with id '{}' was completed", goal.id);
+      state.publish(model::Event::new(model::EventKind::GoalCompleted, goal.id)
+        .with_user_id(goal.user_id).with_data(&goal));
+    },
+    Err(e) => log::error!("Error completing goals: {e}"),
+  }
+}
@@ -1,7 +1,15 @@
+pub(crate) mod backup;
+pub(crate) mod chore;
 pub(crate) mod config;
+pub(crate) mod expiry;
+pub(crate) mod goal;
+pub(crate) mod redemption;
+pub(crate) mod trash;
 
+use std::sync::{Arc, RwLock};
 use sqlx::sqlite::{ SqlitePool, Sqlite };
 use sqlx::migrate::{MigrateDatabase, Migrator};
+use tokio::sync::broadcast;
 use anyhow::{ anyhow, Result, Context };
 
 use crate::{db, model, security::auth};
@@ -10,11 +18,16 @@ use crate::{db, model, security::auth};
 // - Relative to the project root i.e. where `Cargo.toml` is located.
 static MIGRATOR: Migrator = sqlx::migrate!();
 
+// Number of events buffered per subscriber before slow clients start missing events
+const EVENT_CAPACITY: usize = 256;
+
 /// Application state
 #[derive(Clone)]
 pub(crate) struct State {
   config: model::Config,
   db: SqlitePool,
+  events: broadcast::Sender<model::Event>,
+  backup_status: Arc<RwLock<model::BackupStatus>>,
 }
 
 impl State 
@@ -22,7 +35,12 @@ impl State
   /// Create a new state
   pub(crate) fn new(config: model::Config, db: SqlitePool) -> Self 
   {
-    Self { config, db }
+    let (events, _) = broadcast::channel(EVENT_CAPACITY);
+    let backup_status = model::BackupStatus {
+      enabled: config.backup_dir.is_some(),
+      ..Default::default()
+    };
+    Self { config, db, events, backup_status: Arc::new(RwLock::new(backup_status)) }
   }
 
   /// Get the ip from the config
@@ -55,6 +73,35 @@ impl State
     &self.config
   }
 
+  /// Publish a domain event to all subscribers
+  /// 
+  /// - events are dropped silently when there are no subscribers
+  pub(crate) fn publish(&self, event: model::Event)
+  {
+    log::debug!("Publishing event {} for id '{}'", event.kind.as_str(), event.id);
+    let _ = self.events.send(event);
+  }
+
+  /// Subscribe to the domain events published from here on out
+  pub(crate) fn subscribe(&self) -> broadcast::Receiver<model::Event>
+  {
+    self.events.subscribe()
+  }
+
+  /// Get the status of the scheduled database snapshots
+  pub(crate) fn backup_status(&self) -> model::BackupStatus
+  {
+    self.backup_status.read().map(|x| x.clone()).unwrap_or_default()
+  }
+
+  /// Update the status of the scheduled database snapshots
+  pub(crate) fn update_backup_status(&self, f: impl FnOnce(&mut model::BackupStatus))
+  {
+    if let Ok(mut status) = self.backup_status.write() {
+      f(&mut status);
+    }
+  }
+
   /// Close the database connection pool
   /// This ensures WAL checkpoint and proper cleanup
   pub(crate) async fn close_db(&self) -> Result<()>
@@ -94,6 +141,14 @@ pub(crate) async fn init(config: model::Config) -> Result<State>
   Ok(State::new(config, db))
 }
 
+/// Latest database migration version
+///
+/// - Stamped into backups so that restores are only accepted by a matching schema
+pub(crate) fn schema_version() -> i64
+{
+  MIGRATOR.iter().map(|x| x.version).max().unwrap_or_default()
+}
+
 /// Create a new instance that is useful for testing.
 /// Sqlite in-memory databases are unique for each connection. This means it is safe
 /// to call this function at the beginning of each test and each in memory db instance
@@ -149,6 +204,21 @@ mod tests
     assert_eq!(result, 1);
   }
 
+  #[tokio::test]
+  async fn test_publish_and_subscribe()
+  {
+    let state = test().await;
+
+    // Publishing without subscribers is a no-op
+    state.publish(model::Event::new(model::EventKind::UserCreated, 1));
+
+    let mut rx = state.subscribe();
+    state.publish(model::Event::new(model::EventKind::UserUpdated, 2));
+    let event = rx.recv().await.unwrap();
+    assert_eq!(event.kind, model::EventKind::UserUpdated);
+    assert_eq!(event.id, 2);
+  }
+
   #[tokio::test]
   async fn test_connect() 
   {
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::db;
+use super::State;
+
+// How often redemption requests are checked for expiry
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Start expiring stale redemption requests in the background
+///
+/// - Does nothing when no expiry is configured
+/// - Requests waiting on a decision longer than the expiry are expired releasing their held
+///   points, checking on startup and then every 10 minutes
+pub(crate) fn spawn(state: &State)
+{
+  let Some(expiry) = state.config().redemption_expiry else {
+    log::info!("Redemption expiry disabled, no expiry configured");
+    return;
+  };
+  let state = state.clone();
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+    loop {
+      interval.tick().await;
+      run(&state, Duration::from_secs(expiry)).await;
+    }
+  });
+}
+
+// Expire the requests older than the expiry
+async fn run(state: &State, expiry: Duration)
+{
+  let before = chrono::Duration::from_std(expiry).ok()
+    .and_then(|x| chrono::Utc::now().checked_sub_signed(x));
+  let Some(before) = before else {
+    log::warn!("Redemption expiry is too large, skipping expiry");
+    return;
+  };
+  match db::redemption::expire(state.db(), before).await {
+    Ok(0) => {},
+    Ok(count) => {
+      log::info!("Expired {count} redemption requests");
+
+      // Released holds may complete goals
+      super::goal::check(state).await;
+    },
+    Err(e) => log::error!("Error expiring redemption requests: {e}"),
+  }
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use crate::db;
+use super::State;
+
+// How often the trash is checked for entries past retention
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Start purging the trash in the background
+///
+/// - Entries are purged for good once they have been in the trash longer than the configured
+///   retention, checking on startup and then every hour
+pub(crate) fn spawn(state: &State)
+{
+  let state = state.clone();
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+      interval.tick().await;
+      run(&state).await;
+    }
+  });
+}
+
+// Purge everything in the trash past retention
+async fn run(state: &State)
+{
+  let retention = Duration::from_secs(state.config().trash_retention);
+  let before = chrono::Duration::from_std(retention).ok()
+    .and_then(|x| chrono::Utc::now().checked_sub_signed(x));
+  let Some(before) = before else {
+    log::warn!("Trash retention is too large, skipping purge");
+    return;
+  };
+  match db::trash::purge(state.db(), before).await {
+    Ok(0) => {},
+    Ok(count) => log::info!("Purged {count} entries from the trash"),
+    Err(e) => log::error!("Error purging the trash: {e}"),
+  }
+}